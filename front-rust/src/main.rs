@@ -2,9 +2,8 @@
 
 use dioxus::prelude::*;
 use dioxus_logger::tracing;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
 use std::str::FromStr;
+use todo_types::{CreateLabel, CreateTodo, Label, QuickAdd, TodoEntity};
 
 #[derive(Clone, Routable, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 enum Route {
@@ -80,6 +79,20 @@ fn Home() -> Element {
         //     input { name: "id" }
         //     input { r#type: "submit", value: "DELETE LABEL" }
         // }
+        h2 { "Quick Add" }
+        p { "例: 資料作成 明日15時 #仕事 !high" }
+        form { onsubmit: move |event| {
+                tracing::info!("Submitted! {event:?}");
+                let input = event.values().get("input").unwrap().as_value();
+                wasm_bindgen_futures::spawn_local(async move { // Use `spawn_local` for async tasks in WASM
+                    if let Err(err) = post_quick_add(input.clone()).await {
+                        tracing::error!("Failed to post data: {:?}", err);
+                    }
+                });
+            },
+            input { name: "input" }
+            input { r#type: "submit", value: "QUICK ADD" }
+        }
         h2 { "Todo Set" }
         form { onsubmit: move |event| {
                 tracing::info!("Submitted! {event:?}");
@@ -121,7 +134,7 @@ fn Home() -> Element {
                                 tracing::info!("selected_labels: {:?}", selected_labels);
                             }
                         }
-                        label { "{label.name}" }  // Display label name
+                        LabelChip { label: label.clone() }
                     }
                 }
             }
@@ -163,7 +176,10 @@ fn Home() -> Element {
                         div { "===============================" }
                         div {
                             p { "Label ID: {label.id}" }
-                            p { "Label Name: {label.name}" }
+                            LabelChip { label: label.clone() }
+                            if let Some(ref description) = label.description {
+                                p { "{description}" }
+                            }
                         }
                     }
                     div { "===============================" }
@@ -181,8 +197,7 @@ fn Home() -> Element {
                             p { "Todo Text: {todo.text}" }
                             p { "Todo Completed: {todo.completed}" }
                             for todo_label in todo.labels.iter() {
-                                p { "Todo Labels ID: {todo_label.id}" }
-                                p { "Todo Labels Name: {todo_label.name}" }
+                                LabelChip { label: todo_label.clone() }
                             }
                         }
                     }
@@ -197,21 +212,21 @@ fn Home() -> Element {
     }
 }
 
-// --------------
-// struct
-// --------------
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Label {
-    pub id: i32,
-    pub name: String,
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct TodoEntity {
-    pub id: i32,
-    pub text: String,
-    pub completed: bool,
-    pub labels: Vec<Label>,
+// ラベルの色を背景にして表示する
+#[component]
+fn LabelChip(label: Label) -> Element {
+    let text = match label.icon {
+        Some(ref icon) => format!("{} {}", icon, label.name),
+        None => label.name.clone(),
+    };
+    rsx! {
+        span {
+            class: "label-chip",
+            style: "background-color: {label.color}",
+            title: label.description.clone().unwrap_or_default(),
+            "{text}"
+        }
+    }
 }
 
 // --------------
@@ -232,12 +247,8 @@ async fn get_todo_data() -> Result<Vec<TodoEntity>, ServerFnError> {
 async fn post_todo_data(text: String, labels: Vec<Label>) -> Result<(), ServerFnError> {
     tracing::info!("post: {:?}, labels: {:?}", text, labels);
 
-    let body = json!({
-        "text": text,
-        "labels": labels.iter().map(|label| {
-            json!(label.id)
-        }).collect::<Vec<_>>(), // 各ラベルをJSON形式に変換してPOST
-    });
+    // 型はサーバーと共有しているので、APIの変更はコンパイルエラーになる
+    let body = CreateTodo::new(text, labels.iter().map(|label| label.id).collect());
 
     let client = reqwest::Client::new();
     let res = client
@@ -254,6 +265,31 @@ async fn post_todo_data(text: String, labels: Vec<Label>) -> Result<(), ServerFn
     Ok(())
 }
 
+// 1行の入力から本文・ラベル・優先度・期限をサーバーで解析して作る
+async fn post_quick_add(input: String) -> Result<(), ServerFnError> {
+    tracing::info!("post: {:?}", input);
+
+    let body = QuickAdd {
+        create_labels: true,
+        utc_offset: Some("+09:00".to_string()),
+        ..QuickAdd::new(input)
+    };
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post("データベースURL/todos/quick")
+        .json(&body)
+        .send()
+        .await;
+
+    match res {
+        Ok(response) => tracing::info!("POST successful: {:?}", response),
+        Err(err) => tracing::error!("POST failed: {:?}", err),
+    }
+
+    Ok(())
+}
+
 async fn delete_data(kind: String, id: i32) -> Result<(), ServerFnError> {
     tracing::info!("delete_todo_data: {:?}", id);
 
@@ -289,9 +325,7 @@ async fn get_label_data() -> Result<Vec<Label>, ServerFnError> {
 async fn post_label_data(name: String) -> Result<(), ServerFnError> {
     tracing::info!("post: {:?}", name);
 
-    let body = json!({
-        "name": name,
-    });
+    let body = CreateLabel::new(name);
 
     let client = reqwest::Client::new();
     let res = client
@@ -0,0 +1,15 @@
+CREATE TABLE projects
+(
+    id       SERIAL PRIMARY KEY,
+    name     TEXT    NOT NULL,
+    archived BOOLEAN NOT NULL DEFAULT false
+);
+
+-- 既存のTodoはデフォルトプロジェクトに所属させる
+INSERT INTO projects (id, name) VALUES (1, 'Default');
+SELECT setval('projects_id_seq', (SELECT max(id) FROM projects));
+
+ALTER TABLE todos
+    ADD COLUMN project_id INTEGER NOT NULL DEFAULT 1 REFERENCES projects (id) ON DELETE CASCADE;
+
+CREATE INDEX todos_project_id_idx ON todos (project_id);
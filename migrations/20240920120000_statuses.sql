@@ -0,0 +1,26 @@
+CREATE TABLE statuses
+(
+    id         SERIAL PRIMARY KEY,
+    project_id INTEGER NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    name       TEXT    NOT NULL,
+    position   INTEGER NOT NULL DEFAULT 0,
+    is_done    BOOLEAN NOT NULL DEFAULT false,
+    wip_limit  INTEGER
+);
+
+CREATE INDEX statuses_project_id_idx ON statuses (project_id, position);
+
+-- 既存のプロジェクトには完了/未完了の2列を用意する
+INSERT INTO statuses (project_id, name, position, is_done)
+SELECT id, 'Todo', 0, false FROM projects
+UNION ALL
+SELECT id, 'Done', 1, true FROM projects;
+
+ALTER TABLE todos
+    ADD COLUMN status_id INTEGER REFERENCES statuses (id) ON DELETE SET NULL;
+
+UPDATE todos
+SET status_id = statuses.id
+FROM statuses
+WHERE statuses.project_id = todos.project_id
+  AND statuses.is_done = todos.completed;
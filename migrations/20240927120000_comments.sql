@@ -0,0 +1,11 @@
+CREATE TABLE comments
+(
+    id         SERIAL PRIMARY KEY,
+    todo_id    INTEGER     NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+    author     TEXT        NOT NULL,
+    body       TEXT        NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX comments_todo_id_idx ON comments (todo_id);
@@ -0,0 +1,13 @@
+CREATE TABLE attachments
+(
+    id           SERIAL PRIMARY KEY,
+    -- Todoが削除されるとnullになり、バックグラウンドでBlobごと削除される
+    todo_id      INTEGER     REFERENCES todos (id) ON DELETE SET NULL,
+    filename     TEXT        NOT NULL,
+    content_type TEXT        NOT NULL,
+    size         BIGINT      NOT NULL,
+    storage_key  TEXT        NOT NULL UNIQUE,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX attachments_todo_id_idx ON attachments (todo_id);
@@ -0,0 +1,19 @@
+-- 同期用の変更フィード
+-- Todoを削除しても行を残し、deleted_at を持つトゥームストーンとして扱う
+CREATE SEQUENCE sync_cursor_seq;
+
+CREATE TABLE sync_todos
+(
+    todo_id    INTEGER PRIMARY KEY,
+    version    BIGINT      NOT NULL,
+    cursor     BIGINT      NOT NULL,
+    deleted_at TIMESTAMPTZ,
+    -- フィールドごとの最終更新version・時刻
+    fields     JSONB       NOT NULL DEFAULT '{}'
+);
+
+CREATE INDEX sync_todos_cursor_idx ON sync_todos (cursor);
+
+-- 既存のTodoも初回の同期で取り込めるようにする
+INSERT INTO sync_todos (todo_id, version, cursor)
+SELECT id, 1, nextval('sync_cursor_seq') FROM todos ORDER BY id;
@@ -0,0 +1,7 @@
+-- ラベルの色・説明・アイコン・並び順
+-- 既存のラベルは既定の色で、並び順が同じためID順に並ぶ
+ALTER TABLE labels
+    ADD COLUMN color       TEXT    NOT NULL DEFAULT '#808080',
+    ADD COLUMN description TEXT,
+    ADD COLUMN icon        TEXT,
+    ADD COLUMN position    INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,7 @@
+-- ラベルの階層と排他的なグループ
+-- 親を削除すると子は最上位のラベルになる
+ALTER TABLE labels
+    ADD COLUMN parent_id INTEGER REFERENCES labels (id) ON DELETE SET NULL,
+    ADD COLUMN exclusive BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX labels_parent_id_idx ON labels (parent_id);
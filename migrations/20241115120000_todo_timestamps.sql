@@ -0,0 +1,5 @@
+-- Todoの作成・完了日時 (統計に使う)
+-- 既存のTodoの作成日時はマイグレーションの実行日時、完了日時は不明なためNULLにする
+ALTER TABLE todos
+    ADD COLUMN created_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN completed_at TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+-- Todoの詳細を書くメモ (Markdown)
+ALTER TABLE todos ADD COLUMN notes TEXT;
@@ -0,0 +1,10 @@
+-- Todoの依存関係。todo_id のTodoは blocker_id のTodoが完了するまでブロックされる
+CREATE TABLE todo_dependencies
+(
+    todo_id    INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+    blocker_id INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+    PRIMARY KEY (todo_id, blocker_id),
+    CHECK (todo_id <> blocker_id)
+);
+
+CREATE INDEX todo_dependencies_blocker_id_idx ON todo_dependencies (blocker_id);
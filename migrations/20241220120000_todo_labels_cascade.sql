@@ -0,0 +1,5 @@
+-- ラベルを削除するとTodoとの紐付けも削除する
+ALTER TABLE todo_labels
+    DROP CONSTRAINT todo_labels_label_id_fkey,
+    ADD CONSTRAINT todo_labels_label_id_fkey
+        FOREIGN KEY (label_id) REFERENCES labels (id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED;
@@ -0,0 +1,6 @@
+-- Todoが残っているプロジェクトは削除できないようにする
+-- (cascadeではWebhookやトゥームストーンを記録せずにTodoが消えるため)
+ALTER TABLE todos
+    DROP CONSTRAINT todos_project_id_fkey,
+    ADD CONSTRAINT todos_project_id_fkey
+        FOREIGN KEY (project_id) REFERENCES projects (id) ON DELETE RESTRICT;
@@ -0,0 +1,26 @@
+-- MySQL/MariaDB用のスキーマ
+-- TodoとラベルだけをPostgres版と同じ列構成で持つ
+CREATE TABLE todos
+(
+    id         INT     NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    text       TEXT    NOT NULL,
+    completed  BOOLEAN NOT NULL DEFAULT false,
+    project_id INT     NOT NULL DEFAULT 1,
+    status_id  INT,
+    INDEX todos_project_id_idx (project_id)
+);
+
+CREATE TABLE labels
+(
+    id   INT          NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(255) NOT NULL
+);
+
+CREATE TABLE todo_labels
+(
+    id       INT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    todo_id  INT NOT NULL,
+    label_id INT NOT NULL,
+    FOREIGN KEY (todo_id) REFERENCES todos (id) ON DELETE CASCADE,
+    FOREIGN KEY (label_id) REFERENCES labels (id) ON DELETE CASCADE
+);
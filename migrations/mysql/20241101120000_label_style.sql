@@ -0,0 +1,6 @@
+-- ラベルの色・説明・アイコン・並び順
+ALTER TABLE labels
+    ADD COLUMN color       VARCHAR(7)   NOT NULL DEFAULT '#808080',
+    ADD COLUMN description TEXT,
+    ADD COLUMN icon        VARCHAR(255),
+    ADD COLUMN position    INT          NOT NULL DEFAULT 0;
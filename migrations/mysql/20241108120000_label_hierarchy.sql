@@ -0,0 +1,5 @@
+-- ラベルの階層と排他的なグループ
+ALTER TABLE labels
+    ADD COLUMN parent_id INT,
+    ADD COLUMN exclusive BOOLEAN NOT NULL DEFAULT false,
+    ADD FOREIGN KEY (parent_id) REFERENCES labels (id) ON DELETE SET NULL;
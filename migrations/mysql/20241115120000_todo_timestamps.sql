@@ -0,0 +1,4 @@
+-- Todoの作成・完了日時 (統計に使う)
+ALTER TABLE todos
+    ADD COLUMN created_at   DATETIME(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+    ADD COLUMN completed_at DATETIME(6);
@@ -0,0 +1,8 @@
+-- Todoの更新日時と、ラベルの作成・更新日時
+ALTER TABLE todos
+    ADD COLUMN updated_at DATETIME(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6);
+UPDATE todos SET updated_at = COALESCE(completed_at, created_at);
+
+ALTER TABLE labels
+    ADD COLUMN created_at DATETIME(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6),
+    ADD COLUMN updated_at DATETIME(6) NOT NULL DEFAULT CURRENT_TIMESTAMP(6);
@@ -0,0 +1,2 @@
+-- Todoの詳細を書くメモ (Markdown)。TEXTでは上限の文字数を保存できない場合がある
+ALTER TABLE todos ADD COLUMN notes MEDIUMTEXT;
@@ -0,0 +1,4 @@
+-- Todoの優先度 (1: low 〜 4: urgent) と期限
+ALTER TABLE todos
+    ADD COLUMN priority INT CHECK (priority BETWEEN 1 AND 4),
+    ADD COLUMN due_at DATETIME(6);
@@ -0,0 +1,10 @@
+-- Todoの依存関係。todo_id のTodoは blocker_id のTodoが完了するまでブロックされる
+CREATE TABLE todo_dependencies
+(
+    todo_id    INT NOT NULL,
+    blocker_id INT NOT NULL,
+    PRIMARY KEY (todo_id, blocker_id),
+    INDEX todo_dependencies_blocker_id_idx (blocker_id),
+    FOREIGN KEY (todo_id) REFERENCES todos (id) ON DELETE CASCADE,
+    FOREIGN KEY (blocker_id) REFERENCES todos (id) ON DELETE CASCADE
+);
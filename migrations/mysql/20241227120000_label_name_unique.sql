@@ -0,0 +1,28 @@
+-- ラベル名の重複は同じ親の下だけで判定する (全角・半角の違いはアプリケーションで判定する)
+-- すでに同じ親の下で大文字・小文字だけが違うラベルがあれば、最初に作成したラベルにまとめる
+-- (Todoの付与と子ラベルは残すラベルに移す)
+CREATE TABLE label_merges AS
+SELECT id AS source_id,
+       min(id) OVER (PARTITION BY coalesce(parent_id, 0), lower(name)) AS target_id
+FROM labels;
+DELETE FROM label_merges WHERE source_id = target_id;
+
+INSERT INTO todo_labels (todo_id, label_id)
+SELECT DISTINCT todo_labels.todo_id, label_merges.target_id
+FROM todo_labels
+JOIN label_merges ON todo_labels.label_id = label_merges.source_id
+WHERE NOT EXISTS (
+    SELECT 1 FROM todo_labels AS assigned
+    WHERE assigned.todo_id = todo_labels.todo_id AND assigned.label_id = label_merges.target_id
+);
+DELETE FROM todo_labels WHERE label_id IN (SELECT source_id FROM label_merges);
+
+UPDATE labels SET parent_id = (
+    SELECT target_id FROM label_merges WHERE source_id = labels.parent_id
+)
+WHERE parent_id IN (SELECT source_id FROM label_merges);
+DELETE FROM labels WHERE id IN (SELECT source_id FROM label_merges);
+DROP TABLE label_merges;
+
+-- 関数インデックスはMySQL 8.0.13以降で使える。name は大文字・小文字を区別しない照合順序で比較する
+CREATE UNIQUE INDEX labels_parent_id_name_key ON labels ((coalesce(parent_id, 0)), name);
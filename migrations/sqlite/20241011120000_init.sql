@@ -0,0 +1,25 @@
+-- ローカルモード(SQLite)用のスキーマ
+-- TodoとラベルだけをPostgres版と同じ列構成で持つ
+CREATE TABLE todos
+(
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    text       TEXT    NOT NULL,
+    completed  BOOLEAN NOT NULL DEFAULT false,
+    project_id INTEGER NOT NULL DEFAULT 1,
+    status_id  INTEGER
+);
+
+CREATE INDEX todos_project_id_idx ON todos (project_id);
+
+CREATE TABLE labels
+(
+    id   INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE todo_labels
+(
+    id       INTEGER PRIMARY KEY AUTOINCREMENT,
+    todo_id  INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+    label_id INTEGER NOT NULL REFERENCES labels (id) ON DELETE CASCADE
+);
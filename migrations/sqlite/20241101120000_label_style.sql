@@ -0,0 +1,5 @@
+-- ラベルの色・説明・アイコン・並び順
+ALTER TABLE labels ADD COLUMN color TEXT NOT NULL DEFAULT '#808080';
+ALTER TABLE labels ADD COLUMN description TEXT;
+ALTER TABLE labels ADD COLUMN icon TEXT;
+ALTER TABLE labels ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- ラベルの階層と排他的なグループ
+ALTER TABLE labels ADD COLUMN parent_id INTEGER REFERENCES labels (id) ON DELETE SET NULL;
+ALTER TABLE labels ADD COLUMN exclusive BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,5 @@
+-- Todoの作成・完了日時 (統計に使う)
+-- ADD COLUMN では現在日時を既定値にできないため、既存のTodoは後から更新する
+ALTER TABLE todos ADD COLUMN created_at TEXT NOT NULL DEFAULT '1970-01-01T00:00:00Z';
+ALTER TABLE todos ADD COLUMN completed_at TEXT;
+UPDATE todos SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now');
@@ -0,0 +1,10 @@
+-- Todoの更新日時と、ラベルの作成・更新日時
+-- ADD COLUMN では現在日時を既定値にできないため、既存の行は後から更新する
+ALTER TABLE todos ADD COLUMN updated_at TEXT NOT NULL DEFAULT '1970-01-01T00:00:00Z';
+UPDATE todos SET updated_at = COALESCE(completed_at, created_at);
+
+ALTER TABLE labels ADD COLUMN created_at TEXT NOT NULL DEFAULT '1970-01-01T00:00:00Z';
+ALTER TABLE labels ADD COLUMN updated_at TEXT NOT NULL DEFAULT '1970-01-01T00:00:00Z';
+UPDATE labels SET
+    created_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now'),
+    updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now');
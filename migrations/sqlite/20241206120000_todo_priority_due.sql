@@ -0,0 +1,3 @@
+-- Todoの優先度 (1: low 〜 4: urgent) と期限
+ALTER TABLE todos ADD COLUMN priority INTEGER CHECK (priority BETWEEN 1 AND 4);
+ALTER TABLE todos ADD COLUMN due_at TEXT;
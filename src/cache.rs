@@ -0,0 +1,579 @@
+pub mod local;
+pub mod redis;
+
+use self::redis::RedisCache;
+use crate::repositories::{
+    comment::{Comment, CommentRepository, CreateComment, UpdateComment},
+    label::{CreateLabel, DeleteLabel, Label, LabelRepository, UpdateLabel},
+    project::{CreateProject, Project, ProjectRepository, UpdateProject},
+    status::{CreateStatus, Status, StatusRepository, UpdateStatus},
+    todo::{CreateTodo, TodoEntity, TodoRepository, UpdateTodo},
+};
+use axum::async_trait;
+use local::LocalCache;
+use serde::{de::DeserializeOwned, Serialize};
+use shuttle_runtime::SecretStore;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+const DEFAULT_TTL: Duration = Duration::from_secs(60);
+const DEFAULT_CAPACITY: usize = 1000;
+// 書き込みのたびに世代を変え、古い世代のキーを参照しないようにする
+const GENERATION_KEY: &str = "todos:generation";
+
+/// キャッシュの保存先
+#[async_trait]
+pub trait CacheStore: Clone + std::marker::Send + std::marker::Sync + 'static {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<String>>;
+    /// `ttl` がNoneの場合は期限なしで保存する
+    async fn set(&self, key: &str, value: String, ttl: Option<Duration>) -> anyhow::Result<()>;
+}
+
+/// Secretsの設定で選択されるキャッシュ
+///
+/// `REDIS_URL` があればRedis、なければプロセス内のLRU(`CACHE_CAPACITY` 件、既定は1000件)を使う
+#[derive(Debug, Clone)]
+pub enum CacheBackend {
+    Local(LocalCache),
+    Redis(RedisCache),
+}
+
+impl CacheBackend {
+    pub async fn from_secrets(secrets: &SecretStore) -> anyhow::Result<Self> {
+        match secrets.get("REDIS_URL") {
+            Some(url) => Ok(CacheBackend::Redis(RedisCache::connect(&url).await?)),
+            None => {
+                let capacity = match secrets.get("CACHE_CAPACITY") {
+                    Some(capacity) => capacity.parse()?,
+                    None => DEFAULT_CAPACITY,
+                };
+                Ok(CacheBackend::Local(LocalCache::new(capacity)))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl CacheStore for CacheBackend {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        match self {
+            CacheBackend::Local(cache) => cache.get(key).await,
+            CacheBackend::Redis(cache) => cache.get(key).await,
+        }
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Option<Duration>) -> anyhow::Result<()> {
+        match self {
+            CacheBackend::Local(cache) => cache.set(key, value, ttl).await,
+            CacheBackend::Redis(cache) => cache.set(key, value, ttl).await,
+        }
+    }
+}
+
+/// `CACHE_TTL_SECS` (既定は60秒) をキャッシュの有効期限にする
+pub fn ttl_from_secrets(secrets: &SecretStore) -> anyhow::Result<Duration> {
+    match secrets.get("CACHE_TTL_SECS") {
+        Some(secs) => Ok(Duration::from_secs(secs.parse()?)),
+        None => Ok(DEFAULT_TTL),
+    }
+}
+
+#[derive(Debug, Default)]
+struct CacheMetrics {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl CacheMetrics {
+    fn failed(&self, e: anyhow::Error) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!("cache unavailable: {:?}", e);
+    }
+}
+
+// 追い出されて世代が消えても古いキーに戻らないよう、連番ではなく乱数にする
+async fn next_generation<C: CacheStore>(cache: &C) -> anyhow::Result<String> {
+    let generation = format!("{:016x}", rand::random::<u64>());
+    cache.set(GENERATION_KEY, generation.clone(), None).await?;
+    Ok(generation)
+}
+
+/// キャッシュのヒット・ミスの件数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// キャッシュの読み書きに失敗した件数 (失敗してもリポジトリから読んで応答する)
+    pub errors: u64,
+}
+
+impl CacheStats {
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// 任意のTodoRepositoryの読み込みをキャッシュするデコレーター
+///
+/// - `find`・`all`・`all_in_project` の結果をTTL付きで保存する
+/// - 作成・更新・削除のたびに世代を変えて、それまでのキャッシュをすべて無効にする
+/// - キャッシュが使えない場合はリポジトリから読んで応答する
+///
+/// ラベル・コメント・プロジェクト・ステータスの書き込みもTodoの読み込み結果を変えるため、`invalidator` を渡した `InvalidateTodoCache` を通す
+#[derive(Debug, Clone)]
+pub struct CachedTodoRepository<T: TodoRepository, C: CacheStore> {
+    inner: T,
+    cache: C,
+    ttl: Duration,
+    metrics: Arc<CacheMetrics>,
+}
+
+impl<T: TodoRepository, C: CacheStore> CachedTodoRepository<T, C> {
+    pub fn new(inner: T, cache: C, ttl: Duration) -> Self {
+        Self {
+            inner,
+            cache,
+            ttl,
+            metrics: Arc::default(),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.metrics.hits.load(Ordering::Relaxed),
+            misses: self.metrics.misses.load(Ordering::Relaxed),
+            errors: self.metrics.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Todo以外の書き込みでキャッシュを無効にするためのハンドル
+    pub fn invalidator(&self) -> TodoCacheInvalidator<C> {
+        TodoCacheInvalidator {
+            cache: self.cache.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+
+    /// それまでのキャッシュをすべて無効にする
+    pub async fn invalidate(&self) {
+        self.invalidator().invalidate().await;
+    }
+
+    fn failed(&self, e: anyhow::Error) {
+        self.metrics.failed(e);
+    }
+
+    async fn generation(&self) -> anyhow::Result<String> {
+        match self.cache.get(GENERATION_KEY).await? {
+            Some(generation) => Ok(generation),
+            None => next_generation(&self.cache).await,
+        }
+    }
+
+    // 世代はリポジトリから読む前に取得する
+    // 読んでいる間に書き込みがあっても、古い値は古い世代のキーに保存されるため参照されない
+    async fn cached<V, F>(&self, key: &str, fetch: F) -> anyhow::Result<V>
+    where
+        V: Serialize + DeserializeOwned,
+        F: Future<Output = anyhow::Result<V>> + Send,
+    {
+        let generation = match self.generation().await {
+            Ok(generation) => generation,
+            Err(e) => {
+                self.failed(e);
+                return fetch.await;
+            }
+        };
+        let key = format!("todos:{}:{}", generation, key);
+
+        match self.cache.get(&key).await {
+            Ok(Some(value)) => match serde_json::from_str(&value) {
+                Ok(value) => {
+                    self.metrics.hits.fetch_add(1, Ordering::Relaxed);
+                    tracing::debug!("cache hit [{}]", key);
+                    return Ok(value);
+                }
+                Err(e) => self.failed(e.into()),
+            },
+            Ok(None) => {}
+            Err(e) => self.failed(e),
+        }
+        self.metrics.misses.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!("cache miss [{}]", key);
+
+        let value = fetch.await?;
+        let stored = match serde_json::to_string(&value) {
+            Ok(json) => self.cache.set(&key, json, Some(self.ttl)).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = stored {
+            self.failed(e);
+        }
+        Ok(value)
+    }
+}
+
+#[async_trait]
+impl<T: TodoRepository, C: CacheStore> TodoRepository for CachedTodoRepository<T, C> {
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity> {
+        let todo = self.inner.create(payload).await?;
+        self.invalidate().await;
+        Ok(todo)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<TodoEntity> {
+        self.cached(&format!("todo:{}", id), self.inner.find(id))
+            .await
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<TodoEntity>> {
+        self.cached("all", self.inner.all()).await
+    }
+
+    async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<TodoEntity>> {
+        self.cached(
+            &format!("project:{}", project_id),
+            self.inner.all_in_project(project_id),
+        )
+        .await
+    }
+
+    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity> {
+        let result = self.inner.update(id, payload).await;
+        // 失敗しても一部が書き込まれている可能性があるため、常に無効にする
+        self.invalidate().await;
+        result
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        let result = self.inner.delete(id).await;
+        self.invalidate().await;
+        result
+    }
+
+    async fn merge_labels(&self, target: i32, sources: &[i32]) -> anyhow::Result<()> {
+        let result = self.inner.merge_labels(target, sources).await;
+        self.invalidate().await;
+        result
+    }
+
+    async fn add_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        let result = self.inner.add_dependency(id, blocker_id).await;
+        self.invalidate().await;
+        result
+    }
+
+    async fn remove_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        let result = self.inner.remove_dependency(id, blocker_id).await;
+        self.invalidate().await;
+        result
+    }
+}
+
+/// `CachedTodoRepository` のキャッシュの世代を変えるハンドル
+#[derive(Debug, Clone)]
+pub struct TodoCacheInvalidator<C: CacheStore> {
+    cache: C,
+    metrics: Arc<CacheMetrics>,
+}
+
+impl<C: CacheStore> TodoCacheInvalidator<C> {
+    pub async fn invalidate(&self) {
+        if let Err(e) = next_generation(&self.cache).await {
+            self.metrics.failed(e);
+        }
+    }
+}
+
+/// 書き込みのたびにTodoのキャッシュを無効にするデコレーター
+///
+/// ラベルの変更・削除とコメントの作成・削除は、Todoに含まれるラベルやコメント数を変えるため。
+/// プロジェクトとステータスの削除も、外部キーで所属するTodoやその `status_id` を変える
+#[derive(Debug, Clone)]
+pub struct InvalidateTodoCache<R, C: CacheStore> {
+    inner: R,
+    invalidator: TodoCacheInvalidator<C>,
+}
+
+impl<R, C: CacheStore> InvalidateTodoCache<R, C> {
+    pub fn new(inner: R, invalidator: TodoCacheInvalidator<C>) -> Self {
+        Self { inner, invalidator }
+    }
+}
+
+#[async_trait]
+impl<L: LabelRepository, C: CacheStore> LabelRepository for InvalidateTodoCache<L, C> {
+    async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label> {
+        self.inner.create(payload).await
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<Label>> {
+        self.inner.all().await
+    }
+
+    async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label> {
+        let result = self.inner.update(id, payload).await;
+        self.invalidator.invalidate().await;
+        result
+    }
+
+    async fn delete(&self, id: i32, mode: DeleteLabel) -> anyhow::Result<Vec<i32>> {
+        let result = self.inner.delete(id, mode).await;
+        self.invalidator.invalidate().await;
+        result
+    }
+}
+
+#[async_trait]
+impl<M: CommentRepository, C: CacheStore> CommentRepository for InvalidateTodoCache<M, C> {
+    async fn create(
+        &self,
+        todo_id: i32,
+        author: String,
+        payload: CreateComment,
+    ) -> anyhow::Result<Comment> {
+        let result = self.inner.create(todo_id, author, payload).await;
+        self.invalidator.invalidate().await;
+        result
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Comment> {
+        self.inner.find(id).await
+    }
+
+    async fn all_in_todo(&self, todo_id: i32) -> anyhow::Result<Vec<Comment>> {
+        self.inner.all_in_todo(todo_id).await
+    }
+
+    async fn update(&self, id: i32, payload: UpdateComment) -> anyhow::Result<Comment> {
+        self.inner.update(id, payload).await
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        let result = self.inner.delete(id).await;
+        self.invalidator.invalidate().await;
+        result
+    }
+}
+
+#[async_trait]
+impl<P: ProjectRepository, C: CacheStore> ProjectRepository for InvalidateTodoCache<P, C> {
+    async fn create(&self, payload: CreateProject) -> anyhow::Result<Project> {
+        self.inner.create(payload).await
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Project> {
+        self.inner.find(id).await
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<Project>> {
+        self.inner.all().await
+    }
+
+    async fn update(&self, id: i32, payload: UpdateProject) -> anyhow::Result<Project> {
+        let result = self.inner.update(id, payload).await;
+        self.invalidator.invalidate().await;
+        result
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        let result = self.inner.delete(id).await;
+        self.invalidator.invalidate().await;
+        result
+    }
+}
+
+#[async_trait]
+impl<S: StatusRepository, C: CacheStore> StatusRepository for InvalidateTodoCache<S, C> {
+    async fn create(&self, project_id: i32, payload: CreateStatus) -> anyhow::Result<Status> {
+        self.inner.create(project_id, payload).await
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Status> {
+        self.inner.find(id).await
+    }
+
+    async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<Status>> {
+        self.inner.all_in_project(project_id).await
+    }
+
+    async fn update(&self, id: i32, payload: UpdateStatus) -> anyhow::Result<Status> {
+        let result = self.inner.update(id, payload).await;
+        self.invalidator.invalidate().await;
+        result
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        let result = self.inner.delete(id).await;
+        self.invalidator.invalidate().await;
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::repositories::{
+        comment::test_utils::CommentRepositoryForMemory,
+        label::test_utils::LabelRepositoryForMemory,
+        project::test_utils::ProjectRepositoryForMemory,
+        status::test_utils::StatusRepositoryForMemory, todo::test_utils::TodoRepositoryForMemory,
+    };
+
+    #[tokio::test]
+    async fn cached_todo_repository_scenario() {
+        let repository = CachedTodoRepository::new(
+            TodoRepositoryForMemory::new(vec![]),
+            LocalCache::new(100),
+            Duration::from_secs(60),
+        );
+
+        // 1回目はミス、2回目はヒット
+        let todo = repository
+            .create(CreateTodo::new("cached".to_string(), vec![]))
+            .await
+            .unwrap();
+        assert_eq!(repository.all().await.unwrap(), vec![todo.clone()]);
+        assert_eq!(repository.all().await.unwrap(), vec![todo.clone()]);
+        assert_eq!(repository.find(todo.id).await.unwrap(), todo);
+        assert_eq!(repository.find(todo.id).await.unwrap(), todo);
+        assert_eq!(
+            repository.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 2,
+                errors: 0
+            }
+        );
+
+        // 更新すると古い値は返さない
+        let updated = repository
+            .update(
+                todo.id,
+                UpdateTodo {
+                    completed: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(repository.find(todo.id).await.unwrap(), updated);
+        assert_eq!(repository.all().await.unwrap(), vec![updated.clone()]);
+
+        // 作成と削除でも一覧が更新される
+        let other = repository
+            .create(CreateTodo::new("other".to_string(), vec![]))
+            .await
+            .unwrap();
+        assert_eq!(repository.all().await.unwrap().len(), 2);
+        repository.delete(other.id).await.unwrap();
+        assert_eq!(repository.all().await.unwrap(), vec![updated.clone()]);
+        assert!(repository.find(other.id).await.is_err());
+
+        let stats = repository.stats();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 7);
+    }
+
+    #[tokio::test]
+    async fn should_invalidate_on_label_and_comment_writes() {
+        let work = Label::new(1, "work".to_string());
+        let comments = CommentRepositoryForMemory::new();
+        let inner =
+            TodoRepositoryForMemory::new(vec![work.clone()]).with_comments(comments.clone());
+        let repository =
+            CachedTodoRepository::new(inner.clone(), LocalCache::new(100), Duration::from_secs(60));
+        let labels = InvalidateTodoCache::new(
+            LabelRepositoryForMemory::with_labels(vec![work.clone()]).with_todos(inner),
+            repository.invalidator(),
+        );
+        let comments = InvalidateTodoCache::new(comments, repository.invalidator());
+        let todo = repository
+            .create(CreateTodo::new("cached".to_string(), vec![work.id]))
+            .await
+            .unwrap();
+        assert_eq!(repository.find(todo.id).await.unwrap().comment_count, 0);
+
+        // コメントの作成・削除でコメント数が変わる
+        let comment = comments
+            .create(
+                todo.id,
+                "author".to_string(),
+                CreateComment {
+                    body: "comment".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(repository.find(todo.id).await.unwrap().comment_count, 1);
+        comments.delete(comment.id).await.unwrap();
+        assert_eq!(repository.find(todo.id).await.unwrap().comment_count, 0);
+
+        // ラベルを削除するとTodoから外れる
+        labels.delete(work.id, DeleteLabel::Detach).await.unwrap();
+        assert!(repository.find(todo.id).await.unwrap().labels.is_empty());
+        assert_eq!(repository.stats().hits, 0);
+
+        // プロジェクトとステータスの削除も、所属するTodoを変えるため無効にする
+        let projects =
+            InvalidateTodoCache::new(ProjectRepositoryForMemory::new(), repository.invalidator());
+        let statuses =
+            InvalidateTodoCache::new(StatusRepositoryForMemory::new(), repository.invalidator());
+        let project = projects
+            .create(CreateProject {
+                name: "project".to_string(),
+            })
+            .await
+            .unwrap();
+        let status = statuses
+            .create(
+                project.id,
+                CreateStatus {
+                    name: "doing".to_string(),
+                    position: None,
+                    is_done: false,
+                    wip_limit: None,
+                },
+            )
+            .await
+            .unwrap();
+        repository.find(todo.id).await.unwrap();
+        statuses.delete(status.id).await.unwrap();
+        repository.find(todo.id).await.unwrap();
+        projects.delete(project.id).await.unwrap();
+        repository.find(todo.id).await.unwrap();
+        assert_eq!(repository.stats().hits, 1);
+    }
+
+    #[tokio::test]
+    async fn should_expire_cached_entries() {
+        let inner = TodoRepositoryForMemory::new(vec![]);
+        let repository = CachedTodoRepository::new(
+            inner.clone(),
+            LocalCache::new(100),
+            Duration::from_millis(20),
+        );
+        assert!(repository.all().await.unwrap().is_empty());
+
+        // キャッシュを通さずに書き込んだ値は、TTLが切れるまで見えない
+        inner
+            .create(CreateTodo::new("direct".to_string(), vec![]))
+            .await
+            .unwrap();
+        assert!(repository.all().await.unwrap().is_empty());
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(repository.all().await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,86 @@
+use super::CacheStore;
+use axum::async_trait;
+use lru::LruCache;
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+struct Entry {
+    value: String,
+    expires_at: Option<Instant>,
+}
+
+/// プロセス内のLRUキャッシュ
+///
+/// 容量を超えると最も古く参照されたキーから追い出す。期限切れのキーは参照時に削除する
+#[derive(Debug, Clone)]
+pub struct LocalCache {
+    entries: Arc<Mutex<LruCache<String, Entry>>>,
+}
+
+impl LocalCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Arc::new(Mutex::new(LruCache::new(capacity))),
+        }
+    }
+}
+
+#[async_trait]
+impl CacheStore for LocalCache {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let mut entries = self.entries.lock().unwrap();
+        let expired = match entries.get(key) {
+            None => return Ok(None),
+            Some(entry) => entry
+                .expires_at
+                .is_some_and(|expires_at| expires_at <= Instant::now()),
+        };
+        if expired {
+            entries.pop(key);
+            return Ok(None);
+        }
+        Ok(entries.get(key).map(|entry| entry.value.clone()))
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Option<Duration>) -> anyhow::Result<()> {
+        let entry = Entry {
+            value,
+            expires_at: ttl.map(|ttl| Instant::now() + ttl),
+        };
+        self.entries.lock().unwrap().put(key.to_string(), entry);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_cache_scenario() {
+        let cache = LocalCache::new(2);
+        cache.set("a", "1".to_string(), None).await.unwrap();
+        cache.set("b", "2".to_string(), None).await.unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), Some("1".to_string()));
+
+        // 容量を超えると最も古く参照された b が追い出される
+        cache.set("c", "3".to_string(), None).await.unwrap();
+        assert_eq!(cache.get("b").await.unwrap(), None);
+        assert_eq!(cache.get("a").await.unwrap(), Some("1".to_string()));
+        assert_eq!(cache.get("c").await.unwrap(), Some("3".to_string()));
+
+        // TTLを過ぎたキーは返さない
+        cache
+            .set("a", "10".to_string(), Some(Duration::from_millis(20)))
+            .await
+            .unwrap();
+        assert_eq!(cache.get("a").await.unwrap(), Some("10".to_string()));
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(cache.get("a").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,72 @@
+use super::CacheStore;
+use axum::async_trait;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use std::time::Duration;
+
+/// Redisに保存する。複数のインスタンスでキャッシュを共有できる
+#[derive(Clone)]
+pub struct RedisCache {
+    // 切断されても次のコマンドで再接続する
+    connection: ConnectionManager,
+}
+
+impl std::fmt::Debug for RedisCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisCache").finish_non_exhaustive()
+    }
+}
+
+impl RedisCache {
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url)?;
+        Ok(Self {
+            connection: ConnectionManager::new(client).await?,
+        })
+    }
+}
+
+#[async_trait]
+impl CacheStore for RedisCache {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<String>> {
+        let mut connection = self.connection.clone();
+        Ok(connection.get(key).await?)
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Option<Duration>) -> anyhow::Result<()> {
+        let mut connection = self.connection.clone();
+        match ttl {
+            Some(ttl) => {
+                let millis = u64::try_from(ttl.as_millis())?.max(1);
+                connection.pset_ex(key, value, millis).await?
+            }
+            None => connection.set(key, value).await?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "redis-test")]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn redis_cache_scenario() {
+        let url = std::env::var("REDIS_URL").unwrap_or("redis://127.0.0.1:6379".to_string());
+        let cache = RedisCache::connect(&url)
+            .await
+            .unwrap_or_else(|_| panic!("fail connect redis, url is [{}]", url));
+        let key = format!("todo-cache-test-{}", rand::random::<u32>());
+
+        assert_eq!(cache.get(&key).await.unwrap(), None);
+        cache
+            .set(&key, "value".to_string(), Some(Duration::from_millis(200)))
+            .await
+            .unwrap();
+        assert_eq!(cache.get(&key).await.unwrap(), Some("value".to_string()));
+
+        // TTLを過ぎると消える
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(cache.get(&key).await.unwrap(), None);
+    }
+}
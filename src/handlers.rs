@@ -1,2 +1,19 @@
+pub mod attachment;
+pub mod comment;
 pub mod label;
+pub mod project;
+pub mod stats;
+pub mod status;
+pub mod sync;
 pub mod todo;
+pub mod webhook;
+
+use axum::Extension;
+use std::sync::Arc;
+
+/// 構成によって登録しないリポジトリ (`ApiScope` を参照) のExtensionから中身を取り出す
+fn optional<T>(extension: &Option<Extension<Arc<T>>>) -> Option<&T> {
+    extension
+        .as_ref()
+        .map(|Extension(repository)| &**repository)
+}
@@ -0,0 +1,312 @@
+use crate::repositories::{
+    attachment::{Attachment, AttachmentRepository, CreateAttachment},
+    todo::TodoRepository,
+};
+use crate::storage::{BlobStorage, StorageError};
+use axum::{
+    extract::{Multipart, Path},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+// アップロードできるファイルの最大サイズ (10MiB)
+pub const MAX_ATTACHMENT_SIZE: usize = 10 * 1024 * 1024;
+// アップロードを受け付けるMIMEタイプ
+const ALLOWED_CONTENT_TYPES: [&str; 9] = [
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "application/pdf",
+    "application/zip",
+    "text/plain",
+    "text/markdown",
+    "text/csv",
+];
+const FILE_FIELD: &str = "file";
+
+const ERR_STR_NO_FILE: &str = "Error!: multipart field `file` is required";
+const ERR_STR_TOO_LARGE: &str = "Error!: File is too large";
+const ERR_STR_UNSUPPORTED_TYPE: &str = "Error!: Unsupported file type";
+const ERR_STR_NOT_FOUND: &str = "Attachment not found";
+
+/// クライアントから送られたファイル名からディレクトリ部分を取り除く
+fn sanitize_filename(filename: Option<&str>) -> String {
+    let name = filename
+        .and_then(|name| name.rsplit(['/', '\\']).next())
+        .map(str::trim)
+        .unwrap_or_default();
+    match name {
+        "" | "." | ".." => "file".to_string(),
+        name => name.chars().filter(|c| !c.is_control()).take(255).collect(),
+    }
+}
+
+/// パートのContent-Typeがなければ拡張子から推測する
+fn resolve_content_type(content_type: Option<&str>, filename: &str) -> String {
+    let content_type = match content_type {
+        Some(content_type) if content_type != mime::APPLICATION_OCTET_STREAM.as_ref() => {
+            content_type.to_string()
+        }
+        _ => mime_guess::from_path(filename)
+            .first_or_octet_stream()
+            .to_string(),
+    };
+    // パラメータ (charsetなど) は比較に含めない
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// 画像はブラウザで表示し、それ以外はダウンロードさせる
+fn content_disposition(content_type: &str, filename: &str) -> String {
+    let disposition = if content_type.starts_with("image/") {
+        "inline"
+    } else {
+        "attachment"
+    };
+    let fallback: String = filename
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+    format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        disposition,
+        fallback,
+        utf8_percent_encode(filename, NON_ALPHANUMERIC)
+    )
+}
+
+/// OpenAPIドキュメント用のmultipartの形式
+#[allow(dead_code)]
+#[derive(ToSchema)]
+struct UploadAttachment {
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+fn storage_key(todo_id: i32) -> String {
+    let bytes: [u8; 16] = rand::random();
+    format!("todos/{}/{}", todo_id, hex::encode(bytes))
+}
+
+#[utoipa::path(
+    post, path = "/todos/{id}/attachments", tag = "attachments",
+    params(("id" = i32, Path, description = "Todo ID")),
+    request_body(content = UploadAttachment, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, body = Attachment),
+        (status = 400, description = "fileフィールドがない"),
+        (status = 404, description = "Todoが存在しない"),
+        (status = 413, description = "ファイルサイズが上限を超える"),
+        (status = 415, description = "受け付けないファイル形式"),
+    )
+)]
+pub async fn create_attachment<T: TodoRepository, A: AttachmentRepository, S: BlobStorage>(
+    Path(todo_id): Path<i32>,
+    Extension(todos): Extension<Arc<T>>,
+    Extension(repository): Extension<Arc<A>>,
+    Extension(storage): Extension<Arc<S>>,
+    mut multipart: Multipart,
+) -> Result<Response, StatusCode> {
+    todos.find(todo_id).await.or(Err(StatusCode::NOT_FOUND))?;
+
+    loop {
+        let mut field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => {
+                return Ok((StatusCode::BAD_REQUEST, ERR_STR_NO_FILE.to_string()).into_response())
+            }
+            Err(e) => return Ok((e.status(), e.body_text()).into_response()),
+        };
+        if field.name() != Some(FILE_FIELD) {
+            continue;
+        }
+
+        let filename = sanitize_filename(field.file_name());
+        let content_type = resolve_content_type(field.content_type(), &filename);
+        if !ALLOWED_CONTENT_TYPES.contains(&content_type.as_str()) {
+            return Ok((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                ERR_STR_UNSUPPORTED_TYPE.to_string(),
+            )
+                .into_response());
+        }
+
+        let mut data = Vec::new();
+        loop {
+            match field.chunk().await {
+                Ok(Some(chunk)) => {
+                    if data.len() + chunk.len() > MAX_ATTACHMENT_SIZE {
+                        return Ok(
+                            (StatusCode::PAYLOAD_TOO_LARGE, ERR_STR_TOO_LARGE.to_string())
+                                .into_response(),
+                        );
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+                Ok(None) => break,
+                Err(e) => return Ok((e.status(), e.body_text()).into_response()),
+            }
+        }
+
+        let key = storage_key(todo_id);
+        let size = data.len() as i64;
+        storage
+            .put(&key, data, &content_type)
+            .await
+            .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+        let created = repository
+            .create(CreateAttachment {
+                todo_id,
+                filename,
+                content_type,
+                size,
+                storage_key: key.clone(),
+            })
+            .await;
+        return match created {
+            Ok(attachment) => Ok((StatusCode::CREATED, Json(attachment)).into_response()),
+            Err(_) => {
+                // メタデータが保存できなければBlobも残さない
+                let _ = storage.delete(&key).await;
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        };
+    }
+}
+
+#[utoipa::path(
+    get, path = "/todos/{id}/attachments", tag = "attachments",
+    params(("id" = i32, Path, description = "Todo ID")),
+    responses(
+        (status = 200, body = Vec<Attachment>),
+        (status = 404, description = "Todoが存在しない"),
+    )
+)]
+pub async fn all_attachment<T: TodoRepository, A: AttachmentRepository>(
+    Path(todo_id): Path<i32>,
+    Extension(todos): Extension<Arc<T>>,
+    Extension(repository): Extension<Arc<A>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    todos.find(todo_id).await.or(Err(StatusCode::NOT_FOUND))?;
+    let attachments = repository
+        .all_in_todo(todo_id)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok((StatusCode::OK, Json(attachments)).into_response())
+}
+
+#[utoipa::path(
+    get, path = "/attachments/{id}", tag = "attachments",
+    params(("id" = i32, Path, description = "添付ファイルID")),
+    responses(
+        (status = 200, description = "ファイルの内容", content_type = "application/octet-stream"),
+        (status = 404, description = "添付ファイルが存在しない"),
+    )
+)]
+pub async fn find_attachment<A: AttachmentRepository, S: BlobStorage>(
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<A>>,
+    Extension(storage): Extension<Arc<S>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let attachment = match repository.find(id).await {
+        // 削除待ちのものは見つからない扱いにする
+        Ok(attachment) if attachment.todo_id.is_some() => attachment,
+        _ => return Ok((StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response()),
+    };
+    let data = match storage.get(&attachment.storage_key).await {
+        Ok(data) => data,
+        Err(e) if e.downcast_ref::<StorageError>().is_some() => {
+            return Ok((StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response())
+        }
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    let headers = [
+        (header::CONTENT_TYPE, attachment.content_type.clone()),
+        (
+            header::CONTENT_DISPOSITION,
+            content_disposition(&attachment.content_type, &attachment.filename),
+        ),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+    ];
+    Ok((StatusCode::OK, headers, data).into_response())
+}
+
+#[utoipa::path(
+    delete, path = "/attachments/{id}", tag = "attachments",
+    params(("id" = i32, Path, description = "添付ファイルID")),
+    responses(
+        (status = 204),
+        (status = 404, description = "添付ファイルが存在しない"),
+    )
+)]
+pub async fn delete_attachment<A: AttachmentRepository, S: BlobStorage>(
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<A>>,
+    Extension(storage): Extension<Arc<S>>,
+) -> StatusCode {
+    let Ok(attachment) = repository.find(id).await else {
+        return StatusCode::NOT_FOUND;
+    };
+    if storage.delete(&attachment.storage_key).await.is_err() {
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+    match repository.delete(id).await {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(_) => StatusCode::NOT_FOUND,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sanitize_filename_test() {
+        assert_eq!(sanitize_filename(Some("../../etc/passwd")), "passwd");
+        assert_eq!(
+            sanitize_filename(Some("C:\\Users\\me\\memo.txt")),
+            "memo.txt"
+        );
+        assert_eq!(sanitize_filename(Some("..")), "file");
+        assert_eq!(sanitize_filename(None), "file");
+    }
+
+    #[test]
+    fn resolve_content_type_test() {
+        assert_eq!(resolve_content_type(Some("image/PNG"), "a"), "image/png");
+        assert_eq!(
+            resolve_content_type(Some("text/plain; charset=utf-8"), "a"),
+            "text/plain"
+        );
+        assert_eq!(
+            resolve_content_type(Some("application/octet-stream"), "report.pdf"),
+            "application/pdf"
+        );
+        assert_eq!(
+            resolve_content_type(None, "unknown"),
+            "application/octet-stream"
+        );
+    }
+
+    #[test]
+    fn content_disposition_test() {
+        assert_eq!(
+            content_disposition("application/pdf", "見積書 \"v2\".pdf"),
+            "attachment; filename=\"___ _v2_.pdf\"; filename*=UTF-8''%E8%A6%8B%E7%A9%8D%E6%9B%B8%20%22v2%22%2Epdf"
+        );
+        assert!(content_disposition("image/png", "a.png").starts_with("inline;"));
+    }
+}
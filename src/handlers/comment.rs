@@ -0,0 +1,162 @@
+use crate::repositories::{
+    comment::{Comment, CommentRepository, CreateComment, UpdateComment},
+    todo::TodoRepository,
+};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path},
+    http::{request::Parts, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use std::sync::Arc;
+
+// 投稿者はリクエストヘッダで受け取る
+pub const USER_HEADER: &str = "x-user";
+
+const ERR_STR_EMPTY: &str = "Error!: Can not be Empty";
+const ERR_STR_OVER: &str = "Error!: Over text length";
+const ERR_STR_NOT_FOUND: &str = "Comment not found";
+const ERR_STR_NO_USER: &str = "Error!: X-User header is required";
+const ERR_STR_NOT_AUTHOR: &str = "Error!: Only the author can modify this comment";
+
+/// `X-User` ヘッダから取り出した投稿者名
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Author(pub String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Author {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .headers
+            .get(USER_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|user| !user.is_empty())
+            .map(|user| Author(user.to_string()))
+            .ok_or((StatusCode::UNAUTHORIZED, ERR_STR_NO_USER.to_string()))
+    }
+}
+
+fn validate_body(body: &str) -> Result<(), &'static str> {
+    match body.trim().len() {
+        0 => Err(ERR_STR_EMPTY),
+        len if len > 10000 => Err(ERR_STR_OVER),
+        _ => Ok(()),
+    }
+}
+
+#[utoipa::path(
+    post, path = "/todos/{id}/comments", tag = "comments",
+    params(("id" = i32, Path, description = "Todo ID"), ("x-user" = String, Header, description = "コメントの投稿者")),
+    request_body = CreateComment,
+    responses(
+        (status = 201, body = Comment),
+        (status = 400, description = "入力値が不正"),
+        (status = 401, description = "X-Userヘッダがない"),
+        (status = 404, description = "Todoが存在しない"),
+    )
+)]
+pub async fn create_comment<T: TodoRepository, C: CommentRepository>(
+    Path(todo_id): Path<i32>,
+    author: Author,
+    Extension(todos): Extension<Arc<T>>,
+    Extension(repository): Extension<Arc<C>>,
+    Json(payload): Json<CreateComment>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if let Err(message) = validate_body(&payload.body) {
+        return Ok((StatusCode::BAD_REQUEST, message.to_string()).into_response());
+    }
+    todos.find(todo_id).await.or(Err(StatusCode::NOT_FOUND))?;
+
+    let comment = repository
+        .create(todo_id, author.0, payload)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok((StatusCode::CREATED, Json(comment)).into_response())
+}
+
+#[utoipa::path(
+    get, path = "/todos/{id}/comments", tag = "comments",
+    params(("id" = i32, Path, description = "Todo ID")),
+    responses(
+        (status = 200, body = Vec<Comment>),
+        (status = 404, description = "Todoが存在しない"),
+    )
+)]
+pub async fn all_comment<T: TodoRepository, C: CommentRepository>(
+    Path(todo_id): Path<i32>,
+    Extension(todos): Extension<Arc<T>>,
+    Extension(repository): Extension<Arc<C>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    todos.find(todo_id).await.or(Err(StatusCode::NOT_FOUND))?;
+    let comments = repository
+        .all_in_todo(todo_id)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok((StatusCode::OK, Json(comments)).into_response())
+}
+
+/// 編集できるのは投稿者本人のみ
+#[utoipa::path(
+    patch, path = "/comments/{id}", tag = "comments",
+    params(("id" = i32, Path, description = "コメントID"), ("x-user" = String, Header, description = "コメントの投稿者")),
+    request_body = UpdateComment,
+    responses(
+        (status = 200, body = Comment),
+        (status = 400, description = "入力値が不正"),
+        (status = 401, description = "X-Userヘッダがない"),
+        (status = 403, description = "投稿者ではない"),
+        (status = 404, description = "コメントが存在しない"),
+    )
+)]
+pub async fn update_comment<C: CommentRepository>(
+    Path(id): Path<i32>,
+    author: Author,
+    Extension(repository): Extension<Arc<C>>,
+    Json(payload): Json<UpdateComment>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if let Err(message) = validate_body(&payload.body) {
+        return Ok((StatusCode::BAD_REQUEST, message.to_string()).into_response());
+    }
+    let Ok(comment) = repository.find(id).await else {
+        return Ok((StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response());
+    };
+    if comment.author != author.0 {
+        return Ok((StatusCode::FORBIDDEN, ERR_STR_NOT_AUTHOR.to_string()).into_response());
+    }
+
+    let comment = repository
+        .update(id, payload)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok((StatusCode::OK, Json(comment)).into_response())
+}
+
+/// 削除できるのは投稿者本人のみ
+#[utoipa::path(
+    delete, path = "/comments/{id}", tag = "comments",
+    params(("id" = i32, Path, description = "コメントID"), ("x-user" = String, Header, description = "コメントの投稿者")),
+    responses(
+        (status = 204),
+        (status = 401, description = "X-Userヘッダがない"),
+        (status = 403, description = "投稿者ではない"),
+        (status = 404, description = "コメントが存在しない"),
+    )
+)]
+pub async fn delete_comment<C: CommentRepository>(
+    Path(id): Path<i32>,
+    author: Author,
+    Extension(repository): Extension<Arc<C>>,
+) -> StatusCode {
+    match repository.find(id).await {
+        Ok(comment) if comment.author != author.0 => StatusCode::FORBIDDEN,
+        Ok(_) => match repository.delete(id).await {
+            Ok(_) => StatusCode::NO_CONTENT,
+            Err(_) => StatusCode::NOT_FOUND,
+        },
+        Err(_) => StatusCode::NOT_FOUND,
+    }
+}
@@ -1,42 +1,447 @@
-use crate::repositories::label::{CreateLabel, LabelRepository};
-use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+use super::{
+    optional,
+    stats::{count_by_label, LabelWithCount},
+    sync::record_change,
+};
+use crate::repositories::{
+    label::{
+        check_exclusive, descendants, find_duplicate, CreateLabel, DeleteLabel,
+        ExclusiveLabelError, Label, LabelInUseError, LabelRepository, MergeLabels, UpdateLabel,
+    },
+    sync::SyncRepository,
+    todo::TodoRepository,
+    webhook::{WebhookEvent, WebhookRepository},
+    RepositoryError,
+};
+use crate::{
+    validation::{ValidJson, ValidationErrors},
+    webhook,
+};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Deserialize;
+use serde_json::json;
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
-const ERR_STR_EMPTY: &str = "Error!: Can not be Empty";
-const ERR_STR_OVER: &str = "Error!: Over text length";
+const ERR_STR_NOT_FOUND: &str = "Label not found";
+const ERR_STR_NO_TARGET: &str = "Error!: `to` is required for reassign mode";
+const ERR_STR_TARGET_NOT_FOUND: &str = "Error!: Reassign target label not found";
+const ERR_STR_UNEXPECTED_TARGET: &str = "Error!: `to` is only for reassign mode";
+const ERR_STR_IN_USE: &str = "Error!: Label is in use";
+const ERR_STR_DUPLICATE: &str = "Error!: Label name already exists";
+const ERR_STR_PARENT_NOT_FOUND: &str = "Error!: Parent label not found";
+const ERR_STR_PARENT_CYCLE: &str = "Error!: Label can not be a descendant of itself";
+const ERR_STR_MERGE_SELF: &str = "Error!: Can not merge a label into itself";
+const ERR_STR_EXCLUSIVE: &str = "Error!: Reassigning would break an exclusive label group";
+const ERR_STR_CONFLICT: &str = "Error!: Label was changed by another request";
 
-pub async fn create_label<T: LabelRepository>(
+/// 削除するラベルを使っているTodoの扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteLabelMode {
+    /// Todoからラベルを外す
+    #[default]
+    Detach,
+    /// Todoのラベルを `to` のラベルに付け替える
+    Reassign,
+    /// 使っているTodoがあれば削除しない
+    Restrict,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeleteLabelQuery {
+    /// 既定は `detach`
+    mode: Option<DeleteLabelMode>,
+    /// `reassign` の付け替え先のラベルID
+    to: Option<i32>,
+}
+
+#[utoipa::path(
+    post, path = "/labels", tag = "labels",
+    request_body = CreateLabel,
+    responses(
+        (status = 201, body = Label),
+        (status = 400, description = "入力値または親のラベルが不正"),
+        (status = 409, description = "同じ親の下に、大文字・小文字や全角・半角だけが違う名前のラベルが存在する"),
+    )
+)]
+pub async fn create_label<T: LabelRepository, W: WebhookRepository>(
     Extension(repository): Extension<Arc<T>>,
-    Json(payload): Json<CreateLabel>,
+    Extension(webhooks): Extension<Arc<W>>,
+    ValidJson(payload): ValidJson<CreateLabel>,
 ) -> Result<impl IntoResponse, StatusCode> {
-    let response = match payload.name.len() {
-        0 => (StatusCode::BAD_REQUEST, ERR_STR_EMPTY.to_string()).into_response(),
-        len if len > 100 => (StatusCode::BAD_REQUEST, ERR_STR_OVER.to_string()).into_response(),
-        _ => {
-            let label = repository
-                .create(payload.name)
-                .await
-                .or(Err(StatusCode::NOT_FOUND))?;
-            (StatusCode::CREATED, Json(label)).into_response()
+    let labels = repository
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    // 同じ親の下では、大文字・小文字や全角・半角だけが違う名前も重複とみなす
+    if find_duplicate(&labels, &payload.name, payload.parent_id, None).is_some() {
+        return Ok((StatusCode::CONFLICT, ERR_STR_DUPLICATE.to_string()).into_response());
+    }
+    if let Some(parent_id) = payload.parent_id {
+        if !labels.iter().any(|label| label.id == parent_id) {
+            return Ok((
+                StatusCode::BAD_REQUEST,
+                ERR_STR_PARENT_NOT_FOUND.to_string(),
+            )
+                .into_response());
+        }
+    }
+    let label = match repository.create(payload).await {
+        Ok(label) => label,
+        // 確認した後に同じ名前のラベルが作成された
+        Err(e) if is_duplicate(&e) => {
+            return Ok((StatusCode::CONFLICT, ERR_STR_DUPLICATE.to_string()).into_response())
         }
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
     };
+    webhook::notify(webhooks, WebhookEvent::LabelCreated, &label);
+
+    Ok((StatusCode::CREATED, Json(label)).into_response())
+}
 
-    Ok(response)
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct LabelQuery {
+    /// trueの場合はラベルごとのTodoの件数を `counts` に含める
+    with_counts: Option<bool>,
 }
 
-pub async fn all_label<T: LabelRepository>(
+#[utoipa::path(
+    get, path = "/labels", tag = "labels",
+    params(LabelQuery),
+    responses(
+        (status = 200, body = Vec<Label>),
+        (status = 200, body = Vec<LabelWithCount>, description = "`with_counts=true` の場合"),
+    )
+)]
+pub async fn all_label<T: LabelRepository, Todo: TodoRepository>(
+    Query(query): Query<LabelQuery>,
     Extension(repository): Extension<Arc<T>>,
+    Extension(todos): Extension<Arc<Todo>>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let labels = repository.all().await.unwrap();
+    if !query.with_counts.unwrap_or(false) {
+        return Ok((StatusCode::OK, Json(labels)).into_response());
+    }
+    let todos = todos
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    let labels = count_by_label(labels, &todos);
     Ok((StatusCode::OK, Json(labels)).into_response())
 }
 
-pub async fn delete_label<T: LabelRepository>(
+/// ラベルの名前・色・説明・アイコン・並び順・親を変更する
+///
+/// 並べ替えは `position` を変更して行う。Todoに紐づくラベルも同じ順で返す。
+/// `exclusive` を有効にしても、すでに同じグループのラベルが複数付いているTodoはそのまま残る
+#[utoipa::path(
+    patch, path = "/labels/{id}", tag = "labels",
+    params(("id" = i32, Path, description = "ラベルID")),
+    request_body = UpdateLabel,
+    responses(
+        (status = 200, body = Label),
+        (status = 400, description = "入力値または親のラベルが不正"),
+        (status = 404, description = "ラベルが存在しない"),
+        (status = 409, description = "変更後の親の下に同じ名前のラベルが存在する"),
+    )
+)]
+pub async fn update_label<T: LabelRepository, W: WebhookRepository>(
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<T>>,
+    Extension(webhooks): Extension<Arc<W>>,
+    ValidJson(payload): ValidJson<UpdateLabel>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let labels = repository
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    let Some(current) = labels.iter().find(|label| label.id == id) else {
+        return Ok((StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response());
+    };
+    // 名前か親を変える場合は、変更後の親の下で重複を確認する
+    if payload.name.is_some() || payload.parent_id.is_some() {
+        let name = payload.name.as_ref().unwrap_or(&current.name);
+        let parent_id = payload.parent_id.unwrap_or(current.parent_id);
+        if find_duplicate(&labels, name, parent_id, Some(id)).is_some() {
+            return Ok((StatusCode::CONFLICT, ERR_STR_DUPLICATE.to_string()).into_response());
+        }
+    }
+    if let Some(Some(parent_id)) = payload.parent_id {
+        if !labels.iter().any(|label| label.id == parent_id) {
+            return Ok((
+                StatusCode::BAD_REQUEST,
+                ERR_STR_PARENT_NOT_FOUND.to_string(),
+            )
+                .into_response());
+        }
+        // 自身や子孫を親にすると階層が循環する
+        if descendants(&labels, id).contains(&parent_id) {
+            return Ok((StatusCode::BAD_REQUEST, ERR_STR_PARENT_CYCLE.to_string()).into_response());
+        }
+    }
+
+    let label = match repository.update(id, payload).await {
+        Ok(label) => label,
+        Err(e) if is_duplicate(&e) => {
+            return Ok((StatusCode::CONFLICT, ERR_STR_DUPLICATE.to_string()).into_response())
+        }
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    webhook::notify(webhooks, WebhookEvent::LabelUpdated, &label);
+
+    Ok((StatusCode::OK, Json(label)).into_response())
+}
+
+/// ラベルを削除する
+///
+/// 使っているTodoの更新とラベルの削除は1つのトランザクションで行い、更新したTodoは同期の変更履歴やWebhookにも反映する
+#[utoipa::path(
+    delete, path = "/labels/{id}", tag = "labels",
+    params(("id" = i32, Path, description = "ラベルID"), DeleteLabelQuery),
+    responses(
+        (status = 204),
+        (status = 400, description = "付け替え先の指定が不正"),
+        (status = 404, description = "ラベルが存在しない"),
+        (status = 409, description = "`restrict` でラベルが使われている(`usage` に使っているTodoの件数を返す)、付け替えると排他的なグループに違反する、または最上位に移る子が最上位のラベルと同じ名前になる"),
+    )
+)]
+pub async fn delete_label<
+    T: LabelRepository,
+    Todo: TodoRepository,
+    W: WebhookRepository,
+    Y: SyncRepository,
+>(
+    Path(id): Path<i32>,
+    Query(query): Query<DeleteLabelQuery>,
+    Extension(repository): Extension<Arc<T>>,
+    Extension(todos): Extension<Arc<Todo>>,
+    Extension(webhooks): Extension<Arc<W>>,
+    sync: Option<Extension<Arc<Y>>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let mode = match (query.mode.unwrap_or_default(), query.to) {
+        (DeleteLabelMode::Reassign, None) => {
+            return Ok((StatusCode::BAD_REQUEST, ERR_STR_NO_TARGET.to_string()).into_response())
+        }
+        (DeleteLabelMode::Reassign, Some(to)) if to == id => {
+            return Ok((
+                StatusCode::BAD_REQUEST,
+                ERR_STR_TARGET_NOT_FOUND.to_string(),
+            )
+                .into_response())
+        }
+        (DeleteLabelMode::Reassign, Some(to)) => DeleteLabel::Reassign(to),
+        (_, Some(_)) => {
+            return Ok((
+                StatusCode::BAD_REQUEST,
+                ERR_STR_UNEXPECTED_TARGET.to_string(),
+            )
+                .into_response())
+        }
+        (DeleteLabelMode::Detach, None) => DeleteLabel::Detach,
+        (DeleteLabelMode::Restrict, None) => DeleteLabel::Restrict,
+    };
+
+    // 同期の変更履歴に更新前の内容を残すため、使っているTodoを先に読み出す
+    let using: Vec<_> = todos
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .into_iter()
+        .filter(|todo| todo.labels.iter().any(|label| label.id == id))
+        .collect();
+
+    let todo_ids = match repository.delete(id, mode).await {
+        Ok(todo_ids) => todo_ids,
+        Err(e) => return Ok(delete_error_response(id, e)),
+    };
+    webhook::notify(
+        webhooks.clone(),
+        WebhookEvent::LabelDeleted,
+        &json!({ "id": id }),
+    );
+
+    for todo_id in todo_ids {
+        let Ok(todo) = todos.find(todo_id).await else {
+            continue;
+        };
+        let old_todo = using.iter().find(|old_todo| old_todo.id == todo_id);
+        record_change(optional(&sync), old_todo, &todo).await;
+        webhook::notify(webhooks.clone(), WebhookEvent::TodoUpdated, &todo);
+    }
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// リポジトリでの重複の確認か、DBの一意制約に違反した
+fn is_duplicate(e: &anyhow::Error) -> bool {
+    if let Some(RepositoryError::Duplicate(_)) = e.downcast_ref::<RepositoryError>() {
+        return true;
+    }
+    matches!(
+        e.downcast_ref::<sqlx::Error>(),
+        Some(sqlx::Error::Database(db)) if db.is_unique_violation()
+    )
+}
+
+// 削除するラベルがない場合だけ404にし、使用中・制約違反は409にする
+fn delete_error_response(id: i32, e: anyhow::Error) -> Response {
+    if let Some(e) = e.downcast_ref::<LabelInUseError>() {
+        let body = json!({ "message": ERR_STR_IN_USE, "usage": e.usage });
+        return (StatusCode::CONFLICT, Json(body)).into_response();
+    }
+    if e.downcast_ref::<ExclusiveLabelError>().is_some() {
+        return (StatusCode::CONFLICT, ERR_STR_EXCLUSIVE.to_string()).into_response();
+    }
+    match e.downcast_ref::<RepositoryError>() {
+        Some(RepositoryError::NotFound(not_found)) if *not_found == id => {
+            return (StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response()
+        }
+        Some(RepositoryError::NotFound(_)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                ERR_STR_TARGET_NOT_FOUND.to_string(),
+            )
+                .into_response()
+        }
+        // 子を最上位に移すと、最上位のラベルと名前が重なる
+        Some(RepositoryError::Duplicate(_)) => {
+            return (StatusCode::CONFLICT, ERR_STR_DUPLICATE.to_string()).into_response()
+        }
+        _ => {}
+    }
+    match e.downcast_ref::<sqlx::Error>() {
+        Some(sqlx::Error::Database(db))
+            if db.is_foreign_key_violation() || db.is_unique_violation() =>
+        {
+            (StatusCode::CONFLICT, ERR_STR_CONFLICT.to_string()).into_response()
+        }
+        _ => {
+            tracing::error!("fail delete label [{}]: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// 重複したラベルを1つにまとめる
+///
+/// `sources` のラベルを付けたTodoはパスのラベルに付け替え、`sources` は削除する。
+/// `sources` の子のラベルはパスのラベルの子になる
+#[utoipa::path(
+    post, path = "/labels/{id}/merge", tag = "labels",
+    params(("id" = i32, Path, description = "統合先のラベルID")),
+    request_body = MergeLabels,
+    responses(
+        (status = 200, body = Label),
+        (status = 400, description = "統合元のラベルが不正"),
+        (status = 404, description = "ラベルが存在しない"),
+        (status = 409, description = "付け替えると排他的なグループに違反する、または統合先の子に同じ名前のラベルが重複する"),
+    )
+)]
+pub async fn merge_labels<
+    T: LabelRepository,
+    Todo: TodoRepository,
+    W: WebhookRepository,
+    Y: SyncRepository,
+>(
     Path(id): Path<i32>,
     Extension(repository): Extension<Arc<T>>,
-) -> StatusCode {
-    match repository.delete(id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
-        Err(_) => StatusCode::NOT_FOUND,
+    Extension(todos): Extension<Arc<Todo>>,
+    Extension(webhooks): Extension<Arc<W>>,
+    sync: Option<Extension<Arc<Y>>>,
+    ValidJson(payload): ValidJson<MergeLabels>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let labels = repository
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    let Some(target) = labels.iter().find(|label| label.id == id).cloned() else {
+        return Ok((StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response());
+    };
+    let mut errors = ValidationErrors::default();
+    for (index, source) in payload.sources.iter().enumerate() {
+        let field = format!("sources[{}]", index);
+        if *source == id {
+            errors.push(&field, "invalid", ERR_STR_MERGE_SELF);
+        } else if !labels.iter().any(|label| label.id == *source) {
+            errors.push(&field, "not_found", ERR_STR_NOT_FOUND);
+        } else if descendants(&labels, *source).contains(&id) {
+            // 統合先が統合元の子孫だと、子のラベルを付け替えた時に階層が循環する
+            errors.push(&field, "cycle", ERR_STR_PARENT_CYCLE);
+        }
+    }
+    if !errors.is_empty() {
+        return Ok(errors.into_response());
+    }
+    // 統合元の子は統合先の子になるため、同じ親の下で名前が重複しないか確認する
+    let mut children: Vec<Label> = vec![];
+    for label in &labels {
+        let moved = label
+            .parent_id
+            .is_some_and(|parent_id| parent_id == id || payload.sources.contains(&parent_id));
+        if !moved || label.id == id || payload.sources.contains(&label.id) {
+            continue;
+        }
+        if find_duplicate(&children, &label.name, Some(id), None).is_some() {
+            return Ok((StatusCode::CONFLICT, ERR_STR_DUPLICATE.to_string()).into_response());
+        }
+        children.push(Label {
+            parent_id: Some(id),
+            ..label.clone()
+        });
     }
+
+    let using: Vec<_> = todos
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .into_iter()
+        .filter(|todo| {
+            todo.labels
+                .iter()
+                .any(|label| payload.sources.contains(&label.id))
+        })
+        .collect();
+    for todo in &using {
+        let mut label_ids: Vec<i32> = todo
+            .labels
+            .iter()
+            .map(|label| label.id)
+            .filter(|label_id| !payload.sources.contains(label_id))
+            .collect();
+        if !label_ids.contains(&id) {
+            label_ids.push(id);
+        }
+        if check_exclusive(&labels, &label_ids).is_err() {
+            return Ok((StatusCode::CONFLICT, ERR_STR_EXCLUSIVE.to_string()).into_response());
+        }
+    }
+
+    todos
+        .merge_labels(id, &payload.sources)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    for old_todo in using {
+        let todo = todos
+            .find(old_todo.id)
+            .await
+            .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+        record_change(optional(&sync), Some(&old_todo), &todo).await;
+        webhook::notify(webhooks.clone(), WebhookEvent::TodoUpdated, &todo);
+    }
+    for source in payload.sources {
+        webhook::notify(
+            webhooks.clone(),
+            WebhookEvent::LabelDeleted,
+            &json!({ "id": source }),
+        );
+    }
+
+    Ok((StatusCode::OK, Json(target)).into_response())
 }
@@ -0,0 +1,203 @@
+use crate::repositories::{
+    project::{CreateProject, Project, ProjectRepository, UpdateProject, DEFAULT_PROJECT_ID},
+    todo::{TodoEntity, TodoRepository},
+    RepositoryError,
+};
+use crate::validation::ValidJson;
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use utoipa::IntoParams;
+
+const ERR_STR_NOT_FOUND: &str = "Project not found";
+const ERR_STR_ARCHIVED: &str = "Error!: Project is archived";
+const ERR_STR_DELETE_DEFAULT: &str = "Error!: Can not delete default project";
+const ERR_STR_HAS_TODOS: &str = "Error!: Project still has todos";
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ProjectQuery {
+    /// trueの場合はアーカイブ済みのプロジェクトも含める
+    archived: Option<bool>,
+}
+
+/// Todoの所属先として使えるプロジェクトか確認する
+///
+/// プロジェクトを使えない構成 (`repository` がNone) では既定のプロジェクトだけを受け付ける
+pub async fn validate_project<T: ProjectRepository>(
+    repository: Option<&T>,
+    id: i32,
+) -> Result<(), Response> {
+    let Some(repository) = repository else {
+        if id == DEFAULT_PROJECT_ID {
+            return Ok(());
+        }
+        return Err((StatusCode::BAD_REQUEST, ERR_STR_NOT_FOUND.to_string()).into_response());
+    };
+    match repository.find(id).await {
+        Ok(project) if project.archived => {
+            Err((StatusCode::BAD_REQUEST, ERR_STR_ARCHIVED.to_string()).into_response())
+        }
+        Ok(_) => Ok(()),
+        Err(_) => Err((StatusCode::BAD_REQUEST, ERR_STR_NOT_FOUND.to_string()).into_response()),
+    }
+}
+
+#[utoipa::path(
+    post, path = "/projects", tag = "projects",
+    request_body = CreateProject,
+    responses(
+        (status = 201, body = Project),
+        (status = 400, description = "入力値が不正"),
+    )
+)]
+pub async fn create_project<T: ProjectRepository>(
+    Extension(repository): Extension<Arc<T>>,
+    ValidJson(payload): ValidJson<CreateProject>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let project = repository
+        .create(payload)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok((StatusCode::CREATED, Json(project)).into_response())
+}
+
+#[utoipa::path(
+    get, path = "/projects/{id}", tag = "projects",
+    params(("id" = i32, Path, description = "プロジェクトID")),
+    responses(
+        (status = 200, body = Project),
+        (status = 404, description = "プロジェクトが存在しない"),
+    )
+)]
+pub async fn find_project<T: ProjectRepository>(
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<T>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let project = repository.find(id).await.or(Err(StatusCode::NOT_FOUND))?;
+    Ok((StatusCode::OK, Json(project)).into_response())
+}
+
+/// アーカイブ済みのプロジェクトは `?archived=true` の時のみ返す
+#[utoipa::path(
+    get, path = "/projects", tag = "projects",
+    params(ProjectQuery),
+    responses((status = 200, body = Vec<Project>))
+)]
+pub async fn all_project<T: ProjectRepository>(
+    Query(query): Query<ProjectQuery>,
+    Extension(repository): Extension<Arc<T>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let include_archived = query.archived.unwrap_or(false);
+    let projects: Vec<_> = repository
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?
+        .into_iter()
+        .filter(|project| include_archived || !project.archived)
+        .collect();
+    Ok((StatusCode::OK, Json(projects)).into_response())
+}
+
+#[utoipa::path(
+    patch, path = "/projects/{id}", tag = "projects",
+    params(("id" = i32, Path, description = "プロジェクトID")),
+    request_body = UpdateProject,
+    responses(
+        (status = 200, body = Project),
+        (status = 400, description = "入力値が不正"),
+        (status = 404, description = "プロジェクトが存在しない"),
+    )
+)]
+pub async fn update_project<T: ProjectRepository>(
+    Extension(repository): Extension<Arc<T>>,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<UpdateProject>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let response = match repository.update(id, payload).await {
+        Ok(project) => (StatusCode::OK, Json(project)).into_response(),
+        Err(e) => match e.downcast_ref::<RepositoryError>() {
+            Some(RepositoryError::NotFound(_)) => {
+                (StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response()
+            }
+            _ => {
+                tracing::error!("fail update project [{}]: {}", id, e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        },
+    };
+
+    Ok(response)
+}
+
+/// Todoを削除・移動してから削除する。Todoごと削除するとWebhookや同期の記録が漏れるため、残っている場合は409を返す
+#[utoipa::path(
+    delete, path = "/projects/{id}", tag = "projects",
+    params(("id" = i32, Path, description = "プロジェクトID")),
+    responses(
+        (status = 204),
+        (status = 400, description = "デフォルトプロジェクトは削除できない"),
+        (status = 404, description = "プロジェクトが存在しない"),
+        (status = 409, description = "Todoが残っている"),
+    )
+)]
+pub async fn delete_project<Todo: TodoRepository, Project: ProjectRepository>(
+    Path(id): Path<i32>,
+    Extension(todo_repository): Extension<Arc<Todo>>,
+    Extension(repository): Extension<Arc<Project>>,
+) -> Response {
+    if id == DEFAULT_PROJECT_ID {
+        return (StatusCode::BAD_REQUEST, ERR_STR_DELETE_DEFAULT.to_string()).into_response();
+    }
+    match todo_repository.all_in_project(id).await {
+        Ok(todos) if !todos.is_empty() => {
+            return (StatusCode::CONFLICT, ERR_STR_HAS_TODOS.to_string()).into_response()
+        }
+        Ok(_) => {}
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+    let Err(e) = repository.delete(id).await else {
+        return StatusCode::NO_CONTENT.into_response();
+    };
+    if let Some(RepositoryError::NotFound(_)) = e.downcast_ref::<RepositoryError>() {
+        return (StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response();
+    }
+    match e.downcast_ref::<sqlx::Error>() {
+        // 確認の後に別のリクエストでTodoが追加された
+        Some(sqlx::Error::Database(db)) if db.is_foreign_key_violation() => {
+            (StatusCode::CONFLICT, ERR_STR_HAS_TODOS.to_string()).into_response()
+        }
+        _ => {
+            tracing::error!("fail delete project [{}]: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get, path = "/projects/{id}/todos", tag = "projects",
+    params(("id" = i32, Path, description = "プロジェクトID")),
+    responses(
+        (status = 200, body = Vec<TodoEntity>),
+        (status = 404, description = "プロジェクトが存在しない"),
+    )
+)]
+pub async fn all_project_todo<Todo: TodoRepository, Project: ProjectRepository>(
+    Path(id): Path<i32>,
+    Extension(todo_repository): Extension<Arc<Todo>>,
+    Extension(project_repository): Extension<Arc<Project>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    project_repository
+        .find(id)
+        .await
+        .or(Err(StatusCode::NOT_FOUND))?;
+    let todos = todo_repository
+        .all_in_project(id)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok((StatusCode::OK, Json(todos)).into_response())
+}
@@ -0,0 +1,241 @@
+use crate::repositories::{
+    label::{Label, LabelRepository},
+    todo::{TodoEntity, TodoRepository},
+};
+use axum::{extract::Query, http::StatusCode, response::IntoResponse, Extension, Json};
+use chrono::{Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+use utoipa::{IntoParams, ToSchema};
+
+const DEFAULT_DAYS: u64 = 30;
+const MAX_DAYS: u64 = 366;
+const ERR_STR_DAYS: &str = "Error!: `days` must be between 1 and 366";
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct StatsQuery {
+    /// 日ごとの件数を集計する日数 (今日を含む)。既定は30日
+    days: Option<u64>,
+}
+
+/// Todoの件数
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+pub struct TodoCount {
+    pub total: usize,
+    pub completed: usize,
+    pub open: usize,
+}
+
+impl TodoCount {
+    fn add(&mut self, todo: &TodoEntity) {
+        self.total += 1;
+        if todo.completed {
+            self.completed += 1;
+        } else {
+            self.open += 1;
+        }
+    }
+
+    fn count<'a>(todos: impl IntoIterator<Item = &'a TodoEntity>) -> Self {
+        let mut count = Self::default();
+        for todo in todos {
+            count.add(todo);
+        }
+        count
+    }
+}
+
+/// ラベルごとの件数。ラベルのないTodoは `label` がnull
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct LabelStats {
+    pub label: Option<Label>,
+    #[serde(flatten)]
+    pub count: TodoCount,
+}
+
+/// 1日(UTC)ごとの作成・完了の件数
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct DailyStats {
+    pub date: NaiveDate,
+    pub created: usize,
+    pub completed: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct TodoStats {
+    #[serde(flatten)]
+    pub count: TodoCount,
+    pub labels: Vec<LabelStats>,
+    /// 古い日付から順に、件数が0の日も含めて返す
+    pub daily: Vec<DailyStats>,
+}
+
+/// `GET /labels?with_counts=true` のラベル
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct LabelWithCount {
+    #[serde(flatten)]
+    pub label: Label,
+    /// ラベルが直接付いているTodoの件数 (子のラベルは含まない)
+    pub counts: TodoCount,
+}
+
+/// ラベルごとの件数をラベルの並び順で返す
+pub fn count_by_label(labels: Vec<Label>, todos: &[TodoEntity]) -> Vec<LabelWithCount> {
+    labels
+        .into_iter()
+        .map(|label| {
+            let counts = TodoCount::count(
+                todos
+                    .iter()
+                    .filter(|todo| todo.labels.iter().any(|l| l.id == label.id)),
+            );
+            LabelWithCount { label, counts }
+        })
+        .collect()
+}
+
+/// `today` までの `days` 日間を集計する
+fn collect_stats(
+    todos: &[TodoEntity],
+    labels: Vec<Label>,
+    today: NaiveDate,
+    days: u64,
+) -> TodoStats {
+    let mut labels: Vec<LabelStats> = count_by_label(labels, todos)
+        .into_iter()
+        .map(|LabelWithCount { label, counts }| LabelStats {
+            label: Some(label),
+            count: counts,
+        })
+        .collect();
+    labels.push(LabelStats {
+        label: None,
+        count: TodoCount::count(todos.iter().filter(|todo| todo.labels.is_empty())),
+    });
+
+    let mut created = HashMap::new();
+    let mut completed = HashMap::new();
+    for todo in todos {
+        *created.entry(todo.created_at.date_naive()).or_insert(0) += 1;
+        if let Some(completed_at) = todo.completed_at {
+            *completed.entry(completed_at.date_naive()).or_insert(0) += 1;
+        }
+    }
+    let daily = (0..days)
+        .rev()
+        .filter_map(|offset| today.checked_sub_days(Days::new(offset)))
+        .map(|date| DailyStats {
+            date,
+            created: created.get(&date).copied().unwrap_or(0),
+            completed: completed.get(&date).copied().unwrap_or(0),
+        })
+        .collect();
+
+    TodoStats {
+        count: TodoCount::count(todos),
+        labels,
+        daily,
+    }
+}
+
+/// Todoの件数をラベルごと・日ごとに集計する
+///
+/// 日ごとの完了件数は完了日時で数えるため、完了日時を記録する前に完了したTodoは含まない
+#[utoipa::path(
+    get, path = "/stats", tag = "stats",
+    params(StatsQuery),
+    responses(
+        (status = 200, body = TodoStats),
+        (status = 400, description = "`days` が範囲外"),
+    )
+)]
+pub async fn find_stats<T: TodoRepository, L: LabelRepository>(
+    Query(query): Query<StatsQuery>,
+    Extension(todos): Extension<Arc<T>>,
+    Extension(labels): Extension<Arc<L>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let days = query.days.unwrap_or(DEFAULT_DAYS);
+    if !(1..=MAX_DAYS).contains(&days) {
+        return Ok((StatusCode::BAD_REQUEST, ERR_STR_DAYS.to_string()).into_response());
+    }
+    let todos = todos
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    let labels = labels
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    let stats = collect_stats(&todos, labels, Utc::now().date_naive(), days);
+
+    Ok((StatusCode::OK, Json(stats)).into_response())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{DateTime, TimeZone};
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 11, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn collect_stats_test() {
+        let work = Label::new(1, "work".to_string());
+        let home = Label::new(2, "home".to_string());
+        let todos = vec![
+            TodoEntity {
+                created_at: at(1, 9),
+                ..TodoEntity::new(1, "open".to_string(), vec![work.clone()])
+            },
+            TodoEntity {
+                completed: true,
+                created_at: at(2, 9),
+                completed_at: Some(at(3, 23)),
+                ..TodoEntity::new(2, "done".to_string(), vec![work.clone()])
+            },
+            TodoEntity {
+                created_at: at(3, 0),
+                ..TodoEntity::new(3, "unlabelled".to_string(), vec![])
+            },
+        ];
+
+        let stats = collect_stats(
+            &todos,
+            vec![work.clone(), home.clone()],
+            at(3, 0).date_naive(),
+            2,
+        );
+        assert_eq!(
+            stats.count,
+            TodoCount {
+                total: 3,
+                completed: 1,
+                open: 2
+            }
+        );
+        let labels: Vec<_> = stats
+            .labels
+            .iter()
+            .map(|stats| (stats.label.as_ref().map(|l| l.id), stats.count.total))
+            .collect();
+        assert_eq!(labels, vec![(Some(1), 2), (Some(2), 0), (None, 1)]);
+        // 期間より前に作成したTodoは含まない
+        assert_eq!(
+            stats.daily,
+            vec![
+                DailyStats {
+                    date: at(2, 0).date_naive(),
+                    created: 1,
+                    completed: 0
+                },
+                DailyStats {
+                    date: at(3, 0).date_naive(),
+                    created: 1,
+                    completed: 1
+                },
+            ]
+        );
+    }
+}
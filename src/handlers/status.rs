@@ -0,0 +1,329 @@
+use crate::repositories::{
+    project::{Project, ProjectRepository},
+    status::{CreateStatus, Status, StatusRepository, UpdateStatus},
+    todo::{CreateTodo, TodoEntity, TodoRepository, UpdateTodo},
+};
+use crate::validation::ValidJson;
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+const ERR_STR_NOT_FOUND: &str = "Status not found";
+const ERR_STR_OTHER_PROJECT: &str = "Error!: Status does not belong to the project";
+const ERR_STR_WIP_LIMIT: &str = "Error!: WIP limit exceeded";
+const ERR_STR_BLOCKED: &str = "Error!: Todo is blocked by incomplete todos";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct BoardColumn {
+    pub status: Status,
+    pub todos: Vec<TodoEntity>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Board {
+    pub project: Project,
+    pub columns: Vec<BoardColumn>,
+    // どの列にも割り当てられていないTodo
+    pub unassigned: Vec<TodoEntity>,
+}
+
+fn internal_error<E>(_: E) -> Response {
+    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+}
+
+/// 移動先の列がWIP制限に達していないか確認する
+async fn check_wip_limit<T: TodoRepository>(
+    todos: &T,
+    status: &Status,
+    todo_id: Option<i32>,
+) -> Result<(), Response> {
+    let Some(limit) = status.wip_limit else {
+        return Ok(());
+    };
+    let count = todos
+        .all_in_project(status.project_id)
+        .await
+        .map_err(internal_error)?
+        .iter()
+        .filter(|todo| todo.status_id == Some(status.id) && Some(todo.id) != todo_id)
+        .count();
+    if count as i32 >= limit {
+        return Err((StatusCode::CONFLICT, ERR_STR_WIP_LIMIT.to_string()).into_response());
+    }
+    Ok(())
+}
+
+/// 作成するTodoの初期ステータスを決め、completedをステータスから導出する
+///
+/// 列を指定しなければ、`completed` (同期で完了済みのTodoを作成する場合はtrue) と完了フラグが一致する先頭の列に置く
+///
+/// ステータスを使えない構成 (`statuses` がNone) では列を指定できない
+pub async fn resolve_initial_status<T: TodoRepository, S: StatusRepository>(
+    todos: &T,
+    statuses: Option<&S>,
+    project_id: i32,
+    payload: &mut CreateTodo,
+) -> Result<(), Response> {
+    let Some(statuses) = statuses else {
+        return match payload.status_id {
+            Some(_) => {
+                Err((StatusCode::BAD_REQUEST, ERR_STR_NOT_FOUND.to_string()).into_response())
+            }
+            None => Ok(()),
+        };
+    };
+    let project_statuses = statuses
+        .all_in_project(project_id)
+        .await
+        .map_err(internal_error)?;
+    let status = match payload.status_id {
+        Some(id) => Some(
+            project_statuses
+                .iter()
+                .find(|status| status.id == id)
+                .ok_or_else(|| {
+                    (StatusCode::BAD_REQUEST, ERR_STR_OTHER_PROJECT.to_string()).into_response()
+                })?,
+        ),
+        None => project_statuses
+            .iter()
+            .find(|status| status.is_done == payload.completed),
+    };
+
+    if let Some(status) = status {
+        check_wip_limit(todos, status, None).await?;
+        payload.status_id = Some(status.id);
+        payload.completed = status.is_done;
+    }
+    Ok(())
+}
+
+/// 更新後のステータスを決め、completedと同期させる
+///
+/// - `status_id` が指定されていればその列に移動し、completedは列の完了フラグに従う
+/// - `completed` だけが変わった場合は、完了フラグが一致する先頭の列に移動する
+/// - 別のプロジェクトに移動した場合は、移動先のプロジェクトの列に置き直す
+/// - 未完了のブロッカーがあるTodoは完了にできない
+///
+/// ステータスを使えない構成 (`statuses` がNone) では列を指定できず、completedだけを確認する
+pub async fn resolve_status_transition<T: TodoRepository, S: StatusRepository>(
+    todos: &T,
+    statuses: Option<&S>,
+    old_todo: &TodoEntity,
+    payload: &mut UpdateTodo,
+) -> Result<(), Response> {
+    match statuses {
+        Some(statuses) => move_status(todos, statuses, old_todo, payload).await?,
+        None if matches!(payload.status_id, Some(Some(_))) => {
+            return Err((StatusCode::BAD_REQUEST, ERR_STR_NOT_FOUND.to_string()).into_response());
+        }
+        None => {}
+    }
+    if payload.completed == Some(true) && !old_todo.completed && old_todo.blocked {
+        return Err((StatusCode::CONFLICT, ERR_STR_BLOCKED.to_string()).into_response());
+    }
+    Ok(())
+}
+
+async fn move_status<T: TodoRepository, S: StatusRepository>(
+    todos: &T,
+    statuses: &S,
+    old_todo: &TodoEntity,
+    payload: &mut UpdateTodo,
+) -> Result<(), Response> {
+    let project_id = payload.project_id.unwrap_or(old_todo.project_id);
+    let project_statuses = statuses
+        .all_in_project(project_id)
+        .await
+        .map_err(internal_error)?;
+
+    let target = match payload.status_id {
+        Some(target) => target,
+        None => {
+            let completed = payload.completed.unwrap_or(old_todo.completed);
+            let current = old_todo
+                .status_id
+                .and_then(|id| project_statuses.iter().find(|status| status.id == id));
+            match current {
+                Some(status) if status.is_done == completed => Some(status.id),
+                _ => project_statuses
+                    .iter()
+                    .find(|status| status.is_done == completed)
+                    .map(|status| status.id),
+            }
+        }
+    };
+
+    if let Some(id) = target {
+        let status = project_statuses
+            .iter()
+            .find(|status| status.id == id)
+            .ok_or_else(|| {
+                (StatusCode::BAD_REQUEST, ERR_STR_OTHER_PROJECT.to_string()).into_response()
+            })?;
+        if old_todo.status_id != Some(id) {
+            check_wip_limit(todos, status, Some(old_todo.id)).await?;
+        }
+        payload.completed = Some(status.is_done);
+    }
+    payload.status_id = Some(target);
+    Ok(())
+}
+
+#[utoipa::path(
+    post, path = "/projects/{id}/statuses", tag = "statuses",
+    params(("id" = i32, Path, description = "プロジェクトID")),
+    request_body = CreateStatus,
+    responses(
+        (status = 201, body = Status),
+        (status = 400, description = "入力値が不正"),
+        (status = 404, description = "プロジェクトが存在しない"),
+    )
+)]
+pub async fn create_status<S: StatusRepository, P: ProjectRepository>(
+    Path(project_id): Path<i32>,
+    Extension(repository): Extension<Arc<S>>,
+    Extension(projects): Extension<Arc<P>>,
+    ValidJson(payload): ValidJson<CreateStatus>,
+) -> Result<impl IntoResponse, StatusCode> {
+    projects
+        .find(project_id)
+        .await
+        .or(Err(StatusCode::NOT_FOUND))?;
+
+    let status = repository
+        .create(project_id, payload)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok((StatusCode::CREATED, Json(status)).into_response())
+}
+
+#[utoipa::path(
+    get, path = "/projects/{id}/statuses", tag = "statuses",
+    params(("id" = i32, Path, description = "プロジェクトID")),
+    responses(
+        (status = 200, body = Vec<Status>),
+        (status = 404, description = "プロジェクトが存在しない"),
+    )
+)]
+pub async fn all_status<S: StatusRepository, P: ProjectRepository>(
+    Path(project_id): Path<i32>,
+    Extension(repository): Extension<Arc<S>>,
+    Extension(projects): Extension<Arc<P>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    projects
+        .find(project_id)
+        .await
+        .or(Err(StatusCode::NOT_FOUND))?;
+    let statuses = repository
+        .all_in_project(project_id)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok((StatusCode::OK, Json(statuses)).into_response())
+}
+
+#[utoipa::path(
+    patch, path = "/statuses/{id}", tag = "statuses",
+    params(("id" = i32, Path, description = "ステータスID")),
+    request_body = UpdateStatus,
+    responses(
+        (status = 200, body = Status),
+        (status = 400, description = "入力値が不正"),
+        (status = 404, description = "ステータスが存在しない"),
+    )
+)]
+pub async fn update_status<S: StatusRepository>(
+    Extension(repository): Extension<Arc<S>>,
+    Path(id): Path<i32>,
+    ValidJson(payload): ValidJson<UpdateStatus>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let response = match repository.update(id, payload).await {
+        Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+        Err(_) => (StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response(),
+    };
+    Ok(response)
+}
+
+#[utoipa::path(
+    delete, path = "/statuses/{id}", tag = "statuses",
+    params(("id" = i32, Path, description = "ステータスID")),
+    responses(
+        (status = 204),
+        (status = 404, description = "ステータスが存在しない"),
+    )
+)]
+pub async fn delete_status<S: StatusRepository>(
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<S>>,
+) -> StatusCode {
+    match repository.delete(id).await {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(_) => StatusCode::NOT_FOUND,
+    }
+}
+
+/// プロジェクトのTodoを列ごとにまとめて返す
+#[utoipa::path(
+    get, path = "/projects/{id}/board", tag = "statuses",
+    params(("id" = i32, Path, description = "プロジェクトID")),
+    responses(
+        (status = 200, body = Board),
+        (status = 404, description = "プロジェクトが存在しない"),
+    )
+)]
+pub async fn find_board<T: TodoRepository, P: ProjectRepository, S: StatusRepository>(
+    Path(project_id): Path<i32>,
+    Extension(todos): Extension<Arc<T>>,
+    Extension(projects): Extension<Arc<P>>,
+    Extension(statuses): Extension<Arc<S>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let project = projects
+        .find(project_id)
+        .await
+        .or(Err(StatusCode::NOT_FOUND))?;
+    let project_statuses = statuses
+        .all_in_project(project_id)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    let mut project_todos = todos
+        .all_in_project(project_id)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    project_todos.sort_by_key(|todo| todo.id);
+
+    let columns = project_statuses
+        .into_iter()
+        .map(|status| {
+            let todos = project_todos
+                .iter()
+                .filter(|todo| todo.status_id == Some(status.id))
+                .cloned()
+                .collect();
+            BoardColumn { status, todos }
+        })
+        .collect::<Vec<_>>();
+    let unassigned = project_todos
+        .into_iter()
+        .filter(|todo| {
+            !columns
+                .iter()
+                .any(|column| Some(column.status.id) == todo.status_id)
+        })
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(Board {
+            project,
+            columns,
+            unassigned,
+        }),
+    )
+        .into_response())
+}
@@ -0,0 +1,630 @@
+use super::status::{resolve_initial_status, resolve_status_transition};
+use crate::repositories::{
+    attachment::AttachmentRepository,
+    label::ExclusiveLabelError,
+    project::DEFAULT_PROJECT_ID,
+    status::StatusRepository,
+    sync::{
+        PushTodo, Resolution, SyncChanges, SyncConflict, SyncCreated, SyncField, SyncPush,
+        SyncPushResult, SyncRejected, SyncRepository, SyncState, SyncedTodo,
+    },
+    todo::{CreateTodo, DependencyCycleError, TodoEntity, TodoRepository, UpdateTodo},
+    webhook::{WebhookEvent, WebhookRepository},
+};
+use crate::{
+    validation::{validate, LabelCatalog, ValidationRules},
+    webhook,
+};
+use axum::{
+    body::to_bytes,
+    extract::Query,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use utoipa::IntoParams;
+
+const ERR_STR_NOT_FOUND: &str = "Todo not found";
+const ERR_STR_BLOCKER_NOT_FOUND: &str = "Error!: Blocker todo not found";
+const ERR_STR_BLOCKED: &str = "Error!: Todo is blocked by incomplete todos";
+const ERR_STR_UNEXPECTED: &str = "Error!: Unexpected error";
+
+const DEFAULT_LIMIT: i64 = 500;
+const MAX_LIMIT: i64 = 1000;
+const FIELDS: [SyncField; 7] = [
+    SyncField::Text,
+    SyncField::Notes,
+    SyncField::Completed,
+    SyncField::Labels,
+    SyncField::Priority,
+    SyncField::DueAt,
+    SyncField::BlockedBy,
+];
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ChangesQuery {
+    /// 前回のレスポンスの `cursor` (初回は0)
+    since: Option<i64>,
+    /// 1回で返す最大件数 (既定は500、最大1000)
+    limit: Option<i64>,
+}
+
+fn field_value(todo: &TodoEntity, field: SyncField) -> Value {
+    match field {
+        SyncField::Text => json!(todo.text),
+        SyncField::Notes => json!(todo.notes),
+        SyncField::Completed => json!(todo.completed),
+        SyncField::Labels => json!(label_ids(todo)),
+        SyncField::Priority => json!(todo.priority),
+        SyncField::DueAt => json!(todo.due_at),
+        SyncField::BlockedBy => json!(todo.blocked_by),
+    }
+}
+
+fn pushed_value(change: &PushTodo, field: SyncField) -> Option<Value> {
+    match field {
+        SyncField::Text => change.text.as_ref().map(|text| json!(text)),
+        SyncField::Notes => change.notes.as_ref().map(|notes| json!(notes)),
+        SyncField::Completed => change.completed.map(|completed| json!(completed)),
+        SyncField::Labels => change.labels.as_ref().map(|labels| json!(labels)),
+        SyncField::Priority => change.priority.map(|priority| json!(priority)),
+        SyncField::DueAt => change.due_at.map(|due_at| json!(due_at)),
+        SyncField::BlockedBy => change.blocked_by.as_ref().map(|ids| json!(ids)),
+    }
+}
+
+fn label_ids(todo: &TodoEntity) -> Vec<i32> {
+    todo.labels.iter().map(|label| label.id).collect()
+}
+
+// ラベルの制約違反や依存関係の循環はその内容を理由にし、それ以外は `default` にする
+fn repository_rejection(e: anyhow::Error, default: &str) -> String {
+    if let Some(error) = e.downcast_ref::<ExclusiveLabelError>() {
+        return error.to_string();
+    }
+    match e.downcast_ref::<DependencyCycleError>() {
+        Some(error) => error.to_string(),
+        None => default.to_string(),
+    }
+}
+
+// ステータスの解決で返されたエラーレスポンスの本文を理由にする
+async fn rejection_reason(response: Response) -> String {
+    let status = response.status();
+    match to_bytes(response.into_body(), usize::MAX).await {
+        Ok(bytes) if !bytes.is_empty() => String::from_utf8_lossy(&bytes).to_string(),
+        _ => status.to_string(),
+    }
+}
+
+/// REST APIでのTodoの作成・更新を変更フィードに記録する
+///
+/// 同期の対象外のフィールド(プロジェクトなど)だけが変わった場合や、同期を使えない構成 (`sync` がNone) では記録しない
+pub async fn record_change<Y: SyncRepository>(
+    sync: Option<&Y>,
+    old_todo: Option<&TodoEntity>,
+    todo: &TodoEntity,
+) {
+    let Some(sync) = sync else {
+        return;
+    };
+    let now = Utc::now();
+    let changed: Vec<_> = FIELDS
+        .into_iter()
+        .filter(|field| {
+            old_todo
+                .is_none_or(|old_todo| field_value(old_todo, *field) != field_value(todo, *field))
+        })
+        .map(|field| (field, now))
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+    if let Err(e) = sync.record(todo.id, changed).await {
+        tracing::error!("fail record sync change for todo [{}]: {}", todo.id, e);
+    }
+}
+
+/// REST APIでの依存関係の追加・削除を変更フィードに記録する
+pub async fn record_dependencies<Y: SyncRepository>(sync: Option<&Y>, id: i32) {
+    let Some(sync) = sync else {
+        return;
+    };
+    if let Err(e) = sync
+        .record(id, vec![(SyncField::BlockedBy, Utc::now())])
+        .await
+    {
+        tracing::error!("fail record sync change for todo [{}]: {}", id, e);
+    }
+}
+
+/// REST APIでのTodoの削除をトゥームストーンとして記録する
+pub async fn record_delete<Y: SyncRepository>(sync: Option<&Y>, id: i32) {
+    let Some(sync) = sync else {
+        return;
+    };
+    if let Err(e) = sync.tombstone(id, Utc::now()).await {
+        tracing::error!("fail record sync tombstone for todo [{}]: {}", id, e);
+    }
+}
+
+/// `since` より後に変更・削除されたTodoを変更順に返す
+///
+/// 同じTodoが複数回変更されていても最新の状態を1件だけ返す
+#[utoipa::path(
+    get, path = "/sync/changes", tag = "sync",
+    params(ChangesQuery),
+    responses((status = 200, body = SyncChanges))
+)]
+pub async fn sync_changes<T: TodoRepository, Y: SyncRepository>(
+    Query(query): Query<ChangesQuery>,
+    Extension(repository): Extension<Arc<T>>,
+    Extension(sync): Extension<Arc<Y>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let since = query.since.unwrap_or(0);
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    // 1件多く取得して続きがあるかを判定する
+    let mut states = sync
+        .changes(since, limit + 1)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    let has_more = states.len() as i64 > limit;
+    states.truncate(limit as usize);
+
+    let cursor = states.last().map_or(since, |state| state.cursor);
+    let mut todos = Vec::with_capacity(states.len());
+    for state in states {
+        let todo = match state.deleted_at {
+            Some(_) => None,
+            None => repository.find(state.todo_id).await.ok(),
+        };
+        todos.push(SyncedTodo::new(state, todo));
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(SyncChanges {
+            cursor,
+            has_more,
+            todos,
+        }),
+    )
+        .into_response())
+}
+
+/// クライアントでの変更をまとめて反映する
+///
+/// - 値はREST APIと同じ規則で正規化・検証し、存在しないラベルやブロッカーを含む変更は反映しない
+/// - `base_version` の後にサーバー側で変更されていないフィールドはそのまま反映する
+/// - 両方で変更されたフィールドは更新時刻が新しい方を採用し(同時刻ならサーバー)、`conflicts` で報告する
+/// - 削除は、削除時刻より後にサーバー側で変更されたフィールドがなければ反映する
+/// - 削除済みのTodoへの変更は反映せず、トゥームストーンを返す
+#[utoipa::path(
+    post, path = "/sync/push", tag = "sync",
+    request_body = SyncPush,
+    responses((status = 200, body = SyncPushResult))
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn sync_push<
+    T: TodoRepository,
+    S: StatusRepository,
+    W: WebhookRepository,
+    A: AttachmentRepository,
+    Y: SyncRepository,
+>(
+    Extension(repository): Extension<Arc<T>>,
+    Extension(statuses): Extension<Arc<S>>,
+    Extension(webhooks): Extension<Arc<W>>,
+    Extension(attachments): Extension<Arc<A>>,
+    Extension(sync): Extension<Arc<Y>>,
+    rules: Option<Extension<Arc<ValidationRules>>>,
+    catalog: Option<Extension<Arc<dyn LabelCatalog>>>,
+    Json(payload): Json<SyncPush>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let rules = rules.map(|Extension(rules)| rules).unwrap_or_default();
+    let catalog = catalog.map(|Extension(catalog)| catalog);
+    let pusher = Pusher {
+        rules: &rules,
+        catalog: catalog.as_deref(),
+        repository: &*repository,
+        statuses: &*statuses,
+        webhooks,
+        attachments: &*attachments,
+        sync: &*sync,
+        now: Utc::now(),
+    };
+    let mut result = SyncPushResult::default();
+    for mut change in payload.todos {
+        let (id, client_ref) = (change.id, change.client_ref.clone());
+        let outcome = match (pusher.validate(&mut change).await, id) {
+            (Err(reason), _) => Err(reason),
+            (Ok(()), Some(id)) => pusher.update(id, change, &mut result).await,
+            (Ok(()), None) => pusher.create(change, &mut result).await,
+        };
+        if let Err(reason) = outcome {
+            result.rejected.push(SyncRejected {
+                id,
+                client_ref,
+                reason,
+            });
+        }
+    }
+
+    Ok((StatusCode::OK, Json(result)).into_response())
+}
+
+struct Pusher<'a, T, S, W, A, Y> {
+    rules: &'a ValidationRules,
+    catalog: Option<&'a dyn LabelCatalog>,
+    repository: &'a T,
+    statuses: &'a S,
+    webhooks: Arc<W>,
+    attachments: &'a A,
+    sync: &'a Y,
+    now: DateTime<Utc>,
+}
+
+impl<T, S, W, A, Y> Pusher<'_, T, S, W, A, Y>
+where
+    T: TodoRepository,
+    S: StatusRepository,
+    W: WebhookRepository,
+    A: AttachmentRepository,
+    Y: SyncRepository,
+{
+    // RESTのAPIと同じ規則で正規化・検証し、最初のエラーを拒否の理由にする
+    async fn validate(&self, change: &mut PushTodo) -> Result<(), String> {
+        let errors = validate(change, self.rules, self.catalog)
+            .await
+            .or(Err(ERR_STR_UNEXPECTED.to_string()))?;
+        match errors.errors.into_iter().next() {
+            Some(error) => Err(error.message),
+            None => Ok(()),
+        }
+    }
+
+    // ブロッカーがすべて存在することを確認し、未完了のものがあればtrueを返す
+    async fn blocked(&self, blocked_by: &[i32]) -> Result<bool, String> {
+        let mut blocked = false;
+        for blocker_id in blocked_by {
+            let blocker = self
+                .repository
+                .find(*blocker_id)
+                .await
+                .or(Err(ERR_STR_BLOCKER_NOT_FOUND.to_string()))?;
+            blocked |= !blocker.completed;
+        }
+        Ok(blocked)
+    }
+
+    // 追加できないブロッカーがあれば、追加済みのものを戻して拒否する
+    async fn replace_blockers(
+        &self,
+        old_todo: &TodoEntity,
+        blocked_by: &[i32],
+    ) -> Result<TodoEntity, String> {
+        let id = old_todo.id;
+        let mut todo = old_todo.clone();
+        let mut added = vec![];
+        for blocker_id in blocked_by {
+            if old_todo.blocked_by.contains(blocker_id) {
+                continue;
+            }
+            match self.repository.add_dependency(id, *blocker_id).await {
+                Ok(updated) => {
+                    todo = updated;
+                    added.push(*blocker_id);
+                }
+                Err(e) => {
+                    for blocker_id in added {
+                        if let Err(e) = self.repository.remove_dependency(id, blocker_id).await {
+                            tracing::error!("fail revert dependency of todo [{}]: {}", id, e);
+                        }
+                    }
+                    return Err(repository_rejection(e, ERR_STR_BLOCKER_NOT_FOUND));
+                }
+            }
+        }
+        for blocker_id in &old_todo.blocked_by {
+            if blocked_by.contains(blocker_id) {
+                continue;
+            }
+            todo = self
+                .repository
+                .remove_dependency(id, *blocker_id)
+                .await
+                .or(Err(ERR_STR_UNEXPECTED.to_string()))?;
+        }
+        Ok(todo)
+    }
+
+    fn updated_at(&self, change: &PushTodo, field: SyncField) -> DateTime<Utc> {
+        change.updated_at.get(field).unwrap_or(self.now)
+    }
+
+    async fn create(&self, change: PushTodo, result: &mut SyncPushResult) -> Result<(), String> {
+        // オフラインで作成して削除したTodoは送る必要がない
+        if change.deleted_at.is_some() {
+            return Ok(());
+        }
+        // 作成した後に拒否しないよう、ブロッカーと完了状態を先に確認する
+        let blocked_by = change.blocked_by.clone().unwrap_or_default();
+        let completed = change.completed.unwrap_or(false);
+        if self.blocked(&blocked_by).await? && completed {
+            return Err(ERR_STR_BLOCKED.to_string());
+        }
+
+        // 完了状態は作成時に反映し、WIP制限も作成前に確認する
+        let mut payload = CreateTodo {
+            notes: change.notes.clone().flatten(),
+            priority: change.priority.flatten(),
+            due_at: change.due_at.flatten(),
+            completed,
+            ..CreateTodo::new(
+                change.text.clone().unwrap_or_default(),
+                change.labels.clone().unwrap_or_default(),
+            )
+        };
+        if let Err(response) = resolve_initial_status(
+            self.repository,
+            Some(self.statuses),
+            DEFAULT_PROJECT_ID,
+            &mut payload,
+        )
+        .await
+        {
+            return Err(rejection_reason(response).await);
+        }
+        let mut todo = self
+            .repository
+            .create(payload)
+            .await
+            .map_err(|e| repository_rejection(e, ERR_STR_UNEXPECTED))?;
+        webhook::notify(self.webhooks.clone(), WebhookEvent::TodoCreated, &todo);
+
+        let mut changed: Vec<_> = FIELDS
+            .into_iter()
+            .map(|field| (field, self.updated_at(&change, field)))
+            .collect();
+        // 作成したTodoは記録して返し、反映できなかったブロッカーは競合として報告する
+        if !blocked_by.is_empty() {
+            match self.replace_blockers(&todo, &blocked_by).await {
+                Ok(updated) => todo = updated,
+                Err(_) => {
+                    changed.retain(|(field, _)| *field != SyncField::BlockedBy);
+                    result.conflicts.push(SyncConflict {
+                        id: todo.id,
+                        field: Some(SyncField::BlockedBy),
+                        resolution: Resolution::Server,
+                        client_value: json!(blocked_by),
+                        server_value: field_value(&todo, SyncField::BlockedBy),
+                    });
+                }
+            }
+        }
+        let state = self
+            .sync
+            .record(todo.id, changed)
+            .await
+            .or(Err(ERR_STR_UNEXPECTED.to_string()))?;
+        result.created.push(SyncCreated {
+            client_ref: change.client_ref,
+            id: todo.id,
+        });
+        result.todos.push(SyncedTodo::new(state, Some(todo)));
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        id: i32,
+        change: PushTodo,
+        result: &mut SyncPushResult,
+    ) -> Result<(), String> {
+        let state = self
+            .sync
+            .find(id)
+            .await
+            .or(Err(ERR_STR_UNEXPECTED.to_string()))?
+            .unwrap_or(SyncState::new(id));
+        let Ok(old_todo) = self.repository.find(id).await else {
+            if state.deleted_at.is_none() {
+                return Err(ERR_STR_NOT_FOUND.to_string());
+            }
+            // 削除済みのTodoへの変更はトゥームストーンを優先する
+            if change.deleted_at.is_none() {
+                result.conflicts.push(SyncConflict {
+                    id,
+                    field: None,
+                    resolution: Resolution::Server,
+                    client_value: json!({ "deleted_at": null }),
+                    server_value: json!({ "deleted_at": state.deleted_at }),
+                });
+            }
+            result.todos.push(SyncedTodo::new(state, None));
+            return Ok(());
+        };
+
+        if let Some(deleted_at) = change.deleted_at {
+            return self
+                .delete(old_todo, state, change, deleted_at, result)
+                .await;
+        }
+
+        let mut payload = UpdateTodo::default();
+        let mut blocked_by = None;
+        let mut changed = vec![];
+        for field in FIELDS {
+            let Some(client_value) = pushed_value(&change, field) else {
+                continue;
+            };
+            let server_value = field_value(&old_todo, field);
+            let clock = state.fields.get(field);
+            let updated_at = self.updated_at(&change, field);
+            // 最後に取り込んだ後にサーバー側でも変更されている
+            let concurrent = clock.version > change.base_version;
+            let client_wins = !concurrent || updated_at > clock.updated_at;
+            if concurrent && client_value != server_value {
+                result.conflicts.push(SyncConflict {
+                    id,
+                    field: Some(field),
+                    resolution: if client_wins {
+                        Resolution::Client
+                    } else {
+                        Resolution::Server
+                    },
+                    client_value: client_value.clone(),
+                    server_value: server_value.clone(),
+                });
+            }
+            if !client_wins || client_value == server_value {
+                continue;
+            }
+            match field {
+                SyncField::Text => payload.text = change.text.clone(),
+                SyncField::Notes => payload.notes = change.notes.clone(),
+                SyncField::Completed => payload.completed = change.completed,
+                SyncField::Labels => payload.labels = change.labels.clone(),
+                SyncField::Priority => payload.priority = change.priority,
+                SyncField::DueAt => payload.due_at = change.due_at,
+                SyncField::BlockedBy => blocked_by = change.blocked_by.clone(),
+            }
+            changed.push((field, updated_at));
+        }
+
+        if changed.is_empty() {
+            result.todos.push(SyncedTodo::new(state, Some(old_todo)));
+            return Ok(());
+        }
+        // 書き込む前に、ブロッカーを反映した後の状態で完了状態とWIP制限を確認する
+        let mut projected = old_todo.clone();
+        if let Some(blocked_by) = &blocked_by {
+            projected.blocked = self.blocked(blocked_by).await?;
+            projected.blocked_by = blocked_by.clone();
+        }
+        let has_update = payload != UpdateTodo::default();
+        if has_update {
+            self.transition(&projected, &mut payload).await?;
+        }
+
+        let mut todo = match &blocked_by {
+            Some(blocked_by) => self.replace_blockers(&old_todo, blocked_by).await?,
+            None => old_todo,
+        };
+        if has_update {
+            match self.write(&todo, payload).await {
+                Ok(updated) => todo = updated,
+                // ブロッカーだけを反映した場合は記録して返し、残りは競合として報告する
+                Err(reason) => {
+                    let (saved, failed): (Vec<_>, Vec<_>) = changed
+                        .into_iter()
+                        .partition(|(field, _)| *field == SyncField::BlockedBy);
+                    if saved.is_empty() {
+                        return Err(reason);
+                    }
+                    for (field, _) in failed {
+                        result.conflicts.push(SyncConflict {
+                            id,
+                            field: Some(field),
+                            resolution: Resolution::Server,
+                            client_value: pushed_value(&change, field).unwrap_or_default(),
+                            server_value: field_value(&todo, field),
+                        });
+                    }
+                    changed = saved;
+                }
+            }
+        }
+        let state = self
+            .sync
+            .record(id, changed)
+            .await
+            .or(Err(ERR_STR_UNEXPECTED.to_string()))?;
+        result.todos.push(SyncedTodo::new(state, Some(todo)));
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        old_todo: TodoEntity,
+        state: SyncState,
+        change: PushTodo,
+        deleted_at: DateTime<Utc>,
+        result: &mut SyncPushResult,
+    ) -> Result<(), String> {
+        let id = old_todo.id;
+        // 削除した後にサーバー側で変更されていれば、その変更を残す
+        let edited_later = FIELDS.into_iter().any(|field| {
+            let clock = state.fields.get(field);
+            clock.version > change.base_version && clock.updated_at > deleted_at
+        });
+        if edited_later {
+            result.conflicts.push(SyncConflict {
+                id,
+                field: None,
+                resolution: Resolution::Server,
+                client_value: json!({ "deleted_at": deleted_at }),
+                server_value: json!(old_todo),
+            });
+            result.todos.push(SyncedTodo::new(state, Some(old_todo)));
+            return Ok(());
+        }
+
+        self.repository
+            .delete(id)
+            .await
+            .or(Err(ERR_STR_NOT_FOUND.to_string()))?;
+        // 添付ファイルのBlobはAttachmentJanitorがバックグラウンドで削除する
+        if let Err(e) = self.attachments.detach_todo(id).await {
+            tracing::error!("fail schedule attachment cleanup for todo [{}]: {}", id, e);
+        }
+        webhook::notify(
+            self.webhooks.clone(),
+            WebhookEvent::TodoDeleted,
+            &json!({ "id": id }),
+        );
+        let state = self
+            .sync
+            .tombstone(id, deleted_at)
+            .await
+            .or(Err(ERR_STR_UNEXPECTED.to_string()))?;
+        result.todos.push(SyncedTodo::new(state, None));
+        Ok(())
+    }
+
+    // REST APIの更新と同じくステータスを連動させ、完了できるかとWIP制限を確認する
+    async fn transition(
+        &self,
+        old_todo: &TodoEntity,
+        payload: &mut UpdateTodo,
+    ) -> Result<(), String> {
+        if let Err(response) =
+            resolve_status_transition(self.repository, Some(self.statuses), old_todo, payload).await
+        {
+            return Err(rejection_reason(response).await);
+        }
+        Ok(())
+    }
+
+    // `transition` で確認した変更を反映し、Webhookに通知する
+    async fn write(
+        &self,
+        old_todo: &TodoEntity,
+        payload: UpdateTodo,
+    ) -> Result<TodoEntity, String> {
+        let todo = self
+            .repository
+            .update(old_todo.id, payload)
+            .await
+            .map_err(|e| repository_rejection(e, ERR_STR_NOT_FOUND))?;
+        if !old_todo.completed && todo.completed {
+            webhook::notify(self.webhooks.clone(), WebhookEvent::TodoCompleted, &todo);
+        }
+        webhook::notify(self.webhooks.clone(), WebhookEvent::TodoUpdated, &todo);
+        Ok(todo)
+    }
+}
@@ -1,70 +1,598 @@
-use crate::repositories::todo::{CreateTodo, TodoRepository, UpdateTodo};
-use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
-use std::sync::Arc;
+use super::{
+    optional,
+    project::validate_project,
+    status::{resolve_initial_status, resolve_status_transition},
+    sync::{record_change, record_delete, record_dependencies},
+};
+use crate::repositories::{
+    attachment::AttachmentRepository,
+    label::{
+        descendants, exclusive_conflicts, find_by_path, find_duplicate, normalize_label_name,
+        CreateLabel, ExclusiveLabelError, LabelRepository,
+    },
+    project::{ProjectRepository, DEFAULT_PROJECT_ID},
+    status::StatusRepository,
+    sync::SyncRepository,
+    todo::{
+        next_todos, CreateTodo, DependencyCycleError, QuickAdd, QuickAddLabel, QuickAddPreview,
+        TodoDependency, TodoEntity, TodoRepository, UpdateTodo,
+    },
+    webhook::{WebhookEvent, WebhookRepository},
+    RepositoryError,
+};
+use crate::{
+    markdown::{checklist, render_html, Checklist},
+    quick_add,
+    validation::{ValidJson, Validate, ValidationErrors, ValidationRules},
+    webhook,
+};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{Offset, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{cmp::Ordering, sync::Arc};
+use utoipa::{IntoParams, ToSchema};
 
-const ERR_STR_EMPTY: &str = "Error!: Can not be Empty";
-const ERR_STR_OVER: &str = "Error!: Over text length";
 const ERR_STR_NOT_FOUND: &str = "Todo not found";
+const ERR_STR_LABEL_NOT_FOUND: &str = "Error!: Label not found";
+const ERR_STR_BLOCKER_NOT_FOUND: &str = "Error!: Blocker todo not found";
+const ERR_STR_DEPENDENCY_CYCLE: &str = "Error!: Dependency would create a cycle";
+const ERR_STR_DEPENDENCY_EXISTS: &str = "Error!: Dependency already exists";
+const ERR_STR_DEPENDENCY_NOT_FOUND: &str = "Dependency not found";
 
-pub async fn create_todo<T: TodoRepository>(
+/// `GET /todos` の並べ替えの基準。同じ値のTodoはID順に並べる
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoSort {
+    #[default]
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    /// 未完了のTodoは昇順・降順に関わらず最後に並べる
+    CompletedAt,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// メモの返し方
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotesRender {
+    /// Markdownのまま `notes` で返す
+    #[default]
+    Markdown,
+    /// `notes` に加えて、HTMLに変換したメモを `notes_html` で返す
+    Html,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TodoQuery {
+    /// ラベルのIDまたはパス (例: `area/backend`)。子孫のラベルが付いたTodoも含める
+    label: Option<String>,
+    /// タイトルかメモに含む文字列 (大文字・小文字は区別しない)
+    q: Option<String>,
+    /// 既定は `id`
+    sort: Option<TodoSort>,
+    /// 既定は `desc` (新しい順)
+    order: Option<SortOrder>,
+    /// 既定は `markdown`
+    render: Option<NotesRender>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RenderQuery {
+    /// 既定は `markdown`
+    render: Option<NotesRender>,
+}
+
+/// 取得したTodo。メモのチェックリストの進み具合と、指定した場合はHTMLに変換したメモを含める
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TodoView {
+    #[serde(flatten)]
+    pub todo: TodoEntity,
+    /// メモにタスクリストがなければnull
+    pub checklist: Option<Checklist>,
+    /// `render=html` の場合だけ返す
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes_html: Option<String>,
+}
+
+impl TodoView {
+    fn new(todo: TodoEntity, render: Option<NotesRender>) -> Self {
+        let notes = todo.notes.as_deref().unwrap_or_default();
+        let notes_html =
+            (render.unwrap_or_default() == NotesRender::Html).then(|| render_html(notes));
+        Self {
+            checklist: checklist(notes),
+            notes_html,
+            todo,
+        }
+    }
+}
+
+fn sort_todos(todos: &mut [TodoEntity], sort: TodoSort, order: SortOrder) {
+    todos.sort_by(|a, b| {
+        let incomplete = match sort {
+            TodoSort::CompletedAt => a.completed_at.is_none().cmp(&b.completed_at.is_none()),
+            _ => Ordering::Equal,
+        };
+        let ordering = match sort {
+            TodoSort::Id => Ordering::Equal,
+            TodoSort::CreatedAt => a.created_at.cmp(&b.created_at),
+            TodoSort::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+            TodoSort::CompletedAt => a.completed_at.cmp(&b.completed_at),
+        }
+        .then(a.id.cmp(&b.id));
+        incomplete.then(match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        })
+    });
+}
+
+#[utoipa::path(
+    post, path = "/todos", tag = "todos",
+    request_body = CreateTodo,
+    responses(
+        (status = 201, body = TodoEntity),
+        (status = 400, description = "入力値またはプロジェクトが不正"),
+        (status = 409, description = "WIP制限を超える"),
+    )
+)]
+pub async fn create_todo<
+    T: TodoRepository,
+    P: ProjectRepository,
+    S: StatusRepository,
+    W: WebhookRepository,
+    Y: SyncRepository,
+>(
     Extension(repository): Extension<Arc<T>>,
-    Json(payload): Json<CreateTodo>,
+    projects: Option<Extension<Arc<P>>>,
+    statuses: Option<Extension<Arc<S>>>,
+    Extension(webhooks): Extension<Arc<W>>,
+    sync: Option<Extension<Arc<Y>>>,
+    ValidJson(payload): ValidJson<CreateTodo>,
 ) -> Result<impl IntoResponse, StatusCode> {
-    let response = match payload.text.len() {
-        0 => (StatusCode::BAD_REQUEST, ERR_STR_EMPTY.to_string()).into_response(),
-        len if len > 100 => (StatusCode::BAD_REQUEST, ERR_STR_OVER.to_string()).into_response(),
-        _ => {
-            let todo = repository
-                .create(payload)
-                .await
-                .or(Err(StatusCode::NOT_FOUND))?;
-            (StatusCode::CREATED, Json(todo)).into_response()
-        }
+    let project_id = payload.project_id.unwrap_or(DEFAULT_PROJECT_ID);
+    if let Err(response) = validate_project(optional(&projects), project_id).await {
+        return Ok(response);
+    }
+    insert_todo(
+        &*repository,
+        optional(&statuses),
+        webhooks,
+        optional(&sync),
+        payload,
+    )
+    .await
+}
+
+/// 初期ステータスを決めてTodoを作る。プロジェクトは呼び出し側で確認しておく
+async fn insert_todo<
+    T: TodoRepository,
+    S: StatusRepository,
+    W: WebhookRepository,
+    Y: SyncRepository,
+>(
+    repository: &T,
+    statuses: Option<&S>,
+    webhooks: Arc<W>,
+    sync: Option<&Y>,
+    mut payload: CreateTodo,
+) -> Result<Response, StatusCode> {
+    let project_id = payload.project_id.unwrap_or(DEFAULT_PROJECT_ID);
+    if let Err(response) =
+        resolve_initial_status(repository, statuses, project_id, &mut payload).await
+    {
+        return Ok(response);
+    }
+    let todo = match repository.create(payload).await {
+        Ok(todo) => todo,
+        Err(e) => match e.downcast_ref::<ExclusiveLabelError>() {
+            Some(error) => return Ok(ValidationErrors::from(error).into_response()),
+            None => return Err(StatusCode::NOT_FOUND),
+        },
     };
+    record_change(sync, None, &todo).await;
+    webhook::notify(webhooks, WebhookEvent::TodoCreated, &todo);
 
-    Ok(response)
+    Ok((StatusCode::CREATED, Json(todo)).into_response())
+}
+
+#[utoipa::path(
+    post, path = "/todos/quick", tag = "todos",
+    request_body = QuickAdd,
+    responses(
+        (status = 200, body = QuickAddPreview, description = "`preview` の場合。Todoは作らない"),
+        (status = 201, body = TodoEntity),
+        (status = 400, description = "入力値やプロジェクトが不正、またはラベルが存在しない"),
+        (status = 409, description = "WIP制限を超える"),
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn quick_add_todo<
+    T: TodoRepository,
+    L: LabelRepository,
+    P: ProjectRepository,
+    S: StatusRepository,
+    W: WebhookRepository,
+    Y: SyncRepository,
+>(
+    Extension(repository): Extension<Arc<T>>,
+    Extension(labels): Extension<Arc<L>>,
+    projects: Option<Extension<Arc<P>>>,
+    statuses: Option<Extension<Arc<S>>>,
+    Extension(webhooks): Extension<Arc<W>>,
+    sync: Option<Extension<Arc<Y>>>,
+    rules: Option<Extension<Arc<ValidationRules>>>,
+    ValidJson(payload): ValidJson<QuickAdd>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let rules = rules.map(|Extension(rules)| rules).unwrap_or_default();
+    let offset = payload
+        .utc_offset
+        .as_deref()
+        .and_then(|offset| offset.parse().ok())
+        .unwrap_or(Utc.fix());
+    let parsed = quick_add::parse(&payload.input, Utc::now().with_timezone(&offset));
+    let all_labels = labels
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    let preview = QuickAddPreview {
+        text: parsed.text,
+        labels: parsed
+            .labels
+            .into_iter()
+            .map(|name| {
+                // 大文字・小文字などの違いを無視して最上位のラベルの名前で探し、なければ `親/子` のパスとして探す
+                let id = find_duplicate(&all_labels, &name, None, None)
+                    .or_else(|| find_by_path(&all_labels, &name))
+                    .map(|label| label.id);
+                QuickAddLabel { name, id }
+            })
+            .collect(),
+        priority: parsed.priority,
+        due_at: parsed.due_at.map(|due_at| due_at.with_timezone(&Utc)),
+    };
+    if payload.preview {
+        return Ok((StatusCode::OK, Json(preview)).into_response());
+    }
+
+    let mut errors = ValidationErrors::default();
+    let mut text = preview.text;
+    rules.todo_text.apply("text", &mut text, &mut errors);
+    let mut ids = vec![];
+    let mut new_labels: Vec<CreateLabel> = vec![];
+    for (index, label) in preview.labels.iter().enumerate() {
+        let field = format!("labels[{}]", index);
+        match label.id {
+            Some(id) => {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+            None if payload.create_labels => {
+                let name = normalize_label_name(&label.name);
+                if new_labels
+                    .iter()
+                    .any(|new| normalize_label_name(&new.name) == name)
+                {
+                    continue;
+                }
+                let mut create = CreateLabel::new(label.name.clone());
+                let mut label_errors = ValidationErrors::default();
+                create.validate(&rules, &mut label_errors);
+                for error in label_errors.errors {
+                    errors.push(&field, &error.code, &error.message);
+                }
+                new_labels.push(create);
+            }
+            None => errors.push(&field, "not_found", ERR_STR_LABEL_NOT_FOUND),
+        }
+    }
+    rules.apply_label_count("labels", ids.len() + new_labels.len(), &mut errors);
+    for (_, error) in exclusive_conflicts(&all_labels, &ids) {
+        errors.errors.extend(ValidationErrors::from(&error).errors);
+    }
+    if !errors.is_empty() {
+        return Ok(errors.into_response());
+    }
+    // ラベルを作る前にプロジェクトを確認する
+    let project_id = payload.project_id.unwrap_or(DEFAULT_PROJECT_ID);
+    if let Err(response) = validate_project(optional(&projects), project_id).await {
+        return Ok(response);
+    }
+
+    for create in new_labels {
+        let label = labels
+            .create(create)
+            .await
+            .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+        webhook::notify(webhooks.clone(), WebhookEvent::LabelCreated, &label);
+        ids.push(label.id);
+    }
+    let payload = CreateTodo {
+        priority: preview.priority,
+        due_at: preview.due_at,
+        project_id: payload.project_id,
+        ..CreateTodo::new(text, ids)
+    };
+    insert_todo(
+        &*repository,
+        optional(&statuses),
+        webhooks,
+        optional(&sync),
+        payload,
+    )
+    .await
 }
 
+#[utoipa::path(
+    get, path = "/todos/{id}", tag = "todos",
+    params(("id" = i32, Path, description = "Todo ID"), RenderQuery),
+    responses(
+        (status = 201, body = TodoView),
+        (status = 404, description = "Todoが存在しない"),
+    )
+)]
 pub async fn find_todo<T: TodoRepository>(
     Path(id): Path<i32>,
+    Query(query): Query<RenderQuery>,
     Extension(repository): Extension<Arc<T>>,
 ) -> Result<impl IntoResponse, StatusCode> {
     let todo = repository.find(id).await.or(Err(StatusCode::NOT_FOUND))?;
-    let response = (StatusCode::CREATED, Json(todo)).into_response();
+    let response = (StatusCode::CREATED, Json(TodoView::new(todo, query.render))).into_response();
 
     Ok(response)
 }
 
-pub async fn all_todo<T: TodoRepository>(
+#[utoipa::path(
+    get, path = "/todos", tag = "todos",
+    params(TodoQuery),
+    responses(
+        (status = 200, body = Vec<TodoView>),
+        (status = 400, description = "ラベルが存在しない"),
+    )
+)]
+pub async fn all_todo<T: TodoRepository, L: LabelRepository>(
+    Query(query): Query<TodoQuery>,
     Extension(repository): Extension<Arc<T>>,
+    Extension(labels): Extension<Arc<L>>,
 ) -> Result<impl IntoResponse, StatusCode> {
-    let todos = repository.all().await.unwrap();
+    let mut todos = repository.all().await.unwrap();
+    if let Some(label) = query.label {
+        let labels = labels
+            .all()
+            .await
+            .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+        let Some(label) = find_by_path(&labels, &label) else {
+            return Ok(
+                (StatusCode::BAD_REQUEST, ERR_STR_LABEL_NOT_FOUND.to_string()).into_response(),
+            );
+        };
+        let ids = descendants(&labels, label.id);
+        todos.retain(|todo| todo.labels.iter().any(|label| ids.contains(&label.id)));
+    }
+    if let Some(q) = query.q {
+        todos.retain(|todo| todo.matches(&q));
+    }
+    sort_todos(
+        &mut todos,
+        query.sort.unwrap_or_default(),
+        query.order.unwrap_or_default(),
+    );
+    let todos: Vec<TodoView> = todos
+        .into_iter()
+        .map(|todo| TodoView::new(todo, query.render))
+        .collect();
     Ok((StatusCode::OK, Json(todos)).into_response())
 }
 
-pub async fn update_todo<T: TodoRepository>(
+#[utoipa::path(
+    patch, path = "/todos/{id}", tag = "todos",
+    params(("id" = i32, Path, description = "Todo ID")),
+    request_body = UpdateTodo,
+    responses(
+        (status = 201, body = TodoEntity),
+        (status = 400, description = "入力値またはプロジェクトが不正"),
+        (status = 404, description = "Todoが存在しない"),
+        (status = 409, description = "WIP制限を超える、または未完了のブロッカーがあるのに完了しようとした"),
+    )
+)]
+pub async fn update_todo<
+    T: TodoRepository,
+    P: ProjectRepository,
+    S: StatusRepository,
+    W: WebhookRepository,
+    Y: SyncRepository,
+>(
     Extension(repository): Extension<Arc<T>>,
+    projects: Option<Extension<Arc<P>>>,
+    statuses: Option<Extension<Arc<S>>>,
+    Extension(webhooks): Extension<Arc<W>>,
+    sync: Option<Extension<Arc<Y>>>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateTodo>,
+    ValidJson(mut payload): ValidJson<UpdateTodo>,
 ) -> Result<impl IntoResponse, StatusCode> {
-    let response = match payload.text.as_deref().unwrap_or("").len() {
-        0 => (StatusCode::BAD_REQUEST, ERR_STR_EMPTY.to_string()).into_response(),
-        len if len > 100 => (StatusCode::BAD_REQUEST, ERR_STR_OVER.to_string()).into_response(),
-        _ => match repository.update(id, payload).await {
-            Ok(todo) => (StatusCode::CREATED, Json(todo)).into_response(),
-            Err(_) => (StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response(),
+    let Ok(old_todo) = repository.find(id).await else {
+        return Ok((StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response());
+    };
+    // 別のプロジェクトへ移動する場合は移動先を確認する
+    if let Some(project_id) = payload.project_id {
+        if let Err(response) = validate_project(optional(&projects), project_id).await {
+            return Ok(response);
+        }
+    }
+    if let Err(response) =
+        resolve_status_transition(&*repository, optional(&statuses), &old_todo, &mut payload).await
+    {
+        return Ok(response);
+    }
+    let response = match repository.update(id, payload).await {
+        Ok(todo) => {
+            record_change(optional(&sync), Some(&old_todo), &todo).await;
+            if !old_todo.completed && todo.completed {
+                webhook::notify(webhooks.clone(), WebhookEvent::TodoCompleted, &todo);
+            }
+            webhook::notify(webhooks, WebhookEvent::TodoUpdated, &todo);
+            (StatusCode::CREATED, Json(todo)).into_response()
+        }
+        Err(e) => match e.downcast_ref::<ExclusiveLabelError>() {
+            Some(error) => ValidationErrors::from(error).into_response(),
+            None => (StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response(),
         },
     };
 
     Ok(response)
 }
 
-pub async fn delete_todo<T: TodoRepository>(
+#[utoipa::path(
+    delete, path = "/todos/{id}", tag = "todos",
+    params(("id" = i32, Path, description = "Todo ID")),
+    responses(
+        (status = 204),
+        (status = 404, description = "Todoが存在しない"),
+    )
+)]
+pub async fn delete_todo<
+    T: TodoRepository,
+    W: WebhookRepository,
+    A: AttachmentRepository,
+    Y: SyncRepository,
+>(
     Path(id): Path<i32>,
     Extension(repository): Extension<Arc<T>>,
+    Extension(webhooks): Extension<Arc<W>>,
+    attachments: Option<Extension<Arc<A>>>,
+    sync: Option<Extension<Arc<Y>>>,
 ) -> StatusCode {
     match repository.delete(id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
+        Ok(_) => {
+            record_delete(optional(&sync), id).await;
+            // 添付ファイルのBlobはAttachmentJanitorがバックグラウンドで削除する
+            if let Some(attachments) = optional(&attachments) {
+                if let Err(e) = attachments.detach_todo(id).await {
+                    tracing::error!("fail schedule attachment cleanup for todo [{}]: {}", id, e);
+                }
+            }
+            webhook::notify(webhooks, WebhookEvent::TodoDeleted, &json!({ "id": id }));
+            StatusCode::NO_CONTENT
+        }
         Err(_) => StatusCode::NOT_FOUND,
     }
 }
+
+/// 完了を待つTodo (ブロッカー) を追加する。ブロッカーが間接的にこのTodoを待っていると循環するため追加しない
+#[utoipa::path(
+    post, path = "/todos/{id}/dependencies", tag = "todos",
+    params(("id" = i32, Path, description = "Todo ID")),
+    request_body = TodoDependency,
+    responses(
+        (status = 201, body = TodoEntity),
+        (status = 400, description = "入力値が不正、またはブロッカーが存在しない"),
+        (status = 404, description = "Todoが存在しない"),
+        (status = 409, description = "依存関係が循環する、または追加済み"),
+    )
+)]
+pub async fn add_dependency<T: TodoRepository, W: WebhookRepository, Y: SyncRepository>(
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<T>>,
+    Extension(webhooks): Extension<Arc<W>>,
+    sync: Option<Extension<Arc<Y>>>,
+    ValidJson(payload): ValidJson<TodoDependency>,
+) -> Result<impl IntoResponse, StatusCode> {
+    // 循環の確認はリポジトリが追加と同じトランザクションで行う
+    let e = match repository.add_dependency(id, payload.blocker_id).await {
+        Ok(todo) => {
+            record_dependencies(optional(&sync), id).await;
+            webhook::notify(webhooks, WebhookEvent::TodoUpdated, &todo);
+            return Ok((StatusCode::CREATED, Json(todo)).into_response());
+        }
+        Err(e) => e,
+    };
+    if e.downcast_ref::<DependencyCycleError>().is_some() {
+        return Ok((StatusCode::CONFLICT, ERR_STR_DEPENDENCY_CYCLE.to_string()).into_response());
+    }
+    let response = match e.downcast_ref::<RepositoryError>() {
+        Some(RepositoryError::NotFound(not_found)) if *not_found == id => {
+            (StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response()
+        }
+        Some(RepositoryError::NotFound(_)) => {
+            let mut errors = ValidationErrors::default();
+            errors.push("blocker_id", "not_found", ERR_STR_BLOCKER_NOT_FOUND);
+            errors.into_response()
+        }
+        Some(RepositoryError::Duplicate(_)) => {
+            (StatusCode::CONFLICT, ERR_STR_DEPENDENCY_EXISTS.to_string()).into_response()
+        }
+        _ => {
+            tracing::error!("fail add dependency to todo [{}]: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    };
+    Ok(response)
+}
+
+#[utoipa::path(
+    delete, path = "/todos/{id}/dependencies", tag = "todos",
+    params(("id" = i32, Path, description = "Todo ID")),
+    request_body = TodoDependency,
+    responses(
+        (status = 204),
+        (status = 404, description = "Todoまたは依存関係が存在しない"),
+    )
+)]
+pub async fn remove_dependency<T: TodoRepository, W: WebhookRepository, Y: SyncRepository>(
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<T>>,
+    Extension(webhooks): Extension<Arc<W>>,
+    sync: Option<Extension<Arc<Y>>>,
+    ValidJson(payload): ValidJson<TodoDependency>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if repository.find(id).await.is_err() {
+        return Ok((StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response());
+    }
+    let response = match repository.remove_dependency(id, payload.blocker_id).await {
+        Ok(todo) => {
+            record_dependencies(optional(&sync), id).await;
+            webhook::notify(webhooks, WebhookEvent::TodoUpdated, &todo);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(_) => (
+            StatusCode::NOT_FOUND,
+            ERR_STR_DEPENDENCY_NOT_FOUND.to_string(),
+        )
+            .into_response(),
+    };
+    Ok(response)
+}
+
+/// 次に着手できる順に未完了のTodoを返す
+///
+/// ブロッカーを先に並べ、同時に着手できるものは優先度の高い順、期限の近い順に並べる
+#[utoipa::path(
+    get, path = "/todos/next", tag = "todos",
+    responses(
+        (status = 200, body = Vec<TodoEntity>),
+    )
+)]
+pub async fn next_todo<T: TodoRepository>(
+    Extension(repository): Extension<Arc<T>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let todos = repository
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok((StatusCode::OK, Json(next_todos(todos))).into_response())
+}
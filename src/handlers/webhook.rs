@@ -0,0 +1,158 @@
+use crate::repositories::{
+    webhook::{CreateWebhook, UpdateWebhook, Webhook, WebhookDelivery, WebhookRepository},
+    RepositoryError,
+};
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+const ERR_STR_INVALID_URL: &str = "Error!: Invalid url";
+const ERR_STR_EMPTY_EVENTS: &str = "Error!: Events can not be Empty";
+const ERR_STR_NOT_FOUND: &str = "Webhook not found";
+
+// 作成時のみ署名用の秘密鍵を返す
+#[derive(Serialize, ToSchema)]
+struct CreatedWebhook {
+    #[serde(flatten)]
+    webhook: Webhook,
+    secret: String,
+}
+
+fn is_valid_url(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .map(|url| matches!(url.scheme(), "http" | "https"))
+        .unwrap_or(false)
+}
+
+#[utoipa::path(
+    post, path = "/webhooks", tag = "webhooks",
+    request_body = CreateWebhook,
+    responses(
+        (status = 201, body = CreatedWebhook),
+        (status = 400, description = "URLまたはイベントが不正"),
+    )
+)]
+pub async fn create_webhook<T: WebhookRepository>(
+    Extension(repository): Extension<Arc<T>>,
+    Json(payload): Json<CreateWebhook>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if !is_valid_url(&payload.url) {
+        return Ok((StatusCode::BAD_REQUEST, ERR_STR_INVALID_URL.to_string()).into_response());
+    }
+    if payload.events.is_empty() {
+        return Ok((StatusCode::BAD_REQUEST, ERR_STR_EMPTY_EVENTS.to_string()).into_response());
+    }
+
+    let webhook = repository
+        .create(payload)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    let secret = webhook.secret.clone();
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedWebhook { webhook, secret }),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get, path = "/webhooks", tag = "webhooks",
+    responses((status = 200, body = Vec<Webhook>))
+)]
+pub async fn all_webhook<T: WebhookRepository>(
+    Extension(repository): Extension<Arc<T>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let webhooks = repository
+        .all()
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok((StatusCode::OK, Json(webhooks)).into_response())
+}
+
+#[utoipa::path(
+    patch, path = "/webhooks/{id}", tag = "webhooks",
+    params(("id" = i32, Path, description = "Webhook ID")),
+    request_body = UpdateWebhook,
+    responses(
+        (status = 200, body = Webhook),
+        (status = 400, description = "URLまたはイベントが不正"),
+        (status = 404, description = "Webhookが存在しない"),
+    )
+)]
+pub async fn update_webhook<T: WebhookRepository>(
+    Extension(repository): Extension<Arc<T>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateWebhook>,
+) -> Result<impl IntoResponse, StatusCode> {
+    if payload.url.as_deref().is_some_and(|url| !is_valid_url(url)) {
+        return Ok((StatusCode::BAD_REQUEST, ERR_STR_INVALID_URL.to_string()).into_response());
+    }
+    if payload
+        .events
+        .as_ref()
+        .is_some_and(|events| events.is_empty())
+    {
+        return Ok((StatusCode::BAD_REQUEST, ERR_STR_EMPTY_EVENTS.to_string()).into_response());
+    }
+
+    let response = match repository.update(id, payload).await {
+        Ok(webhook) => (StatusCode::OK, Json(webhook)).into_response(),
+        Err(e) => match e.downcast_ref::<RepositoryError>() {
+            Some(RepositoryError::NotFound(_)) => {
+                (StatusCode::NOT_FOUND, ERR_STR_NOT_FOUND.to_string()).into_response()
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        },
+    };
+    Ok(response)
+}
+
+#[utoipa::path(
+    delete, path = "/webhooks/{id}", tag = "webhooks",
+    params(("id" = i32, Path, description = "Webhook ID")),
+    responses(
+        (status = 204),
+        (status = 404, description = "Webhookが存在しない"),
+        (status = 500),
+    )
+)]
+pub async fn delete_webhook<T: WebhookRepository>(
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<T>>,
+) -> StatusCode {
+    match repository.delete(id).await {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(e) => match e.downcast_ref::<RepositoryError>() {
+            Some(RepositoryError::NotFound(_)) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        },
+    }
+}
+
+#[utoipa::path(
+    get, path = "/webhooks/{id}/deliveries", tag = "webhooks",
+    params(("id" = i32, Path, description = "Webhook ID")),
+    responses(
+        (status = 200, body = Vec<WebhookDelivery>),
+        (status = 404, description = "Webhookが存在しない"),
+        (status = 500),
+    )
+)]
+pub async fn all_webhook_delivery<T: WebhookRepository>(
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<T>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    repository
+        .find(id)
+        .await
+        .map_err(|e| match e.downcast_ref::<RepositoryError>() {
+            Some(RepositoryError::NotFound(_)) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+    let deliveries = repository
+        .deliveries(id)
+        .await
+        .or(Err(StatusCode::INTERNAL_SERVER_ERROR))?;
+    Ok((StatusCode::OK, Json(deliveries)).into_response())
+}
@@ -0,0 +1,2626 @@
+pub mod cache;
+mod handlers;
+mod markdown;
+mod openapi;
+mod quick_add;
+pub mod repositories;
+pub mod storage;
+pub mod validation;
+pub mod webhook;
+
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{delete, get, post},
+    Extension, Router,
+};
+use handlers::{
+    attachment::{
+        all_attachment, create_attachment, delete_attachment, find_attachment, MAX_ATTACHMENT_SIZE,
+    },
+    comment::{all_comment, create_comment, delete_comment, update_comment, USER_HEADER},
+    label::{all_label, create_label, delete_label, merge_labels, update_label},
+    project::{
+        all_project, all_project_todo, create_project, delete_project, find_project, update_project,
+    },
+    stats::find_stats,
+    status::{all_status, create_status, delete_status, find_board, update_status},
+    sync::{sync_changes, sync_push},
+    todo::{
+        add_dependency, all_todo, create_todo, delete_todo, find_todo, next_todo, quick_add_todo,
+        remove_dependency, update_todo,
+    },
+    webhook::{all_webhook, all_webhook_delivery, create_webhook, delete_webhook, update_webhook},
+};
+use hyper::header::{HeaderName, CONTENT_TYPE};
+use repositories::{
+    attachment::AttachmentRepository, comment::CommentRepository, label::LabelRepository,
+    project::ProjectRepository, status::StatusRepository, sync::SyncRepository,
+    todo::TodoRepository, webhook::WebhookRepository,
+};
+use std::sync::Arc;
+use storage::BlobStorage;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use validation::LabelCatalog;
+
+/// 公開するAPIの範囲
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApiScope {
+    #[default]
+    Full,
+    /// Todo・ラベル・Webhook・統計だけを公開する
+    ///
+    /// プロジェクト・ステータス・コメント・添付ファイル・同期はPostgres上でTodoを参照するため、
+    /// TodoをPostgres以外に保存する構成ではルートもAPI仕様も外し、Todoの操作からも使わない
+    TodosAndLabels,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_app<
+    Todo: TodoRepository,
+    Label: LabelRepository,
+    Webhook: WebhookRepository,
+    Project: ProjectRepository,
+    Status: StatusRepository,
+    Comment: CommentRepository,
+    Attachment: AttachmentRepository,
+    Storage: BlobStorage,
+    SyncLog: SyncRepository,
+>(
+    todo_repository: Todo,
+    label_repository: Label,
+    webhook_repository: Webhook,
+    project_repository: Project,
+    status_repository: Status,
+    comment_repository: Comment,
+    attachment_repository: Attachment,
+    storage: Storage,
+    sync_repository: SyncLog,
+    app_url: String,
+    scope: ApiScope,
+) -> Router {
+    let allowed_origins = vec![
+        "http://localhost:3001".parse().unwrap(),
+        "http://127.0.0.1:3001".parse().unwrap(),
+        "http://localhost:8080".parse().unwrap(),
+        "http://127.0.0.1:8080".parse().unwrap(),
+        app_url.parse().unwrap(),
+    ];
+
+    let cors = CorsLayer::new()
+        .allow_origin(AllowOrigin::list(allowed_origins))
+        .allow_methods(Any)
+        .allow_headers(vec![CONTENT_TYPE, HeaderName::from_static(USER_HEADER)]);
+
+    let router = Router::new()
+        .route("/", get(root))
+        .route("/openapi.json", get(openapi::openapi_json))
+        .route("/docs", get(openapi::redoc))
+        .route(
+            "/todos",
+            post(create_todo::<Todo, Project, Status, Webhook, SyncLog>)
+                .get(all_todo::<Todo, Label>),
+        )
+        .route(
+            "/todos/quick",
+            post(quick_add_todo::<Todo, Label, Project, Status, Webhook, SyncLog>),
+        )
+        .route("/todos/next", get(next_todo::<Todo>))
+        .route(
+            "/todos/:id",
+            get(find_todo::<Todo>)
+                .delete(delete_todo::<Todo, Webhook, Attachment, SyncLog>)
+                .patch(update_todo::<Todo, Project, Status, Webhook, SyncLog>),
+        )
+        .route(
+            "/todos/:id/dependencies",
+            post(add_dependency::<Todo, Webhook, SyncLog>)
+                .delete(remove_dependency::<Todo, Webhook, SyncLog>),
+        )
+        .route(
+            "/labels",
+            post(create_label::<Label, Webhook>).get(all_label::<Label, Todo>),
+        )
+        .route(
+            "/labels/:id",
+            delete(delete_label::<Label, Todo, Webhook, SyncLog>)
+                .patch(update_label::<Label, Webhook>),
+        )
+        .route(
+            "/labels/:id/merge",
+            post(merge_labels::<Label, Todo, Webhook, SyncLog>),
+        )
+        .route(
+            "/webhooks",
+            post(create_webhook::<Webhook>).get(all_webhook::<Webhook>),
+        )
+        .route(
+            "/webhooks/:id",
+            delete(delete_webhook::<Webhook>).patch(update_webhook::<Webhook>),
+        )
+        .route(
+            "/webhooks/:id/deliveries",
+            get(all_webhook_delivery::<Webhook>),
+        )
+        .route("/stats", get(find_stats::<Todo, Label>));
+
+    // Postgres上でTodoを参照する機能は、TodoもPostgresに保存する場合だけ公開する
+    let router = match scope {
+        ApiScope::Full => router
+            .route(
+                "/projects",
+                post(create_project::<Project>).get(all_project::<Project>),
+            )
+            .route(
+                "/projects/:id",
+                get(find_project::<Project>)
+                    .delete(delete_project::<Todo, Project>)
+                    .patch(update_project::<Project>),
+            )
+            .route(
+                "/projects/:id/todos",
+                get(all_project_todo::<Todo, Project>),
+            )
+            .route(
+                "/projects/:id/statuses",
+                post(create_status::<Status, Project>).get(all_status::<Status, Project>),
+            )
+            .route(
+                "/projects/:id/board",
+                get(find_board::<Todo, Project, Status>),
+            )
+            .route(
+                "/statuses/:id",
+                delete(delete_status::<Status>).patch(update_status::<Status>),
+            )
+            .route(
+                "/todos/:id/comments",
+                post(create_comment::<Todo, Comment>).get(all_comment::<Todo, Comment>),
+            )
+            .route(
+                "/comments/:id",
+                delete(delete_comment::<Comment>).patch(update_comment::<Comment>),
+            )
+            .route(
+                "/todos/:id/attachments",
+                post(create_attachment::<Todo, Attachment, Storage>)
+                    .get(all_attachment::<Todo, Attachment>)
+                    // multipartの境界などの分だけ余裕を持たせる
+                    .layer(DefaultBodyLimit::max(MAX_ATTACHMENT_SIZE + 64 * 1024)),
+            )
+            .route(
+                "/attachments/:id",
+                get(find_attachment::<Attachment, Storage>)
+                    .delete(delete_attachment::<Attachment, Storage>),
+            )
+            .route("/sync/changes", get(sync_changes::<Todo, SyncLog>))
+            .route(
+                "/sync/push",
+                post(sync_push::<Todo, Status, Webhook, Attachment, SyncLog>),
+            )
+            .layer(Extension(Arc::new(project_repository)))
+            .layer(Extension(Arc::new(status_repository)))
+            .layer(Extension(Arc::new(comment_repository)))
+            .layer(Extension(Arc::new(attachment_repository)))
+            .layer(Extension(Arc::new(storage)))
+            .layer(Extension(Arc::new(sync_repository))),
+        ApiScope::TodosAndLabels => router,
+    };
+
+    router
+        .layer(Extension(Arc::new(todo_repository)))
+        // 入力値の検証でラベルの存在を確認するため、リポジトリの型を消して渡す
+        .layer(Extension(
+            Arc::new(label_repository.clone()) as Arc<dyn LabelCatalog>
+        ))
+        .layer(Extension(Arc::new(label_repository)))
+        .layer(Extension(Arc::new(webhook_repository)))
+        .layer(Extension(scope))
+        .layer(cors)
+}
+
+async fn root() -> &'static str {
+    "Hello, World!"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::repositories::attachment::test_utils::AttachmentRepositoryForMemory;
+    use crate::repositories::comment::{test_utils::CommentRepositoryForMemory, Comment};
+    use crate::repositories::label::{
+        test_utils::LabelRepositoryForMemory, CreateLabel, Label, UpdateLabel, DEFAULT_LABEL_COLOR,
+    };
+    use crate::repositories::project::{
+        test_utils::ProjectRepositoryForMemory, CreateProject, Project, UpdateProject,
+        DEFAULT_PROJECT_ID,
+    };
+    use crate::repositories::status::{test_utils::StatusRepositoryForMemory, CreateStatus};
+    use crate::repositories::sync::{
+        test_utils::SyncRepositoryForMemory, FieldTimes, PushTodo, Resolution, SyncChanges,
+        SyncField, SyncPush, SyncPushResult, SyncedTodo,
+    };
+    use crate::repositories::todo::{
+        test_utils::{ManualClock, TodoRepositoryForMemory},
+        CreateTodo, Priority, QuickAdd, TodoEntity, UpdateTodo,
+    };
+    use crate::repositories::webhook::{
+        test_utils::WebhookRepositoryForMemory, CreateWebhook, WebhookEvent,
+    };
+    use crate::storage::{test_utils::MemoryStorage, AttachmentJanitor};
+    use crate::validation::{TextRule, ValidationRules};
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header, Method, Request, StatusCode},
+        response::Response,
+    };
+    use chrono::{DateTime, Duration, Utc};
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    /// Todoとラベル以外の構成。既定はメモリのリポジトリですべてのAPIを公開する。差し替える場合は `app_with` に渡す
+    #[derive(Default)]
+    struct AppParts {
+        webhooks: WebhookRepositoryForMemory,
+        projects: ProjectRepositoryForMemory,
+        statuses: StatusRepositoryForMemory,
+        comments: CommentRepositoryForMemory,
+        attachments: AttachmentRepositoryForMemory,
+        storage: MemoryStorage,
+        sync: SyncRepositoryForMemory,
+        scope: ApiScope,
+    }
+
+    fn app<T: TodoRepository, L: LabelRepository>(todos: T, labels: L) -> Router {
+        app_with(todos, labels, AppParts::default())
+    }
+
+    fn app_with<T: TodoRepository, L: LabelRepository>(
+        todos: T,
+        labels: L,
+        parts: AppParts,
+    ) -> Router {
+        create_app(
+            todos,
+            labels,
+            parts.webhooks,
+            parts.projects,
+            parts.statuses,
+            parts.comments,
+            parts.attachments,
+            parts.storage,
+            parts.sync,
+            "url".to_string(),
+            parts.scope,
+        )
+    }
+
+    fn build_req_with_json(path: &str, method: Method, json_body: String) -> Request<Body> {
+        Request::builder()
+            .uri(path)
+            .method(method)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(json_body))
+            .unwrap()
+    }
+
+    fn build_todo_req_with_empty(method: Method, path: &str) -> Request<Body> {
+        Request::builder()
+            .uri(path)
+            .method(method)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    async fn res_to_todo(res: Response) -> TodoEntity {
+        // axum 0.4.8, hyper 0.14.16
+        // let bytes = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        // axum 0.7.5, hyper 1.4.1
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: String = String::from_utf8(bytes.to_vec()).unwrap();
+        let todo: TodoEntity = serde_json::from_str(&body)
+            .unwrap_or_else(|_| panic!("cannot convert Todo instance. body:{}", body));
+        todo
+    }
+
+    async fn res_to_label(res: Response) -> Label {
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: String = String::from_utf8(bytes.to_vec()).unwrap();
+        let label: Label = serde_json::from_str(&body)
+            .unwrap_or_else(|_| panic!("cannot convert Label instance. body: {}", body));
+        label
+    }
+
+    fn label_fixture() -> (Vec<Label>, Vec<i32>) {
+        let id = 999;
+        (
+            vec![Label::new(id, String::from("test label main"))],
+            vec![id],
+        )
+    }
+
+    #[tokio::test]
+    async fn should_created_todo() {
+        let (labels, _label_ids) = label_fixture();
+        let expected = TodoEntity::new(1, "should_return_created_todo".to_string(), labels.clone());
+
+        let req = build_req_with_json(
+            "/todos",
+            Method::POST,
+            r#"{ "text": "should_return_created_todo", "labels": [999] }"#.to_string(),
+        );
+        let res = app(
+            TodoRepositoryForMemory::new(labels.clone()),
+            LabelRepositoryForMemory::with_labels(labels),
+        )
+        .oneshot(req)
+        .await
+        .unwrap();
+        let todo = res_to_todo(res).await;
+        assert_eq!(expected, todo);
+    }
+
+    #[tokio::test]
+    async fn should_find_todo() {
+        let (labels, label_ids) = label_fixture();
+        let expected = TodoEntity::new(1, "should_find_todo".to_string(), labels.clone());
+
+        let todo_repository = TodoRepositoryForMemory::new(labels.clone());
+        todo_repository
+            .create(CreateTodo::new("should_find_todo".to_string(), label_ids))
+            .await
+            .expect("failed create todo");
+        let req = build_todo_req_with_empty(Method::GET, "/todos/1");
+        let res = app(todo_repository, LabelRepositoryForMemory::new())
+            .oneshot(req)
+            .await
+            .unwrap();
+        let todo = res_to_todo(res).await;
+        assert_eq!(expected, todo);
+    }
+
+    #[tokio::test]
+    async fn should_get_all_todos() {
+        let (labels, label_ids) = label_fixture();
+        let expected = TodoEntity::new(1, "should_get_all_todos".to_string(), labels.clone());
+
+        let todo_repository = TodoRepositoryForMemory::new(labels.clone());
+        todo_repository
+            .create(CreateTodo::new(
+                "should_get_all_todos".to_string(),
+                label_ids,
+            ))
+            .await
+            .expect("failed create todo");
+        let req = build_todo_req_with_empty(Method::GET, "/todos");
+        let res = app(todo_repository, LabelRepositoryForMemory::new())
+            .oneshot(req)
+            .await
+            .unwrap();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: String = String::from_utf8(bytes.to_vec()).unwrap();
+        let todos: Vec<TodoEntity> = serde_json::from_str(&body)
+            .unwrap_or_else(|_| panic!("cannot convert Todo list instance. body: {}", body));
+        assert_eq!(vec![expected], todos);
+    }
+
+    #[tokio::test]
+    async fn should_sort_todos() {
+        let clock = ManualClock::default();
+        let todo_repository = TodoRepositoryForMemory::new(vec![]).with_clock(clock.clone());
+        for text in ["first", "second", "third"] {
+            todo_repository
+                .create(CreateTodo::new(text.to_string(), vec![]))
+                .await
+                .expect("failed create todo");
+            clock.advance(Duration::minutes(1));
+        }
+        // 1件目を最後に更新し、2件目と3件目をこの順に完了にする
+        for (id, completed) in [(2, true), (3, true), (1, false)] {
+            todo_repository
+                .update(
+                    id,
+                    UpdateTodo {
+                        completed: Some(completed),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .expect("failed update todo");
+            clock.advance(Duration::minutes(1));
+        }
+        let app = app(todo_repository, LabelRepositoryForMemory::new());
+        let ids = |path: &str| {
+            let req = build_todo_req_with_empty(Method::GET, path);
+            let app = app.clone();
+            async move {
+                let res = app.oneshot(req).await.unwrap();
+                let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+                let todos: Vec<TodoEntity> = serde_json::from_slice(&bytes).unwrap();
+                todos.into_iter().map(|todo| todo.id).collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(ids("/todos").await, vec![3, 2, 1]);
+        assert_eq!(ids("/todos?sort=created_at&order=asc").await, vec![1, 2, 3]);
+        assert_eq!(ids("/todos?sort=updated_at").await, vec![1, 3, 2]);
+        // 未完了のTodoは順序に関わらず最後に並ぶ
+        assert_eq!(ids("/todos?sort=completed_at").await, vec![3, 2, 1]);
+        assert_eq!(
+            ids("/todos?sort=completed_at&order=asc").await,
+            vec![2, 3, 1]
+        );
+        let req = build_todo_req_with_empty(Method::GET, "/todos?sort=text");
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_quick_add_todo() {
+        // メモリのTodoリポジトリはラベルのリポジトリとつながっていないため、作られるラベルを先に渡す
+        let labels = vec![
+            Label::new(1, "Backend".to_string()),
+            Label::new(2, "new".to_string()),
+        ];
+        let app = app(
+            TodoRepositoryForMemory::new(labels),
+            LabelRepositoryForMemory::new(),
+        );
+        let send = |path: &str, body: &str| {
+            let req = build_req_with_json(path, Method::POST, body.to_string());
+            let app = app.clone();
+            async move {
+                let res = app.oneshot(req).await.unwrap();
+                let status = res.status();
+                let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+                (status, body)
+            }
+        };
+        send("/labels", r#"{ "name": "Backend" }"#).await;
+
+        // previewでは解析の結果だけを返し、Todoもラベルも作らない
+        let input = "Fix login bug 2030-01-15 15:00 #backend #new !high";
+        let (status, body) = send(
+            "/todos/quick",
+            &format!(r#"{{ "input": "{input}", "utc_offset": "+09:00", "preview": true }}"#),
+        )
+        .await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "text": "Fix login bug",
+                "labels": [{ "name": "backend", "id": 1 }, { "name": "new", "id": null }],
+                "priority": "high",
+                "due_at": "2030-01-15T06:00:00Z",
+            })
+        );
+
+        // 存在しないラベルは create_labels を指定しなければエラー
+        let (status, body) = send(
+            "/todos/quick",
+            &format!(r#"{{ "input": "{input}", "utc_offset": "+09:00" }}"#),
+        )
+        .await;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        assert_eq!(body["errors"][0]["field"], "labels[1]");
+        assert_eq!(body["errors"][0]["code"], "not_found");
+        let (status, body) = send(
+            "/todos/quick",
+            &format!(r#"{{ "input": "{input}", "utc_offset": "+09:00", "create_labels": true }}"#),
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(body["text"], "Fix login bug");
+        assert_eq!(body["labels"][0]["name"], "Backend");
+        assert_eq!(body["labels"][1]["name"], "new");
+        assert_eq!(body["priority"], "high");
+        assert_eq!(body["due_at"], "2030-01-15T06:00:00Z");
+
+        // 日本語の日付も解釈する
+        let (status, body) = send("/todos/quick", r#"{ "input": "資料作成 明日 #new" }"#).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(body["text"], "資料作成");
+        assert!(body["due_at"].is_string());
+
+        let (status, body) = send(
+            "/todos/quick",
+            r##"{ "input": "#backend tomorrow", "utc_offset": "JST" }"##,
+        )
+        .await;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        let fields: Vec<_> = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, vec!["utc_offset"]);
+        // 本文が残らない入力はエラー
+        let (status, body) = send("/todos/quick", r##"{ "input": "#backend tomorrow" }"##).await;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+        assert_eq!(body["errors"][0]["field"], "text");
+    }
+
+    #[tokio::test]
+    async fn should_manage_dependencies() {
+        let todo_repository = TodoRepositoryForMemory::new(vec![]);
+        for text in ["design", "implement", "release"] {
+            todo_repository
+                .create(CreateTodo::new(text.to_string(), vec![]))
+                .await
+                .expect("failed create todo");
+        }
+        todo_repository
+            .create(CreateTodo {
+                priority: Some(Priority::High),
+                ..CreateTodo::new("hotfix".to_string(), vec![])
+            })
+            .await
+            .expect("failed create todo");
+        let app = app(todo_repository, LabelRepositoryForMemory::new());
+        let send = |path: &str, method: Method, body: &str| {
+            let req = if body.is_empty() {
+                build_todo_req_with_empty(method, path)
+            } else {
+                build_req_with_json(path, method, body.to_string())
+            };
+            let app = app.clone();
+            async move {
+                let res = app.oneshot(req).await.unwrap();
+                let status = res.status();
+                let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+                (status, body)
+            }
+        };
+
+        // implement は design を、release は implement を待つ
+        let (status, body) = send(
+            "/todos/2/dependencies",
+            Method::POST,
+            r#"{ "blocker_id": 1 }"#,
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(body["blocked_by"], serde_json::json!([1]));
+        assert_eq!(body["blocked"], true);
+        let (status, _) = send(
+            "/todos/3/dependencies",
+            Method::POST,
+            r#"{ "blocker_id": 2 }"#,
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, status);
+
+        // 循環・自身・追加済みは409、存在しないブロッカーは400、存在しないTodoは404
+        for (path, body) in [
+            ("/todos/1/dependencies", r#"{ "blocker_id": 3 }"#),
+            ("/todos/1/dependencies", r#"{ "blocker_id": 1 }"#),
+            ("/todos/2/dependencies", r#"{ "blocker_id": 1 }"#),
+        ] {
+            let (status, _) = send(path, Method::POST, body).await;
+            assert_eq!(StatusCode::CONFLICT, status, "{} {}", path, body);
+        }
+        for body in [r#"{ "blocker_id": 99 }"#, r#"{ "blocker_id": 0 }"#] {
+            let (status, body) = send("/todos/1/dependencies", Method::POST, body).await;
+            assert_eq!(StatusCode::BAD_REQUEST, status);
+            assert_eq!(body["errors"][0]["field"], "blocker_id");
+        }
+        let (status, _) = send(
+            "/todos/99/dependencies",
+            Method::POST,
+            r#"{ "blocker_id": 1 }"#,
+        )
+        .await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+
+        // ブロッカーが先、同時に着手できるものは優先度順
+        let (status, body) = send("/todos/next", Method::GET, "").await;
+        assert_eq!(StatusCode::OK, status);
+        let ids: Vec<_> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|todo| todo["id"].as_i64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![4, 1, 2, 3]);
+
+        // 未完了のブロッカーがあるうちは完了にできない
+        let completed = r#"{ "completed": true }"#;
+        let (status, _) = send("/todos/2", Method::PATCH, completed).await;
+        assert_eq!(StatusCode::CONFLICT, status);
+        let (status, _) = send("/todos/1", Method::PATCH, completed).await;
+        assert_eq!(StatusCode::CREATED, status);
+        let (_, body) = send("/todos/2", Method::GET, "").await;
+        assert_eq!(body["blocked"], false);
+        let (status, _) = send("/todos/2", Method::PATCH, completed).await;
+        assert_eq!(StatusCode::CREATED, status);
+        let (_, body) = send("/todos/next", Method::GET, "").await;
+        assert_eq!(body.as_array().unwrap().len(), 2);
+
+        let (status, _) = send(
+            "/todos/3/dependencies",
+            Method::DELETE,
+            r#"{ "blocker_id": 2 }"#,
+        )
+        .await;
+        assert_eq!(StatusCode::NO_CONTENT, status);
+        let (status, _) = send(
+            "/todos/3/dependencies",
+            Method::DELETE,
+            r#"{ "blocker_id": 2 }"#,
+        )
+        .await;
+        assert_eq!(StatusCode::NOT_FOUND, status);
+        let (_, body) = send("/todos/3", Method::GET, "").await;
+        assert_eq!(body["blocked_by"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn should_render_and_search_notes() {
+        let app = app(
+            TodoRepositoryForMemory::new(vec![]),
+            LabelRepositoryForMemory::new(),
+        );
+        let send = |method: Method, path: &str, body: Option<&str>| {
+            let req = match body {
+                Some(body) => build_req_with_json(path, method, body.to_string()),
+                None => build_todo_req_with_empty(method, path),
+            };
+            let app = app.clone();
+            async move {
+                let res = app.oneshot(req).await.unwrap();
+                let status = res.status();
+                let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice(&bytes).unwrap_or_default())
+            }
+        };
+
+        // メモは改行を含められ、CRLFはLFにそろえる
+        let (status, body): (_, serde_json::Value) = send(
+            Method::POST,
+            "/todos",
+            Some(
+                r#"{ "text": "release", "labels": [],
+                "notes": "Steps\r\n\n- [x] **tag**\n- [ ] publish <script>alert(1)</script>" }"#,
+            ),
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(
+            body["notes"],
+            "Steps\n\n- [x] **tag**\n- [ ] publish <script>alert(1)</script>"
+        );
+        send(
+            Method::POST,
+            "/todos",
+            Some(r#"{ "text": "no notes", "labels": [] }"#),
+        )
+        .await;
+
+        let (status, body) = send(Method::GET, "/todos/1?render=html", None).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(
+            body["checklist"],
+            serde_json::json!({ "done": 1, "total": 2 })
+        );
+        let html = body["notes_html"].as_str().unwrap();
+        assert!(html.contains("<strong>tag</strong>"));
+        assert!(html.contains(r#"type="checkbox""#));
+        assert!(!html.contains("<script"));
+        // 指定しない場合はHTMLを返さない
+        let (_, body) = send(Method::GET, "/todos/1", None).await;
+        assert!(body.get("notes_html").is_none());
+        let (_, body) = send(Method::GET, "/todos/2", None).await;
+        assert_eq!(body["notes"], serde_json::Value::Null);
+        assert_eq!(body["checklist"], serde_json::Value::Null);
+
+        // タイトルとメモを検索する
+        let ids = |body: serde_json::Value| -> Vec<i64> {
+            body.as_array()
+                .unwrap()
+                .iter()
+                .map(|todo| todo["id"].as_i64().unwrap())
+                .collect()
+        };
+        let (_, body) = send(Method::GET, "/todos?q=PUBLISH", None).await;
+        assert_eq!(ids(body), vec![1]);
+        let (_, body) = send(Method::GET, "/todos?q=notes", None).await;
+        assert_eq!(ids(body), vec![2]);
+        let (_, body) = send(Method::GET, "/todos?q=e&render=html", None).await;
+        assert_eq!(ids(body.clone()), vec![2, 1]);
+        assert_eq!(body[0]["notes_html"], "");
+
+        // nullでメモを消す
+        let (status, body) = send(Method::PATCH, "/todos/1", Some(r#"{ "notes": null }"#)).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(body["notes"], serde_json::Value::Null);
+        let (status, _) = send(
+            Method::PATCH,
+            "/todos/1",
+            Some(&format!(r#"{{ "notes": "{}" }}"#, "a".repeat(10001))),
+        )
+        .await;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+    }
+
+    #[tokio::test]
+    async fn should_update_todo() {
+        let (labels, label_ids) = label_fixture();
+        let expected = TodoEntity::new(1, "should_update_todo".to_string(), labels.clone());
+
+        let todo_repository = TodoRepositoryForMemory::new(labels);
+        todo_repository
+            .create(CreateTodo::new("before_update_todo".to_string(), label_ids))
+            .await
+            .expect("failed create todo");
+        let req = build_req_with_json(
+            "/todos/1",
+            Method::PATCH,
+            r#"{
+                "text": "should_update_todo",
+                "completed": false
+            }"#
+            .to_string(),
+        );
+        let res = app(todo_repository, LabelRepositoryForMemory::new())
+            .oneshot(req)
+            .await
+            .unwrap();
+        let todo = res_to_todo(res).await;
+        assert_eq!(expected, todo);
+    }
+
+    #[tokio::test]
+    async fn should_delete_todo() {
+        let (labels, label_ids) = label_fixture();
+        let todo_repository = TodoRepositoryForMemory::new(labels);
+        todo_repository
+            .create(CreateTodo::new("should_delete_todo".to_string(), label_ids))
+            .await
+            .expect("failed create todo");
+        let req = build_todo_req_with_empty(Method::DELETE, "/todos/1");
+        let res = app(todo_repository, LabelRepositoryForMemory::new())
+            .oneshot(req)
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_validate_todo_payload() {
+        let (labels, _label_ids) = label_fixture();
+        let app = app(
+            TodoRepositoryForMemory::new(labels.clone()),
+            LabelRepositoryForMemory::with_labels(labels.clone()),
+        );
+
+        // 日本語も100文字まで作成でき、前後の空白は取り除かれる
+        let text = "あ".repeat(100);
+        let req = build_req_with_json(
+            "/todos",
+            Method::POST,
+            serde_json::json!({ "text": format!("  {}  ", text), "labels": [999] }).to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let todo = res_to_todo(res).await;
+        assert_eq!(todo.text, text);
+        assert_eq!(todo.labels, labels);
+
+        // エラーはまとめて返す
+        let req = build_req_with_json(
+            "/todos",
+            Method::POST,
+            serde_json::json!({ "text": "あ".repeat(101), "labels": [999, 999, 0, 5] }).to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let errors: Vec<(&str, &str)> = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| {
+                (
+                    error["field"].as_str().unwrap(),
+                    error["code"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("text", "too_long"),
+                ("labels[1]", "duplicate"),
+                ("labels[2]", "invalid"),
+                ("labels[3]", "not_found"),
+            ]
+        );
+
+        // 更新では送ったフィールドだけを検証する
+        let req = build_req_with_json(
+            &format!("/todos/{}", todo.id),
+            Method::PATCH,
+            r#"{ "completed": true }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let req = build_req_with_json(
+            &format!("/todos/{}", todo.id),
+            Method::PATCH,
+            r#"{ "text": "tab\there" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+
+        // 制限はExtensionで変更できる
+        let rules = ValidationRules {
+            label_name: TextRule::new(1, 3),
+            ..Default::default()
+        };
+        let app = app.layer(Extension(Arc::new(rules)));
+        let req = build_req_with_json("/labels", Method::POST, r#"{ "name": "four" }"#.to_string());
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        let req = build_req_with_json(
+            "/labels",
+            Method::POST,
+            r#"{ "name": "三文字" }"#.to_string(),
+        );
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_created_label() {
+        let (labels, _label_ids) = label_fixture();
+        let expected = Label::new(1, "should_created_label".to_string());
+
+        let req = build_req_with_json(
+            "/labels",
+            Method::POST,
+            r#"{ "name": "should_created_label" }"#.to_string(),
+        );
+        let res = app(
+            TodoRepositoryForMemory::new(labels),
+            LabelRepositoryForMemory::new(),
+        )
+        .oneshot(req)
+        .await
+        .unwrap();
+        let label = res_to_label(res).await;
+        assert_eq!(expected, label);
+    }
+
+    #[tokio::test]
+    async fn should_all_label_readed() {
+        let expected = Label::new(1, "should_all_label_readed".to_string());
+        let label_repository = LabelRepositoryForMemory::new();
+        let label = label_repository
+            .create(CreateLabel::new("should_all_label_readed".to_string()))
+            .await
+            .expect("failed create label");
+
+        let req = build_todo_req_with_empty(Method::GET, "/labels");
+        let res = app(TodoRepositoryForMemory::new(vec![label]), label_repository)
+            .oneshot(req)
+            .await
+            .unwrap();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: String = String::from_utf8(bytes.to_vec()).unwrap();
+        let labels: Vec<Label> = serde_json::from_str(&body)
+            .unwrap_or_else(|_| panic!("cannot convert Label list instance. body: {}", body));
+        assert_eq!(vec![expected], labels);
+    }
+
+    #[tokio::test]
+    async fn should_delete_label() {
+        let label_repository = LabelRepositoryForMemory::new();
+        let label = label_repository
+            .create(CreateLabel::new("should_delete_label".to_string()))
+            .await
+            .expect("failed create label");
+        let req = build_todo_req_with_empty(Method::DELETE, "/labels/1");
+        let res = app(TodoRepositoryForMemory::new(vec![label]), label_repository)
+            .oneshot(req)
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_not_delete_label_with_clashing_child() {
+        let area = Label::new(1, "area".to_string());
+        let child = Label {
+            parent_id: Some(area.id),
+            ..Label::new(2, "work".to_string())
+        };
+        let root = Label::new(3, "Work".to_string());
+        let labels = vec![area, child, root];
+        let req = build_todo_req_with_empty(Method::DELETE, "/labels/1");
+        let res = app(
+            TodoRepositoryForMemory::new(labels.clone()),
+            LabelRepositoryForMemory::with_labels(labels),
+        )
+        .oneshot(req)
+        .await
+        .unwrap();
+        assert_eq!(StatusCode::CONFLICT, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_delete_label_with_mode() {
+        let work = Label::new(999, "work".to_string());
+        let home = Label::new(1000, "home".to_string());
+        let labels = vec![work.clone(), home.clone()];
+        let todo_repository = TodoRepositoryForMemory::new(labels.clone());
+        let todo = todo_repository
+            .create(CreateTodo::new("labeled".to_string(), vec![work.id]))
+            .await
+            .expect("failed create todo");
+        let app = app(
+            todo_repository.clone(),
+            LabelRepositoryForMemory::with_labels(labels).with_todos(todo_repository.clone()),
+        );
+        let delete = |path: &str| {
+            let app = app.clone();
+            let req = build_todo_req_with_empty(Method::DELETE, path);
+            async move { app.oneshot(req).await.unwrap() }
+        };
+
+        // 使われているラベルはrestrictでは削除できず、件数を返す
+        let res = delete("/labels/999?mode=restrict").await;
+        assert_eq!(StatusCode::CONFLICT, res.status());
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["usage"], 1);
+
+        // 付け替え先の指定が不正
+        for path in [
+            "/labels/999?mode=reassign",
+            "/labels/999?mode=reassign&to=999",
+            "/labels/999?mode=reassign&to=5",
+            "/labels/999?mode=detach&to=1000",
+        ] {
+            assert_eq!(
+                StatusCode::BAD_REQUEST,
+                delete(path).await.status(),
+                "{}",
+                path
+            );
+        }
+
+        // reassign
+        let res = delete("/labels/999?mode=reassign&to=1000").await;
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        let todo = todo_repository.find(todo.id).await.unwrap();
+        assert_eq!(todo.labels, vec![home.clone()]);
+
+        // detach (既定)
+        let res = delete("/labels/1000").await;
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        let todo = todo_repository.find(todo.id).await.unwrap();
+        assert!(todo.labels.is_empty());
+        assert_eq!(StatusCode::NOT_FOUND, delete("/labels/1000").await.status());
+
+        // 外した変更は同期の変更履歴にも残る
+        let req = build_todo_req_with_empty(Method::GET, "/sync/changes");
+        let res = app.clone().oneshot(req).await.unwrap();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let changes: SyncChanges = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(changes.todos.len(), 1);
+        assert_eq!(changes.todos[0].todo.as_ref(), Some(&todo));
+        assert_eq!(changes.todos[0].version, 2);
+    }
+
+    #[tokio::test]
+    async fn should_update_label() {
+        let work = Label::new(1, "work".to_string());
+        let home = Label::new(2, "home".to_string());
+        let app = app(
+            TodoRepositoryForMemory::new(vec![]),
+            LabelRepositoryForMemory::with_labels(vec![work.clone(), home.clone()]),
+        );
+        let patch = |path: &str, body: &str| {
+            let app = app.clone();
+            let req = build_req_with_json(path, Method::PATCH, body.to_string());
+            async move { app.oneshot(req).await.unwrap() }
+        };
+
+        // 色は小文字にそろえ、省略した項目は変更しない
+        let res = patch(
+            "/labels/1",
+            r##"{ "color": "#FF0000", "icon": "💼", "description": "仕事", "position": 5 }"##,
+        )
+        .await;
+        assert_eq!(StatusCode::OK, res.status());
+        let updated = res_to_label(res).await;
+        assert_eq!(
+            updated,
+            Label {
+                color: "#ff0000".to_string(),
+                description: Some("仕事".to_string()),
+                icon: Some("💼".to_string()),
+                position: 5,
+                ..work.clone()
+            }
+        );
+
+        // 並び順で返す
+        let req = build_todo_req_with_empty(Method::GET, "/labels");
+        let res = app.clone().oneshot(req).await.unwrap();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let labels: Vec<Label> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(labels, vec![home.clone(), updated.clone()]);
+
+        // nullで外す
+        let res = patch("/labels/1", r#"{ "icon": null }"#).await;
+        assert_eq!(res_to_label(res).await.icon, None);
+
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            patch("/labels/1", r#"{ "color": "red" }"#).await.status()
+        );
+        assert_eq!(
+            StatusCode::CONFLICT,
+            patch("/labels/1", r#"{ "name": "home" }"#).await.status()
+        );
+        assert_eq!(
+            StatusCode::NOT_FOUND,
+            patch("/labels/3", r#"{ "name": "other" }"#).await.status()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_filter_todos_by_label_hierarchy() {
+        let label = |id: i32, name: &str, parent_id: Option<i32>, exclusive: bool| Label {
+            parent_id,
+            exclusive,
+            ..Label::new(id, name.to_string())
+        };
+        let labels = vec![
+            label(1, "area", None, false),
+            label(2, "backend", Some(1), false),
+            label(3, "priority", None, true),
+            label(4, "high", Some(3), false),
+            label(5, "low", Some(3), false),
+        ];
+        let todo_repository = TodoRepositoryForMemory::new(labels.clone());
+        todo_repository
+            .create(CreateTodo::new("backend".to_string(), vec![2, 4]))
+            .await
+            .expect("failed create todo");
+        todo_repository
+            .create(CreateTodo::new("other".to_string(), vec![5]))
+            .await
+            .expect("failed create todo");
+        let app = app(
+            todo_repository.clone(),
+            LabelRepositoryForMemory::with_labels(labels).with_todos(todo_repository),
+        );
+        let texts = |res: Response| async move {
+            let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+            let todos: Vec<TodoEntity> = serde_json::from_slice(&bytes).unwrap();
+            todos.into_iter().map(|todo| todo.text).collect::<Vec<_>>()
+        };
+
+        // 親のラベルで絞り込むと子のラベルが付いたTodoも返す
+        for path in [
+            "/todos?label=area",
+            "/todos?label=area%2Fbackend",
+            "/todos?label=1",
+        ] {
+            let req = build_todo_req_with_empty(Method::GET, path);
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(StatusCode::OK, res.status());
+            assert_eq!(texts(res).await, vec!["backend"]);
+        }
+        let req = build_todo_req_with_empty(Method::GET, "/todos?label=unknown");
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+
+        // 排他的なグループからは1つしか選べない
+        let req = build_req_with_json(
+            "/todos",
+            Method::POST,
+            r#"{ "text": "both", "labels": [4, 5] }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["errors"][0]["field"], "labels[1]");
+        assert_eq!(body["errors"][0]["code"], "exclusive");
+
+        // 自身の子孫は親にできない
+        let req = build_req_with_json(
+            "/labels/1",
+            Method::PATCH,
+            r#"{ "parent_id": 2 }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        let req = build_req_with_json(
+            "/labels",
+            Method::POST,
+            r#"{ "name": "orphan", "parent_id": 99 }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+
+        // 付け替えると排他的なグループに違反する場合は削除しない
+        let req = build_todo_req_with_empty(Method::DELETE, "/labels/2?mode=reassign&to=5");
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CONFLICT, res.status());
+        let req = build_todo_req_with_empty(Method::DELETE, "/labels/5?mode=reassign&to=4");
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_merge_labels() {
+        let bug = Label::new(1, "bug".to_string());
+        let labels = vec![
+            bug.clone(),
+            Label::new(2, "bugs".to_string()),
+            Label::new(3, "Ｂｕｇ".to_string()),
+        ];
+        let todo_repository = TodoRepositoryForMemory::new(labels.clone());
+        let both = todo_repository
+            .create(CreateTodo::new("both".to_string(), vec![1, 2]))
+            .await
+            .expect("failed create todo");
+        let other = todo_repository
+            .create(CreateTodo::new("other".to_string(), vec![3]))
+            .await
+            .expect("failed create todo");
+        let label_repository = LabelRepositoryForMemory::with_labels(labels);
+        let todo_repository = todo_repository.with_label_store(&label_repository);
+        let app = app(todo_repository.clone(), label_repository.clone());
+        let post = |path: &str, body: &str| {
+            let app = app.clone();
+            let req = build_req_with_json(path, Method::POST, body.to_string());
+            async move { app.oneshot(req).await.unwrap() }
+        };
+
+        // 付け替え先がすでに付いているTodoには重複して付けない
+        let res = post("/labels/1/merge", r#"{ "sources": [2, 3] }"#).await;
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!(res_to_label(res).await, bug);
+        let todo = todo_repository.find(both.id).await.unwrap();
+        assert_eq!(todo.labels, vec![bug.clone()]);
+        let todo = todo_repository.find(other.id).await.unwrap();
+        assert_eq!(todo.labels, vec![bug.clone()]);
+        // 統合元のラベルは削除される
+        assert_eq!(label_repository.all().await.unwrap(), vec![bug.clone()]);
+
+        let res = post("/labels/1/merge", r#"{ "sources": [1, 99] }"#).await;
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["errors"][0]["field"], "sources[0]");
+        assert_eq!(body["errors"][1]["code"], "not_found");
+        let res = post("/labels/1/merge", r#"{ "sources": [] }"#).await;
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        let res = post("/labels/99/merge", r#"{ "sources": [2] }"#).await;
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+
+        // 大文字・小文字や全角・半角だけが違う名前では作成できない
+        let res = post("/labels", r#"{ "name": "ＢＵＧ" }"#).await;
+        assert_eq!(StatusCode::CONFLICT, res.status());
+
+        // 親が違えば同じ名前のラベルを作成できる
+        let area = res_to_label(post("/labels", r#"{ "name": "area" }"#).await).await;
+        let team = res_to_label(post("/labels", r#"{ "name": "team" }"#).await).await;
+        for parent in [area.id, team.id] {
+            let body = format!(r#"{{ "name": "frontend", "parent_id": {} }}"#, parent);
+            assert_eq!(StatusCode::CREATED, post("/labels", &body).await.status());
+        }
+        let body = format!(r#"{{ "name": "Frontend", "parent_id": {} }}"#, area.id);
+        assert_eq!(StatusCode::CONFLICT, post("/labels", &body).await.status());
+        // 統合すると統合先の子の名前が重複する
+        let path = format!("/labels/{}/merge", area.id);
+        let body = format!(r#"{{ "sources": [{}] }}"#, team.id);
+        assert_eq!(StatusCode::CONFLICT, post(&path, &body).await.status());
+    }
+
+    #[tokio::test]
+    async fn should_get_stats() {
+        let (labels, label_ids) = label_fixture();
+        // 今日の件数として集計されるよう、時計を現在の日時に合わせる
+        let todo_repository =
+            TodoRepositoryForMemory::new(labels.clone()).with_clock(ManualClock::new(Utc::now()));
+        let labelled = todo_repository
+            .create(CreateTodo::new("labelled".to_string(), label_ids))
+            .await
+            .expect("failed create todo");
+        todo_repository
+            .create(CreateTodo::new("unlabelled".to_string(), vec![]))
+            .await
+            .expect("failed create todo");
+        let app = app(
+            todo_repository,
+            LabelRepositoryForMemory::with_labels(labels.clone()),
+        );
+        let get = |path: &str| {
+            let app = app.clone();
+            let req = build_todo_req_with_empty(Method::GET, path);
+            async move {
+                let res = app.oneshot(req).await.unwrap();
+                let status = res.status();
+                let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice(&bytes).unwrap_or_default())
+            }
+        };
+
+        // 完了にすると完了日時が記録され、今日の完了件数に含まれる
+        let req = build_req_with_json(
+            &format!("/todos/{}", labelled.id),
+            Method::PATCH,
+            r#"{ "completed": true }"#.to_string(),
+        );
+        let todo = res_to_todo(app.clone().oneshot(req).await.unwrap()).await;
+        assert!(todo.completed_at.is_some());
+
+        let (status, body): (_, serde_json::Value) = get("/stats?days=7").await;
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(body["total"], 2);
+        assert_eq!(body["completed"], 1);
+        assert_eq!(body["open"], 1);
+        assert_eq!(body["labels"][0]["label"]["id"], 999);
+        assert_eq!(body["labels"][0]["completed"], 1);
+        assert_eq!(body["labels"][1]["label"], serde_json::Value::Null);
+        assert_eq!(body["labels"][1]["open"], 1);
+        let daily = body["daily"].as_array().unwrap();
+        assert_eq!(daily.len(), 7);
+        assert_eq!(daily[6]["created"], 2);
+        assert_eq!(daily[6]["completed"], 1);
+        let (status, _) = get("/stats?days=0").await;
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+
+        // ラベルの一覧に件数を含める
+        let (_, body) = get("/labels?with_counts=true").await;
+        assert_eq!(
+            body,
+            serde_json::json!([{
+                "id": 999,
+                "name": "test label main",
+                "color": DEFAULT_LABEL_COLOR,
+                "description": null,
+                "icon": null,
+                "position": 0,
+                "parent_id": null,
+                "exclusive": false,
+                "created_at": "1970-01-01T00:00:00Z",
+                "updated_at": "1970-01-01T00:00:00Z",
+                "counts": { "total": 1, "completed": 1, "open": 0 },
+            }])
+        );
+        let (_, body) = get("/labels").await;
+        assert_eq!(body, serde_json::to_value(&labels).unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_created_webhook() {
+        let (labels, _label_ids) = label_fixture();
+        let req = build_req_with_json(
+            "/webhooks",
+            Method::POST,
+            r#"{ "url": "http://localhost/hook", "events": ["todo.created"], "secret": "s3cret" }"#
+                .to_string(),
+        );
+        let res = app(
+            TodoRepositoryForMemory::new(labels),
+            LabelRepositoryForMemory::new(),
+        )
+        .oneshot(req)
+        .await
+        .unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let webhook: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(webhook["url"], "http://localhost/hook");
+        assert_eq!(webhook["events"], serde_json::json!(["todo.created"]));
+        assert_eq!(webhook["secret"], "s3cret");
+    }
+
+    #[tokio::test]
+    async fn should_reject_webhook_with_invalid_url() {
+        let (labels, _label_ids) = label_fixture();
+        let req = build_req_with_json(
+            "/webhooks",
+            Method::POST,
+            r#"{ "url": "ftp://localhost/hook", "events": ["todo.created"] }"#.to_string(),
+        );
+        let res = app(
+            TodoRepositoryForMemory::new(labels),
+            LabelRepositoryForMemory::new(),
+        )
+        .oneshot(req)
+        .await
+        .unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_not_found_missing_webhook() {
+        let app = app(
+            TodoRepositoryForMemory::new(vec![]),
+            LabelRepositoryForMemory::new(),
+        );
+        let req = build_req_with_json(
+            "/webhooks/99",
+            Method::PATCH,
+            r#"{ "enabled": false }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+
+        let req = build_todo_req_with_empty(Method::DELETE, "/webhooks/99");
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+
+        let req = build_todo_req_with_empty(Method::GET, "/webhooks/99/deliveries");
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_enqueue_webhook_on_todo_created() {
+        let (labels, _label_ids) = label_fixture();
+        let webhook_repository = WebhookRepositoryForMemory::new();
+        let webhook = webhook_repository
+            .create(CreateWebhook {
+                url: "http://localhost/hook".to_string(),
+                events: vec![WebhookEvent::TodoCreated],
+                secret: None,
+            })
+            .await
+            .expect("failed create webhook");
+
+        let req = build_req_with_json(
+            "/todos",
+            Method::POST,
+            r#"{ "text": "should_enqueue_webhook", "labels": [999] }"#.to_string(),
+        );
+        let res = app_with(
+            TodoRepositoryForMemory::new(labels.clone()),
+            LabelRepositoryForMemory::with_labels(labels),
+            AppParts {
+                webhooks: webhook_repository.clone(),
+                ..Default::default()
+            },
+        )
+        .oneshot(req)
+        .await
+        .unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+
+        // 配信キューへの登録はバックグラウンドで行われる
+        let mut deliveries = vec![];
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+            deliveries = webhook_repository.deliveries(webhook.id).await.unwrap();
+            if !deliveries.is_empty() {
+                break;
+            }
+        }
+        assert_eq!(deliveries.len(), 1);
+        let payload: serde_json::Value = serde_json::from_str(&deliveries[0].payload).unwrap();
+        assert_eq!(payload["event"], "todo.created");
+        assert_eq!(payload["data"]["text"], "should_enqueue_webhook");
+    }
+
+    #[tokio::test]
+    async fn should_created_project() {
+        let (labels, _label_ids) = label_fixture();
+        let expected = Project::new(2, "should_created_project".to_string());
+
+        let app = app(
+            TodoRepositoryForMemory::new(labels),
+            LabelRepositoryForMemory::new(),
+        );
+        let req = build_req_with_json(
+            "/projects",
+            Method::POST,
+            r#"{ "name": " should_created_project " }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let project: Project = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(expected, project);
+
+        // 名前は文字数で数える
+        let name = "案".repeat(100);
+        let req = build_req_with_json(
+            "/projects/2",
+            Method::PATCH,
+            format!(r#"{{ "name": "{}" }}"#, name),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        for body in [
+            r#"{ "name": "  " }"#.to_string(),
+            format!(r#"{{ "name": "{}案" }}"#, name),
+        ] {
+            let req = build_req_with_json("/projects", Method::POST, body);
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(StatusCode::BAD_REQUEST, res.status());
+        }
+    }
+
+    #[tokio::test]
+    async fn should_move_todo_to_project() {
+        let (labels, label_ids) = label_fixture();
+        let todo_repository = TodoRepositoryForMemory::new(labels);
+        todo_repository
+            .create(CreateTodo::new("should_move_todo".to_string(), label_ids))
+            .await
+            .expect("failed create todo");
+        let project_repository = ProjectRepositoryForMemory::new();
+        let project = project_repository
+            .create(CreateProject {
+                name: "move target".to_string(),
+            })
+            .await
+            .expect("failed create project");
+        let app = app_with(
+            todo_repository,
+            LabelRepositoryForMemory::new(),
+            AppParts {
+                projects: project_repository,
+                ..Default::default()
+            },
+        );
+
+        let req = build_req_with_json(
+            "/todos/1",
+            Method::PATCH,
+            format!(
+                r#"{{ "text": "should_move_todo", "project_id": {} }}"#,
+                project.id
+            ),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        let todo = res_to_todo(res).await;
+        assert_eq!(project.id, todo.project_id);
+
+        let req =
+            build_todo_req_with_empty(Method::GET, &format!("/projects/{}/todos", project.id));
+        let res = app.clone().oneshot(req).await.unwrap();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let todos: Vec<TodoEntity> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(vec![todo], todos);
+
+        // Todoが残っているプロジェクトは削除できない
+        let path = format!("/projects/{}", project.id);
+        let req = build_todo_req_with_empty(Method::DELETE, &path);
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CONFLICT, res.status());
+        let req = build_todo_req_with_empty(Method::DELETE, "/todos/1");
+        app.clone().oneshot(req).await.unwrap();
+        let req = build_todo_req_with_empty(Method::DELETE, &path);
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        let req = build_todo_req_with_empty(Method::DELETE, &path);
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_reject_todo_in_archived_project() {
+        let (labels, _label_ids) = label_fixture();
+        let project_repository = ProjectRepositoryForMemory::new();
+        let project = project_repository
+            .create(CreateProject {
+                name: "archived".to_string(),
+            })
+            .await
+            .expect("failed create project");
+        project_repository
+            .update(
+                project.id,
+                UpdateProject {
+                    name: None,
+                    archived: Some(true),
+                },
+            )
+            .await
+            .expect("failed archive project");
+
+        let req = build_req_with_json(
+            "/todos",
+            Method::POST,
+            format!(
+                r#"{{ "text": "archived", "labels": [], "project_id": {} }}"#,
+                project.id
+            ),
+        );
+        let res = app_with(
+            TodoRepositoryForMemory::new(labels),
+            LabelRepositoryForMemory::new(),
+            AppParts {
+                projects: project_repository,
+                ..Default::default()
+            },
+        )
+        .oneshot(req)
+        .await
+        .unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, res.status());
+    }
+
+    async fn status_fixture() -> (StatusRepositoryForMemory, i32, i32) {
+        let status_repository = StatusRepositoryForMemory::new();
+        let in_progress = status_repository
+            .create(
+                1,
+                CreateStatus {
+                    name: "In Progress".to_string(),
+                    position: None,
+                    is_done: false,
+                    wip_limit: Some(1),
+                },
+            )
+            .await
+            .expect("failed create status");
+        let done = status_repository
+            .create(
+                1,
+                CreateStatus {
+                    name: "Done".to_string(),
+                    position: None,
+                    is_done: true,
+                    wip_limit: None,
+                },
+            )
+            .await
+            .expect("failed create status");
+        (status_repository, in_progress.id, done.id)
+    }
+
+    #[tokio::test]
+    async fn should_derive_completed_from_status() {
+        let (labels, _label_ids) = label_fixture();
+        let (status_repository, in_progress_id, done_id) = status_fixture().await;
+        let app = app_with(
+            TodoRepositoryForMemory::new(labels),
+            LabelRepositoryForMemory::new(),
+            AppParts {
+                statuses: status_repository,
+                ..Default::default()
+            },
+        );
+
+        // 作成時は先頭の未完了の列に置かれる
+        let req = build_req_with_json(
+            "/todos",
+            Method::POST,
+            r#"{ "text": "should_derive_completed", "labels": [] }"#.to_string(),
+        );
+        let todo = res_to_todo(app.clone().oneshot(req).await.unwrap()).await;
+        assert_eq!(Some(in_progress_id), todo.status_id);
+        assert!(!todo.completed);
+
+        let req = build_req_with_json(
+            "/todos/1",
+            Method::PATCH,
+            format!(
+                r#"{{ "text": "should_derive_completed", "status_id": {} }}"#,
+                done_id
+            ),
+        );
+        let todo = res_to_todo(app.clone().oneshot(req).await.unwrap()).await;
+        assert_eq!(Some(done_id), todo.status_id);
+        assert!(todo.completed);
+
+        // completedを戻すと未完了の列に戻る
+        let req = build_req_with_json(
+            "/todos/1",
+            Method::PATCH,
+            r#"{ "text": "should_derive_completed", "completed": false }"#.to_string(),
+        );
+        let todo = res_to_todo(app.oneshot(req).await.unwrap()).await;
+        assert_eq!(Some(in_progress_id), todo.status_id);
+        assert!(!todo.completed);
+    }
+
+    #[tokio::test]
+    async fn should_reject_transition_over_wip_limit() {
+        let (labels, _label_ids) = label_fixture();
+        let (status_repository, in_progress_id, done_id) = status_fixture().await;
+        let todo_repository = TodoRepositoryForMemory::new(labels);
+        let mut payload = CreateTodo::new("in progress".to_string(), vec![]);
+        payload.status_id = Some(in_progress_id);
+        todo_repository.create(payload).await.unwrap();
+        let mut payload = CreateTodo::new("done".to_string(), vec![]);
+        payload.status_id = Some(done_id);
+        todo_repository.create(payload).await.unwrap();
+
+        let req = build_req_with_json(
+            "/todos/2",
+            Method::PATCH,
+            format!(r#"{{ "text": "done", "status_id": {} }}"#, in_progress_id),
+        );
+        let res = app_with(
+            todo_repository,
+            LabelRepositoryForMemory::new(),
+            AppParts {
+                statuses: status_repository,
+                ..Default::default()
+            },
+        )
+        .oneshot(req)
+        .await
+        .unwrap();
+        assert_eq!(StatusCode::CONFLICT, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_get_board() {
+        let (labels, _label_ids) = label_fixture();
+        let (status_repository, in_progress_id, done_id) = status_fixture().await;
+        let todo_repository = TodoRepositoryForMemory::new(labels);
+        let mut payload = CreateTodo::new("in progress".to_string(), vec![]);
+        payload.status_id = Some(in_progress_id);
+        todo_repository.create(payload).await.unwrap();
+        todo_repository
+            .create(CreateTodo::new("unassigned".to_string(), vec![]))
+            .await
+            .unwrap();
+
+        let req = build_todo_req_with_empty(Method::GET, "/projects/1/board");
+        let res = app_with(
+            todo_repository,
+            LabelRepositoryForMemory::new(),
+            AppParts {
+                statuses: status_repository,
+                ..Default::default()
+            },
+        )
+        .oneshot(req)
+        .await
+        .unwrap();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let board: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(board["columns"][0]["status"]["id"], in_progress_id);
+        assert_eq!(board["columns"][0]["todos"][0]["text"], "in progress");
+        assert_eq!(board["columns"][1]["status"]["id"], done_id);
+        assert_eq!(board["columns"][1]["todos"], serde_json::json!([]));
+        assert_eq!(board["unassigned"][0]["text"], "unassigned");
+    }
+
+    fn build_comment_req(
+        path: &str,
+        method: Method,
+        user: &str,
+        json_body: String,
+    ) -> Request<Body> {
+        Request::builder()
+            .uri(path)
+            .method(method)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .header(USER_HEADER, user)
+            .body(Body::from(json_body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_created_comment() {
+        let (labels, _label_ids) = label_fixture();
+        let comment_repository = CommentRepositoryForMemory::new();
+        let todo_repository =
+            TodoRepositoryForMemory::new(labels).with_comments(comment_repository.clone());
+        todo_repository
+            .create(CreateTodo::new("commented".to_string(), vec![]))
+            .await
+            .unwrap();
+        let app = app_with(
+            todo_repository,
+            LabelRepositoryForMemory::new(),
+            AppParts {
+                comments: comment_repository,
+                ..Default::default()
+            },
+        );
+
+        let req = build_comment_req(
+            "/todos/1/comments",
+            Method::POST,
+            "alice",
+            r#"{ "body": "**looks good**" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let comment: Comment = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(comment.author, "alice");
+        assert_eq!(comment.body, "**looks good**");
+
+        // Todoにコメント数が載る
+        let req = build_todo_req_with_empty(Method::GET, "/todos/1");
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res_to_todo(res).await.comment_count, 1);
+
+        // X-Userがなければ投稿できない
+        let req = build_req_with_json(
+            "/todos/1/comments",
+            Method::POST,
+            r#"{ "body": "anonymous" }"#.to_string(),
+        );
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, res.status());
+    }
+
+    #[tokio::test]
+    async fn should_reject_comment_edit_by_other_user() {
+        let (labels, _label_ids) = label_fixture();
+        let comment_repository = CommentRepositoryForMemory::new();
+        let todo_repository =
+            TodoRepositoryForMemory::new(labels).with_comments(comment_repository.clone());
+        todo_repository
+            .create(CreateTodo::new("commented".to_string(), vec![]))
+            .await
+            .unwrap();
+        let app = app_with(
+            todo_repository,
+            LabelRepositoryForMemory::new(),
+            AppParts {
+                comments: comment_repository,
+                ..Default::default()
+            },
+        );
+        let req = build_comment_req(
+            "/todos/1/comments",
+            Method::POST,
+            "alice",
+            r#"{ "body": "original" }"#.to_string(),
+        );
+        app.clone().oneshot(req).await.unwrap();
+
+        let req = build_comment_req(
+            "/comments/1",
+            Method::PATCH,
+            "bob",
+            r#"{ "body": "hijacked" }"#.to_string(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::FORBIDDEN, res.status());
+
+        let req = build_comment_req(
+            "/comments/1",
+            Method::PATCH,
+            "alice",
+            r#"{ "body": "edited" }"#.to_string(),
+        );
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+    }
+
+    fn build_multipart_req(
+        path: &str,
+        filename: &str,
+        content_type: &str,
+        data: &str,
+    ) -> Request<Body> {
+        let boundary = "test-boundary";
+        let body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\nContent-Type: {content_type}\r\n\r\n{data}\r\n--{boundary}--\r\n"
+        );
+        Request::builder()
+            .uri(path)
+            .method(Method::POST)
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    fn attachment_app(
+        todo_repository: TodoRepositoryForMemory,
+        attachment_repository: AttachmentRepositoryForMemory,
+        storage: MemoryStorage,
+    ) -> Router {
+        app_with(
+            todo_repository,
+            LabelRepositoryForMemory::new(),
+            AppParts {
+                attachments: attachment_repository,
+                storage,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn should_upload_and_download_attachment() {
+        let (labels, _label_ids) = label_fixture();
+        let todo_repository = TodoRepositoryForMemory::new(labels);
+        todo_repository
+            .create(CreateTodo::new("attached".to_string(), vec![]))
+            .await
+            .unwrap();
+        let app = attachment_app(
+            todo_repository,
+            AttachmentRepositoryForMemory::new(),
+            MemoryStorage::new(),
+        );
+
+        let req = build_multipart_req("/todos/1/attachments", "メモ.txt", "text/plain", "hello");
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::CREATED, res.status());
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let attachment: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(attachment["filename"], "メモ.txt");
+        assert_eq!(attachment["size"], 5);
+        assert!(attachment.get("storage_key").is_none());
+
+        let req = build_todo_req_with_empty(Method::GET, "/attachments/1");
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/plain");
+        assert_eq!(
+            res.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"__.txt\"; filename*=UTF-8''%E3%83%A1%E3%83%A2%2Etxt"
+        );
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn should_reject_unsupported_attachment_type() {
+        let (labels, _label_ids) = label_fixture();
+        let todo_repository = TodoRepositoryForMemory::new(labels);
+        todo_repository
+            .create(CreateTodo::new("attached".to_string(), vec![]))
+            .await
+            .unwrap();
+        let storage = MemoryStorage::new();
+        let app = attachment_app(
+            todo_repository,
+            AttachmentRepositoryForMemory::new(),
+            storage.clone(),
+        );
+
+        let req = build_multipart_req(
+            "/todos/1/attachments",
+            "run.exe",
+            "application/x-msdownload",
+            "MZ",
+        );
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, res.status());
+        assert_eq!(storage.blob_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_schedule_attachment_cleanup_on_todo_deleted() {
+        let (labels, _label_ids) = label_fixture();
+        let todo_repository = TodoRepositoryForMemory::new(labels);
+        todo_repository
+            .create(CreateTodo::new("attached".to_string(), vec![]))
+            .await
+            .unwrap();
+        let attachment_repository = AttachmentRepositoryForMemory::new();
+        let storage = MemoryStorage::new();
+        let app = attachment_app(
+            todo_repository,
+            attachment_repository.clone(),
+            storage.clone(),
+        );
+        let req = build_multipart_req("/todos/1/attachments", "a.png", "image/png", "png");
+        app.clone().oneshot(req).await.unwrap();
+
+        let req = build_todo_req_with_empty(Method::DELETE, "/todos/1");
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+
+        let janitor = AttachmentJanitor::new(
+            Arc::new(attachment_repository.clone()),
+            Arc::new(storage.clone()),
+        );
+        assert_eq!(janitor.sweep().await.unwrap(), 1);
+        assert_eq!(storage.blob_count(), 0);
+        assert!(attachment_repository.orphaned(10).await.unwrap().is_empty());
+    }
+
+    /// 実際にポートを開いてtodo-clientから全ルートを呼び出す
+    #[tokio::test]
+    async fn should_work_with_todo_client() {
+        let (labels, label_ids) = label_fixture();
+        let app = app(
+            TodoRepositoryForMemory::new(labels.clone()),
+            LabelRepositoryForMemory::with_labels(labels),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let client = todo_client::TodoClient::new(&format!("http://{}", addr)).unwrap();
+
+        // todo
+        let todo = client
+            .create_todo(&CreateTodo::new("client".to_string(), label_ids))
+            .await
+            .expect("failed create todo");
+        assert_eq!(todo.labels.len(), 1);
+        assert_eq!(client.find_todo(todo.id).await.unwrap(), todo);
+        let updated = client
+            .update_todo(
+                todo.id,
+                &UpdateTodo {
+                    text: Some("client".to_string()),
+                    completed: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("failed update todo");
+        assert!(updated.completed);
+        assert_eq!(client.all_todo().await.unwrap(), vec![updated]);
+        let err = client
+            .create_todo(&CreateTodo::new("".to_string(), vec![]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::BAD_REQUEST));
+        let preview = client
+            .preview_quick_add(&QuickAdd::new("review 2030-01-15 !urgent".to_string()))
+            .await
+            .expect("failed preview quick add");
+        assert_eq!(preview.text, "review");
+        assert_eq!(preview.priority, Some(Priority::Urgent));
+        assert_eq!(client.all_todo().await.unwrap().len(), 1);
+        let err = client.add_dependency(todo.id, todo.id).await.unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::CONFLICT));
+        assert!(client.remove_dependency(todo.id, todo.id).await.is_err());
+        // 完了済みのTodoは次のTodoに含まない
+        assert!(client.next_todos().await.unwrap().is_empty());
+
+        // label
+        let label = client
+            .create_label(&CreateLabel {
+                color: Some("#FF8800".to_string()),
+                ..CreateLabel::new("client".to_string())
+            })
+            .await
+            .expect("failed create label");
+        assert_eq!(label.color, "#ff8800");
+        assert!(client.all_label().await.unwrap().contains(&label));
+        let label = client
+            .update_label(
+                label.id,
+                &UpdateLabel {
+                    icon: Some(Some("📌".to_string())),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("failed update label");
+        assert_eq!(label.icon.as_deref(), Some("📌"));
+        client.delete_label(label.id).await.unwrap();
+
+        // project
+        let project = client
+            .create_project(&CreateProject {
+                name: "client".to_string(),
+            })
+            .await
+            .expect("failed create project");
+        assert_eq!(client.find_project(project.id).await.unwrap(), project);
+        client
+            .update_project(
+                project.id,
+                &UpdateProject {
+                    archived: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(client.all_project(false).await.unwrap().len(), 1);
+        assert_eq!(client.all_project(true).await.unwrap().len(), 2);
+        assert_eq!(
+            client.all_project_todo(DEFAULT_PROJECT_ID).await.unwrap()[0].id,
+            todo.id
+        );
+        client.delete_project(project.id).await.unwrap();
+
+        client.delete_todo(todo.id).await.unwrap();
+        assert!(client.find_todo(todo.id).await.unwrap_err().is_not_found());
+    }
+
+    /// 同期のテスト用のクライアント
+    ///
+    /// ローカルのTodoRepositoryForMemoryで変更を行い、pushとpullでサーバーと同期する
+    struct Replica {
+        app: Router,
+        local: TodoRepositoryForMemory,
+        // サーバーのID -> (ローカルのID, 取り込んだversion)
+        synced: HashMap<i32, (i32, i64)>,
+        pending: Vec<PushTodo>,
+        cursor: i64,
+    }
+
+    impl Replica {
+        fn new(app: Router, labels: Vec<Label>) -> Self {
+            Self {
+                app,
+                local: TodoRepositoryForMemory::new(labels),
+                synced: HashMap::new(),
+                pending: vec![],
+                cursor: 0,
+            }
+        }
+
+        fn server_id(&self, local_id: i32) -> (i32, i64) {
+            self.synced
+                .iter()
+                .find(|(_, (id, _))| *id == local_id)
+                .map(|(server_id, (_, version))| (*server_id, *version))
+                .unwrap()
+        }
+
+        async fn create(&mut self, text: &str, at: DateTime<Utc>) -> i32 {
+            let todo = self
+                .local
+                .create(CreateTodo::new(text.to_string(), vec![]))
+                .await
+                .unwrap();
+            self.pending.push(PushTodo {
+                client_ref: Some(todo.id.to_string()),
+                text: Some(text.to_string()),
+                completed: Some(false),
+                labels: Some(vec![]),
+                updated_at: FieldTimes {
+                    text: Some(at),
+                    completed: Some(at),
+                    labels: Some(at),
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+            todo.id
+        }
+
+        async fn update(&mut self, local_id: i32, payload: UpdateTodo, at: DateTime<Utc>) {
+            self.local.update(local_id, payload.clone()).await.unwrap();
+            let (id, base_version) = self.server_id(local_id);
+            self.pending.push(PushTodo {
+                id: Some(id),
+                base_version,
+                updated_at: FieldTimes {
+                    text: payload.text.as_ref().map(|_| at),
+                    completed: payload.completed.map(|_| at),
+                    labels: payload.labels.as_ref().map(|_| at),
+                    ..Default::default()
+                },
+                text: payload.text,
+                completed: payload.completed,
+                labels: payload.labels,
+                ..Default::default()
+            });
+        }
+
+        async fn delete(&mut self, local_id: i32, at: DateTime<Utc>) {
+            self.local.delete(local_id).await.unwrap();
+            let (id, base_version) = self.server_id(local_id);
+            self.synced.remove(&id);
+            self.pending.push(PushTodo {
+                id: Some(id),
+                base_version,
+                deleted_at: Some(at),
+                ..Default::default()
+            });
+        }
+
+        async fn push(&mut self) -> SyncPushResult {
+            let payload = SyncPush {
+                todos: std::mem::take(&mut self.pending),
+            };
+            let req = build_req_with_json(
+                "/sync/push",
+                Method::POST,
+                serde_json::to_string(&payload).unwrap(),
+            );
+            let res = self.app.clone().oneshot(req).await.unwrap();
+            assert_eq!(StatusCode::OK, res.status());
+            let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+            let result: SyncPushResult = serde_json::from_slice(&bytes).unwrap();
+            for created in &result.created {
+                let local_id = created.client_ref.as_ref().unwrap().parse().unwrap();
+                self.synced.insert(created.id, (local_id, 0));
+            }
+            for synced in &result.todos {
+                self.apply(synced).await;
+            }
+            result
+        }
+
+        async fn pull(&mut self) {
+            loop {
+                let req = build_todo_req_with_empty(
+                    Method::GET,
+                    &format!("/sync/changes?since={}&limit=2", self.cursor),
+                );
+                let res = self.app.clone().oneshot(req).await.unwrap();
+                let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+                let changes: SyncChanges = serde_json::from_slice(&bytes).unwrap();
+                for synced in &changes.todos {
+                    self.apply(synced).await;
+                }
+                self.cursor = changes.cursor;
+                if !changes.has_more {
+                    break;
+                }
+            }
+        }
+
+        // サーバーの状態をローカルに取り込む
+        async fn apply(&mut self, synced: &SyncedTodo) {
+            let local_id = self.synced.get(&synced.id).map(|(id, _)| *id);
+            match (local_id, &synced.todo) {
+                (Some(local_id), Some(todo)) => {
+                    let payload = UpdateTodo {
+                        text: Some(todo.text.clone()),
+                        completed: Some(todo.completed),
+                        labels: Some(todo.labels.iter().map(|label| label.id).collect()),
+                        ..Default::default()
+                    };
+                    self.local.update(local_id, payload).await.unwrap();
+                    self.synced.insert(synced.id, (local_id, synced.version));
+                }
+                (None, Some(todo)) => {
+                    let labels = todo.labels.iter().map(|label| label.id).collect();
+                    let mut local = self
+                        .local
+                        .create(CreateTodo::new(todo.text.clone(), labels))
+                        .await
+                        .unwrap();
+                    if todo.completed {
+                        local = self
+                            .local
+                            .update(
+                                local.id,
+                                UpdateTodo {
+                                    completed: Some(true),
+                                    ..Default::default()
+                                },
+                            )
+                            .await
+                            .unwrap();
+                    }
+                    self.synced.insert(synced.id, (local.id, synced.version));
+                }
+                (Some(local_id), None) => {
+                    self.local.delete(local_id).await.unwrap();
+                    self.synced.remove(&synced.id);
+                }
+                (None, None) => {}
+            }
+        }
+
+        async fn snapshot(&self) -> Vec<(String, bool, usize)> {
+            let mut todos: Vec<_> = self
+                .local
+                .all()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|todo| (todo.text, todo.completed, todo.labels.len()))
+                .collect();
+            todos.sort();
+            todos
+        }
+
+        async fn local_id(&self, text: &str) -> i32 {
+            let todos = self.local.all().await.unwrap();
+            todos.iter().find(|todo| todo.text == text).unwrap().id
+        }
+    }
+
+    #[tokio::test]
+    async fn should_sync_two_replicas_through_router() {
+        let (labels, label_ids) = label_fixture();
+        let app = app(
+            TodoRepositoryForMemory::new(labels.clone()),
+            LabelRepositoryForMemory::with_labels(labels.clone()),
+        );
+        let mut a = Replica::new(app.clone(), labels.clone());
+        let mut b = Replica::new(app.clone(), labels);
+        let t0 = Utc::now();
+        let at = |seconds| t0 + chrono::Duration::seconds(seconds);
+
+        // オフラインで作成したTodoをサーバーに送り、もう一方が取り込む
+        a.create("buy milk", at(1)).await;
+        a.create("write report", at(1)).await;
+        let result = a.push().await;
+        assert_eq!(result.created.len(), 2);
+        assert!(result.conflicts.is_empty());
+        b.pull().await;
+        assert_eq!(a.snapshot().await, b.snapshot().await);
+
+        // 別々のフィールドの変更はそのまま併合される
+        let id = a.local_id("buy milk").await;
+        let update = UpdateTodo {
+            text: Some("buy oat milk".to_string()),
+            ..Default::default()
+        };
+        a.update(id, update, at(2)).await;
+        let id = b.local_id("buy milk").await;
+        let update = UpdateTodo {
+            completed: Some(true),
+            labels: Some(label_ids),
+            ..Default::default()
+        };
+        b.update(id, update, at(2)).await;
+
+        // 同じフィールドの変更は更新時刻が新しい方が採用される
+        let id = a.local_id("write report").await;
+        let update = UpdateTodo {
+            text: Some("report by a".to_string()),
+            ..Default::default()
+        };
+        a.update(id, update, at(4)).await;
+        let id = b.local_id("write report").await;
+        let update = UpdateTodo {
+            text: Some("report by b".to_string()),
+            ..Default::default()
+        };
+        b.update(id, update, at(3)).await;
+
+        assert!(a.push().await.conflicts.is_empty());
+        let result = b.push().await;
+        assert!(result.rejected.is_empty());
+        assert_eq!(result.conflicts.len(), 1);
+        let conflict = &result.conflicts[0];
+        assert_eq!(conflict.field, Some(SyncField::Text));
+        assert_eq!(conflict.resolution, Resolution::Server);
+        assert_eq!(conflict.client_value, serde_json::json!("report by b"));
+        assert_eq!(conflict.server_value, serde_json::json!("report by a"));
+
+        a.pull().await;
+        b.pull().await;
+        let expected = vec![
+            ("buy oat milk".to_string(), true, 1),
+            ("report by a".to_string(), false, 0),
+        ];
+        assert_eq!(a.snapshot().await, expected);
+        assert_eq!(b.snapshot().await, expected);
+
+        // 削除した後に相手が更新していれば、削除は反映されない
+        let id = b.local_id("buy oat milk").await;
+        let update = UpdateTodo {
+            text: Some("buy soy milk".to_string()),
+            ..Default::default()
+        };
+        b.update(id, update, at(6)).await;
+        b.push().await;
+        let id = a.local_id("buy oat milk").await;
+        a.delete(id, at(5)).await;
+        let result = a.push().await;
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].field, None);
+        assert_eq!(result.conflicts[0].resolution, Resolution::Server);
+
+        // 削除済みのTodoへの変更はトゥームストーンが優先される
+        let id = a.local_id("report by a").await;
+        a.delete(id, at(7)).await;
+        a.push().await;
+        let id = b.local_id("report by a").await;
+        let update = UpdateTodo {
+            completed: Some(true),
+            ..Default::default()
+        };
+        b.update(id, update, at(8)).await;
+        let result = b.push().await;
+        assert_eq!(result.conflicts.len(), 1);
+        assert!(result.todos[0].deleted_at.is_some());
+
+        // REST APIでの変更も変更フィードに載る
+        let req = build_req_with_json(
+            "/todos",
+            Method::POST,
+            r#"{ "text": "from web", "labels": [] }"#.to_string(),
+        );
+        app.clone().oneshot(req).await.unwrap();
+
+        a.pull().await;
+        b.pull().await;
+        let expected = vec![
+            ("buy soy milk".to_string(), true, 1),
+            ("from web".to_string(), false, 0),
+        ];
+        assert_eq!(a.snapshot().await, expected);
+        assert_eq!(b.snapshot().await, expected);
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_sync_push() {
+        let (labels, _label_ids) = label_fixture();
+        let mut replica = Replica::new(openapi_app(ApiScope::Full), labels);
+        replica.create("", Utc::now()).await;
+        replica.pending.push(PushTodo {
+            id: Some(404),
+            text: Some("missing".to_string()),
+            ..Default::default()
+        });
+        // 存在しないラベルやブロッカーはREST APIと同じく拒否する
+        replica.pending.push(PushTodo {
+            client_ref: Some("unknown label".to_string()),
+            text: Some("with label".to_string()),
+            labels: Some(vec![404]),
+            ..Default::default()
+        });
+        replica.pending.push(PushTodo {
+            client_ref: Some("unknown blocker".to_string()),
+            text: Some("with blocker".to_string()),
+            blocked_by: Some(vec![404]),
+            ..Default::default()
+        });
+        let result = replica.push().await;
+        assert!(result.todos.is_empty());
+        let rejected: Vec<_> = result
+            .rejected
+            .iter()
+            .map(|rejected| (rejected.id, rejected.client_ref.as_deref()))
+            .collect();
+        assert_eq!(
+            rejected,
+            vec![
+                (None, Some("1")),
+                (Some(404), None),
+                (None, Some("unknown label")),
+                (None, Some("unknown blocker")),
+            ]
+        );
+        assert_eq!(result.rejected[2].reason, "Error!: Label not found");
+    }
+
+    #[tokio::test]
+    async fn should_sync_notes_priority_and_dependencies() {
+        let (labels, _label_ids) = label_fixture();
+        let app = openapi_app(ApiScope::Full);
+        let mut a = Replica::new(app.clone(), labels.clone());
+        let mut b = Replica::new(app.clone(), labels);
+        let t0 = Utc::now();
+        let at = |seconds| t0 + chrono::Duration::seconds(seconds);
+        let due_at = "2030-01-15T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        a.create("blocker", at(1)).await;
+        a.create("waiting", at(1)).await;
+        let pending = a.pending.last_mut().unwrap();
+        pending.notes = Some(Some(" - [ ] step\r\n".to_string()));
+        pending.priority = Some(Some(Priority::High));
+        pending.due_at = Some(Some(due_at));
+        let result = a.push().await;
+        assert!(result.rejected.is_empty());
+        let blocker = result.created[0].id;
+        let waiting = result.created[1].id;
+        let todo = result.todos[1].todo.clone().unwrap();
+        assert_eq!(todo.notes.as_deref(), Some("- [ ] step"));
+        assert_eq!(todo.priority, Some(Priority::High));
+        assert_eq!(todo.due_at, Some(due_at));
+        let base_version = result.todos[1].version;
+
+        // メモ・優先度・期限・ブロッカーも変更フィードに載る
+        b.pull().await;
+        let push = |notes: &str, at: DateTime<Utc>| PushTodo {
+            id: Some(waiting),
+            base_version,
+            notes: Some(Some(notes.to_string())),
+            updated_at: FieldTimes {
+                notes: Some(at),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        b.pending.push(PushTodo {
+            blocked_by: Some(vec![blocker]),
+            priority: Some(None),
+            ..push("by b", at(3))
+        });
+        let result = b.push().await;
+        assert!(result.conflicts.is_empty());
+        let todo = result.todos[0].todo.clone().unwrap();
+        assert_eq!(todo.blocked_by, vec![blocker]);
+        assert!(todo.blocked);
+        assert_eq!(todo.priority, None);
+
+        // 同じフィールドの変更は更新時刻が新しい方が採用される
+        a.pending.push(push("by a", at(2)));
+        let result = a.push().await;
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].field, Some(SyncField::Notes));
+        assert_eq!(result.conflicts[0].resolution, Resolution::Server);
+        let todo = result.todos[0].todo.clone().unwrap();
+        assert_eq!(todo.notes.as_deref(), Some("by b"));
+
+        // 循環する依存関係は反映しない
+        let version = result.todos[0].version;
+        a.pending.push(PushTodo {
+            id: Some(blocker),
+            base_version: version,
+            blocked_by: Some(vec![waiting]),
+            ..Default::default()
+        });
+        let result = a.push().await;
+        assert_eq!(result.rejected.len(), 1);
+        assert_eq!(result.rejected[0].id, Some(blocker));
+
+        // REST APIでの依存関係の変更も変更フィードに載る
+        let cursor = b.cursor;
+        let req = build_req_with_json(
+            &format!("/todos/{}/dependencies", waiting),
+            Method::DELETE,
+            format!(r#"{{ "blocker_id": {} }}"#, blocker),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::NO_CONTENT, res.status());
+        let req =
+            build_todo_req_with_empty(Method::GET, &format!("/sync/changes?since={}", cursor));
+        let res = app.clone().oneshot(req).await.unwrap();
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let changes: SyncChanges = serde_json::from_slice(&bytes).unwrap();
+        let synced = changes
+            .todos
+            .iter()
+            .find(|synced| synced.id == waiting)
+            .unwrap();
+        assert!(synced.todo.as_ref().unwrap().blocked_by.is_empty());
+        // 未完了のブロッカーがあるTodoは、作成・更新のどちらも書き込む前に拒否する
+        a.create("done while blocked", at(9)).await;
+        let pending = a.pending.last_mut().unwrap();
+        pending.completed = Some(true);
+        pending.blocked_by = Some(vec![blocker]);
+        a.pending.push(PushTodo {
+            id: Some(waiting),
+            base_version: i64::MAX,
+            completed: Some(true),
+            blocked_by: Some(vec![blocker]),
+            ..Default::default()
+        });
+        let result = a.push().await;
+        assert!(result.created.is_empty());
+        assert_eq!(result.rejected.len(), 2);
+        assert!(result
+            .rejected
+            .iter()
+            .all(|rejected| rejected.reason == "Error!: Todo is blocked by incomplete todos"));
+        let req = build_todo_req_with_empty(Method::GET, "/todos");
+        let res = app.clone().oneshot(req).await.unwrap();
+        let todos: Vec<TodoEntity> =
+            serde_json::from_slice(&to_bytes(res.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(todos.len(), 2);
+        let todo = todos.iter().find(|todo| todo.id == waiting).unwrap();
+        assert!(todo.blocked_by.is_empty());
+        assert!(!todo.completed);
+    }
+
+    // API仕様に含めないルート
+    const UNDOCUMENTED_ROUTES: [&str; 3] = ["/", "/openapi.json", "/docs"];
+
+    fn openapi_app(scope: ApiScope) -> Router {
+        let (labels, _label_ids) = label_fixture();
+        app_with(
+            TodoRepositoryForMemory::new(labels),
+            LabelRepositoryForMemory::new(),
+            AppParts {
+                scope,
+                ..Default::default()
+            },
+        )
+        // ルートが存在しない場合をハンドラの404と区別する
+        .fallback(|| async { StatusCode::IM_A_TEAPOT })
+    }
+
+    #[tokio::test]
+    async fn should_serve_openapi_json() {
+        let req = build_todo_req_with_empty(Method::GET, "/openapi.json");
+        let res = openapi_app(ApiScope::Full).oneshot(req).await.unwrap();
+        assert_eq!(StatusCode::OK, res.status());
+        let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let spec: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3.1"));
+        assert!(spec["components"]["schemas"]["TodoEntity"].is_object());
+        assert!(spec["components"]["schemas"]["CreateLabel"].is_object());
+    }
+
+    #[tokio::test]
+    async fn should_route_undocumented_paths() {
+        let app = openapi_app(ApiScope::Full);
+        for path in UNDOCUMENTED_ROUTES {
+            let req = build_todo_req_with_empty(Method::GET, path);
+            let status = app.clone().oneshot(req).await.unwrap().status();
+            assert_ne!(StatusCode::IM_A_TEAPOT, status, "{} is not routed", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_route_every_openapi_operation() {
+        let methods = [
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ];
+        let all_paths =
+            serde_json::to_value(openapi::spec(ApiScope::Full)).unwrap()["paths"].clone();
+        for scope in [ApiScope::Full, ApiScope::TodosAndLabels] {
+            let spec = serde_json::to_value(openapi::spec(scope)).unwrap();
+            let app = openapi_app(scope);
+            for (path, operations) in all_paths.as_object().unwrap() {
+                let published = spec["paths"].get(path).is_some();
+                let uri = path.replace("{id}", "1");
+                for method in methods.iter() {
+                    let documented = operations.get(method.as_str().to_lowercase()).is_some();
+                    let req = build_req_with_json(&uri, method.clone(), "{}".to_string());
+                    let status = app.clone().oneshot(req).await.unwrap().status();
+                    if !published {
+                        // API仕様から外したパスはルーティングもしない
+                        assert_eq!(StatusCode::IM_A_TEAPOT, status, "{:?} {}", scope, path);
+                        continue;
+                    }
+                    assert_ne!(StatusCode::IM_A_TEAPOT, status, "{} is not routed", path);
+                    assert_eq!(
+                        documented,
+                        status != StatusCode::METHOD_NOT_ALLOWED,
+                        "{} {} differs between router and openapi",
+                        method,
+                        path
+                    );
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn should_serve_todos_without_postgres_features() {
+        let app = openapi_app(ApiScope::TodosAndLabels);
+        let send = |method: Method, path: &str, body: &str| {
+            let req = build_req_with_json(path, method, body.to_string());
+            let app = app.clone();
+            async move {
+                let res = app.oneshot(req).await.unwrap();
+                let status = res.status();
+                let bytes = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+                (status, body)
+            }
+        };
+
+        let (status, spec) = send(Method::GET, "/openapi.json", "").await;
+        assert_eq!(StatusCode::OK, status);
+        assert!(spec["paths"]["/todos"].is_object());
+        assert!(spec["paths"]["/projects"].is_null());
+        assert!(spec["paths"]["/todos/{id}/comments"].is_null());
+
+        // プロジェクトとステータスは既定のものしか使えない
+        let (status, todo) = send(
+            Method::POST,
+            "/todos",
+            r#"{ "text": "local", "labels": [] }"#,
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(todo["status_id"], serde_json::Value::Null);
+        for body in [
+            r#"{ "text": "other", "labels": [], "project_id": 2 }"#,
+            r#"{ "text": "other", "labels": [], "status_id": 1 }"#,
+        ] {
+            let (status, _) = send(Method::POST, "/todos", body).await;
+            assert_eq!(StatusCode::BAD_REQUEST, status, "{}", body);
+        }
+        let (status, todo) = send(Method::PATCH, "/todos/1", r#"{ "completed": true }"#).await;
+        assert_eq!(StatusCode::CREATED, status);
+        assert_eq!(todo["completed"], true);
+        let (status, _) = send(Method::DELETE, "/todos/1", "").await;
+        assert_eq!(StatusCode::NO_CONTENT, status);
+    }
+}
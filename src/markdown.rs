@@ -0,0 +1,88 @@
+use pulldown_cmark::{html, Event, Options, Parser};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::HashSet};
+use utoipa::ToSchema;
+
+/// メモのタスクリスト (`- [ ]` / `- [x]`) の進み具合
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub struct Checklist {
+    pub done: usize,
+    pub total: usize,
+}
+
+fn parser(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(
+        markdown,
+        Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES,
+    )
+}
+
+/// MarkdownをHTMLに変換する。スクリプトやイベント属性などは取り除き、タスクリストのチェックボックスだけを残す
+pub fn render_html(markdown: &str) -> String {
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, parser(markdown));
+    ammonia::Builder::default()
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .attribute_filter(|element, attribute, value| {
+            if element == "input" && attribute == "type" && value != "checkbox" {
+                None
+            } else {
+                Some(Cow::Borrowed(value))
+            }
+        })
+        .link_rel(Some("noopener noreferrer nofollow"))
+        .url_schemes(HashSet::from(["http", "https", "mailto"]))
+        .clean(&unsafe_html)
+        .to_string()
+}
+
+/// タスクリストの項目を数える。コードブロック内の `- [ ]` は数えない。項目がなければNone
+pub fn checklist(markdown: &str) -> Option<Checklist> {
+    let mut checklist = Checklist { done: 0, total: 0 };
+    for event in parser(markdown) {
+        if let Event::TaskListMarker(checked) = event {
+            checklist.total += 1;
+            if checked {
+                checklist.done += 1;
+            }
+        }
+    }
+    (checklist.total > 0).then_some(checklist)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_render_sanitized_html() {
+        let html = render_html("# Title\n\n**bold** [link](https://example.com)");
+        assert!(html.contains("<h1>Title</h1>"));
+        assert!(html.contains("<strong>bold</strong>"));
+        assert!(html.contains(r#"href="https://example.com""#));
+
+        // スクリプトやイベント属性、javascript: のリンクは取り除く
+        let html = render_html("<script>alert(1)</script><img src=x onerror=alert(1)>");
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("onerror"));
+        let html = render_html("[x](javascript:alert(1))");
+        assert!(!html.contains("javascript:"));
+        let html = render_html(r#"<input type="text" value="x">"#);
+        assert!(!html.contains("type="));
+    }
+
+    #[test]
+    fn should_keep_task_list_checkboxes() {
+        let html = render_html("- [x] done\n- [ ] open");
+        assert!(html.contains(r#"<input disabled="" type="checkbox" checked="">"#));
+        assert!(html.contains(r#"<input disabled="" type="checkbox">"#));
+    }
+
+    #[test]
+    fn should_count_checklist() {
+        let notes = "- [x] one\n- [ ] two\n  - [X] nested\n\n```\n- [ ] code\n```\n";
+        assert_eq!(checklist(notes), Some(Checklist { done: 2, total: 3 }));
+        assert_eq!(checklist("- plain item\n[ ] not a list"), None);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::handlers::{attachment, comment, label, project, stats, status, sync, todo, webhook};
+use crate::ApiScope;
+use axum::{response::Html, Extension, Json};
+use utoipa::OpenApi;
+
+/// ハンドラの `#[utoipa::path]` から生成するAPI仕様
+///
+/// ルートを追加した場合はここにも追加する (lib.rsのテストで、記載したパスとメソッドがルーティングされているかを確認する)
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Todo API", description = "Todo・ラベル・プロジェクトを管理するAPI"),
+    paths(
+        todo::create_todo,
+        todo::quick_add_todo,
+        todo::all_todo,
+        todo::find_todo,
+        todo::update_todo,
+        todo::delete_todo,
+        todo::add_dependency,
+        todo::remove_dependency,
+        todo::next_todo,
+        label::create_label,
+        label::all_label,
+        label::update_label,
+        label::delete_label,
+        label::merge_labels,
+        project::create_project,
+        project::all_project,
+        project::find_project,
+        project::update_project,
+        project::delete_project,
+        project::all_project_todo,
+        status::create_status,
+        status::all_status,
+        status::update_status,
+        status::delete_status,
+        status::find_board,
+        stats::find_stats,
+        comment::create_comment,
+        comment::all_comment,
+        comment::update_comment,
+        comment::delete_comment,
+        attachment::create_attachment,
+        attachment::all_attachment,
+        attachment::find_attachment,
+        attachment::delete_attachment,
+        webhook::create_webhook,
+        webhook::all_webhook,
+        webhook::update_webhook,
+        webhook::delete_webhook,
+        webhook::all_webhook_delivery,
+        sync::sync_changes,
+        sync::sync_push,
+    ),
+    tags(
+        (name = "todos"),
+        (name = "labels"),
+        (name = "projects"),
+        (name = "statuses", description = "カンバンの列"),
+        (name = "stats", description = "Todoの件数の集計"),
+        (name = "comments"),
+        (name = "attachments"),
+        (name = "webhooks"),
+        (name = "sync", description = "オフラインのクライアントとの双方向同期"),
+    )
+)]
+pub struct ApiDoc;
+
+const REDOC_HTML: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>Todo API</title>
+    <meta charset="utf-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1" />
+  </head>
+  <body>
+    <redoc spec-url="/openapi.json"></redoc>
+    <script src="https://cdn.redoc.ly/redoc/latest/bundles/redoc.standalone.js"></script>
+  </body>
+</html>
+"#;
+
+// `ApiScope::TodosAndLabels` で外す機能のタグ
+const POSTGRES_ONLY_TAGS: [&str; 5] = ["projects", "statuses", "comments", "attachments", "sync"];
+
+/// 公開している範囲のAPI仕様
+pub fn spec(scope: ApiScope) -> utoipa::openapi::OpenApi {
+    let mut spec = ApiDoc::openapi();
+    if scope == ApiScope::TodosAndLabels {
+        spec.paths.paths.retain(|_, item| {
+            [&item.get, &item.post, &item.put, &item.patch, &item.delete]
+                .into_iter()
+                .flatten()
+                .flat_map(|operation| operation.tags.iter().flatten())
+                .all(|tag| !POSTGRES_ONLY_TAGS.contains(&tag.as_str()))
+        });
+    }
+    spec
+}
+
+pub async fn openapi_json(Extension(scope): Extension<ApiScope>) -> Json<utoipa::openapi::OpenApi> {
+    Json(spec(scope))
+}
+
+pub async fn redoc() -> Html<&'static str> {
+    Html(REDOC_HTML)
+}
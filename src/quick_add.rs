@@ -0,0 +1,363 @@
+use chrono::{DateTime, Datelike, Days, FixedOffset, NaiveDate, NaiveTime, TimeZone, Weekday};
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+use todo_types::Priority;
+
+/// クイック追加の1行を分解した結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedTodo {
+    pub text: String,
+    /// `#` を除いたラベル名。重複は除き、出てきた順に並べる
+    pub labels: Vec<String>,
+    pub priority: Option<Priority>,
+    pub due_at: Option<DateTime<FixedOffset>>,
+}
+
+type DateRule = fn(&Captures, NaiveDate) -> Option<NaiveDate>;
+type TimeRule = fn(&Captures) -> Option<NaiveTime>;
+
+// 上から順に試し、最初に解釈できたものを使う。「来週月曜」が「来週」より先になるように並べる
+static DATE_RULES: LazyLock<Vec<(Regex, DateRule)>> = LazyLock::new(|| {
+    let rules: Vec<(&str, DateRule)> = vec![
+        (
+            r"(?i)\b(?:(?:by|due|on)\s+)?day after tomorrow\b",
+            |_, today| after(today, 2),
+        ),
+        (
+            r"(?i)\b(?:(?:by|due)\s+)?(?:today|tonight)\b",
+            |_, today| Some(today),
+        ),
+        (r"(?i)\b(?:(?:by|due)\s+)?tomorrow\b", |_, today| {
+            after(today, 1)
+        }),
+        (
+            r"(?i)\b(?:(?:by|due|on)\s+)?(?:(next|this)\s+)?(monday|tuesday|wednesday|thursday|friday|saturday|sunday)\b",
+            |c, today| {
+                let weekday = c[2].parse::<Weekday>().ok()?;
+                match c.get(1).map(|m| m.as_str().to_lowercase()).as_deref() {
+                    Some("next") => after(week_start(today), 7 + days_from_monday(weekday)),
+                    Some(_) => after(week_start(today), days_from_monday(weekday)),
+                    None => next_weekday(today, weekday),
+                }
+            },
+        ),
+        (r"(?i)\b(?:(?:by|due)\s+)?next week\b", |_, today| {
+            next_weekday(today, Weekday::Mon)
+        }),
+        (
+            r"(?i)\b(?:(?:by|due)\s+)?in\s+(\d{1,3})\s+(days?|weeks?)\b",
+            |c, today| {
+                let n: u64 = c[1].parse().ok()?;
+                let unit = if c[2].to_lowercase().starts_with("week") {
+                    7
+                } else {
+                    1
+                };
+                after(today, n * unit)
+            },
+        ),
+        (
+            r"\b(?:(?:by|due|on)\s+)?(\d{4})-(\d{1,2})-(\d{1,2})\b",
+            |c, _| {
+                NaiveDate::from_ymd_opt(c[1].parse().ok()?, c[2].parse().ok()?, c[3].parse().ok()?)
+            },
+        ),
+        (
+            r"\b(?:(?:by|due|on)\s+)?(\d{1,2})/(\d{1,2})\b",
+            |c, today| month_day(today, c[1].parse().ok()?, c[2].parse().ok()?),
+        ),
+        (
+            r"(?:明後日|あさって)(?:までに|まで|に)?",
+            |_, today| after(today, 2),
+        ),
+        (
+            r"(?:今日|本日|きょう)(?:中に|までに|まで|に)?",
+            |_, today| Some(today),
+        ),
+        (
+            r"(?:明日|あした)(?:までに|まで|に)?",
+            |_, today| after(today, 1),
+        ),
+        (
+            r"(来週の?|今週の?|次の)?([月火水木金土日])曜日?(?:までに|まで|に)?",
+            |c, today| {
+                let weekday = weekday_ja(&c[2])?;
+                match c.get(1).map(|m| m.as_str()) {
+                    Some(prefix) if prefix.starts_with("来週") => {
+                        after(week_start(today), 7 + days_from_monday(weekday))
+                    }
+                    Some(prefix) if prefix.starts_with("今週") => {
+                        after(week_start(today), days_from_monday(weekday))
+                    }
+                    _ => next_weekday(today, weekday),
+                }
+            },
+        ),
+        (r"来週(?:中に|までに|まで|に)?", |_, today| {
+            next_weekday(today, Weekday::Mon)
+        }),
+        (
+            r"(\d{1,2})月(\d{1,2})日(?:までに|まで|に)?",
+            |c, today| month_day(today, c[1].parse().ok()?, c[2].parse().ok()?),
+        ),
+        (
+            r"(\d{1,3})(日|週間)後(?:までに|まで|に)?",
+            |c, today| {
+                let n: u64 = c[1].parse().ok()?;
+                after(today, if &c[2] == "週間" { n * 7 } else { n })
+            },
+        ),
+    ];
+    compile(rules)
+});
+
+static TIME_RULES: LazyLock<Vec<(Regex, TimeRule)>> = LazyLock::new(|| {
+    let rules: Vec<(&str, TimeRule)> = vec![
+        (r"(?i)\b(?:at\s+)?(\d{1,2})(?::(\d{2}))?\s*(am|pm)\b", |c| {
+            let hour: u32 = c[1].parse().ok()?;
+            if !(1..=12).contains(&hour) {
+                return None;
+            }
+            let pm = c[3].eq_ignore_ascii_case("pm");
+            let minute = c.get(2).map_or(Some(0), |m| m.as_str().parse().ok())?;
+            NaiveTime::from_hms_opt(hour % 12 + if pm { 12 } else { 0 }, minute, 0)
+        }),
+        (
+            r"(?i)(?:\bat\s+)?\b(\d{1,2}):(\d{2})(?:までに|まで|に)?",
+            |c| NaiveTime::from_hms_opt(c[1].parse().ok()?, c[2].parse().ok()?, 0),
+        ),
+        (
+            r"(午前|午後)?(\d{1,2})時(?:(\d{1,2})分|(半))?(?:までに|まで|に)?",
+            |c| {
+                let mut hour: u32 = c[2].parse().ok()?;
+                if c.get(1).is_some_and(|m| m.as_str() == "午後") && hour < 12 {
+                    hour += 12;
+                }
+                let minute = match (c.get(3), c.get(4)) {
+                    (Some(m), _) => m.as_str().parse().ok()?,
+                    (None, Some(_)) => 30,
+                    (None, None) => 0,
+                };
+                NaiveTime::from_hms_opt(hour, minute, 0)
+            },
+        ),
+    ];
+    compile(rules)
+});
+
+fn compile<T>(rules: Vec<(&str, T)>) -> Vec<(Regex, T)> {
+    rules
+        .into_iter()
+        .map(|(pattern, rule)| (Regex::new(pattern).unwrap(), rule))
+        .collect()
+}
+
+fn after(date: NaiveDate, days: u64) -> Option<NaiveDate> {
+    date.checked_add_days(Days::new(days))
+}
+
+fn days_from_monday(weekday: Weekday) -> u64 {
+    weekday.num_days_from_monday().into()
+}
+
+/// その週の月曜日
+fn week_start(today: NaiveDate) -> NaiveDate {
+    today - Days::new(days_from_monday(today.weekday()))
+}
+
+/// 今日より後で最初に来る曜日
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> Option<NaiveDate> {
+    let days = (7 + days_from_monday(weekday) - days_from_monday(today.weekday())) % 7;
+    after(today, if days == 0 { 7 } else { days })
+}
+
+/// 月日だけの指定。今年のその日が過ぎていれば来年にする
+fn month_day(today: NaiveDate, month: u32, day: u32) -> Option<NaiveDate> {
+    let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+    if date < today {
+        NaiveDate::from_ymd_opt(today.year() + 1, month, day)
+    } else {
+        Some(date)
+    }
+}
+
+fn weekday_ja(c: &str) -> Option<Weekday> {
+    Some(match c {
+        "月" => Weekday::Mon,
+        "火" => Weekday::Tue,
+        "水" => Weekday::Wed,
+        "木" => Weekday::Thu,
+        "金" => Weekday::Fri,
+        "土" => Weekday::Sat,
+        "日" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn parse_priority(word: &str) -> Option<Priority> {
+    Some(match word.to_lowercase().as_str() {
+        "low" | "低" => Priority::Low,
+        "medium" | "med" | "中" => Priority::Medium,
+        "high" | "高" => Priority::High,
+        "urgent" | "緊急" => Priority::Urgent,
+        _ => return None,
+    })
+}
+
+/// 最初に解釈できた表現を取り除いて、その値を返す
+fn take<T, R>(
+    rest: &mut String,
+    rules: &[(Regex, R)],
+    resolve: impl Fn(&R, &Captures) -> Option<T>,
+) -> Option<T> {
+    for (regex, rule) in rules {
+        let found = regex
+            .captures_iter(rest)
+            .find_map(|c| Some((c.get(0)?.range(), resolve(rule, &c)?)));
+        if let Some((range, value)) = found {
+            rest.replace_range(range, " ");
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// `Fix login bug tomorrow 15:00 #backend !high` のような1行を本文・ラベル・優先度・期限に分ける。
+/// 日付や時刻は `now` のタイムゾーンで解釈する
+pub fn parse(input: &str, now: DateTime<FixedOffset>) -> ParsedTodo {
+    let mut labels: Vec<String> = Vec::new();
+    let mut priority = None;
+    let mut words = Vec::new();
+    for word in input.split_whitespace() {
+        match (word.strip_prefix('#'), word.strip_prefix('!')) {
+            (Some(label), _) if !label.is_empty() => {
+                if !labels.iter().any(|l| l == label) {
+                    labels.push(label.to_string());
+                }
+            }
+            (_, Some(word)) if parse_priority(word).is_some() => priority = parse_priority(word),
+            _ => words.push(word),
+        }
+    }
+
+    let mut rest = words.join(" ");
+    let today = now.date_naive();
+    let date = take(&mut rest, &DATE_RULES, |rule, c| rule(c, today));
+    let time = take(&mut rest, &TIME_RULES, |rule, c| rule(c));
+    let due = match (date, time) {
+        (None, None) => None,
+        (Some(date), Some(time)) => Some(date.and_time(time)),
+        // 日付だけならその日の終わりを期限にする
+        (Some(date), None) => date.and_hms_opt(23, 59, 59),
+        // 時刻だけなら、今日のその時刻が過ぎていれば明日にする
+        (None, Some(time)) if time > now.time() => Some(today.and_time(time)),
+        (None, Some(time)) => after(today, 1).map(|date| date.and_time(time)),
+    };
+
+    ParsedTodo {
+        text: rest.split_whitespace().collect::<Vec<_>>().join(" "),
+        labels,
+        priority,
+        due_at: due.and_then(|due| now.offset().from_local_datetime(&due).single()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 2024-11-20 (水) 10:00 JST
+    fn now() -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2024-11-20T10:00:00+09:00").unwrap()
+    }
+
+    fn at(s: &str) -> Option<DateTime<FixedOffset>> {
+        Some(DateTime::parse_from_rfc3339(s).unwrap())
+    }
+
+    #[test]
+    fn should_parse_labels_priority_and_due() {
+        let parsed = parse("Fix login bug tomorrow 15:00 #backend !high", now());
+        assert_eq!(
+            parsed,
+            ParsedTodo {
+                text: "Fix login bug".to_string(),
+                labels: vec!["backend".to_string()],
+                priority: Some(Priority::High),
+                due_at: at("2024-11-21T15:00:00+09:00"),
+            }
+        );
+
+        // 知らない `!` はそのまま本文に残し、ラベルの重複はまとめる
+        let parsed = parse("review !later #a #work/api #a", now());
+        assert_eq!(parsed.text, "review !later");
+        assert_eq!(parsed.labels, vec!["a", "work/api"]);
+        assert_eq!(parsed.priority, None);
+        assert_eq!(parsed.due_at, None);
+    }
+
+    #[test]
+    fn should_parse_english_dates() {
+        let cases = [
+            ("call mom friday 3pm", "2024-11-22T15:00:00+09:00"),
+            (
+                "meeting next friday at 10:30am",
+                "2024-11-29T10:30:00+09:00",
+            ),
+            ("report due today", "2024-11-20T23:59:59+09:00"),
+            ("plan next week", "2024-11-25T23:59:59+09:00"),
+            ("renew in 2 weeks", "2024-12-04T23:59:59+09:00"),
+            ("pay rent 12/1", "2024-12-01T23:59:59+09:00"),
+            ("new year 1/1", "2025-01-01T23:59:59+09:00"),
+            ("release 2025-03-31 18:00", "2025-03-31T18:00:00+09:00"),
+            // 時刻だけで過ぎていれば翌日
+            ("water plants 9:00", "2024-11-21T09:00:00+09:00"),
+            ("lunch 12:15", "2024-11-20T12:15:00+09:00"),
+        ];
+        for (input, expected) in cases {
+            let parsed = parse(input, now());
+            assert_eq!(parsed.due_at, at(expected), "{input}");
+            assert!(!parsed.text.is_empty(), "{input}");
+        }
+        assert_eq!(parse("call mom friday 3pm", now()).text, "call mom");
+    }
+
+    #[test]
+    fn should_parse_japanese_dates() {
+        let cases = [
+            (
+                "資料作成 明日15時に #仕事",
+                "資料作成",
+                "2024-11-21T15:00:00+09:00",
+            ),
+            (
+                "来週月曜 定例の準備 !高",
+                "定例の準備",
+                "2024-11-25T23:59:59+09:00",
+            ),
+            ("月曜日までに報告書", "報告書", "2024-11-25T23:59:59+09:00"),
+            (
+                "今週金曜 午後3時半 振り返り",
+                "振り返り",
+                "2024-11-22T15:30:00+09:00",
+            ),
+            ("あさって 歯医者", "歯医者", "2024-11-22T23:59:59+09:00"),
+            ("3日後に請求書", "請求書", "2024-11-23T23:59:59+09:00"),
+            ("12月1日 大掃除", "大掃除", "2024-12-01T23:59:59+09:00"),
+        ];
+        for (input, text, expected) in cases {
+            let parsed = parse(input, now());
+            assert_eq!(parsed.text, text, "{input}");
+            assert_eq!(parsed.due_at, at(expected), "{input}");
+        }
+        let parsed = parse("来週月曜 定例の準備 !高", now());
+        assert_eq!(parsed.priority, Some(Priority::High));
+    }
+
+    #[test]
+    fn should_use_offset_of_now() {
+        let now = DateTime::parse_from_rfc3339("2024-11-20T23:30:00-05:00").unwrap();
+        let parsed = parse("standup tomorrow 9am", now);
+        assert_eq!(parsed.due_at, at("2024-11-21T09:00:00-05:00"));
+    }
+}
@@ -1,10 +1,33 @@
+pub mod attachment;
+pub mod comment;
+pub mod file;
 pub mod label;
+pub mod project;
+pub mod status;
+pub mod sync;
 pub mod todo;
+pub mod webhook;
 
+use crate::ApiScope;
+use label::{
+    LabelRepositoryBackend, LabelRepositoryForDb, LabelRepositoryForFile, LabelRepositoryForMySql,
+    LabelRepositoryForSqlite,
+};
+use shuttle_runtime::SecretStore;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    MySqlPool, PgPool, SqlitePool,
+};
+use std::str::FromStr;
 use thiserror::Error;
+use todo::{
+    TodoRepositoryBackend, TodoRepositoryForDb, TodoRepositoryForFile, TodoRepositoryForMySql,
+    TodoRepositoryForSqlite,
+};
+use todo_types::deserialize_double_option;
 
 #[derive(Debug, Error)]
-enum RepositoryError {
+pub enum RepositoryError {
     #[error("Unexpected Error: [{0}]")]
     Unexpected(String),
     #[error("NotFound, id is {0}")]
@@ -12,3 +35,99 @@ enum RepositoryError {
     #[error("Duplicate data, id is {0}")]
     Duplicate(i32),
 }
+
+/// 接続先URLのスキームで選択されるデータベース
+///
+/// `sqlite:` ならローカルモード用のSQLite、`postgres://` ならPostgres、`mysql://` ならMySQL/MariaDBに接続する
+#[derive(Debug, Clone)]
+pub enum Database {
+    Postgres(PgPool),
+    MySql(MySqlPool),
+    Sqlite(SqlitePool),
+}
+
+impl Database {
+    /// 接続してマイグレーションまで行う
+    pub async fn connect(database_url: &str) -> anyhow::Result<Self> {
+        if database_url.starts_with("sqlite:") {
+            Ok(Database::Sqlite(connect_sqlite(database_url).await?))
+        } else if database_url.starts_with("postgres://")
+            || database_url.starts_with("postgresql://")
+        {
+            let pool = PgPool::connect(database_url).await?;
+            sqlx::migrate!().run(&pool).await?;
+            Ok(Database::Postgres(pool))
+        } else if database_url.starts_with("mysql://") {
+            Ok(Database::MySql(connect_mysql(database_url).await?))
+        } else {
+            anyhow::bail!("unsupported database url: {}", database_url)
+        }
+    }
+}
+
+/// ファイルがなければ作成し、SQLite用のマイグレーションを適用する
+pub async fn connect_sqlite(database_url: &str) -> anyhow::Result<SqlitePool> {
+    let options = SqliteConnectOptions::from_str(database_url)?
+        .create_if_missing(true)
+        .foreign_keys(true);
+    // ローカルモードは単一ユーザーなので接続は1本にする (インメモリDBが接続ごとに別になるのも防ぐ)
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await?;
+    sqlx::migrate!("./migrations/sqlite").run(&pool).await?;
+    Ok(pool)
+}
+
+/// MySQL/MariaDB用のマイグレーションを適用する
+pub async fn connect_mysql(database_url: &str) -> anyhow::Result<MySqlPool> {
+    let pool = MySqlPool::connect(database_url).await?;
+    sqlx::migrate!("./migrations/mysql").run(&pool).await?;
+    Ok(pool)
+}
+
+/// Secretsの設定でTodoとラベルの保存先を選択し、公開するAPIの範囲と合わせて返す
+///
+/// - `DATA_DIR` があればそのディレクトリのファイルに保存する
+/// - `TODO_DATABASE_URL` があれば接続先URLのスキームでDBを選択する
+/// - どちらもなければ `pool` のDBに保存する
+///
+/// `pool` 以外に保存する場合、`pool` 上でTodoを参照するプロジェクトやコメントなどは公開しない
+pub async fn backends_from_secrets(
+    secrets: &SecretStore,
+    pool: &PgPool,
+) -> anyhow::Result<(TodoRepositoryBackend, LabelRepositoryBackend, ApiScope)> {
+    if let Some(dir) = secrets.get("DATA_DIR") {
+        let labels = LabelRepositoryForFile::open(&dir)?;
+        let todos = TodoRepositoryForFile::open(&dir, &labels)?;
+        let labels = labels.with_todos(&todos);
+        return Ok((
+            TodoRepositoryBackend::File(todos),
+            LabelRepositoryBackend::File(labels),
+            ApiScope::TodosAndLabels,
+        ));
+    }
+
+    let (database, scope) = match secrets.get("TODO_DATABASE_URL") {
+        Some(database_url) => (
+            Database::connect(&database_url).await?,
+            ApiScope::TodosAndLabels,
+        ),
+        None => (Database::Postgres(pool.clone()), ApiScope::Full),
+    };
+    let (todos, labels) = match database {
+        Database::Postgres(pool) => (
+            TodoRepositoryBackend::Db(TodoRepositoryForDb::new(pool.clone())),
+            LabelRepositoryBackend::Db(LabelRepositoryForDb::new(pool)),
+        ),
+        Database::MySql(pool) => (
+            TodoRepositoryBackend::MySql(TodoRepositoryForMySql::new(pool.clone())),
+            LabelRepositoryBackend::MySql(LabelRepositoryForMySql::new(pool)),
+        ),
+        Database::Sqlite(pool) => (
+            TodoRepositoryBackend::Sqlite(TodoRepositoryForSqlite::new(pool.clone())),
+            LabelRepositoryBackend::Sqlite(LabelRepositoryForSqlite::new(pool)),
+        ),
+    };
+    Ok((todos, labels, scope))
+}
@@ -0,0 +1,353 @@
+use super::RepositoryError;
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use utoipa::ToSchema;
+
+#[async_trait]
+pub trait AttachmentRepository: Clone + std::marker::Send + std::marker::Sync + 'static {
+    async fn create(&self, payload: CreateAttachment) -> anyhow::Result<Attachment>;
+    async fn find(&self, id: i32) -> anyhow::Result<Attachment>;
+    async fn all_in_todo(&self, todo_id: i32) -> anyhow::Result<Vec<Attachment>>;
+    async fn delete(&self, id: i32) -> anyhow::Result<()>;
+    /// Todoとの紐付けを外し、Blobの削除待ちにする
+    async fn detach_todo(&self, todo_id: i32) -> anyhow::Result<()>;
+    /// Todoから外れたBlob削除待ちの添付ファイルを返す
+    async fn orphaned(&self, limit: i64) -> anyhow::Result<Vec<Attachment>>;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct Attachment {
+    pub id: i32,
+    pub todo_id: Option<i32>,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateAttachment {
+    pub todo_id: i32,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub storage_key: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AttachmentRepositoryForDb {
+    pool: PgPool,
+}
+
+impl AttachmentRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AttachmentRepository for AttachmentRepositoryForDb {
+    async fn create(&self, payload: CreateAttachment) -> anyhow::Result<Attachment> {
+        let attachment = sqlx::query_as::<_, Attachment>(
+            r#"
+                insert into attachments (todo_id, filename, content_type, size, storage_key)
+                values ($1, $2, $3, $4, $5) returning *;
+            "#,
+        )
+        .bind(payload.todo_id)
+        .bind(payload.filename)
+        .bind(payload.content_type)
+        .bind(payload.size)
+        .bind(payload.storage_key)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(attachment)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Attachment> {
+        let attachment = sqlx::query_as::<_, Attachment>(
+            r#"
+                select * from attachments where id=$1;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(attachment)
+    }
+
+    async fn all_in_todo(&self, todo_id: i32) -> anyhow::Result<Vec<Attachment>> {
+        let attachments = sqlx::query_as::<_, Attachment>(
+            r#"
+                select * from attachments where todo_id=$1 order by id asc;
+            "#,
+        )
+        .bind(todo_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(attachments)
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        let result = sqlx::query(
+            r#"
+                delete from attachments where id=$1;
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(())
+    }
+
+    async fn detach_todo(&self, todo_id: i32) -> anyhow::Result<()> {
+        // プロジェクトの削除などでTodoが消えた場合は外部キーによってnullになる
+        sqlx::query(
+            r#"
+                update attachments set todo_id=null where todo_id=$1;
+            "#,
+        )
+        .bind(todo_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn orphaned(&self, limit: i64) -> anyhow::Result<Vec<Attachment>> {
+        let attachments = sqlx::query_as::<_, Attachment>(
+            r#"
+                select * from attachments where todo_id is null order by id asc limit $1;
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(attachments)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use crate::repositories::todo::{CreateTodo, TodoRepository, TodoRepositoryForDb};
+    use dotenv::dotenv;
+    use sqlx::PgPool;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+
+        let todo_repository = TodoRepositoryForDb::new(pool.clone());
+        let todo = todo_repository
+            .create(CreateTodo::new(
+                "[attachment crud_scenario] text".to_string(),
+                vec![],
+            ))
+            .await
+            .expect("Failed to prepare todo data.");
+        let repository = AttachmentRepositoryForDb::new(pool.clone());
+
+        // create
+        let storage_key = format!("todos/{}/crud_scenario", todo.id);
+        let attachment = repository
+            .create(CreateAttachment {
+                todo_id: todo.id,
+                filename: "screenshot.png".to_string(),
+                content_type: "image/png".to_string(),
+                size: 3,
+                storage_key: storage_key.clone(),
+            })
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(attachment.todo_id, Some(todo.id));
+        assert_eq!(attachment.storage_key, storage_key);
+
+        // all_in_todo
+        let attachments = repository
+            .all_in_todo(todo.id)
+            .await
+            .expect("[all_in_todo] returned Err");
+        assert_eq!(vec![attachment.clone()], attachments);
+
+        // todoを削除すると削除待ちになる
+        todo_repository
+            .delete(todo.id)
+            .await
+            .expect("[delete] returned Err");
+        let orphaned = repository
+            .orphaned(100)
+            .await
+            .expect("[orphaned] returned Err");
+        assert!(orphaned.iter().any(|orphan| orphan.id == attachment.id));
+
+        // delete
+        repository
+            .delete(attachment.id)
+            .await
+            .expect("[delete] returned Err");
+        assert!(repository.find(attachment.id).await.is_err());
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    };
+
+    type AttachmentData = HashMap<i32, Attachment>;
+
+    #[derive(Debug, Clone)]
+    pub struct AttachmentRepositoryForMemory {
+        store: Arc<RwLock<AttachmentData>>,
+    }
+
+    impl Default for AttachmentRepositoryForMemory {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl AttachmentRepositoryForMemory {
+        pub fn new() -> Self {
+            AttachmentRepositoryForMemory {
+                store: Arc::default(),
+            }
+        }
+
+        fn write_store_ref(&self) -> RwLockWriteGuard<'_, AttachmentData> {
+            self.store.write().unwrap()
+        }
+
+        fn read_store_ref(&self) -> RwLockReadGuard<'_, AttachmentData> {
+            self.store.read().unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl AttachmentRepository for AttachmentRepositoryForMemory {
+        async fn create(&self, payload: CreateAttachment) -> anyhow::Result<Attachment> {
+            let mut store = self.write_store_ref();
+            let id = store.keys().max().unwrap_or(&0) + 1;
+            let attachment = Attachment {
+                id,
+                todo_id: Some(payload.todo_id),
+                filename: payload.filename,
+                content_type: payload.content_type,
+                size: payload.size,
+                storage_key: payload.storage_key,
+                created_at: Utc::now(),
+            };
+            store.insert(id, attachment.clone());
+            Ok(attachment)
+        }
+
+        async fn find(&self, id: i32) -> anyhow::Result<Attachment> {
+            let store = self.read_store_ref();
+            let attachment = store
+                .get(&id)
+                .cloned()
+                .ok_or(RepositoryError::NotFound(id))?;
+            Ok(attachment)
+        }
+
+        async fn all_in_todo(&self, todo_id: i32) -> anyhow::Result<Vec<Attachment>> {
+            let store = self.read_store_ref();
+            let mut attachments = store
+                .values()
+                .filter(|attachment| attachment.todo_id == Some(todo_id))
+                .cloned()
+                .collect::<Vec<_>>();
+            attachments.sort_by_key(|attachment| attachment.id);
+            Ok(attachments)
+        }
+
+        async fn delete(&self, id: i32) -> anyhow::Result<()> {
+            let mut store = self.write_store_ref();
+            store.remove(&id).ok_or(RepositoryError::NotFound(id))?;
+            Ok(())
+        }
+
+        async fn detach_todo(&self, todo_id: i32) -> anyhow::Result<()> {
+            let mut store = self.write_store_ref();
+            store
+                .values_mut()
+                .filter(|attachment| attachment.todo_id == Some(todo_id))
+                .for_each(|attachment| attachment.todo_id = None);
+            Ok(())
+        }
+
+        async fn orphaned(&self, limit: i64) -> anyhow::Result<Vec<Attachment>> {
+            let store = self.read_store_ref();
+            let mut attachments = store
+                .values()
+                .filter(|attachment| attachment.todo_id.is_none())
+                .cloned()
+                .collect::<Vec<_>>();
+            attachments.sort_by_key(|attachment| attachment.id);
+            attachments.truncate(limit as usize);
+            Ok(attachments)
+        }
+    }
+
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn attachment_crud_scenario() {
+            let repository = AttachmentRepositoryForMemory::new();
+
+            // create
+            let attachment = repository
+                .create(CreateAttachment {
+                    todo_id: 1,
+                    filename: "memo.txt".to_string(),
+                    content_type: "text/plain".to_string(),
+                    size: 4,
+                    storage_key: "todos/1/memo".to_string(),
+                })
+                .await
+                .expect("failed create attachment");
+            assert_eq!(attachment.id, 1);
+
+            // all_in_todo
+            let attachments = repository.all_in_todo(1).await.unwrap();
+            assert_eq!(vec![attachment.clone()], attachments);
+            assert!(repository.orphaned(10).await.unwrap().is_empty());
+
+            // detach_todo
+            repository.detach_todo(1).await.unwrap();
+            assert!(repository.all_in_todo(1).await.unwrap().is_empty());
+            let orphaned = repository.orphaned(10).await.unwrap();
+            assert_eq!(orphaned[0].id, attachment.id);
+
+            // delete
+            let res = repository.delete(attachment.id).await;
+            assert!(res.is_ok());
+            assert!(repository.find(attachment.id).await.is_err());
+        }
+    }
+}
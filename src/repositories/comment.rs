@@ -0,0 +1,365 @@
+use super::RepositoryError;
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use utoipa::ToSchema;
+
+#[async_trait]
+pub trait CommentRepository: Clone + std::marker::Send + std::marker::Sync + 'static {
+    async fn create(
+        &self,
+        todo_id: i32,
+        author: String,
+        payload: CreateComment,
+    ) -> anyhow::Result<Comment>;
+    async fn find(&self, id: i32) -> anyhow::Result<Comment>;
+    /// Todoに付いたコメントを投稿順で返す
+    async fn all_in_todo(&self, todo_id: i32) -> anyhow::Result<Vec<Comment>>;
+    async fn update(&self, id: i32, payload: UpdateComment) -> anyhow::Result<Comment>;
+    async fn delete(&self, id: i32) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct Comment {
+    pub id: i32,
+    pub todo_id: i32,
+    pub author: String,
+    // Markdownのまま保存する
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct CreateComment {
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct UpdateComment {
+    pub body: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommentRepositoryForDb {
+    pool: PgPool,
+}
+
+impl CommentRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl CommentRepository for CommentRepositoryForDb {
+    async fn create(
+        &self,
+        todo_id: i32,
+        author: String,
+        payload: CreateComment,
+    ) -> anyhow::Result<Comment> {
+        let comment = sqlx::query_as::<_, Comment>(
+            r#"
+                insert into comments (todo_id, author, body) values ($1, $2, $3) returning *;
+            "#,
+        )
+        .bind(todo_id)
+        .bind(author)
+        .bind(payload.body)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(comment)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Comment> {
+        let comment = sqlx::query_as::<_, Comment>(
+            r#"
+                select * from comments where id=$1;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(comment)
+    }
+
+    async fn all_in_todo(&self, todo_id: i32) -> anyhow::Result<Vec<Comment>> {
+        let comments = sqlx::query_as::<_, Comment>(
+            r#"
+                select * from comments where todo_id=$1 order by id asc;
+            "#,
+        )
+        .bind(todo_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(comments)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateComment) -> anyhow::Result<Comment> {
+        let comment = sqlx::query_as::<_, Comment>(
+            r#"
+                update comments set body=$1, updated_at=now() where id=$2 returning *;
+            "#,
+        )
+        .bind(payload.body)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(comment)
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        let result = sqlx::query(
+            r#"
+                delete from comments where id=$1;
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use crate::repositories::todo::{CreateTodo, TodoRepository, TodoRepositoryForDb};
+    use dotenv::dotenv;
+    use sqlx::PgPool;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+
+        let todo_repository = TodoRepositoryForDb::new(pool.clone());
+        let todo = todo_repository
+            .create(CreateTodo::new(
+                "[comment crud_scenario] text".to_string(),
+                vec![],
+            ))
+            .await
+            .expect("Failed to prepare todo data.");
+        let repository = CommentRepositoryForDb::new(pool.clone());
+
+        // create
+        let comment = repository
+            .create(
+                todo.id,
+                "alice".to_string(),
+                CreateComment {
+                    body: "**first**".to_string(),
+                },
+            )
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(comment.author, "alice");
+        assert_eq!(comment.body, "**first**");
+        assert_eq!(
+            todo_repository.find(todo.id).await.unwrap().comment_count,
+            1
+        );
+
+        // all_in_todo
+        let comments = repository
+            .all_in_todo(todo.id)
+            .await
+            .expect("[all_in_todo] returned Err");
+        assert_eq!(vec![comment.clone()], comments);
+
+        // update
+        let updated = repository
+            .update(
+                comment.id,
+                UpdateComment {
+                    body: "edited".to_string(),
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert_eq!(updated.body, "edited");
+        assert!(updated.updated_at >= comment.updated_at);
+
+        // todoの削除でコメントも削除される
+        todo_repository
+            .delete(todo.id)
+            .await
+            .expect("[delete] returned Err");
+        assert!(repository.find(comment.id).await.is_err());
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    };
+
+    type CommentData = HashMap<i32, Comment>;
+
+    #[derive(Debug, Clone)]
+    pub struct CommentRepositoryForMemory {
+        store: Arc<RwLock<CommentData>>,
+    }
+
+    impl Default for CommentRepositoryForMemory {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl CommentRepositoryForMemory {
+        pub fn new() -> Self {
+            CommentRepositoryForMemory {
+                store: Arc::default(),
+            }
+        }
+
+        fn write_store_ref(&self) -> RwLockWriteGuard<'_, CommentData> {
+            self.store.write().unwrap()
+        }
+
+        fn read_store_ref(&self) -> RwLockReadGuard<'_, CommentData> {
+            self.store.read().unwrap()
+        }
+
+        /// TodoRepositoryForMemoryからコメント数を参照するために使う
+        pub fn count_in_todo(&self, todo_id: i32) -> i64 {
+            let store = self.read_store_ref();
+            store
+                .values()
+                .filter(|comment| comment.todo_id == todo_id)
+                .count() as i64
+        }
+
+        /// TodoRepositoryForMemoryのdeleteから呼ばれ、外部キーのcascadeを再現する
+        pub fn delete_in_todo(&self, todo_id: i32) {
+            let mut store = self.write_store_ref();
+            store.retain(|_id, comment| comment.todo_id != todo_id);
+        }
+    }
+
+    #[async_trait]
+    impl CommentRepository for CommentRepositoryForMemory {
+        async fn create(
+            &self,
+            todo_id: i32,
+            author: String,
+            payload: CreateComment,
+        ) -> anyhow::Result<Comment> {
+            let mut store = self.write_store_ref();
+            let id = store.keys().max().unwrap_or(&0) + 1;
+            let now = Utc::now();
+            let comment = Comment {
+                id,
+                todo_id,
+                author,
+                body: payload.body,
+                created_at: now,
+                updated_at: now,
+            };
+            store.insert(id, comment.clone());
+            Ok(comment)
+        }
+
+        async fn find(&self, id: i32) -> anyhow::Result<Comment> {
+            let store = self.read_store_ref();
+            let comment = store
+                .get(&id)
+                .cloned()
+                .ok_or(RepositoryError::NotFound(id))?;
+            Ok(comment)
+        }
+
+        async fn all_in_todo(&self, todo_id: i32) -> anyhow::Result<Vec<Comment>> {
+            let store = self.read_store_ref();
+            let mut comments = store
+                .values()
+                .filter(|comment| comment.todo_id == todo_id)
+                .cloned()
+                .collect::<Vec<_>>();
+            comments.sort_by_key(|comment| comment.id);
+            Ok(comments)
+        }
+
+        async fn update(&self, id: i32, payload: UpdateComment) -> anyhow::Result<Comment> {
+            let mut store = self.write_store_ref();
+            let comment = store.get_mut(&id).ok_or(RepositoryError::NotFound(id))?;
+            comment.body = payload.body;
+            comment.updated_at = Utc::now();
+            Ok(comment.clone())
+        }
+
+        async fn delete(&self, id: i32) -> anyhow::Result<()> {
+            let mut store = self.write_store_ref();
+            store.remove(&id).ok_or(RepositoryError::NotFound(id))?;
+            Ok(())
+        }
+    }
+
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn comment_crud_scenario() {
+            let repository = CommentRepositoryForMemory::new();
+
+            // create
+            let comment = repository
+                .create(
+                    1,
+                    "alice".to_string(),
+                    CreateComment {
+                        body: "comment body".to_string(),
+                    },
+                )
+                .await
+                .expect("failed create comment");
+            assert_eq!(comment.id, 1);
+            assert_eq!(repository.count_in_todo(1), 1);
+
+            // all_in_todo
+            let comments = repository.all_in_todo(1).await.unwrap();
+            assert_eq!(vec![comment.clone()], comments);
+            assert!(repository.all_in_todo(2).await.unwrap().is_empty());
+
+            // update
+            let comment = repository
+                .update(
+                    comment.id,
+                    UpdateComment {
+                        body: "edited".to_string(),
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(comment.body, "edited");
+
+            // delete
+            let res = repository.delete(comment.id).await;
+            assert!(res.is_ok());
+            assert_eq!(repository.count_in_todo(1), 0);
+        }
+    }
+}
@@ -0,0 +1,421 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+// この件数だけログに追記したらスナップショットに書き出してログを空にする
+const DEFAULT_COMPACT_EVERY: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum LogRecord<T> {
+    Put { id: i32, value: T },
+    Delete { id: i32 },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot<M> {
+    sequence: i32,
+    entries: M,
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    log_path: PathBuf,
+    snapshot_path: PathBuf,
+    log: File,
+    entries: BTreeMap<i32, T>,
+    // DBのserialと同じく、削除済みのIDは再利用しない
+    sequence: i32,
+    appended: usize,
+    compact_every: usize,
+}
+
+/// JSON Linesの追記ログで永続化するストア
+///
+/// - 書き込みはログへの追記とfsyncが完了してからメモリ上の状態に反映する
+/// - 一定件数ごとにスナップショット(`<name>.snapshot.json`)へ書き出し、ログ(`<name>.jsonl`)を空にする
+/// - 起動時はスナップショットにログを再生して復元する。書き込み途中でクラッシュした最終行は切り捨てる
+#[derive(Debug)]
+pub struct FileStore<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+impl<T> Clone for FileStore<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> FileStore<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + 'static,
+{
+    pub fn open(dir: impl AsRef<Path>, name: &str) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let log_path = dir.join(format!("{}.jsonl", name));
+        let snapshot_path = dir.join(format!("{}.snapshot.json", name));
+
+        let Snapshot {
+            mut sequence,
+            mut entries,
+        }: Snapshot<BTreeMap<i32, T>> = match fs::read(&snapshot_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Snapshot {
+                sequence: 0,
+                entries: BTreeMap::new(),
+            },
+            Err(e) => return Err(e.into()),
+        };
+
+        let (records, valid_len) = read_log::<T>(&log_path)?;
+        let appended = records.len();
+        for record in records {
+            match record {
+                LogRecord::Put { id, value } => {
+                    sequence = sequence.max(id);
+                    entries.insert(id, value);
+                }
+                LogRecord::Delete { id } => {
+                    entries.remove(&id);
+                }
+            }
+        }
+
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)?;
+        // 途中で切れた最終行を取り除き、以降の追記が壊れた行に続かないようにする
+        if log.metadata()?.len() != valid_len {
+            tracing::warn!("truncate torn record in [{}]", log_path.display());
+            log.set_len(valid_len)?;
+            log.sync_all()?;
+        }
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Inner {
+                log_path,
+                snapshot_path,
+                log,
+                entries,
+                sequence,
+                appended,
+                compact_every: DEFAULT_COMPACT_EVERY,
+            })),
+        })
+    }
+
+    /// スナップショットに書き出す間隔(追記した件数)を変更する
+    pub fn with_compact_every(self, compact_every: usize) -> Self {
+        self.lock().compact_every = compact_every.max(1);
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap()
+    }
+
+    pub fn get(&self, id: i32) -> Option<T> {
+        self.lock().entries.get(&id).cloned()
+    }
+
+    /// IDの昇順で返す
+    pub fn values(&self) -> Vec<T> {
+        self.lock().entries.values().cloned().collect()
+    }
+
+    /// 新しいIDを採番して追加する
+    pub async fn insert(&self, build: impl FnOnce(i32) -> T + Send + 'static) -> anyhow::Result<T> {
+        self.write(move |inner| {
+            let id = inner.sequence + 1;
+            let value = build(id);
+            inner.append(LogRecord::Put {
+                id,
+                value: value.clone(),
+            })?;
+            Ok(value)
+        })
+        .await
+    }
+
+    pub async fn put(&self, id: i32, value: T) -> anyhow::Result<()> {
+        self.write(move |inner| inner.append(LogRecord::Put { id, value }))
+            .await
+    }
+
+    /// 現在の値を `f` で変更して書き込み、変更後の値を返す。存在しない場合はNone
+    ///
+    /// 読み込みから書き込みまでロックを保持するため、同時に行われた変更を上書きしない。`f` がエラーを返した場合は書き込まない
+    pub async fn update(
+        &self,
+        id: i32,
+        f: impl FnOnce(&mut T) -> anyhow::Result<()> + Send + 'static,
+    ) -> anyhow::Result<Option<T>> {
+        self.write(move |inner| {
+            let Some(mut value) = inner.entries.get(&id).cloned() else {
+                return Ok(None);
+            };
+            f(&mut value)?;
+            inner.append(LogRecord::Put {
+                id,
+                value: value.clone(),
+            })?;
+            Ok(Some(value))
+        })
+        .await
+    }
+
+    /// 削除した値を返す。存在しない場合はNone
+    pub async fn remove(&self, id: i32) -> anyhow::Result<Option<T>> {
+        self.write(move |inner| {
+            let Some(value) = inner.entries.get(&id).cloned() else {
+                return Ok(None);
+            };
+            inner.append(LogRecord::Delete { id })?;
+            Ok(Some(value))
+        })
+        .await
+    }
+
+    // fsyncでランタイムのスレッドを止めないよう、ブロッキング用のスレッドで書き込む
+    async fn write<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Inner<T>) -> anyhow::Result<R> + Send + 'static,
+    ) -> anyhow::Result<R> {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || f(&mut inner.lock().unwrap())).await?
+    }
+}
+
+impl<T: Serialize> Inner<T> {
+    fn append(&mut self, record: LogRecord<T>) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        let len = self.log.metadata()?.len();
+        if let Err(e) = self.log.write_all(&line).and_then(|_| self.log.sync_data()) {
+            // 書きかけの行を残すと、以降の追記が壊れた行の後ろに続いてしまう
+            let _ = self.log.set_len(len);
+            return Err(e.into());
+        }
+
+        match record {
+            LogRecord::Put { id, value } => {
+                self.sequence = self.sequence.max(id);
+                self.entries.insert(id, value);
+            }
+            LogRecord::Delete { id } => {
+                self.entries.remove(&id);
+            }
+        }
+
+        self.appended += 1;
+        // 追記は確定しているため、書き出しに失敗しても成功として返し、次の追記で再試行する
+        if self.appended >= self.compact_every {
+            if let Err(e) = self.compact() {
+                tracing::warn!("fail compact [{}]: {}", self.log_path.display(), e);
+            }
+        }
+        Ok(())
+    }
+
+    /// スナップショットを書き出してからログを空にする
+    ///
+    /// 書き出し後にクラッシュしてログが残っても、再生結果は変わらない
+    fn compact(&mut self) -> anyhow::Result<()> {
+        let snapshot = Snapshot {
+            sequence: self.sequence,
+            entries: &self.entries,
+        };
+        let tmp_path = self.snapshot_path.with_extension("json.tmp");
+        let mut tmp = File::create(&tmp_path)?;
+        serde_json::to_writer(&mut tmp, &snapshot)?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, &self.snapshot_path)?;
+        if let Some(dir) = self.snapshot_path.parent() {
+            File::open(dir)?.sync_all()?;
+        }
+
+        self.log.set_len(0)?;
+        self.log.sync_all()?;
+        self.appended = 0;
+        tracing::debug!("compacted [{}]", self.log_path.display());
+        Ok(())
+    }
+}
+
+/// ログを読み込み、レコードと正常に読めた部分のバイト数を返す
+///
+/// 最終行が読めない場合は書き込み途中のクラッシュとみなして無視する。途中の行が読めない場合はエラーにする
+fn read_log<T: DeserializeOwned>(path: &Path) -> anyhow::Result<(Vec<LogRecord<T>>, u64)> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((vec![], 0)),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut records = vec![];
+    let mut offset = 0;
+    // 改行まで書き込まれていない最終行は、読めたとしても確定していないため読まない
+    while let Some(end) = bytes[offset..].iter().position(|b| *b == b'\n') {
+        let next = offset + end + 1;
+        match serde_json::from_slice(&bytes[offset..offset + end]) {
+            Ok(record) => records.push(record),
+            Err(_) if next == bytes.len() => break,
+            Err(e) => anyhow::bail!(
+                "corrupted record at byte {} in [{}]: {}",
+                offset,
+                path.display(),
+                e
+            ),
+        }
+        offset = next;
+    }
+    Ok((records, offset as u64))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("todo-file-store-{}", rand::random::<u32>()))
+    }
+
+    #[tokio::test]
+    async fn file_store_scenario() {
+        let dir = temp_dir();
+        let store = FileStore::<String>::open(&dir, "items").unwrap();
+
+        // insert / put / remove
+        let first = store.insert(|id| format!("item {}", id)).await.unwrap();
+        assert_eq!(first, "item 1");
+        store.insert(|id| format!("item {}", id)).await.unwrap();
+        store.put(1, "updated".to_string()).await.unwrap();
+        assert_eq!(
+            store
+                .update(2, |value| {
+                    value.push('!');
+                    Ok(())
+                })
+                .await
+                .unwrap(),
+            Some("item 2!".to_string())
+        );
+        assert_eq!(store.update(3, |_| Ok(())).await.unwrap(), None);
+        assert!(store
+            .update(2, |_| anyhow::bail!("rejected"))
+            .await
+            .is_err());
+        assert_eq!(store.get(2), Some("item 2!".to_string()));
+        assert_eq!(store.remove(2).await.unwrap(), Some("item 2!".to_string()));
+        assert_eq!(store.remove(2).await.unwrap(), None);
+        assert_eq!(store.values(), vec!["updated".to_string()]);
+        drop(store);
+
+        // 再起動してもログから復元され、削除済みのIDは再利用しない
+        let store = FileStore::<String>::open(&dir, "items").unwrap();
+        assert_eq!(store.get(1), Some("updated".to_string()));
+        assert_eq!(store.get(2), None);
+        let third = store.insert(|id| format!("item {}", id)).await.unwrap();
+        assert_eq!(third, "item 3");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_compact_log_into_snapshot() {
+        let dir = temp_dir();
+        let store = FileStore::<String>::open(&dir, "items")
+            .unwrap()
+            .with_compact_every(2);
+        store.insert(|id| format!("item {}", id)).await.unwrap();
+        store.insert(|id| format!("item {}", id)).await.unwrap();
+        assert_eq!(fs::read(dir.join("items.jsonl")).unwrap().len(), 0);
+        assert!(dir.join("items.snapshot.json").exists());
+        store.remove(2).await.unwrap();
+        drop(store);
+
+        let store = FileStore::<String>::open(&dir, "items").unwrap();
+        assert_eq!(store.values(), vec!["item 1".to_string()]);
+        let next = store.insert(|id| format!("item {}", id)).await.unwrap();
+        assert_eq!(next, "item 3");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_keep_appended_record_when_compaction_fails() {
+        let dir = temp_dir();
+        let store = FileStore::<String>::open(&dir, "items")
+            .unwrap()
+            .with_compact_every(1);
+        // スナップショットの置き換えが失敗するよう、同じ名前のディレクトリを置く
+        let snapshot_path = dir.join("items.snapshot.json");
+        fs::create_dir_all(snapshot_path.join("blocker")).unwrap();
+        store.insert(|id| format!("item {}", id)).await.unwrap();
+        assert_eq!(store.values(), vec!["item 1".to_string()]);
+        assert!(!fs::read(dir.join("items.jsonl")).unwrap().is_empty());
+
+        // 次の追記で書き出し直す
+        fs::remove_dir_all(&snapshot_path).unwrap();
+        store.insert(|id| format!("item {}", id)).await.unwrap();
+        assert!(snapshot_path.is_file());
+        assert_eq!(fs::read(dir.join("items.jsonl")).unwrap().len(), 0);
+        drop(store);
+
+        let store = FileStore::<String>::open(&dir, "items").unwrap();
+        assert_eq!(store.values().len(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_recover_from_torn_last_line() {
+        let dir = temp_dir();
+        let store = FileStore::<String>::open(&dir, "items").unwrap();
+        store.insert(|id| format!("item {}", id)).await.unwrap();
+        drop(store);
+
+        // 追記の途中でクラッシュした状態
+        let log_path = dir.join("items.jsonl");
+        let mut log = OpenOptions::new().append(true).open(&log_path).unwrap();
+        log.write_all(br#"{"op":"put","id":2,"val"#).unwrap();
+        drop(log);
+
+        let store = FileStore::<String>::open(&dir, "items").unwrap();
+        assert_eq!(store.values(), vec!["item 1".to_string()]);
+        store.insert(|id| format!("item {}", id)).await.unwrap();
+        drop(store);
+
+        let store = FileStore::<String>::open(&dir, "items").unwrap();
+        assert_eq!(
+            store.values(),
+            vec!["item 1".to_string(), "item 2".to_string()]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn should_reject_corrupted_middle_line() {
+        let dir = temp_dir();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("items.jsonl"),
+            "{\"op\":\"put\",\"id\":1,\"value\":\"a\"}\nbroken\n{\"op\":\"delete\",\"id\":1}\n",
+        )
+        .unwrap();
+
+        assert!(FileStore::<String>::open(&dir, "items").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
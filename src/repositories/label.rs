@@ -1,30 +1,231 @@
-use super::RepositoryError;
+use super::{file::FileStore, todo::TodoRepositoryForFile, RepositoryError};
 use axum::async_trait;
-use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool};
+use chrono::{DateTime, Utc};
+use sqlx::{MySqlPool, PgPool, SqlitePool};
+use std::{collections::HashSet, path::Path};
+use thiserror::Error;
+pub use todo_types::{CreateLabel, Label, MergeLabels, UpdateLabel, DEFAULT_LABEL_COLOR};
+use unicode_normalization::UnicodeNormalization;
 
 #[async_trait]
 pub trait LabelRepository: Clone + std::marker::Send + std::marker::Sync + 'static {
-    async fn create(&self, name: String) -> anyhow::Result<Label>;
+    async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label>;
+    /// 並び順(`position`, `id`)で返す
     async fn all(&self) -> anyhow::Result<Vec<Label>>;
-    async fn delete(&self, id: i32) -> anyhow::Result<()>;
+    async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label>;
+    /// 使っているTodoを `mode` に従って更新し、同じトランザクションでラベルを削除する。更新したTodoのIDを返す
+    async fn delete(&self, id: i32, mode: DeleteLabel) -> anyhow::Result<Vec<i32>>;
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow)]
-pub struct Label {
-    pub id: i32,
-    pub name: String,
+/// 削除するラベルを使っているTodoの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteLabel {
+    /// Todoからラベルを外す
+    Detach,
+    /// Todoのラベルを指定したラベルに付け替える
+    Reassign(i32),
+    /// 使っているTodoがあれば削除しない
+    Restrict,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
-pub struct CreateLabel {
-    pub name: String,
+/// `DeleteLabel::Restrict` で削除しようとしたラベルを使っているTodoがある
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("label [{label_id}] is used by {usage} todos")]
+pub struct LabelInUseError {
+    pub label_id: i32,
+    /// 使っているTodoの件数
+    pub usage: usize,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct UpdateLabel {
-    id: i32,
-    name: String,
+/// 更新の内容を反映したラベルを返す。保存先ごとの実装で共通して使う
+fn apply_update(label: Label, payload: UpdateLabel, now: DateTime<Utc>) -> Label {
+    Label {
+        id: label.id,
+        name: payload.name.unwrap_or(label.name),
+        color: payload.color.unwrap_or(label.color),
+        description: payload.description.unwrap_or(label.description),
+        icon: payload.icon.unwrap_or(label.icon),
+        position: payload.position.unwrap_or(label.position),
+        parent_id: payload.parent_id.unwrap_or(label.parent_id),
+        exclusive: payload.exclusive.unwrap_or(label.exclusive),
+        created_at: label.created_at,
+        updated_at: now,
+    }
+}
+
+/// ファイル・メモリの実装で、作成したラベルを最後に並べる
+fn next_position(labels: &[Label]) -> i32 {
+    labels
+        .iter()
+        .map(|label| label.position + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// 重複を判定するためのラベル名
+///
+/// 大文字・小文字と全角・半角(`Ｂｕｇ` と `bug`、`ﾊﾞｸﾞ` と `バグ`)を区別しない
+pub fn normalize_label_name(name: &str) -> String {
+    name.trim().nfkc().flat_map(char::to_lowercase).collect()
+}
+
+/// 同じ親(`parent_id`)の下で `name` と同じ名前とみなすラベル。`except` は更新するラベル自身
+///
+/// 親が違えば同じ名前を使える (`area/frontend` と `team/frontend`)
+pub fn find_duplicate<'a>(
+    labels: &'a [Label],
+    name: &str,
+    parent_id: Option<i32>,
+    except: Option<i32>,
+) -> Option<&'a Label> {
+    let name = normalize_label_name(name);
+    labels.iter().find(|label| {
+        Some(label.id) != except
+            && label.parent_id == parent_id
+            && normalize_label_name(&label.name) == name
+    })
+}
+
+/// `id` を削除すると最上位になる子が、最上位のラベルと同じ名前にならないか確認する
+///
+/// DBの on delete set null と同じく、削除したラベルの子は最上位のラベルにする
+fn check_orphans(labels: &[Label], id: i32) -> Result<(), RepositoryError> {
+    for child in labels.iter().filter(|label| label.parent_id == Some(id)) {
+        if let Some(other) = find_duplicate(labels, &child.name, None, Some(id)) {
+            return Err(RepositoryError::Duplicate(other.id));
+        }
+    }
+    Ok(())
+}
+
+/// ラベルの並び順。Todoに紐づくラベルも同じ順で返す
+pub(super) fn sort_labels(labels: &mut [Label]) {
+    labels.sort_by_key(|label| (label.position, label.id));
+}
+
+/// 排他的なグループ(`exclusive` のラベルの子)から2つ以上のラベルを選んだ
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("label [{label_id}] conflicts with another label in exclusive group [{group}]")]
+pub struct ExclusiveLabelError {
+    /// `exclusive` の親ラベル
+    pub group: i32,
+    /// 同じグループから2つ目以降に選ばれたラベル
+    pub label_id: i32,
+}
+
+/// `ids` のうち、排他的なグループの制約に違反するラベルの位置と内容を返す
+pub fn exclusive_conflicts(labels: &[Label], ids: &[i32]) -> Vec<(usize, ExclusiveLabelError)> {
+    let mut groups = HashSet::new();
+    let mut conflicts = vec![];
+    for (index, id) in ids.iter().enumerate() {
+        let Some(group) = labels
+            .iter()
+            .find(|label| label.id == *id)
+            .and_then(|label| label.parent_id)
+        else {
+            continue;
+        };
+        let exclusive = labels
+            .iter()
+            .any(|label| label.id == group && label.exclusive);
+        if exclusive && !groups.insert(group) {
+            let error = ExclusiveLabelError {
+                group,
+                label_id: *id,
+            };
+            conflicts.push((index, error));
+        }
+    }
+    conflicts
+}
+
+/// Todoの作成・更新時に保存先ごとの実装から呼ぶ
+pub fn check_exclusive(labels: &[Label], ids: &[i32]) -> Result<(), ExclusiveLabelError> {
+    match exclusive_conflicts(labels, ids).into_iter().next() {
+        Some((_, error)) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// `from` のラベルを `to` に付け替えても排他的なグループに違反しないか確認する
+///
+/// `todo_labels` は付け替えるTodoに付いているラベルの `(todo_id, label_id)`
+pub fn check_reassign(
+    labels: &[Label],
+    todo_labels: &[(i32, i32)],
+    from: i32,
+    to: i32,
+) -> Result<(), ExclusiveLabelError> {
+    let mut todo_ids: Vec<i32> = todo_labels.iter().map(|(todo_id, _)| *todo_id).collect();
+    todo_ids.sort_unstable();
+    todo_ids.dedup();
+    for todo_id in todo_ids {
+        let mut ids: Vec<i32> = todo_labels
+            .iter()
+            .filter(|(id, label_id)| *id == todo_id && *label_id != from)
+            .map(|(_, label_id)| *label_id)
+            .collect();
+        if !ids.contains(&to) {
+            ids.push(to);
+        }
+        check_exclusive(labels, &ids)?;
+    }
+    Ok(())
+}
+
+// 削除するラベルを使っているTodoのID
+fn using_todo_ids(todo_labels: &[(i32, i32)], id: i32) -> Vec<i32> {
+    let mut todo_ids: Vec<i32> = todo_labels
+        .iter()
+        .filter(|(_, label_id)| *label_id == id)
+        .map(|(todo_id, _)| *todo_id)
+        .collect();
+    todo_ids.sort_unstable();
+    todo_ids.dedup();
+    todo_ids
+}
+
+/// ラベル自身とその子孫のID
+pub fn descendants(labels: &[Label], id: i32) -> Vec<i32> {
+    let mut ids = vec![id];
+    let mut index = 0;
+    while let Some(parent) = ids.get(index).copied() {
+        for label in labels {
+            // 親子関係が循環していても止まるよう、追加済みのラベルは辿らない
+            if label.parent_id == Some(parent) && !ids.contains(&label.id) {
+                ids.push(label.id);
+            }
+        }
+        index += 1;
+    }
+    ids
+}
+
+/// 親からの名前を `/` でつないだパス (例: `area/backend`)
+pub fn label_path(labels: &[Label], label: &Label) -> String {
+    let mut names = vec![label.name.as_str()];
+    let mut parent_id = label.parent_id;
+    while let Some(parent) = parent_id.and_then(|id| labels.iter().find(|label| label.id == id)) {
+        if names.len() > labels.len() {
+            break;
+        }
+        names.push(&parent.name);
+        parent_id = parent.parent_id;
+    }
+    names.reverse();
+    names.join("/")
+}
+
+/// IDまたはパスでラベルを探す
+pub fn find_by_path<'a>(labels: &'a [Label], query: &str) -> Option<&'a Label> {
+    if let Ok(id) = query.parse::<i32>() {
+        if let Some(label) = labels.iter().find(|label| label.id == id) {
+            return Some(label);
+        }
+    }
+    labels
+        .iter()
+        .find(|label| label_path(labels, label) == query)
 }
 
 #[derive(Debug, Clone)]
@@ -36,30 +237,259 @@ impl LabelRepositoryForDb {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Label> {
+        let label = sqlx::query_as::<_, Label>(
+            r#"
+                select * from labels where id=$1;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(label)
+    }
+
+    // 名前の重複は同じ親の下だけで判定する
+    async fn siblings(&self, parent_id: Option<i32>) -> anyhow::Result<Vec<Label>> {
+        let labels = sqlx::query_as::<_, Label>(
+            r#"
+                select * from labels where parent_id is not distinct from $1;
+            "#,
+        )
+        .bind(parent_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(labels)
+    }
 }
 
 #[async_trait]
 impl LabelRepository for LabelRepositoryForDb {
-    async fn create(&self, name: String) -> anyhow::Result<Label> {
-        let optional_label = sqlx::query_as::<_, Label>(
+    async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label> {
+        let labels = self.siblings(payload.parent_id).await?;
+        if let Some(label) = find_duplicate(&labels, &payload.name, payload.parent_id, None) {
+            return Err(RepositoryError::Duplicate(label.id).into());
+        }
+
+        // 並び順を省略した場合は最後に並べる
+        let label = sqlx::query_as::<_, Label>(
+            r#"
+                insert into labels
+                (name, color, description, icon, position, parent_id, exclusive, created_at, updated_at)
+                select $1, $2, $3, $4, coalesce($5, max(position) + 1, 0), $6, $7, $8, $8 from labels
+                returning *;
+            "#,
+        )
+        .bind(payload.name)
+        .bind(payload.color.unwrap_or(DEFAULT_LABEL_COLOR.to_string()))
+        .bind(payload.description)
+        .bind(payload.icon)
+        .bind(payload.position)
+        .bind(payload.parent_id)
+        .bind(payload.exclusive)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(label)
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<Label>> {
+        let labels = sqlx::query_as::<_, Label>(
+            r#"
+                select * from labels order by labels.position asc, labels.id asc;
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(labels)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label> {
+        let label = apply_update(self.find(id).await?, payload, Utc::now());
+        let labels = self.siblings(label.parent_id).await?;
+        if let Some(other) = find_duplicate(&labels, &label.name, label.parent_id, Some(id)) {
+            return Err(RepositoryError::Duplicate(other.id).into());
+        }
+
+        let label = sqlx::query_as::<_, Label>(
+            r#"
+                update labels
+                set name=$1, color=$2, description=$3, icon=$4, position=$5, parent_id=$6,
+                exclusive=$7, updated_at=$8
+                where id=$9 returning *;
+            "#,
+        )
+        .bind(label.name)
+        .bind(label.color)
+        .bind(label.description)
+        .bind(label.icon)
+        .bind(label.position)
+        .bind(label.parent_id)
+        .bind(label.exclusive)
+        .bind(label.updated_at)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(label)
+    }
+
+    async fn delete(&self, id: i32, mode: DeleteLabel) -> anyhow::Result<Vec<i32>> {
+        let mut tx = self.pool.begin().await?;
+        // 削除するまでに他のTodoへ付けられないよう、ラベルの行をロックする
+        sqlx::query(
+            r#"
+                select id from labels where id=$1 for update;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+        let labels = sqlx::query_as::<_, Label>("select * from labels;")
+            .fetch_all(&mut *tx)
+            .await?;
+        check_orphans(&labels, id)?;
+        let todo_labels = sqlx::query_as::<_, (i32, i32)>(
+            r#"
+                select todo_id, label_id from todo_labels
+                where todo_id in (select todo_id from todo_labels where label_id=$1)
+                order by todo_id asc, id asc;
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let todo_ids = using_todo_ids(&todo_labels, id);
+
+        match mode {
+            DeleteLabel::Restrict if !todo_ids.is_empty() => {
+                let usage = todo_ids.len();
+                return Err(LabelInUseError {
+                    label_id: id,
+                    usage,
+                }
+                .into());
+            }
+            DeleteLabel::Reassign(to) => {
+                if to == id || !labels.iter().any(|label| label.id == to) {
+                    return Err(RepositoryError::NotFound(to).into());
+                }
+                check_reassign(&labels, &todo_labels, id, to)?;
+                // 付け替え先のラベルがすでに付いているTodoには追加しない
+                sqlx::query(
+                    r#"
+                        insert into todo_labels (todo_id, label_id)
+                        select distinct todo_id, $1 from todo_labels
+                        where label_id=$2 and todo_id not in (
+                            select todo_id from todo_labels where label_id=$1
+                        );
+                    "#,
+                )
+                .bind(to)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            }
+            _ => {}
+        }
+
+        sqlx::query(
+            r#"
+                update todos set updated_at=$1
+                where id in (select todo_id from todo_labels where label_id=$2);
+            "#,
+        )
+        .bind(Utc::now())
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        // todo_labels は外部キーのカスケードで削除される
+        sqlx::query(
             r#"
-                select * from labels where name=$1;
+                delete from labels where id=$1;
             "#,
         )
-        .bind(name.clone())
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(todo_ids)
+    }
+}
+
+/// ローカルモード用のSQLite実装
+#[derive(Debug, Clone)]
+pub struct LabelRepositoryForSqlite {
+    pool: SqlitePool,
+}
+
+impl LabelRepositoryForSqlite {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Label> {
+        let label = sqlx::query_as::<_, Label>(
+            r#"
+                select * from labels where id=$1;
+            "#,
+        )
+        .bind(id)
         .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(label)
+    }
+
+    // 名前の重複は同じ親の下だけで判定する
+    async fn siblings(&self, parent_id: Option<i32>) -> anyhow::Result<Vec<Label>> {
+        let labels = sqlx::query_as::<_, Label>(
+            r#"
+                select * from labels where parent_id is $1;
+            "#,
+        )
+        .bind(parent_id)
+        .fetch_all(&self.pool)
         .await?;
 
-        if let Some(label) = optional_label {
+        Ok(labels)
+    }
+}
+
+#[async_trait]
+impl LabelRepository for LabelRepositoryForSqlite {
+    async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label> {
+        let labels = self.siblings(payload.parent_id).await?;
+        if let Some(label) = find_duplicate(&labels, &payload.name, payload.parent_id, None) {
             return Err(RepositoryError::Duplicate(label.id).into());
         }
 
         let label = sqlx::query_as::<_, Label>(
             r#"
-                insert into labels (name) values ($1) returning *;
+                insert into labels
+                (name, color, description, icon, position, parent_id, exclusive, created_at, updated_at)
+                select $1, $2, $3, $4, coalesce($5, max(position) + 1, 0), $6, $7, $8, $8 from labels
+                returning *;
             "#,
         )
-        .bind(name.clone())
+        .bind(payload.name)
+        .bind(payload.color.unwrap_or(DEFAULT_LABEL_COLOR.to_string()))
+        .bind(payload.description)
+        .bind(payload.icon)
+        .bind(payload.position)
+        .bind(payload.parent_id)
+        .bind(payload.exclusive)
+        .bind(Utc::now())
         .fetch_one(&self.pool)
         .await?;
 
@@ -69,7 +499,7 @@ impl LabelRepository for LabelRepositoryForDb {
     async fn all(&self) -> anyhow::Result<Vec<Label>> {
         let labels = sqlx::query_as::<_, Label>(
             r#"
-                select * from labels order by labels.id asc;
+                select * from labels order by labels.position asc, labels.id asc;
             "#,
         )
         .fetch_all(&self.pool)
@@ -78,21 +508,485 @@ impl LabelRepository for LabelRepositoryForDb {
         Ok(labels)
     }
 
-    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+    async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label> {
+        let label = apply_update(self.find(id).await?, payload, Utc::now());
+        let labels = self.siblings(label.parent_id).await?;
+        if let Some(other) = find_duplicate(&labels, &label.name, label.parent_id, Some(id)) {
+            return Err(RepositoryError::Duplicate(other.id).into());
+        }
+
+        let label = sqlx::query_as::<_, Label>(
+            r#"
+                update labels
+                set name=$1, color=$2, description=$3, icon=$4, position=$5, parent_id=$6,
+                exclusive=$7, updated_at=$8
+                where id=$9 returning *;
+            "#,
+        )
+        .bind(label.name)
+        .bind(label.color)
+        .bind(label.description)
+        .bind(label.icon)
+        .bind(label.position)
+        .bind(label.parent_id)
+        .bind(label.exclusive)
+        .bind(label.updated_at)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(label)
+    }
+
+    async fn delete(&self, id: i32, mode: DeleteLabel) -> anyhow::Result<Vec<i32>> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+                select id from labels where id=$1;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+        let labels = sqlx::query_as::<_, Label>("select * from labels;")
+            .fetch_all(&mut *tx)
+            .await?;
+        check_orphans(&labels, id)?;
+        let todo_labels = sqlx::query_as::<_, (i32, i32)>(
+            r#"
+                select todo_id, label_id from todo_labels
+                where todo_id in (select todo_id from todo_labels where label_id=$1)
+                order by todo_id asc, id asc;
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let todo_ids = using_todo_ids(&todo_labels, id);
+
+        match mode {
+            DeleteLabel::Restrict if !todo_ids.is_empty() => {
+                let usage = todo_ids.len();
+                return Err(LabelInUseError {
+                    label_id: id,
+                    usage,
+                }
+                .into());
+            }
+            DeleteLabel::Reassign(to) => {
+                if to == id || !labels.iter().any(|label| label.id == to) {
+                    return Err(RepositoryError::NotFound(to).into());
+                }
+                check_reassign(&labels, &todo_labels, id, to)?;
+                sqlx::query(
+                    r#"
+                        insert into todo_labels (todo_id, label_id)
+                        select distinct todo_id, $1 from todo_labels
+                        where label_id=$2 and todo_id not in (
+                            select todo_id from todo_labels where label_id=$1
+                        );
+                    "#,
+                )
+                .bind(to)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            }
+            _ => {}
+        }
+
+        sqlx::query(
+            r#"
+                update todos set updated_at=$1
+                where id in (select todo_id from todo_labels where label_id=$2);
+            "#,
+        )
+        .bind(Utc::now())
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        // todo_labels は外部キーのカスケードで削除される
         sqlx::query(
             r#"
                 delete from labels where id=$1;
             "#,
         )
         .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(todo_ids)
+    }
+}
+
+/// MySQL/MariaDB用の実装
+#[derive(Debug, Clone)]
+pub struct LabelRepositoryForMySql {
+    pool: MySqlPool,
+}
+
+impl LabelRepositoryForMySql {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Label> {
+        let label = sqlx::query_as::<_, Label>(
+            r#"
+                select * from labels where id=?;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(label)
+    }
+
+    // 名前の重複は同じ親の下だけで判定する
+    async fn siblings(&self, parent_id: Option<i32>) -> anyhow::Result<Vec<Label>> {
+        let labels = sqlx::query_as::<_, Label>(
+            r#"
+                select * from labels where parent_id <=> ?;
+            "#,
+        )
+        .bind(parent_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(labels)
+    }
+}
+
+#[async_trait]
+impl LabelRepository for LabelRepositoryForMySql {
+    async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label> {
+        let labels = self.siblings(payload.parent_id).await?;
+        if let Some(label) = find_duplicate(&labels, &payload.name, payload.parent_id, None) {
+            return Err(RepositoryError::Duplicate(label.id).into());
+        }
+
+        // returning が使えないため、採番したIDは last_insert_id で受け取る
+        let now = Utc::now();
+        let result = sqlx::query(
+            r#"
+                insert into labels
+                (name, color, description, icon, position, parent_id, exclusive, created_at, updated_at)
+                select ?, ?, ?, ?, coalesce(?, max(position) + 1, 0), ?, ?, ?, ? from labels;
+            "#,
+        )
+        .bind(payload.name)
+        .bind(payload.color.unwrap_or(DEFAULT_LABEL_COLOR.to_string()))
+        .bind(payload.description)
+        .bind(payload.icon)
+        .bind(payload.position)
+        .bind(payload.parent_id)
+        .bind(payload.exclusive)
+        .bind(now)
+        .bind(now)
         .execute(&self.pool)
-        .await
-        .map_err(|e| match e {
-            sqlx::Error::RowNotFound => RepositoryError::NotFound(id),
-            _ => RepositoryError::Unexpected(e.to_string()),
-        })?;
+        .await?;
 
-        Ok(())
+        self.find(i32::try_from(result.last_insert_id())?).await
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<Label>> {
+        let labels = sqlx::query_as::<_, Label>(
+            r#"
+                select * from labels order by labels.position asc, labels.id asc;
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(labels)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label> {
+        let label = apply_update(self.find(id).await?, payload, Utc::now());
+        let labels = self.siblings(label.parent_id).await?;
+        if let Some(other) = find_duplicate(&labels, &label.name, label.parent_id, Some(id)) {
+            return Err(RepositoryError::Duplicate(other.id).into());
+        }
+
+        sqlx::query(
+            r#"
+                update labels
+                set name=?, color=?, description=?, icon=?, position=?, parent_id=?, exclusive=?,
+                updated_at=?
+                where id=?;
+            "#,
+        )
+        .bind(label.name.clone())
+        .bind(label.color.clone())
+        .bind(label.description.clone())
+        .bind(label.icon.clone())
+        .bind(label.position)
+        .bind(label.parent_id)
+        .bind(label.exclusive)
+        .bind(label.updated_at)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(label)
+    }
+
+    async fn delete(&self, id: i32, mode: DeleteLabel) -> anyhow::Result<Vec<i32>> {
+        let mut tx = self.pool.begin().await?;
+        // 削除するまでに他のTodoへ付けられないよう、ラベルの行をロックする
+        sqlx::query(
+            r#"
+                select id from labels where id=? for update;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+        let labels = sqlx::query_as::<_, Label>("select * from labels;")
+            .fetch_all(&mut *tx)
+            .await?;
+        check_orphans(&labels, id)?;
+        let todo_labels = sqlx::query_as::<_, (i32, i32)>(
+            r#"
+                select todo_id, label_id from todo_labels
+                where todo_id in (select todo_id from todo_labels where label_id=?)
+                order by todo_id asc, id asc;
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let todo_ids = using_todo_ids(&todo_labels, id);
+
+        match mode {
+            DeleteLabel::Restrict if !todo_ids.is_empty() => {
+                let usage = todo_ids.len();
+                return Err(LabelInUseError {
+                    label_id: id,
+                    usage,
+                }
+                .into());
+            }
+            DeleteLabel::Reassign(to) => {
+                if to == id || !labels.iter().any(|label| label.id == to) {
+                    return Err(RepositoryError::NotFound(to).into());
+                }
+                check_reassign(&labels, &todo_labels, id, to)?;
+                // 付け替え先のラベルがすでに付いているTodoには追加しない
+                sqlx::query(
+                    r#"
+                        insert into todo_labels (todo_id, label_id)
+                        select distinct todo_id, ? from todo_labels
+                        where label_id=? and todo_id not in (
+                            select todo_id from todo_labels where label_id=?
+                        );
+                    "#,
+                )
+                .bind(to)
+                .bind(id)
+                .bind(to)
+                .execute(&mut *tx)
+                .await?;
+            }
+            _ => {}
+        }
+
+        sqlx::query(
+            r#"
+                update todos set updated_at=?
+                where id in (select todo_id from todo_labels where label_id=?);
+            "#,
+        )
+        .bind(Utc::now())
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        // todo_labels は外部キーのカスケードで削除される
+        sqlx::query(
+            r#"
+                delete from labels where id=?;
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(todo_ids)
+    }
+}
+
+/// ファイルに保存する実装 (DBを用意せずにサーバーを動かす場合に使う)
+#[derive(Debug, Clone)]
+pub struct LabelRepositoryForFile {
+    store: FileStore<Label>,
+    todos: Option<TodoRepositoryForFile>,
+}
+
+impl LabelRepositoryForFile {
+    pub fn open(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(Self {
+            store: FileStore::open(dir, "labels")?,
+            todos: None,
+        })
+    }
+
+    /// 削除時に使っているTodoを更新するため、同じディレクトリのTodoを共有する
+    pub fn with_todos(mut self, todos: &TodoRepositoryForFile) -> Self {
+        self.todos = Some(todos.clone());
+        self
+    }
+
+    /// TodoRepositoryForFileからラベルを解決するために使う
+    pub(crate) fn store(&self) -> FileStore<Label> {
+        self.store.clone()
+    }
+
+    fn find_by_name(
+        &self,
+        name: &str,
+        parent_id: Option<i32>,
+        except: Option<i32>,
+    ) -> Option<Label> {
+        find_duplicate(&self.store.values(), name, parent_id, except).cloned()
+    }
+}
+
+#[async_trait]
+impl LabelRepository for LabelRepositoryForFile {
+    async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label> {
+        if let Some(label) = self.find_by_name(&payload.name, payload.parent_id, None) {
+            return Err(RepositoryError::Duplicate(label.id).into());
+        }
+        let position = payload
+            .position
+            .unwrap_or_else(|| next_position(&self.store.values()));
+        let now = Utc::now();
+        self.store
+            .insert(move |id| Label {
+                id,
+                name: payload.name,
+                color: payload.color.unwrap_or(DEFAULT_LABEL_COLOR.to_string()),
+                description: payload.description,
+                icon: payload.icon,
+                position,
+                parent_id: payload.parent_id,
+                exclusive: payload.exclusive,
+                created_at: now,
+                updated_at: now,
+            })
+            .await
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<Label>> {
+        let mut labels = self.store.values();
+        sort_labels(&mut labels);
+        Ok(labels)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label> {
+        let label = self.store.get(id).ok_or(RepositoryError::NotFound(id))?;
+        let now = Utc::now();
+        let label = apply_update(label, payload.clone(), now);
+        if let Some(other) = self.find_by_name(&label.name, label.parent_id, Some(id)) {
+            return Err(RepositoryError::Duplicate(other.id).into());
+        }
+        let label = self
+            .store
+            .update(id, move |label| {
+                *label = apply_update(label.clone(), payload, now);
+                Ok(())
+            })
+            .await?
+            .ok_or(RepositoryError::NotFound(id))?;
+        Ok(label)
+    }
+
+    async fn delete(&self, id: i32, mode: DeleteLabel) -> anyhow::Result<Vec<i32>> {
+        self.store.get(id).ok_or(RepositoryError::NotFound(id))?;
+        check_orphans(&self.store.values(), id)?;
+        if let DeleteLabel::Reassign(to) = mode {
+            if to == id || self.store.get(to).is_none() {
+                return Err(RepositoryError::NotFound(to).into());
+            }
+        }
+        // ファイルは途中で失敗すると戻せないため、Todoを更新できてからラベルを削除する
+        let todo_ids = match &self.todos {
+            Some(todos) => todos.replace_label(id, mode).await?,
+            None => vec![],
+        };
+        self.store.remove(id).await?;
+        // DBの on delete set null と同じく、子は最上位のラベルにする
+        for child in self
+            .store
+            .values()
+            .into_iter()
+            .filter(|label| label.parent_id == Some(id))
+        {
+            self.store
+                .update(child.id, move |child| {
+                    if child.parent_id == Some(id) {
+                        child.parent_id = None;
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+        Ok(todo_ids)
+    }
+}
+
+/// Secretsの設定で選択されるラベルの保存先
+#[derive(Debug, Clone)]
+pub enum LabelRepositoryBackend {
+    Db(LabelRepositoryForDb),
+    MySql(LabelRepositoryForMySql),
+    Sqlite(LabelRepositoryForSqlite),
+    File(LabelRepositoryForFile),
+}
+
+#[async_trait]
+impl LabelRepository for LabelRepositoryBackend {
+    async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label> {
+        match self {
+            LabelRepositoryBackend::Db(repository) => repository.create(payload).await,
+            LabelRepositoryBackend::MySql(repository) => repository.create(payload).await,
+            LabelRepositoryBackend::Sqlite(repository) => repository.create(payload).await,
+            LabelRepositoryBackend::File(repository) => repository.create(payload).await,
+        }
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<Label>> {
+        match self {
+            LabelRepositoryBackend::Db(repository) => repository.all().await,
+            LabelRepositoryBackend::MySql(repository) => repository.all().await,
+            LabelRepositoryBackend::Sqlite(repository) => repository.all().await,
+            LabelRepositoryBackend::File(repository) => repository.all().await,
+        }
+    }
+
+    async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label> {
+        match self {
+            LabelRepositoryBackend::Db(repository) => repository.update(id, payload).await,
+            LabelRepositoryBackend::MySql(repository) => repository.update(id, payload).await,
+            LabelRepositoryBackend::Sqlite(repository) => repository.update(id, payload).await,
+            LabelRepositoryBackend::File(repository) => repository.update(id, payload).await,
+        }
+    }
+
+    async fn delete(&self, id: i32, mode: DeleteLabel) -> anyhow::Result<Vec<i32>> {
+        match self {
+            LabelRepositoryBackend::Db(repository) => repository.delete(id, mode).await,
+            LabelRepositoryBackend::MySql(repository) => repository.delete(id, mode).await,
+            LabelRepositoryBackend::Sqlite(repository) => repository.delete(id, mode).await,
+            LabelRepositoryBackend::File(repository) => repository.delete(id, mode).await,
+        }
     }
 }
 
@@ -110,43 +1004,363 @@ mod test {
         let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
         let pool = PgPool::connect(database_url)
             .await
-            .expect(&format!("fail connect database, url is [{}]", database_url));
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+
+        scenario::crud_scenario(&LabelRepositoryForDb::new(pool)).await;
+    }
+}
 
-        let repository = LabelRepositoryForDb::new(pool);
-        let label_text = "test label from repositories/label.rs"; // repositories::todo::test::crud_scenario_dbのラベル名と重複してはいけない
+/// DBの実装ごとに同じ操作を確認するシナリオ
+///
+/// 他のテストのデータが残っているDBでも動くよう、作成したデータだけを確認する
+#[cfg(test)]
+mod scenario {
+    use super::*;
+
+    pub async fn crud_scenario<L: LabelRepository>(repository: &L) -> Label {
+        // repositories::todo::scenario のラベル名と重複しないよう、名前に乱数を付ける
+        let label_text = format!(
+            "test label from repositories/label.rs {}",
+            rand::random::<u32>()
+        );
 
         // create
         let label = repository
-            .create(label_text.to_string())
+            .create(CreateLabel::new(label_text.clone()))
             .await
             .expect("[create] returned Err");
         assert_eq!(label.name, label_text);
-        println!(
-            "[repositories::label::test::crud_scenario] create label is {:?}",
-            label
+        assert_eq!(label.color, DEFAULT_LABEL_COLOR);
+        assert_eq!(label.updated_at, label.created_at);
+        // 大文字・小文字や全角・半角だけが違う名前も重複とみなす
+        for name in [label_text.clone(), label_text.to_uppercase()] {
+            let res = repository.create(CreateLabel::new(name)).await;
+            assert!(res.is_err());
+        }
+
+        // 並び順を省略すると最後に並ぶ
+        let first = repository
+            .create(CreateLabel {
+                color: Some("#ff0000".to_string()),
+                description: Some("description".to_string()),
+                icon: Some("🔥".to_string()),
+                position: Some(-1),
+                ..CreateLabel::new(format!("{} first", label_text))
+            })
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(first.icon.as_deref(), Some("🔥"));
+        assert_eq!(first.position, -1);
+        assert!(label.position >= 0);
+        // 他のテストが作成したラベルが混ざっても、互いの順序は変わらない
+        let index_of = |labels: &[Label], id: i32| labels.iter().position(|label| label.id == id);
+        let labels = repository.all().await.expect("[all] returned Err");
+        assert!(index_of(&labels, first.id) < index_of(&labels, label.id));
+
+        // update
+        let updated = repository
+            .update(
+                first.id,
+                UpdateLabel {
+                    color: Some("#00ff00".to_string()),
+                    description: Some(None),
+                    position: Some(label.position + 1),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert_eq!(
+            updated,
+            Label {
+                color: "#00ff00".to_string(),
+                description: None,
+                position: label.position + 1,
+                updated_at: updated.updated_at,
+                ..first.clone()
+            }
         );
+        assert!(updated.updated_at >= first.updated_at);
+        let labels = repository.all().await.expect("[all] returned Err");
+        assert!(index_of(&labels, updated.id) > index_of(&labels, label.id));
+        let res = repository
+            .update(
+                first.id,
+                UpdateLabel {
+                    name: Some(label_text.clone()),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(res.is_err());
+
+        // 親のラベルを削除すると子は最上位のラベルになる
+        let child = repository
+            .create(CreateLabel {
+                parent_id: Some(first.id),
+                ..CreateLabel::new(format!("{} child", label_text))
+            })
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(child.parent_id, Some(first.id));
+        assert!(!child.exclusive);
+        // 親が違えば同じ名前を使えるが、同じ親の下では使えない
+        let same_name = repository
+            .create(CreateLabel {
+                parent_id: Some(first.id),
+                ..CreateLabel::new(label_text.clone())
+            })
+            .await
+            .expect("[create] returned Err");
+        let res = repository
+            .create(CreateLabel {
+                parent_id: Some(first.id),
+                ..CreateLabel::new(label_text.to_uppercase())
+            })
+            .await;
+        assert!(res.is_err());
+        let res = repository
+            .update(
+                same_name.id,
+                UpdateLabel {
+                    parent_id: Some(None),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(res.is_err());
+        repository
+            .delete(same_name.id, DeleteLabel::Detach)
+            .await
+            .expect("[delete] returned Err");
+        let updated = repository
+            .update(
+                first.id,
+                UpdateLabel {
+                    exclusive: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert!(updated.exclusive);
+        repository
+            .delete(first.id, DeleteLabel::Detach)
+            .await
+            .expect("[delete] returned Err");
+        let labels = repository.all().await.expect("[all] returned Err");
+        let child = labels
+            .iter()
+            .find(|label| label.id == child.id)
+            .expect("child label is deleted");
+        assert_eq!(child.parent_id, None);
+        repository
+            .delete(child.id, DeleteLabel::Detach)
+            .await
+            .expect("[delete] returned Err");
 
         // all
-        // let labels = repository.all().await.expect("[all] returned Err");
-        // let label = labels.first().unwrap();
-        // assert_eq!(label.name, label_text);
-        // println!("[repositories::label::test::crud_scenario] all labels is {:?}", labels);
+        let labels = repository.all().await.expect("[all] returned Err");
+        assert!(labels.contains(&label));
 
         // delete
         repository
-            .delete(label.id)
+            .delete(label.id, DeleteLabel::Detach)
             .await
             .expect("[delete] returned Err");
-        println!(
-            "[repositories::label::test::crud_scenario] delete label is {:?}",
-            label
+        assert!(!repository.all().await.unwrap().contains(&label));
+
+        label
+    }
+}
+
+#[cfg(test)]
+mod helper_test {
+    use super::*;
+
+    fn label(id: i32, name: &str, parent_id: Option<i32>, exclusive: bool) -> Label {
+        Label {
+            parent_id,
+            exclusive,
+            ..Label::new(id, name.to_string())
+        }
+    }
+
+    fn labels() -> Vec<Label> {
+        vec![
+            label(1, "area", None, false),
+            label(2, "backend", Some(1), false),
+            label(3, "frontend", Some(1), false),
+            label(4, "priority", None, true),
+            label(5, "high", Some(4), false),
+            label(6, "low", Some(4), false),
+            label(7, "api", Some(2), false),
+        ]
+    }
+
+    #[test]
+    fn should_find_exclusive_conflicts() {
+        let labels = labels();
+        assert!(check_exclusive(&labels, &[2, 3, 5]).is_ok());
+        assert_eq!(
+            exclusive_conflicts(&labels, &[5, 2, 6]),
+            vec![(
+                2,
+                ExclusiveLabelError {
+                    group: 4,
+                    label_id: 6
+                }
+            )]
+        );
+        assert!(check_exclusive(&labels, &[6, 5]).is_err());
+    }
+
+    #[test]
+    fn should_find_duplicate_ignoring_case_and_width() {
+        let labels = vec![label(1, "Bug", None, false), label(2, "バグ", None, false)];
+        for name in ["bug", "BUG", "Ｂｕｇ", " bug "] {
+            let found = find_duplicate(&labels, name, None, None);
+            assert_eq!(found.map(|l| l.id), Some(1));
+        }
+        let found = find_duplicate(&labels, "ﾊﾞｸﾞ", None, None);
+        assert_eq!(found.map(|l| l.id), Some(2));
+        assert_eq!(find_duplicate(&labels, "bugs", None, None), None);
+        assert_eq!(find_duplicate(&labels, "bug", None, Some(1)), None);
+
+        // 親が違えば同じ名前を使える
+        let labels = self::labels();
+        let found = find_duplicate(&labels, "Frontend", Some(1), None);
+        assert_eq!(found.map(|l| l.id), Some(3));
+        assert_eq!(find_duplicate(&labels, "frontend", None, None), None);
+        assert_eq!(find_duplicate(&labels, "frontend", Some(4), None), None);
+    }
+
+    #[test]
+    fn should_resolve_label_hierarchy() {
+        let labels = labels();
+        assert_eq!(descendants(&labels, 1), vec![1, 2, 3, 7]);
+        assert_eq!(descendants(&labels, 7), vec![7]);
+        assert_eq!(label_path(&labels, &labels[6]), "area/backend/api");
+        assert_eq!(find_by_path(&labels, "area/backend").map(|l| l.id), Some(2));
+        assert_eq!(find_by_path(&labels, "5").map(|l| l.id), Some(5));
+        assert_eq!(find_by_path(&labels, "backend"), None);
+
+        // 親子関係が循環していても止まる
+        let cyclic = vec![label(1, "a", Some(2), false), label(2, "b", Some(1), false)];
+        assert_eq!(descendants(&cyclic, 1), vec![1, 2]);
+        assert!(label_path(&cyclic, &cyclic[0]).ends_with("a"));
+    }
+}
+
+#[cfg(test)]
+mod sqlite_test {
+    use super::*;
+    use crate::repositories::connect_sqlite;
+
+    #[tokio::test]
+    async fn crud_scenario_sqlite() {
+        let pool = connect_sqlite("sqlite::memory:")
+            .await
+            .expect("fail connect sqlite");
+        let repository = LabelRepositoryForSqlite::new(pool);
+        let label = scenario::crud_scenario(&repository).await;
+        assert!(repository
+            .delete(label.id, DeleteLabel::Detach)
+            .await
+            .is_err());
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "mysql-test")]
+mod mysql_test {
+    use super::*;
+    use crate::repositories::connect_mysql;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario_mysql() {
+        dotenv().ok();
+        let database_url = &env::var("MYSQL_DATABASE_URL").expect("undefined [MYSQL_DATABASE_URL]");
+        let pool = connect_mysql(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+        let repository = LabelRepositoryForMySql::new(pool);
+        let label = scenario::crud_scenario(&repository).await;
+        assert!(repository
+            .delete(label.id, DeleteLabel::Detach)
+            .await
+            .is_err());
+    }
+}
+
+#[cfg(test)]
+mod file_test {
+    use super::*;
+
+    #[tokio::test]
+    async fn crud_scenario_file() {
+        let dir = std::env::temp_dir().join(format!("todo-file-label-{}", rand::random::<u32>()));
+        let repository = LabelRepositoryForFile::open(&dir).expect("fail open store");
+        let label_text = "label text";
+
+        // create
+        let label = repository
+            .create(CreateLabel::new(label_text.to_string()))
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(
+            label,
+            Label {
+                created_at: label.created_at,
+                updated_at: label.created_at,
+                ..Label::new(1, label_text.to_string())
+            }
         );
+        assert!(repository
+            .create(CreateLabel::new(label_text.to_string()))
+            .await
+            .is_err());
+        let label = repository
+            .update(
+                label.id,
+                UpdateLabel {
+                    color: Some("#123abc".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+
+        // 再起動しても残っている
+        drop(repository);
+        let repository = LabelRepositoryForFile::open(&dir).expect("fail reopen store");
+        let labels = repository.all().await.expect("[all] returned Err");
+        assert_eq!(labels, vec![label.clone()]);
+
+        // delete
+        repository
+            .delete(label.id, DeleteLabel::Detach)
+            .await
+            .expect("[delete] returned Err");
+        assert!(repository.all().await.unwrap().is_empty());
+        assert!(repository
+            .delete(label.id, DeleteLabel::Detach)
+            .await
+            .is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
 
 #[cfg(test)]
 pub mod test_utils {
-    use crate::repositories::label::{LabelRepository, RepositoryError};
+    use crate::repositories::label::{
+        apply_update, check_orphans, find_duplicate, next_position, sort_labels, CreateLabel,
+        DeleteLabel, LabelRepository, RepositoryError, UpdateLabel, DEFAULT_LABEL_COLOR,
+    };
+    use crate::repositories::todo::test_utils::{ManualClock, TodoRepositoryForMemory};
     use axum::async_trait;
     use std::{
         collections::HashMap,
@@ -155,59 +1369,138 @@ pub mod test_utils {
 
     use super::Label;
 
-    impl Label {
-        pub fn new(id: i32, name: String) -> Self {
-            Label { id, name }
-        }
-    }
-
-    type LabelData = HashMap<i32, Label>;
+    pub type LabelData = HashMap<i32, Label>;
 
     #[derive(Debug, Clone)]
     pub struct LabelRepositoryForMemory {
         store: Arc<RwLock<LabelData>>,
+        clock: ManualClock,
+        todos: Option<TodoRepositoryForMemory>,
+    }
+
+    impl Default for LabelRepositoryForMemory {
+        fn default() -> Self {
+            Self::new()
+        }
     }
 
     impl LabelRepositoryForMemory {
         pub fn new() -> Self {
             LabelRepositoryForMemory {
                 store: Arc::default(),
+                clock: ManualClock::default(),
+                todos: None,
             }
         }
 
-        fn write_store_ref(&self) -> RwLockWriteGuard<LabelData> {
+        /// 登録済みのラベルを持った状態で作成する
+        pub fn with_labels(labels: Vec<Label>) -> Self {
+            let store = labels.into_iter().map(|label| (label.id, label)).collect();
+            LabelRepositoryForMemory {
+                store: Arc::new(RwLock::new(store)),
+                clock: ManualClock::default(),
+                todos: None,
+            }
+        }
+
+        /// 作成・更新日時を外から進められるよう、時計を共有する
+        pub fn with_clock(mut self, clock: ManualClock) -> Self {
+            self.clock = clock;
+            self
+        }
+
+        /// `TodoRepositoryForMemory` がラベルを統合する時に使う
+        pub fn store(&self) -> Arc<RwLock<LabelData>> {
+            self.store.clone()
+        }
+
+        /// 削除時に使っているTodoを更新するため、Todoのストアを共有する
+        pub fn with_todos(mut self, todos: TodoRepositoryForMemory) -> Self {
+            self.todos = Some(todos);
+            self
+        }
+
+        fn write_store_ref(&self) -> RwLockWriteGuard<'_, LabelData> {
             self.store.write().unwrap()
         }
 
-        fn read_store_ref(&self) -> RwLockReadGuard<LabelData> {
+        fn read_store_ref(&self) -> RwLockReadGuard<'_, LabelData> {
             self.store.read().unwrap()
         }
     }
 
     #[async_trait]
     impl LabelRepository for LabelRepositoryForMemory {
-        async fn create(&self, name: String) -> anyhow::Result<Label> {
+        async fn create(&self, payload: CreateLabel) -> anyhow::Result<Label> {
             let mut store = self.write_store_ref();
-            if let Some((_key, label)) = store.iter().find(|(_key, label)| label.name == name) {
+            let labels: Vec<Label> = store.values().cloned().collect();
+            if let Some(label) = find_duplicate(&labels, &payload.name, payload.parent_id, None) {
                 return Ok(label.clone());
             };
 
-            let id = (store.len() + 1) as i32;
-            let label = Label::new(id, name.clone());
+            // 統合や削除で欠番があっても既存のIDと重複しないようにする
+            let id = store.keys().max().copied().unwrap_or(0) + 1;
+            let label = Label {
+                id,
+                name: payload.name,
+                color: payload.color.unwrap_or(DEFAULT_LABEL_COLOR.to_string()),
+                description: payload.description,
+                icon: payload.icon,
+                position: payload.position.unwrap_or_else(|| next_position(&labels)),
+                parent_id: payload.parent_id,
+                exclusive: payload.exclusive,
+                created_at: self.clock.now(),
+                updated_at: self.clock.now(),
+            };
             store.insert(id, label.clone());
             Ok(label)
         }
 
         async fn all(&self) -> anyhow::Result<Vec<Label>> {
             let store = self.read_store_ref();
-            let labels = Vec::from_iter(store.values().map(|label| label.clone()));
+            let mut labels = Vec::from_iter(store.values().cloned());
+            sort_labels(&mut labels);
             Ok(labels)
         }
 
-        async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        async fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label> {
+            let mut store = self.write_store_ref();
+            let label = store
+                .get(&id)
+                .cloned()
+                .ok_or(RepositoryError::NotFound(id))?;
+            let label = apply_update(label, payload, self.clock.now());
+            let labels: Vec<Label> = store.values().cloned().collect();
+            if let Some(other) = find_duplicate(&labels, &label.name, label.parent_id, Some(id)) {
+                return Err(RepositoryError::Duplicate(other.id).into());
+            }
+            store.insert(id, label.clone());
+            Ok(label)
+        }
+
+        async fn delete(&self, id: i32, mode: DeleteLabel) -> anyhow::Result<Vec<i32>> {
             let mut store = self.write_store_ref();
-            store.remove(&id).ok_or(RepositoryError::NotFound(id))?;
-            Ok(())
+            if !store.contains_key(&id) {
+                return Err(RepositoryError::NotFound(id).into());
+            }
+            let labels: Vec<Label> = store.values().cloned().collect();
+            check_orphans(&labels, id)?;
+            if let DeleteLabel::Reassign(to) = mode {
+                if to == id || !store.contains_key(&to) {
+                    return Err(RepositoryError::NotFound(to).into());
+                }
+            }
+            let todo_ids = match &self.todos {
+                Some(todos) => todos.replace_label(id, mode, &labels)?,
+                None => vec![],
+            };
+            store.remove(&id);
+            for label in store.values_mut() {
+                if label.parent_id == Some(id) {
+                    label.parent_id = None;
+                }
+            }
+            Ok(todo_ids)
         }
     }
 
@@ -215,7 +1508,7 @@ pub mod test_utils {
         use std::vec;
 
         use super::{LabelRepository, LabelRepositoryForMemory};
-        use crate::repositories::label::Label;
+        use crate::repositories::label::{CreateLabel, DeleteLabel, Label};
 
         #[tokio::test]
         async fn label_crud_scenario() {
@@ -226,7 +1519,7 @@ pub mod test_utils {
             // create
             let repository = LabelRepositoryForMemory::new();
             let label = repository
-                .create(text.clone())
+                .create(CreateLabel::new(text.clone()))
                 .await
                 .expect("failed label create");
             assert_eq!(expected, label);
@@ -236,7 +1529,7 @@ pub mod test_utils {
             assert_eq!(vec![expected], label);
 
             // delete
-            let res = repository.delete(id).await;
+            let res = repository.delete(id, DeleteLabel::Detach).await;
             assert!(res.is_ok())
         }
     }
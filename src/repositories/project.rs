@@ -0,0 +1,300 @@
+use super::RepositoryError;
+use axum::async_trait;
+use sqlx::PgPool;
+
+pub use todo_types::{CreateProject, Project, UpdateProject, DEFAULT_PROJECT_ID};
+
+#[async_trait]
+pub trait ProjectRepository: Clone + std::marker::Send + std::marker::Sync + 'static {
+    async fn create(&self, payload: CreateProject) -> anyhow::Result<Project>;
+    async fn find(&self, id: i32) -> anyhow::Result<Project>;
+    async fn all(&self) -> anyhow::Result<Vec<Project>>;
+    async fn update(&self, id: i32, payload: UpdateProject) -> anyhow::Result<Project>;
+    async fn delete(&self, id: i32) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct ProjectRepositoryForDb {
+    pool: PgPool,
+}
+
+impl ProjectRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ProjectRepository for ProjectRepositoryForDb {
+    async fn create(&self, payload: CreateProject) -> anyhow::Result<Project> {
+        let project = sqlx::query_as::<_, Project>(
+            r#"
+                insert into projects (name) values ($1) returning *;
+            "#,
+        )
+        .bind(payload.name)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(project)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Project> {
+        let project = sqlx::query_as::<_, Project>(
+            r#"
+                select * from projects where id=$1;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(project)
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<Project>> {
+        let projects = sqlx::query_as::<_, Project>(
+            r#"
+                select * from projects order by projects.id asc;
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(projects)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateProject) -> anyhow::Result<Project> {
+        let old_project = self.find(id).await?;
+        let project = sqlx::query_as::<_, Project>(
+            r#"
+                update projects set name=$1, archived=$2 where id=$3 returning *;
+            "#,
+        )
+        .bind(payload.name.unwrap_or(old_project.name))
+        .bind(payload.archived.unwrap_or(old_project.archived))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(project)
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        // Todoが残っている場合は外部キー制約で失敗する
+        let result = sqlx::query(
+            r#"
+                delete from projects where id=$1;
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use dotenv::dotenv;
+    use sqlx::PgPool;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+
+        let repository = ProjectRepositoryForDb::new(pool);
+
+        // default project
+        let default_project = repository
+            .find(DEFAULT_PROJECT_ID)
+            .await
+            .expect("[find] default project is not migrated");
+        assert!(!default_project.archived);
+
+        // create
+        let project_name = "test project from repositories/project.rs";
+        let project = repository
+            .create(CreateProject {
+                name: project_name.to_string(),
+            })
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(project.name, project_name);
+        assert!(!project.archived);
+
+        // all
+        let projects = repository.all().await.expect("[all] returned Err");
+        assert!(projects.contains(&project));
+
+        // update (archive)
+        let archived = repository
+            .update(
+                project.id,
+                UpdateProject {
+                    name: None,
+                    archived: Some(true),
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert_eq!(archived.name, project_name);
+        assert!(archived.archived);
+
+        // delete
+        repository
+            .delete(project.id)
+            .await
+            .expect("[delete] returned Err");
+        assert!(repository.find(project.id).await.is_err());
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    };
+
+    type ProjectData = HashMap<i32, Project>;
+
+    #[derive(Debug, Clone)]
+    pub struct ProjectRepositoryForMemory {
+        store: Arc<RwLock<ProjectData>>,
+    }
+
+    impl Default for ProjectRepositoryForMemory {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl ProjectRepositoryForMemory {
+        /// マイグレーションと同様にデフォルトプロジェクトを作成した状態で始める
+        pub fn new() -> Self {
+            let store = HashMap::from([(
+                DEFAULT_PROJECT_ID,
+                Project::new(DEFAULT_PROJECT_ID, String::from("Default")),
+            )]);
+            ProjectRepositoryForMemory {
+                store: Arc::new(RwLock::new(store)),
+            }
+        }
+
+        fn write_store_ref(&self) -> RwLockWriteGuard<'_, ProjectData> {
+            self.store.write().unwrap()
+        }
+
+        fn read_store_ref(&self) -> RwLockReadGuard<'_, ProjectData> {
+            self.store.read().unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl ProjectRepository for ProjectRepositoryForMemory {
+        async fn create(&self, payload: CreateProject) -> anyhow::Result<Project> {
+            let mut store = self.write_store_ref();
+            let id = store.keys().max().unwrap_or(&0) + 1;
+            let project = Project::new(id, payload.name);
+            store.insert(id, project.clone());
+            Ok(project)
+        }
+
+        async fn find(&self, id: i32) -> anyhow::Result<Project> {
+            let store = self.read_store_ref();
+            let project = store
+                .get(&id)
+                .cloned()
+                .ok_or(RepositoryError::NotFound(id))?;
+            Ok(project)
+        }
+
+        async fn all(&self) -> anyhow::Result<Vec<Project>> {
+            let store = self.read_store_ref();
+            let mut projects = Vec::from_iter(store.values().cloned());
+            projects.sort_by_key(|project| project.id);
+            Ok(projects)
+        }
+
+        async fn update(&self, id: i32, payload: UpdateProject) -> anyhow::Result<Project> {
+            let mut store = self.write_store_ref();
+            let project = store.get_mut(&id).ok_or(RepositoryError::NotFound(id))?;
+            if let Some(name) = payload.name {
+                project.name = name;
+            }
+            if let Some(archived) = payload.archived {
+                project.archived = archived;
+            }
+            Ok(project.clone())
+        }
+
+        async fn delete(&self, id: i32) -> anyhow::Result<()> {
+            let mut store = self.write_store_ref();
+            store.remove(&id).ok_or(RepositoryError::NotFound(id))?;
+            Ok(())
+        }
+    }
+
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn project_crud_scenario() {
+            let repository = ProjectRepositoryForMemory::new();
+
+            // create
+            let project = repository
+                .create(CreateProject {
+                    name: "project".to_string(),
+                })
+                .await
+                .expect("failed create project");
+            assert_eq!(Project::new(2, "project".to_string()), project);
+
+            // all
+            let projects = repository.all().await.unwrap();
+            assert_eq!(
+                vec![
+                    Project::new(DEFAULT_PROJECT_ID, "Default".to_string()),
+                    project.clone()
+                ],
+                projects
+            );
+
+            // update
+            let project = repository
+                .update(
+                    project.id,
+                    UpdateProject {
+                        name: Some("renamed".to_string()),
+                        archived: Some(true),
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(project.name, "renamed");
+            assert!(project.archived);
+
+            // delete
+            let res = repository.delete(project.id).await;
+            assert!(res.is_ok());
+            assert!(repository.find(project.id).await.is_err());
+        }
+    }
+}
@@ -0,0 +1,409 @@
+use super::{deserialize_double_option, RepositoryError};
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use utoipa::ToSchema;
+
+#[async_trait]
+pub trait StatusRepository: Clone + std::marker::Send + std::marker::Sync + 'static {
+    async fn create(&self, project_id: i32, payload: CreateStatus) -> anyhow::Result<Status>;
+    async fn find(&self, id: i32) -> anyhow::Result<Status>;
+    /// プロジェクトのステータスを列の並び順で返す
+    async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<Status>>;
+    async fn update(&self, id: i32, payload: UpdateStatus) -> anyhow::Result<Status>;
+    async fn delete(&self, id: i32) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, FromRow, ToSchema)]
+pub struct Status {
+    pub id: i32,
+    pub project_id: i32,
+    pub name: String,
+    pub position: i32,
+    pub is_done: bool,
+    pub wip_limit: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct CreateStatus {
+    pub name: String,
+    // 省略時は末尾の列になる
+    pub position: Option<i32>,
+    #[serde(default)]
+    pub is_done: bool,
+    pub wip_limit: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct UpdateStatus {
+    pub name: Option<String>,
+    pub position: Option<i32>,
+    pub is_done: Option<bool>,
+    // nullを指定するとWIP制限を解除する
+    #[serde(default, deserialize_with = "deserialize_double_option")]
+    pub wip_limit: Option<Option<i32>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StatusRepositoryForDb {
+    pool: PgPool,
+}
+
+impl StatusRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl StatusRepository for StatusRepositoryForDb {
+    async fn create(&self, project_id: i32, payload: CreateStatus) -> anyhow::Result<Status> {
+        let status = sqlx::query_as::<_, Status>(
+            r#"
+                insert into statuses (project_id, name, position, is_done, wip_limit)
+                values (
+                    $1, $2,
+                    coalesce($3, (select coalesce(max(position) + 1, 0) from statuses where project_id=$1)),
+                    $4, $5
+                )
+                returning *;
+            "#,
+        )
+        .bind(project_id)
+        .bind(payload.name)
+        .bind(payload.position)
+        .bind(payload.is_done)
+        .bind(payload.wip_limit)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(status)
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Status> {
+        let status = sqlx::query_as::<_, Status>(
+            r#"
+                select * from statuses where id=$1;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(status)
+    }
+
+    async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<Status>> {
+        let statuses = sqlx::query_as::<_, Status>(
+            r#"
+                select * from statuses where project_id=$1 order by position asc, id asc;
+            "#,
+        )
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(statuses)
+    }
+
+    async fn update(&self, id: i32, payload: UpdateStatus) -> anyhow::Result<Status> {
+        let old_status = self.find(id).await?;
+        let status = sqlx::query_as::<_, Status>(
+            r#"
+                update statuses set name=$1, position=$2, is_done=$3, wip_limit=$4
+                where id=$5 returning *;
+            "#,
+        )
+        .bind(payload.name.unwrap_or(old_status.name))
+        .bind(payload.position.unwrap_or(old_status.position))
+        .bind(payload.is_done.unwrap_or(old_status.is_done))
+        .bind(payload.wip_limit.unwrap_or(old_status.wip_limit))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        Ok(status)
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        // このステータスのTodoは外部キーによって未割り当て(null)になる
+        let result = sqlx::query(
+            r#"
+                delete from statuses where id=$1;
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use crate::repositories::project::{CreateProject, ProjectRepository, ProjectRepositoryForDb};
+    use dotenv::dotenv;
+    use sqlx::PgPool;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+
+        let project = ProjectRepositoryForDb::new(pool.clone())
+            .create(CreateProject {
+                name: "test project from repositories/status.rs".to_string(),
+            })
+            .await
+            .expect("Failed to prepare project data.");
+        let repository = StatusRepositoryForDb::new(pool.clone());
+
+        // create
+        let backlog = repository
+            .create(
+                project.id,
+                CreateStatus {
+                    name: "Backlog".to_string(),
+                    position: None,
+                    is_done: false,
+                    wip_limit: None,
+                },
+            )
+            .await
+            .expect("[create] returned Err");
+        let done = repository
+            .create(
+                project.id,
+                CreateStatus {
+                    name: "Done".to_string(),
+                    position: None,
+                    is_done: true,
+                    wip_limit: Some(3),
+                },
+            )
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(backlog.position, 0);
+        assert_eq!(done.position, 1);
+
+        // all_in_project
+        let statuses = repository
+            .all_in_project(project.id)
+            .await
+            .expect("[all_in_project] returned Err");
+        assert_eq!(vec![backlog.clone(), done.clone()], statuses);
+
+        // update
+        let updated = repository
+            .update(
+                done.id,
+                UpdateStatus {
+                    name: None,
+                    position: Some(5),
+                    is_done: None,
+                    wip_limit: Some(None),
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert_eq!(updated.position, 5);
+        assert_eq!(updated.wip_limit, None);
+
+        // delete
+        repository
+            .delete(backlog.id)
+            .await
+            .expect("[delete] returned Err");
+        assert!(repository.find(backlog.id).await.is_err());
+
+        // delete project data prepare
+        ProjectRepositoryForDb::new(pool)
+            .delete(project.id)
+            .await
+            .expect("[delete] returned Err");
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    };
+
+    type StatusData = HashMap<i32, Status>;
+
+    #[derive(Debug, Clone)]
+    pub struct StatusRepositoryForMemory {
+        store: Arc<RwLock<StatusData>>,
+    }
+
+    impl Default for StatusRepositoryForMemory {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl StatusRepositoryForMemory {
+        pub fn new() -> Self {
+            StatusRepositoryForMemory {
+                store: Arc::default(),
+            }
+        }
+
+        fn write_store_ref(&self) -> RwLockWriteGuard<'_, StatusData> {
+            self.store.write().unwrap()
+        }
+
+        fn read_store_ref(&self) -> RwLockReadGuard<'_, StatusData> {
+            self.store.read().unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl StatusRepository for StatusRepositoryForMemory {
+        async fn create(&self, project_id: i32, payload: CreateStatus) -> anyhow::Result<Status> {
+            let mut store = self.write_store_ref();
+            let id = (store.len() + 1) as i32;
+            let position = payload.position.unwrap_or_else(|| {
+                store
+                    .values()
+                    .filter(|status| status.project_id == project_id)
+                    .map(|status| status.position + 1)
+                    .max()
+                    .unwrap_or(0)
+            });
+            let status = Status {
+                id,
+                project_id,
+                name: payload.name,
+                position,
+                is_done: payload.is_done,
+                wip_limit: payload.wip_limit,
+            };
+            store.insert(id, status.clone());
+            Ok(status)
+        }
+
+        async fn find(&self, id: i32) -> anyhow::Result<Status> {
+            let store = self.read_store_ref();
+            let status = store
+                .get(&id)
+                .cloned()
+                .ok_or(RepositoryError::NotFound(id))?;
+            Ok(status)
+        }
+
+        async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<Status>> {
+            let store = self.read_store_ref();
+            let mut statuses = store
+                .values()
+                .filter(|status| status.project_id == project_id)
+                .cloned()
+                .collect::<Vec<_>>();
+            statuses.sort_by_key(|status| (status.position, status.id));
+            Ok(statuses)
+        }
+
+        async fn update(&self, id: i32, payload: UpdateStatus) -> anyhow::Result<Status> {
+            let mut store = self.write_store_ref();
+            let status = store.get_mut(&id).ok_or(RepositoryError::NotFound(id))?;
+            if let Some(name) = payload.name {
+                status.name = name;
+            }
+            if let Some(position) = payload.position {
+                status.position = position;
+            }
+            if let Some(is_done) = payload.is_done {
+                status.is_done = is_done;
+            }
+            if let Some(wip_limit) = payload.wip_limit {
+                status.wip_limit = wip_limit;
+            }
+            Ok(status.clone())
+        }
+
+        async fn delete(&self, id: i32) -> anyhow::Result<()> {
+            let mut store = self.write_store_ref();
+            store.remove(&id).ok_or(RepositoryError::NotFound(id))?;
+            Ok(())
+        }
+    }
+
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn status_crud_scenario() {
+            let repository = StatusRepositoryForMemory::new();
+
+            // create
+            let todo = repository
+                .create(
+                    1,
+                    CreateStatus {
+                        name: "Todo".to_string(),
+                        position: None,
+                        is_done: false,
+                        wip_limit: Some(2),
+                    },
+                )
+                .await
+                .expect("failed create status");
+            let done = repository
+                .create(
+                    1,
+                    CreateStatus {
+                        name: "Done".to_string(),
+                        position: None,
+                        is_done: true,
+                        wip_limit: None,
+                    },
+                )
+                .await
+                .expect("failed create status");
+            assert_eq!((todo.position, done.position), (0, 1));
+
+            // all_in_project
+            let statuses = repository.all_in_project(1).await.unwrap();
+            assert_eq!(vec![todo.clone(), done.clone()], statuses);
+            assert!(repository.all_in_project(2).await.unwrap().is_empty());
+
+            // update (reorder)
+            let todo = repository
+                .update(
+                    todo.id,
+                    UpdateStatus {
+                        name: None,
+                        position: Some(2),
+                        is_done: None,
+                        wip_limit: Some(None),
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(todo.wip_limit, None);
+            let statuses = repository.all_in_project(1).await.unwrap();
+            assert_eq!(vec![done.clone(), todo.clone()], statuses);
+
+            // delete
+            let res = repository.delete(todo.id).await;
+            assert!(res.is_ok());
+            assert!(repository.find(todo.id).await.is_err());
+        }
+    }
+}
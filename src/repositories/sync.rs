@@ -0,0 +1,660 @@
+use super::todo::{Priority, TodoEntity};
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json, FromRow, PgPool};
+use todo_types::deserialize_double_option;
+use utoipa::ToSchema;
+
+/// 同期の対象となるTodoのフィールド
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncField {
+    Text,
+    Notes,
+    Completed,
+    Labels,
+    Priority,
+    DueAt,
+    /// ブロッカーのIDの一覧
+    BlockedBy,
+}
+
+/// フィールドを最後に変更したversionと、その変更が行われた時刻
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct FieldClock {
+    pub version: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for FieldClock {
+    fn default() -> Self {
+        Self {
+            version: 0,
+            updated_at: DateTime::UNIX_EPOCH,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FieldClocks {
+    #[serde(default)]
+    pub text: FieldClock,
+    #[serde(default)]
+    pub notes: FieldClock,
+    #[serde(default)]
+    pub completed: FieldClock,
+    #[serde(default)]
+    pub labels: FieldClock,
+    #[serde(default)]
+    pub priority: FieldClock,
+    #[serde(default)]
+    pub due_at: FieldClock,
+    #[serde(default)]
+    pub blocked_by: FieldClock,
+}
+
+impl FieldClocks {
+    pub fn get(&self, field: SyncField) -> FieldClock {
+        match field {
+            SyncField::Text => self.text,
+            SyncField::Notes => self.notes,
+            SyncField::Completed => self.completed,
+            SyncField::Labels => self.labels,
+            SyncField::Priority => self.priority,
+            SyncField::DueAt => self.due_at,
+            SyncField::BlockedBy => self.blocked_by,
+        }
+    }
+
+    fn set(&mut self, field: SyncField, clock: FieldClock) {
+        match field {
+            SyncField::Text => self.text = clock,
+            SyncField::Notes => self.notes = clock,
+            SyncField::Completed => self.completed = clock,
+            SyncField::Labels => self.labels = clock,
+            SyncField::Priority => self.priority = clock,
+            SyncField::DueAt => self.due_at = clock,
+            SyncField::BlockedBy => self.blocked_by = clock,
+        }
+    }
+}
+
+/// Todoごとの同期情報
+///
+/// Todoを削除しても `deleted_at` を持つトゥームストーンとして残り、変更フィードで削除を伝える
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncState {
+    pub todo_id: i32,
+    /// 変更のたびに1ずつ増える
+    pub version: i64,
+    /// 全Todoで共通の変更の通し番号
+    pub cursor: i64,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub fields: FieldClocks,
+}
+
+impl SyncState {
+    /// 同期情報がまだないTodoの状態
+    pub fn new(todo_id: i32) -> Self {
+        Self {
+            todo_id,
+            version: 0,
+            cursor: 0,
+            deleted_at: None,
+            fields: FieldClocks::default(),
+        }
+    }
+
+    // cursorは保存時に採番する
+    fn advance(
+        mut self,
+        changed: &[(SyncField, DateTime<Utc>)],
+        deleted_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.version += 1;
+        for (field, updated_at) in changed {
+            self.fields.set(
+                *field,
+                FieldClock {
+                    version: self.version,
+                    updated_at: *updated_at,
+                },
+            );
+        }
+        if deleted_at.is_some() {
+            self.deleted_at = deleted_at;
+        }
+        self
+    }
+}
+
+/// フィールドごとの更新時刻
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+pub struct FieldTimes {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_by: Option<DateTime<Utc>>,
+}
+
+impl FieldTimes {
+    pub fn get(&self, field: SyncField) -> Option<DateTime<Utc>> {
+        match field {
+            SyncField::Text => self.text,
+            SyncField::Notes => self.notes,
+            SyncField::Completed => self.completed,
+            SyncField::Labels => self.labels,
+            SyncField::Priority => self.priority,
+            SyncField::DueAt => self.due_at,
+            SyncField::BlockedBy => self.blocked_by,
+        }
+    }
+}
+
+impl From<FieldClocks> for FieldTimes {
+    fn from(clocks: FieldClocks) -> Self {
+        Self {
+            text: Some(clocks.text.updated_at),
+            notes: Some(clocks.notes.updated_at),
+            completed: Some(clocks.completed.updated_at),
+            labels: Some(clocks.labels.updated_at),
+            priority: Some(clocks.priority.updated_at),
+            due_at: Some(clocks.due_at.updated_at),
+            blocked_by: Some(clocks.blocked_by.updated_at),
+        }
+    }
+}
+
+/// 変更フィードに載るTodoの最新の状態
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct SyncedTodo {
+    pub id: i32,
+    pub version: i64,
+    pub cursor: i64,
+    /// 削除済み(トゥームストーン)の場合は `todo` を持たない
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub todo: Option<TodoEntity>,
+    pub updated_at: FieldTimes,
+}
+
+impl SyncedTodo {
+    pub fn new(state: SyncState, todo: Option<TodoEntity>) -> Self {
+        Self {
+            id: state.todo_id,
+            version: state.version,
+            cursor: state.cursor,
+            deleted_at: state.deleted_at,
+            todo,
+            updated_at: state.fields.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct SyncChanges {
+    /// 次回の `since` に指定する値
+    pub cursor: i64,
+    /// trueの場合は続きの変更がある
+    pub has_more: bool,
+    pub todos: Vec<SyncedTodo>,
+}
+
+/// クライアントで行ったTodoへの変更
+///
+/// 値を指定したフィールドのみ反映する。更新時刻を省略したフィールドはサーバーの受信時刻で扱う
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default, ToSchema)]
+pub struct PushTodo {
+    /// オフラインで作成したTodoはNone
+    #[serde(default)]
+    pub id: Option<i32>,
+    /// 作成したTodoとサーバーで採番したIDを対応付けるためのクライアント側の識別子
+    #[serde(default)]
+    pub client_ref: Option<String>,
+    /// クライアントが最後に取り込んだversion (作成時は0)
+    #[serde(default)]
+    pub base_version: i64,
+    #[serde(default)]
+    pub text: Option<String>,
+    /// nullを指定するとメモを消す
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub notes: Option<Option<String>>,
+    #[serde(default)]
+    pub completed: Option<bool>,
+    #[serde(default)]
+    pub labels: Option<Vec<i32>>,
+    /// nullを指定すると優先度・期限を外す
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub priority: Option<Option<Priority>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+    /// 指定したTodoだけをブロッカーにする
+    #[serde(default)]
+    pub blocked_by: Option<Vec<i32>>,
+    /// 指定した場合はTodoを削除する
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub updated_at: FieldTimes,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct SyncPush {
+    pub todos: Vec<PushTodo>,
+}
+
+/// 競合したフィールドで採用した側
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    Client,
+    Server,
+}
+
+/// 最後に取り込んだ後にサーバー側でも変更されていたフィールド
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct SyncConflict {
+    pub id: i32,
+    /// 削除と更新の競合ではNone
+    pub field: Option<SyncField>,
+    pub resolution: Resolution,
+    #[schema(value_type = Object)]
+    pub client_value: serde_json::Value,
+    #[schema(value_type = Object)]
+    pub server_value: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct SyncCreated {
+    pub client_ref: Option<String>,
+    pub id: i32,
+}
+
+/// 入力値の不正などで反映できなかった変更
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct SyncRejected {
+    pub id: Option<i32>,
+    pub client_ref: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default, ToSchema)]
+pub struct SyncPushResult {
+    /// 反映後の状態 (クライアントはこのversionを次回の `base_version` に使う)
+    pub todos: Vec<SyncedTodo>,
+    pub created: Vec<SyncCreated>,
+    pub conflicts: Vec<SyncConflict>,
+    pub rejected: Vec<SyncRejected>,
+}
+
+#[async_trait]
+pub trait SyncRepository: Clone + std::marker::Send + std::marker::Sync + 'static {
+    /// 同期情報がないTodoはNone
+    async fn find(&self, todo_id: i32) -> anyhow::Result<Option<SyncState>>;
+    /// cursorが `since` より後の状態を古い順に最大 `limit` 件返す
+    async fn changes(&self, since: i64, limit: i64) -> anyhow::Result<Vec<SyncState>>;
+    /// 変更したフィールドの更新時刻を記録し、versionとcursorを進める
+    async fn record(
+        &self,
+        todo_id: i32,
+        changed: Vec<(SyncField, DateTime<Utc>)>,
+    ) -> anyhow::Result<SyncState>;
+    /// 削除をトゥームストーンとして記録する
+    async fn tombstone(&self, todo_id: i32, deleted_at: DateTime<Utc>)
+        -> anyhow::Result<SyncState>;
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct SyncStateFromRow {
+    todo_id: i32,
+    version: i64,
+    cursor: i64,
+    deleted_at: Option<DateTime<Utc>>,
+    fields: Json<FieldClocks>,
+}
+
+impl From<SyncStateFromRow> for SyncState {
+    fn from(row: SyncStateFromRow) -> Self {
+        Self {
+            todo_id: row.todo_id,
+            version: row.version,
+            cursor: row.cursor,
+            deleted_at: row.deleted_at,
+            fields: row.fields.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncRepositoryForDb {
+    pool: PgPool,
+}
+
+impl SyncRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    async fn save(
+        &self,
+        todo_id: i32,
+        changed: &[(SyncField, DateTime<Utc>)],
+        deleted_at: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<SyncState> {
+        let mut tx = self.pool.begin().await?;
+        // 同じTodoへの記録が同時に行われてもversionが飛ばないよう行をロックする
+        let current = sqlx::query_as::<_, SyncStateFromRow>(
+            r#"
+                select * from sync_todos where todo_id=$1 for update;
+            "#,
+        )
+        .bind(todo_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .map(SyncState::from)
+        .unwrap_or(SyncState::new(todo_id));
+
+        let state = current.advance(changed, deleted_at);
+        let row = sqlx::query_as::<_, SyncStateFromRow>(
+            r#"
+                insert into sync_todos (todo_id, version, cursor, deleted_at, fields)
+                values ($1, $2, nextval('sync_cursor_seq'), $3, $4)
+                on conflict (todo_id) do update
+                set version=excluded.version, cursor=excluded.cursor,
+                    deleted_at=excluded.deleted_at, fields=excluded.fields
+                returning *;
+            "#,
+        )
+        .bind(todo_id)
+        .bind(state.version)
+        .bind(state.deleted_at)
+        .bind(Json(state.fields))
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(row.into())
+    }
+}
+
+#[async_trait]
+impl SyncRepository for SyncRepositoryForDb {
+    async fn find(&self, todo_id: i32) -> anyhow::Result<Option<SyncState>> {
+        let row = sqlx::query_as::<_, SyncStateFromRow>(
+            r#"
+                select * from sync_todos where todo_id=$1;
+            "#,
+        )
+        .bind(todo_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(SyncState::from))
+    }
+
+    async fn changes(&self, since: i64, limit: i64) -> anyhow::Result<Vec<SyncState>> {
+        let rows = sqlx::query_as::<_, SyncStateFromRow>(
+            r#"
+                select * from sync_todos where cursor > $1 order by cursor asc limit $2;
+            "#,
+        )
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(SyncState::from).collect())
+    }
+
+    async fn record(
+        &self,
+        todo_id: i32,
+        changed: Vec<(SyncField, DateTime<Utc>)>,
+    ) -> anyhow::Result<SyncState> {
+        self.save(todo_id, &changed, None).await
+    }
+
+    async fn tombstone(
+        &self,
+        todo_id: i32,
+        deleted_at: DateTime<Utc>,
+    ) -> anyhow::Result<SyncState> {
+        self.save(todo_id, &[], Some(deleted_at)).await
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+        let repository = SyncRepositoryForDb::new(pool.clone());
+
+        // Todoの行とは独立して記録できる
+        let todo_id = i32::MAX;
+        sqlx::query("delete from sync_todos where todo_id=$1;")
+            .bind(todo_id)
+            .execute(&pool)
+            .await
+            .expect("Failed to prepare sync data.");
+        let now = Utc::now();
+
+        // record
+        let first = repository
+            .record(todo_id, vec![(SyncField::Text, now)])
+            .await
+            .expect("[record] returned Err");
+        assert_eq!(first.version, 1);
+        assert_eq!(first.fields.text.version, 1);
+        assert_eq!(first.fields.completed, FieldClock::default());
+        let second = repository
+            .record(todo_id, vec![(SyncField::Completed, now)])
+            .await
+            .expect("[record] returned Err");
+        assert_eq!(second.version, 2);
+        assert!(second.cursor > first.cursor);
+        assert_eq!(second.fields.text, first.fields.text);
+
+        // find
+        let state = repository.find(todo_id).await.expect("[find] returned Err");
+        assert_eq!(state, Some(second.clone()));
+
+        // changes
+        let changes = repository
+            .changes(first.cursor, 100)
+            .await
+            .expect("[changes] returned Err");
+        assert!(changes.contains(&second));
+
+        // tombstone
+        let state = repository
+            .tombstone(todo_id, now)
+            .await
+            .expect("[tombstone] returned Err");
+        assert_eq!(state.version, 3);
+        assert!(state.deleted_at.is_some());
+
+        sqlx::query("delete from sync_todos where todo_id=$1;")
+            .bind(todo_id)
+            .execute(&pool)
+            .await
+            .expect("Failed to cleanup sync data.");
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    };
+
+    #[derive(Debug, Default)]
+    struct SyncData {
+        cursor: i64,
+        states: HashMap<i32, SyncState>,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct SyncRepositoryForMemory {
+        store: Arc<RwLock<SyncData>>,
+    }
+
+    impl Default for SyncRepositoryForMemory {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl SyncRepositoryForMemory {
+        pub fn new() -> Self {
+            SyncRepositoryForMemory {
+                store: Arc::default(),
+            }
+        }
+
+        fn write_store_ref(&self) -> RwLockWriteGuard<'_, SyncData> {
+            self.store.write().unwrap()
+        }
+
+        fn read_store_ref(&self) -> RwLockReadGuard<'_, SyncData> {
+            self.store.read().unwrap()
+        }
+
+        fn save(
+            &self,
+            todo_id: i32,
+            changed: &[(SyncField, DateTime<Utc>)],
+            deleted_at: Option<DateTime<Utc>>,
+        ) -> SyncState {
+            let mut store = self.write_store_ref();
+            store.cursor += 1;
+            let mut state = store
+                .states
+                .get(&todo_id)
+                .cloned()
+                .unwrap_or(SyncState::new(todo_id))
+                .advance(changed, deleted_at);
+            state.cursor = store.cursor;
+            store.states.insert(todo_id, state.clone());
+            state
+        }
+    }
+
+    #[async_trait]
+    impl SyncRepository for SyncRepositoryForMemory {
+        async fn find(&self, todo_id: i32) -> anyhow::Result<Option<SyncState>> {
+            let store = self.read_store_ref();
+            Ok(store.states.get(&todo_id).cloned())
+        }
+
+        async fn changes(&self, since: i64, limit: i64) -> anyhow::Result<Vec<SyncState>> {
+            let store = self.read_store_ref();
+            let mut states: Vec<SyncState> = store
+                .states
+                .values()
+                .filter(|state| state.cursor > since)
+                .cloned()
+                .collect();
+            states.sort_by_key(|state| state.cursor);
+            states.truncate(limit as usize);
+            Ok(states)
+        }
+
+        async fn record(
+            &self,
+            todo_id: i32,
+            changed: Vec<(SyncField, DateTime<Utc>)>,
+        ) -> anyhow::Result<SyncState> {
+            Ok(self.save(todo_id, &changed, None))
+        }
+
+        async fn tombstone(
+            &self,
+            todo_id: i32,
+            deleted_at: DateTime<Utc>,
+        ) -> anyhow::Result<SyncState> {
+            Ok(self.save(todo_id, &[], Some(deleted_at)))
+        }
+    }
+
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn sync_crud_scenario() {
+            let repository = SyncRepositoryForMemory::new();
+            let now = Utc::now();
+
+            // record
+            let first = repository
+                .record(1, vec![(SyncField::Text, now), (SyncField::Labels, now)])
+                .await
+                .unwrap();
+            assert_eq!(first.version, 1);
+            assert_eq!(first.cursor, 1);
+            assert_eq!(first.fields.labels.version, 1);
+            let other = repository
+                .record(2, vec![(SyncField::Text, now)])
+                .await
+                .unwrap();
+            let second = repository
+                .record(1, vec![(SyncField::Completed, now)])
+                .await
+                .unwrap();
+            assert_eq!(second.version, 2);
+            assert_eq!(second.cursor, 3);
+            assert_eq!(second.fields.text, first.fields.text);
+            assert_eq!(second.fields.completed.version, 2);
+
+            // find
+            assert_eq!(repository.find(1).await.unwrap(), Some(second.clone()));
+            assert_eq!(repository.find(3).await.unwrap(), None);
+
+            // changes
+            let changes = repository.changes(0, 100).await.unwrap();
+            assert_eq!(changes, vec![other.clone(), second.clone()]);
+            let changes = repository.changes(2, 100).await.unwrap();
+            assert_eq!(changes, vec![second]);
+            let changes = repository.changes(0, 1).await.unwrap();
+            assert_eq!(changes, vec![other]);
+
+            // tombstone
+            let state = repository.tombstone(1, now).await.unwrap();
+            assert_eq!(state.version, 3);
+            assert_eq!(state.deleted_at, Some(now));
+        }
+    }
+}
@@ -1,9 +1,21 @@
-use super::label::Label;
+use super::file::FileStore;
+use super::label::{
+    check_exclusive, check_reassign, sort_labels, DeleteLabel, Label, LabelInUseError,
+    LabelRepositoryForFile,
+};
+use super::project::DEFAULT_PROJECT_ID;
 use super::RepositoryError;
 use anyhow::Ok;
 use axum::async_trait;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool};
+use sqlx::{FromRow, MySql, MySqlPool, PgPool, QueryBuilder, SqlitePool};
+use std::{path::Path, sync::Arc};
+use thiserror::Error;
+pub use todo_types::{
+    CreateTodo, Priority, QuickAdd, QuickAddLabel, QuickAddPreview, TodoDependency, TodoEntity,
+    UpdateTodo,
+};
 
 #[derive(Debug, Clone)]
 pub struct TodoRepositoryForDb {
@@ -14,18 +26,59 @@ impl TodoRepositoryForDb {
     pub fn new(pool: PgPool) -> Self {
         TodoRepositoryForDb { pool }
     }
+
+    async fn check_labels(&self, labels: &[i32]) -> anyhow::Result<()> {
+        // 2件以上でなければ排他的なグループに違反しない
+        if labels.len() < 2 {
+            return Ok(());
+        }
+        let all = sqlx::query_as::<_, Label>("select * from labels;")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(check_exclusive(&all, labels)?)
+    }
+
+    // idがNoneの場合はすべてのTodoの依存関係を返す
+    async fn dependencies(&self, id: Option<i32>) -> anyhow::Result<Vec<DependencyFromRow>> {
+        let rows = sqlx::query_as::<_, DependencyFromRow>(
+            r#"
+                select d.todo_id, d.blocker_id, b.completed as blocker_completed
+                from todo_dependencies d
+                join todos b on b.id = d.blocker_id
+                where ($1::integer is null or d.todo_id=$1)
+                order by d.blocker_id asc;
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
 }
 
 #[async_trait]
 impl TodoRepository for TodoRepositoryForDb {
     async fn create(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity> {
+        self.check_labels(&payload.labels).await?;
+        let now = Utc::now();
         let tx = self.pool.begin().await?;
         let row = sqlx::query_as::<_, TodoFromRow>(
             r#"
-                insert into todos (text, completed) values ($1, false) returning *;
+                insert into todos
+                (text, notes, completed, project_id, status_id, priority, due_at,
+                created_at, updated_at, completed_at)
+                values ($1, $2, $3, $4, $5, $6, $7, $8, $8, $9) returning *;
             "#,
         )
         .bind(payload.text.clone())
+        .bind(payload.notes)
+        .bind(payload.completed)
+        .bind(payload.project_id.unwrap_or(DEFAULT_PROJECT_ID))
+        .bind(payload.status_id)
+        .bind(payload.priority)
+        .bind(payload.due_at)
+        .bind(now)
+        .bind(payload.completed.then_some(now))
         .fetch_one(&self.pool)
         .await?;
 
@@ -49,7 +102,13 @@ impl TodoRepository for TodoRepositoryForDb {
     async fn find(&self, id: i32) -> anyhow::Result<TodoEntity> {
         let items = sqlx::query_as::<_, TodoWithLabelFromRow>(
             r#"
-                select todos.*, labels.id as label_id, labels.name as label_name from todos
+                select todos.*, labels.id as label_id, labels.name as label_name,
+                labels.color as label_color, labels.description as label_description,
+                labels.icon as label_icon, labels.position as label_position,
+                labels.parent_id as label_parent_id, labels.exclusive as label_exclusive,
+                labels.created_at as label_created_at, labels.updated_at as label_updated_at,
+                (select count(*) from comments c where c.todo_id = todos.id) as comment_count
+                from todos
                 left outer join todo_labels tl on todos.id = tl.todo_id
                 left outer join labels on labels.id = tl.label_id
                 where todos.id=$1;
@@ -63,7 +122,8 @@ impl TodoRepository for TodoRepositoryForDb {
             _ => RepositoryError::Unexpected(e.to_string()),
         })?;
 
-        let todos = fold_entities(items);
+        let mut todos = fold_entities(items);
+        apply_dependencies(&mut todos, &self.dependencies(Some(id)).await?);
         let todo = todos.first().ok_or(RepositoryError::NotFound(id))?;
 
         Ok(todo.clone())
@@ -72,30 +132,79 @@ impl TodoRepository for TodoRepositoryForDb {
     async fn all(&self) -> anyhow::Result<Vec<TodoEntity>> {
         let items = sqlx::query_as::<_, TodoWithLabelFromRow>(
             r#"
-                select todos.*, labels.id as label_id, labels.name as label_name from todos
+                select todos.*, labels.id as label_id, labels.name as label_name,
+                labels.color as label_color, labels.description as label_description,
+                labels.icon as label_icon, labels.position as label_position,
+                labels.parent_id as label_parent_id, labels.exclusive as label_exclusive,
+                labels.created_at as label_created_at, labels.updated_at as label_updated_at,
+                (select count(*) from comments c where c.todo_id = todos.id) as comment_count
+                from todos
+                left outer join todo_labels tl on todos.id = tl.todo_id
+                left outer join labels on labels.id = tl.label_id
+                order by todos.id desc;
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut todos = fold_entities(items);
+        apply_dependencies(&mut todos, &self.dependencies(None).await?);
+        Ok(todos)
+    }
+
+    async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<TodoEntity>> {
+        let items = sqlx::query_as::<_, TodoWithLabelFromRow>(
+            r#"
+                select todos.*, labels.id as label_id, labels.name as label_name,
+                labels.color as label_color, labels.description as label_description,
+                labels.icon as label_icon, labels.position as label_position,
+                labels.parent_id as label_parent_id, labels.exclusive as label_exclusive,
+                labels.created_at as label_created_at, labels.updated_at as label_updated_at,
+                (select count(*) from comments c where c.todo_id = todos.id) as comment_count
+                from todos
                 left outer join todo_labels tl on todos.id = tl.todo_id
                 left outer join labels on labels.id = tl.label_id
+                where todos.project_id=$1
                 order by todos.id desc;
             "#,
         )
+        .bind(project_id)
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(fold_entities(items))
+        let mut todos = fold_entities(items);
+        apply_dependencies(&mut todos, &self.dependencies(None).await?);
+        Ok(todos)
     }
 
     async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity> {
+        if let Some(labels) = &payload.labels {
+            self.check_labels(labels).await?;
+        }
         let tx = self.pool.begin().await?;
 
         // todo update
         let old_todo = self.find(id).await?;
+        let completed = payload.completed.unwrap_or(old_todo.completed);
+        let now = Utc::now();
+        let completed_at =
+            completed_at((old_todo.completed, old_todo.completed_at), completed, now);
         sqlx::query(
             r#"
-                update todos set text=$1, completed=$2 where id=$3 returning *;
+                update todos set text=$1, notes=$2, completed=$3, project_id=$4, status_id=$5,
+                priority=$6, due_at=$7, completed_at=$8, updated_at=$9
+                where id=$10 returning *;
             "#,
         )
         .bind(payload.text.unwrap_or(old_todo.text))
-        .bind(payload.completed.unwrap_or(old_todo.completed))
+        .bind(payload.notes.unwrap_or(old_todo.notes))
+        .bind(completed)
+        .bind(payload.project_id.unwrap_or(old_todo.project_id))
+        .bind(payload.status_id.unwrap_or(old_todo.status_id))
+        .bind(payload.priority.unwrap_or(old_todo.priority))
+        .bind(payload.due_at.unwrap_or(old_todo.due_at))
+        .bind(completed_at)
+        .bind(now)
         .bind(id)
         .fetch_one(&self.pool)
         .await?;
@@ -147,6 +256,20 @@ impl TodoRepository for TodoRepositoryForDb {
             _ => RepositoryError::Unexpected(e.to_string()),
         })?;
 
+        // todo's comment delete
+        sqlx::query(
+            r#"
+                delete from comments where todo_id=$1;
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => RepositoryError::NotFound(id),
+            _ => RepositoryError::Unexpected(e.to_string()),
+        })?;
+
         // todo delete
         sqlx::query(
             r#"
@@ -165,210 +288,2213 @@ impl TodoRepository for TodoRepositoryForDb {
 
         Ok(())
     }
-}
 
-#[async_trait]
-pub trait TodoRepository: Clone + std::marker::Send + std::marker::Sync + 'static {
-    async fn create(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity>;
-    async fn find(&self, id: i32) -> anyhow::Result<TodoEntity>;
-    async fn all(&self) -> anyhow::Result<Vec<TodoEntity>>;
-    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity>;
-    async fn delete(&self, id: i32) -> anyhow::Result<()>;
+    async fn merge_labels(&self, target: i32, sources: &[i32]) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+                select id from labels where id=$1;
+            "#,
+        )
+        .bind(target)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(RepositoryError::NotFound(target))?;
+
+        let now = Utc::now();
+        for source in sources.iter().copied() {
+            sqlx::query(
+                r#"
+                    update todos set updated_at=$1
+                    where id in (select todo_id from todo_labels where label_id=$2);
+                "#,
+            )
+            .bind(now)
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            // 付け替え先のラベルがすでに付いているTodoには追加しない
+            sqlx::query(
+                r#"
+                    insert into todo_labels (todo_id, label_id)
+                    select distinct todo_id, $1 from todo_labels
+                    where label_id=$2 and todo_id not in (
+                        select todo_id from todo_labels where label_id=$1
+                    );
+                "#,
+            )
+            .bind(target)
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(
+                r#"
+                    delete from todo_labels where label_id=$1;
+                "#,
+            )
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            // 子のラベルは付け替え先の子にする
+            sqlx::query(
+                r#"
+                    update labels set parent_id=$1 where parent_id=$2 and id<>$1;
+                "#,
+            )
+            .bind(target)
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            let result = sqlx::query(
+                r#"
+                    delete from labels where id=$1;
+                "#,
+            )
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            // コミットせずに戻るとロールバックされる
+            if result.rows_affected() == 0 {
+                return Err(RepositoryError::NotFound(source).into());
+            }
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn add_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        let mut tx = self.pool.begin().await?;
+        // 逆向きの依存関係を同時に追加して循環しないよう、依存関係の追加を1つずつ行う
+        sqlx::query("lock table todo_dependencies in share row exclusive mode;")
+            .execute(&mut *tx)
+            .await?;
+        for todo_id in [id, blocker_id] {
+            sqlx::query(
+                r#"
+                    select id from todos where id=$1;
+                "#,
+            )
+            .bind(todo_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(RepositoryError::NotFound(todo_id))?;
+        }
+        // ブロッカーが完了を待っているTodoを間接的なものも含めて辿る
+        let (cycle,) = sqlx::query_as::<_, (bool,)>(
+            r#"
+                with recursive waiting (id) as (
+                    select $2
+                    union
+                    select d.blocker_id from todo_dependencies d join waiting w on d.todo_id = w.id
+                )
+                select exists (select 1 from waiting where id=$1);
+            "#,
+        )
+        .bind(id)
+        .bind(blocker_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if cycle {
+            return Err(DependencyCycleError {
+                todo_id: id,
+                blocker_id,
+            }
+            .into());
+        }
+        let result = sqlx::query(
+            r#"
+                insert into todo_dependencies (todo_id, blocker_id) values ($1, $2)
+                on conflict do nothing;
+            "#,
+        )
+        .bind(id)
+        .bind(blocker_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::Duplicate(blocker_id).into());
+        }
+        tx.commit().await?;
+
+        self.find(id).await
+    }
+
+    async fn remove_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        let result = sqlx::query(
+            r#"
+                delete from todo_dependencies where todo_id=$1 and blocker_id=$2;
+            "#,
+        )
+        .bind(id)
+        .bind(blocker_id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(blocker_id).into());
+        }
+
+        self.find(id).await
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
-pub struct TodoFromRow {
-    id: i32,
-    text: String,
-    completed: bool,
+/// ローカルモード用のSQLite実装
+///
+/// コメントは扱わないため `comment_count` は常に0になる
+#[derive(Debug, Clone)]
+pub struct TodoRepositoryForSqlite {
+    pool: SqlitePool,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
-pub struct TodoWithLabelFromRow {
-    id: i32,
-    text: String,
-    completed: bool,
-    label_id: Option<i32>,
-    label_name: Option<String>,
+impl TodoRepositoryForSqlite {
+    pub fn new(pool: SqlitePool) -> Self {
+        TodoRepositoryForSqlite { pool }
+    }
+
+    async fn check_labels(&self, labels: &[i32]) -> anyhow::Result<()> {
+        if labels.len() < 2 {
+            return Ok(());
+        }
+        let all = sqlx::query_as::<_, Label>("select * from labels;")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(check_exclusive(&all, labels)?)
+    }
+
+    // SQLiteには unnest がないため1件ずつ追加する
+    async fn insert_labels(
+        tx: &mut sqlx::SqliteConnection,
+        todo_id: i32,
+        labels: Vec<i32>,
+    ) -> anyhow::Result<()> {
+        for label_id in labels {
+            sqlx::query(
+                r#"
+                    insert into todo_labels (todo_id, label_id) values ($1, $2);
+                "#,
+            )
+            .bind(todo_id)
+            .bind(label_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn fetch(
+        &self,
+        id: Option<i32>,
+        project_id: Option<i32>,
+    ) -> anyhow::Result<Vec<TodoEntity>> {
+        let items = sqlx::query_as::<_, TodoWithLabelFromRow>(
+            r#"
+                select todos.*, labels.id as label_id, labels.name as label_name,
+                labels.color as label_color, labels.description as label_description,
+                labels.icon as label_icon, labels.position as label_position,
+                labels.parent_id as label_parent_id, labels.exclusive as label_exclusive,
+                labels.created_at as label_created_at, labels.updated_at as label_updated_at,
+                0 as comment_count
+                from todos
+                left outer join todo_labels tl on todos.id = tl.todo_id
+                left outer join labels on labels.id = tl.label_id
+                where ($1 is null or todos.id=$1) and ($2 is null or todos.project_id=$2)
+                order by todos.id desc, tl.id asc;
+            "#,
+        )
+        .bind(id)
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let dependencies = sqlx::query_as::<_, DependencyFromRow>(
+            r#"
+                select d.todo_id, d.blocker_id, b.completed as blocker_completed
+                from todo_dependencies d
+                join todos b on b.id = d.blocker_id
+                where ($1 is null or d.todo_id=$1)
+                order by d.blocker_id asc;
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut todos = fold_entities(items);
+        apply_dependencies(&mut todos, &dependencies);
+        Ok(todos)
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct TodoEntity {
-    pub id: i32,
-    pub text: String,
-    pub completed: bool,
-    pub labels: Vec<Label>,
-}
+#[async_trait]
+impl TodoRepository for TodoRepositoryForSqlite {
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity> {
+        self.check_labels(&payload.labels).await?;
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        let row = sqlx::query_as::<_, TodoFromRow>(
+            r#"
+                insert into todos
+                (text, notes, completed, project_id, status_id, priority, due_at,
+                created_at, updated_at, completed_at)
+                values ($1, $2, $3, $4, $5, $6, $7, $8, $8, $9) returning *;
+            "#,
+        )
+        .bind(payload.text.clone())
+        .bind(payload.notes)
+        .bind(payload.completed)
+        .bind(payload.project_id.unwrap_or(DEFAULT_PROJECT_ID))
+        .bind(payload.status_id)
+        .bind(payload.priority)
+        .bind(payload.due_at)
+        .bind(now)
+        .bind(payload.completed.then_some(now))
+        .fetch_one(&mut *tx)
+        .await?;
+
+        Self::insert_labels(&mut tx, row.id, payload.labels).await?;
+        tx.commit().await?;
+
+        self.find(row.id).await
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<TodoEntity> {
+        let todos = self.fetch(Some(id), None).await?;
+        let todo = todos.first().ok_or(RepositoryError::NotFound(id))?;
+        Ok(todo.clone())
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<TodoEntity>> {
+        self.fetch(None, None).await
+    }
+
+    async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<TodoEntity>> {
+        self.fetch(None, Some(project_id)).await
+    }
+
+    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity> {
+        let old_todo = self.find(id).await?;
+        let completed = payload.completed.unwrap_or(old_todo.completed);
+        let now = Utc::now();
+        let completed_at =
+            completed_at((old_todo.completed, old_todo.completed_at), completed, now);
+        if let Some(labels) = &payload.labels {
+            self.check_labels(labels).await?;
+        }
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+                update todos set text=$1, notes=$2, completed=$3, project_id=$4, status_id=$5,
+                priority=$6, due_at=$7, completed_at=$8, updated_at=$9
+                where id=$10;
+            "#,
+        )
+        .bind(payload.text.unwrap_or(old_todo.text))
+        .bind(payload.notes.unwrap_or(old_todo.notes))
+        .bind(completed)
+        .bind(payload.project_id.unwrap_or(old_todo.project_id))
+        .bind(payload.status_id.unwrap_or(old_todo.status_id))
+        .bind(payload.priority.unwrap_or(old_todo.priority))
+        .bind(payload.due_at.unwrap_or(old_todo.due_at))
+        .bind(completed_at)
+        .bind(now)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(labels) = payload.labels {
+            sqlx::query(
+                r#"
+                    delete from todo_labels where todo_id=$1;
+                "#,
+            )
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+            Self::insert_labels(&mut tx, id, labels).await?;
+        }
+        tx.commit().await?;
+
+        self.find(id).await
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        // todo_labels と todo_dependencies は外部キーのカスケードで削除される
+        let result = sqlx::query(
+            r#"
+                delete from todos where id=$1;
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(())
+    }
+
+    async fn merge_labels(&self, target: i32, sources: &[i32]) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+                select id from labels where id=$1;
+            "#,
+        )
+        .bind(target)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(RepositoryError::NotFound(target))?;
+
+        let now = Utc::now();
+        for source in sources.iter().copied() {
+            sqlx::query(
+                r#"
+                    update todos set updated_at=$1
+                    where id in (select todo_id from todo_labels where label_id=$2);
+                "#,
+            )
+            .bind(now)
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            // 付け替え先のラベルがすでに付いているTodoには追加しない
+            sqlx::query(
+                r#"
+                    insert into todo_labels (todo_id, label_id)
+                    select distinct todo_id, $1 from todo_labels
+                    where label_id=$2 and todo_id not in (
+                        select todo_id from todo_labels where label_id=$1
+                    );
+                "#,
+            )
+            .bind(target)
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(
+                r#"
+                    delete from todo_labels where label_id=$1;
+                "#,
+            )
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            // 子のラベルは付け替え先の子にする
+            sqlx::query(
+                r#"
+                    update labels set parent_id=$1 where parent_id=$2 and id<>$1;
+                "#,
+            )
+            .bind(target)
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            let result = sqlx::query(
+                r#"
+                    delete from labels where id=$1;
+                "#,
+            )
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            // コミットせずに戻るとロールバックされる
+            if result.rows_affected() == 0 {
+                return Err(RepositoryError::NotFound(source).into());
+            }
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn add_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        // 接続は1本なので、トランザクションの間は他の依存関係の追加を待たせる
+        let mut tx = self.pool.begin().await?;
+        for todo_id in [id, blocker_id] {
+            sqlx::query(
+                r#"
+                    select id from todos where id=$1;
+                "#,
+            )
+            .bind(todo_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(RepositoryError::NotFound(todo_id))?;
+        }
+        let (cycle,) = sqlx::query_as::<_, (bool,)>(
+            r#"
+                with recursive waiting (id) as (
+                    select $2
+                    union
+                    select d.blocker_id from todo_dependencies d join waiting w on d.todo_id = w.id
+                )
+                select exists (select 1 from waiting where id=$1);
+            "#,
+        )
+        .bind(id)
+        .bind(blocker_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if cycle {
+            return Err(DependencyCycleError {
+                todo_id: id,
+                blocker_id,
+            }
+            .into());
+        }
+        let result = sqlx::query(
+            r#"
+                insert or ignore into todo_dependencies (todo_id, blocker_id) values ($1, $2);
+            "#,
+        )
+        .bind(id)
+        .bind(blocker_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::Duplicate(blocker_id).into());
+        }
+        tx.commit().await?;
+
+        self.find(id).await
+    }
+
+    async fn remove_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        let result = sqlx::query(
+            r#"
+                delete from todo_dependencies where todo_id=$1 and blocker_id=$2;
+            "#,
+        )
+        .bind(id)
+        .bind(blocker_id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(blocker_id).into());
+        }
+
+        self.find(id).await
+    }
+}
+
+/// MySQL/MariaDB用の実装
+///
+/// `returning` と `unnest` が使えないため、採番したIDは `last_insert_id` で受け取り、
+/// ラベルの紐付けは複数行の `values` でまとめて追加する。
+/// コメントは扱わないため `comment_count` は常に0になる
+#[derive(Debug, Clone)]
+pub struct TodoRepositoryForMySql {
+    pool: MySqlPool,
+}
+
+impl TodoRepositoryForMySql {
+    pub fn new(pool: MySqlPool) -> Self {
+        TodoRepositoryForMySql { pool }
+    }
+
+    async fn check_labels(&self, labels: &[i32]) -> anyhow::Result<()> {
+        if labels.len() < 2 {
+            return Ok(());
+        }
+        let all = sqlx::query_as::<_, Label>("select * from labels;")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(check_exclusive(&all, labels)?)
+    }
+
+    async fn insert_labels(
+        tx: &mut sqlx::MySqlConnection,
+        todo_id: i32,
+        labels: Vec<i32>,
+    ) -> anyhow::Result<()> {
+        if labels.is_empty() {
+            return Ok(());
+        }
+        let mut query = QueryBuilder::<MySql>::new("insert into todo_labels (todo_id, label_id) ");
+        query.push_values(labels, |mut row, label_id| {
+            row.push_bind(todo_id).push_bind(label_id);
+        });
+        query.build().execute(&mut *tx).await?;
+        Ok(())
+    }
+
+    async fn fetch(
+        &self,
+        id: Option<i32>,
+        project_id: Option<i32>,
+    ) -> anyhow::Result<Vec<TodoEntity>> {
+        // MySQLのプレースホルダは位置で対応するため、同じ値を2回bindする
+        let items = sqlx::query_as::<_, TodoWithLabelFromRow>(
+            r#"
+                select todos.*, labels.id as label_id, labels.name as label_name,
+                labels.color as label_color, labels.description as label_description,
+                labels.icon as label_icon, labels.position as label_position,
+                labels.parent_id as label_parent_id, labels.exclusive as label_exclusive,
+                labels.created_at as label_created_at, labels.updated_at as label_updated_at,
+                cast(0 as signed) as comment_count
+                from todos
+                left outer join todo_labels tl on todos.id = tl.todo_id
+                left outer join labels on labels.id = tl.label_id
+                where (? is null or todos.id=?) and (? is null or todos.project_id=?)
+                order by todos.id desc, tl.id asc;
+            "#,
+        )
+        .bind(id)
+        .bind(id)
+        .bind(project_id)
+        .bind(project_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let dependencies = sqlx::query_as::<_, DependencyFromRow>(
+            r#"
+                select d.todo_id, d.blocker_id, b.completed as blocker_completed
+                from todo_dependencies d
+                join todos b on b.id = d.blocker_id
+                where (? is null or d.todo_id=?)
+                order by d.blocker_id asc;
+            "#,
+        )
+        .bind(id)
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut todos = fold_entities(items);
+        apply_dependencies(&mut todos, &dependencies);
+        Ok(todos)
+    }
+}
+
+#[async_trait]
+impl TodoRepository for TodoRepositoryForMySql {
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity> {
+        self.check_labels(&payload.labels).await?;
+        let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            r#"
+                insert into todos
+                (text, notes, completed, project_id, status_id, priority, due_at,
+                created_at, updated_at, completed_at)
+                values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
+            "#,
+        )
+        .bind(payload.text.clone())
+        .bind(payload.notes)
+        .bind(payload.completed)
+        .bind(payload.project_id.unwrap_or(DEFAULT_PROJECT_ID))
+        .bind(payload.status_id)
+        .bind(payload.priority)
+        .bind(payload.due_at)
+        .bind(now)
+        .bind(now)
+        .bind(payload.completed.then_some(now))
+        .execute(&mut *tx)
+        .await?;
+        let id = i32::try_from(result.last_insert_id())?;
+
+        Self::insert_labels(&mut tx, id, payload.labels).await?;
+        tx.commit().await?;
+
+        self.find(id).await
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<TodoEntity> {
+        let todos = self.fetch(Some(id), None).await?;
+        let todo = todos.first().ok_or(RepositoryError::NotFound(id))?;
+        Ok(todo.clone())
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<TodoEntity>> {
+        self.fetch(None, None).await
+    }
+
+    async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<TodoEntity>> {
+        self.fetch(None, Some(project_id)).await
+    }
+
+    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity> {
+        let old_todo = self.find(id).await?;
+        let completed = payload.completed.unwrap_or(old_todo.completed);
+        let now = Utc::now();
+        let completed_at =
+            completed_at((old_todo.completed, old_todo.completed_at), completed, now);
+        if let Some(labels) = &payload.labels {
+            self.check_labels(labels).await?;
+        }
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+                update todos set text=?, notes=?, completed=?, project_id=?, status_id=?,
+                priority=?, due_at=?, completed_at=?, updated_at=?
+                where id=?;
+            "#,
+        )
+        .bind(payload.text.unwrap_or(old_todo.text))
+        .bind(payload.notes.unwrap_or(old_todo.notes))
+        .bind(completed)
+        .bind(payload.project_id.unwrap_or(old_todo.project_id))
+        .bind(payload.status_id.unwrap_or(old_todo.status_id))
+        .bind(payload.priority.unwrap_or(old_todo.priority))
+        .bind(payload.due_at.unwrap_or(old_todo.due_at))
+        .bind(completed_at)
+        .bind(now)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(labels) = payload.labels {
+            sqlx::query(
+                r#"
+                    delete from todo_labels where todo_id=?;
+                "#,
+            )
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+            Self::insert_labels(&mut tx, id, labels).await?;
+        }
+        tx.commit().await?;
+
+        self.find(id).await
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        // todo_labels と todo_dependencies は外部キーのカスケードで削除される
+        let result = sqlx::query(
+            r#"
+                delete from todos where id=?;
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(())
+    }
+
+    async fn merge_labels(&self, target: i32, sources: &[i32]) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+                select id from labels where id=?;
+            "#,
+        )
+        .bind(target)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(RepositoryError::NotFound(target))?;
+
+        let now = Utc::now();
+        for source in sources.iter().copied() {
+            sqlx::query(
+                r#"
+                    update todos set updated_at=?
+                    where id in (select todo_id from todo_labels where label_id=?);
+                "#,
+            )
+            .bind(now)
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            // 付け替え先のラベルがすでに付いているTodoには追加しない
+            sqlx::query(
+                r#"
+                    insert into todo_labels (todo_id, label_id)
+                    select distinct todo_id, ? from todo_labels
+                    where label_id=? and todo_id not in (
+                        select todo_id from todo_labels where label_id=?
+                    );
+                "#,
+            )
+            .bind(target)
+            .bind(source)
+            .bind(target)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(
+                r#"
+                    delete from todo_labels where label_id=?;
+                "#,
+            )
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            // 子のラベルは付け替え先の子にする
+            sqlx::query(
+                r#"
+                    update labels set parent_id=? where parent_id=? and id<>?;
+                "#,
+            )
+            .bind(target)
+            .bind(source)
+            .bind(target)
+            .execute(&mut *tx)
+            .await?;
+            let result = sqlx::query(
+                r#"
+                    delete from labels where id=?;
+                "#,
+            )
+            .bind(source)
+            .execute(&mut *tx)
+            .await?;
+            // コミットせずに戻るとロールバックされる
+            if result.rows_affected() == 0 {
+                return Err(RepositoryError::NotFound(source).into());
+            }
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn add_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        let mut tx = self.pool.begin().await?;
+        // 逆向きの依存関係を同時に追加して循環しないよう、Todoの行をロックして1つずつ行う
+        let ids: Vec<(i32,)> = sqlx::query_as(
+            r#"
+                select id from todos for update;
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        for todo_id in [id, blocker_id] {
+            if !ids.contains(&(todo_id,)) {
+                return Err(RepositoryError::NotFound(todo_id).into());
+            }
+        }
+        // ブロッカーが完了を待っているTodoを間接的なものも含めて辿る
+        let (cycle,) = sqlx::query_as::<_, (i64,)>(
+            r#"
+                with recursive waiting (id) as (
+                    select cast(? as signed)
+                    union
+                    select d.blocker_id from todo_dependencies d join waiting w on d.todo_id = w.id
+                )
+                select count(*) from waiting where id=?;
+            "#,
+        )
+        .bind(blocker_id)
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if cycle > 0 {
+            return Err(DependencyCycleError {
+                todo_id: id,
+                blocker_id,
+            }
+            .into());
+        }
+        let result = sqlx::query(
+            r#"
+                insert ignore into todo_dependencies (todo_id, blocker_id) values (?, ?);
+            "#,
+        )
+        .bind(id)
+        .bind(blocker_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::Duplicate(blocker_id).into());
+        }
+        tx.commit().await?;
+
+        self.find(id).await
+    }
+
+    async fn remove_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        let result = sqlx::query(
+            r#"
+                delete from todo_dependencies where todo_id=? and blocker_id=?;
+            "#,
+        )
+        .bind(id)
+        .bind(blocker_id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(blocker_id).into());
+        }
+
+        self.find(id).await
+    }
+}
+
+// ファイルに保存する1件分。ラベルはIDだけを持ち、読み出す時に解決する
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TodoRecord {
+    id: i32,
+    text: String,
+    #[serde(default)]
+    notes: Option<String>,
+    completed: bool,
+    project_id: i32,
+    status_id: Option<i32>,
+    labels: Vec<i32>,
+    #[serde(default)]
+    priority: Option<Priority>,
+    #[serde(default)]
+    due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    blocked_by: Vec<i32>,
+    // 日時を記録する前に保存したTodoは作成日時がUNIX_EPOCHになる
+    #[serde(default)]
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    updated_at: DateTime<Utc>,
+    #[serde(default)]
+    completed_at: Option<DateTime<Utc>>,
+}
+
+/// ファイルに保存する実装 (DBを用意せずにサーバーを動かす場合に使う)
+///
+/// コメントは扱わないため `comment_count` は常に0になる
+#[derive(Debug, Clone)]
+pub struct TodoRepositoryForFile {
+    store: FileStore<TodoRecord>,
+    labels: FileStore<Label>,
+    dependency_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl TodoRepositoryForFile {
+    /// ラベルは同じディレクトリの `LabelRepositoryForFile` から解決する
+    pub fn open(dir: impl AsRef<Path>, labels: &LabelRepositoryForFile) -> anyhow::Result<Self> {
+        Ok(Self {
+            store: FileStore::open(dir, "todos")?,
+            labels: labels.store(),
+            dependency_lock: Arc::default(),
+        })
+    }
+
+    // 削除されたラベルとブロッカーは外す
+    fn entity(&self, record: TodoRecord) -> TodoEntity {
+        let blockers: Vec<TodoRecord> = record
+            .blocked_by
+            .iter()
+            .filter_map(|id| self.store.get(*id))
+            .collect();
+        let mut todo = TodoEntity {
+            id: record.id,
+            text: record.text,
+            notes: record.notes,
+            completed: record.completed,
+            project_id: record.project_id,
+            status_id: record.status_id,
+            labels: record
+                .labels
+                .iter()
+                .filter_map(|id| self.labels.get(*id))
+                .collect(),
+            priority: record.priority,
+            due_at: record.due_at,
+            blocked_by: blockers.iter().map(|blocker| blocker.id).collect(),
+            blocked: blockers.iter().any(|blocker| !blocker.completed),
+            comment_count: 0,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            completed_at: record.completed_at,
+        };
+        sort_labels(&mut todo.labels);
+        todo
+    }
+
+    fn validate_labels(&self, labels: &[i32]) -> anyhow::Result<()> {
+        match labels.iter().find(|id| self.labels.get(**id).is_none()) {
+            Some(id) => Err(RepositoryError::NotFound(*id).into()),
+            None => Ok(check_exclusive(&self.labels.values(), labels)?),
+        }
+    }
+
+    fn records(&self) -> impl Iterator<Item = TodoRecord> {
+        self.store.values().into_iter().rev()
+    }
+
+    /// `from` のラベルを使っているTodoを `mode` に従って更新し、更新したTodoのIDを返す
+    ///
+    /// `LabelRepositoryForFile` がラベルを削除する前に呼ぶ。途中で失敗すると戻せないため、書き込む前にすべて確認する
+    pub(crate) async fn replace_label(
+        &self,
+        from: i32,
+        mode: DeleteLabel,
+    ) -> anyhow::Result<Vec<i32>> {
+        let records: Vec<TodoRecord> = self
+            .store
+            .values()
+            .into_iter()
+            .filter(|record| record.labels.contains(&from))
+            .collect();
+        match mode {
+            DeleteLabel::Restrict if !records.is_empty() => {
+                let usage = records.len();
+                return Err(LabelInUseError {
+                    label_id: from,
+                    usage,
+                }
+                .into());
+            }
+            DeleteLabel::Reassign(to) => {
+                let todo_labels: Vec<(i32, i32)> = records
+                    .iter()
+                    .flat_map(|record| record.labels.iter().map(|id| (record.id, *id)))
+                    .collect();
+                check_reassign(&self.labels.values(), &todo_labels, from, to)?;
+            }
+            _ => {}
+        }
+
+        let now = Utc::now();
+        let mut todo_ids = vec![];
+        for record in records {
+            let updated = self
+                .store
+                .update(record.id, move |record| {
+                    if !record.labels.contains(&from) {
+                        return Ok(());
+                    }
+                    record.labels.retain(|id| *id != from);
+                    if let DeleteLabel::Reassign(to) = mode {
+                        if !record.labels.contains(&to) {
+                            record.labels.push(to);
+                        }
+                    }
+                    record.updated_at = now;
+                    Ok(())
+                })
+                .await?;
+            // 確認した後に削除されたTodoは更新しない
+            if updated.is_some() {
+                todo_ids.push(record.id);
+            }
+        }
+        Ok(todo_ids)
+    }
+}
+
+#[async_trait]
+impl TodoRepository for TodoRepositoryForFile {
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity> {
+        self.validate_labels(&payload.labels)?;
+        let now = Utc::now();
+        let record = self
+            .store
+            .insert(move |id| TodoRecord {
+                id,
+                text: payload.text,
+                notes: payload.notes,
+                completed: payload.completed,
+                project_id: payload.project_id.unwrap_or(DEFAULT_PROJECT_ID),
+                status_id: payload.status_id,
+                labels: payload.labels,
+                priority: payload.priority,
+                due_at: payload.due_at,
+                blocked_by: vec![],
+                created_at: now,
+                updated_at: now,
+                completed_at: payload.completed.then_some(now),
+            })
+            .await?;
+        Ok(self.entity(record))
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<TodoEntity> {
+        let record = self.store.get(id).ok_or(RepositoryError::NotFound(id))?;
+        Ok(self.entity(record))
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<TodoEntity>> {
+        Ok(self.records().map(|record| self.entity(record)).collect())
+    }
+
+    async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<TodoEntity>> {
+        Ok(self
+            .records()
+            .filter(|record| record.project_id == project_id)
+            .map(|record| self.entity(record))
+            .collect())
+    }
+
+    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity> {
+        if let Some(labels) = &payload.labels {
+            self.validate_labels(labels)?;
+        }
+        let now = Utc::now();
+        let record = self
+            .store
+            .update(id, move |record| {
+                if let Some(labels) = payload.labels {
+                    record.labels = labels;
+                }
+                if let Some(text) = payload.text {
+                    record.text = text;
+                }
+                if let Some(notes) = payload.notes {
+                    record.notes = notes;
+                }
+                let completed = payload.completed.unwrap_or(record.completed);
+                record.completed_at =
+                    completed_at((record.completed, record.completed_at), completed, now);
+                record.updated_at = now;
+                record.completed = completed;
+                record.project_id = payload.project_id.unwrap_or(record.project_id);
+                record.status_id = payload.status_id.unwrap_or(record.status_id);
+                record.priority = payload.priority.unwrap_or(record.priority);
+                record.due_at = payload.due_at.unwrap_or(record.due_at);
+                Ok(())
+            })
+            .await?
+            .ok_or(RepositoryError::NotFound(id))?;
+        Ok(self.entity(record))
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        self.store
+            .remove(id)
+            .await?
+            .ok_or(RepositoryError::NotFound(id))?;
+        Ok(())
+    }
+
+    async fn merge_labels(&self, target: i32, sources: &[i32]) -> anyhow::Result<()> {
+        // ファイルは途中で失敗すると戻せないため、書き込む前にすべて確認する
+        for id in std::iter::once(&target).chain(sources) {
+            self.labels.get(*id).ok_or(RepositoryError::NotFound(*id))?;
+        }
+        let now = Utc::now();
+        for record in self.store.values() {
+            if !record.labels.iter().any(|id| sources.contains(id)) {
+                continue;
+            }
+            let sources = sources.to_vec();
+            self.store
+                .update(record.id, move |record| {
+                    record.updated_at = now;
+                    record.labels.retain(|id| !sources.contains(id));
+                    if !record.labels.contains(&target) {
+                        record.labels.push(target);
+                    }
+                    Ok(())
+                })
+                .await?;
+        }
+        for label in self.labels.values() {
+            if label
+                .parent_id
+                .is_some_and(|parent_id| sources.contains(&parent_id))
+            {
+                self.labels
+                    .update(label.id, move |label| {
+                        label.parent_id = (label.id != target).then_some(target);
+                        Ok(())
+                    })
+                    .await?;
+            }
+        }
+        for id in sources {
+            self.labels.remove(*id).await?;
+        }
+        Ok(())
+    }
+
+    async fn add_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        // 循環の確認から書き込みまでの間に、他の依存関係が追加されないようにする
+        let _guard = self.dependency_lock.lock().await;
+        self.store.get(id).ok_or(RepositoryError::NotFound(id))?;
+        self.store
+            .get(blocker_id)
+            .ok_or(RepositoryError::NotFound(blocker_id))?;
+        check_cycle(id, blocker_id, |current| {
+            self.store
+                .get(current)
+                .map(|record| record.blocked_by)
+                .unwrap_or_default()
+        })?;
+        let record = self
+            .store
+            .update(id, move |record| {
+                if record.blocked_by.contains(&blocker_id) {
+                    return Err(RepositoryError::Duplicate(blocker_id).into());
+                }
+                record.blocked_by.push(blocker_id);
+                record.blocked_by.sort_unstable();
+                Ok(())
+            })
+            .await?
+            .ok_or(RepositoryError::NotFound(id))?;
+        Ok(self.entity(record))
+    }
+
+    async fn remove_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        let record = self
+            .store
+            .update(id, move |record| {
+                if !record.blocked_by.contains(&blocker_id) {
+                    return Err(RepositoryError::NotFound(blocker_id).into());
+                }
+                record.blocked_by.retain(|id| *id != blocker_id);
+                Ok(())
+            })
+            .await?
+            .ok_or(RepositoryError::NotFound(id))?;
+        Ok(self.entity(record))
+    }
+}
+
+/// Secretsの設定で選択されるTodoの保存先
+#[derive(Debug, Clone)]
+pub enum TodoRepositoryBackend {
+    Db(TodoRepositoryForDb),
+    MySql(TodoRepositoryForMySql),
+    Sqlite(TodoRepositoryForSqlite),
+    File(TodoRepositoryForFile),
+}
+
+#[async_trait]
+impl TodoRepository for TodoRepositoryBackend {
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity> {
+        match self {
+            TodoRepositoryBackend::Db(repository) => repository.create(payload).await,
+            TodoRepositoryBackend::MySql(repository) => repository.create(payload).await,
+            TodoRepositoryBackend::Sqlite(repository) => repository.create(payload).await,
+            TodoRepositoryBackend::File(repository) => repository.create(payload).await,
+        }
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<TodoEntity> {
+        match self {
+            TodoRepositoryBackend::Db(repository) => repository.find(id).await,
+            TodoRepositoryBackend::MySql(repository) => repository.find(id).await,
+            TodoRepositoryBackend::Sqlite(repository) => repository.find(id).await,
+            TodoRepositoryBackend::File(repository) => repository.find(id).await,
+        }
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<TodoEntity>> {
+        match self {
+            TodoRepositoryBackend::Db(repository) => repository.all().await,
+            TodoRepositoryBackend::MySql(repository) => repository.all().await,
+            TodoRepositoryBackend::Sqlite(repository) => repository.all().await,
+            TodoRepositoryBackend::File(repository) => repository.all().await,
+        }
+    }
+
+    async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<TodoEntity>> {
+        match self {
+            TodoRepositoryBackend::Db(repository) => repository.all_in_project(project_id).await,
+            TodoRepositoryBackend::MySql(repository) => repository.all_in_project(project_id).await,
+            TodoRepositoryBackend::Sqlite(repository) => {
+                repository.all_in_project(project_id).await
+            }
+            TodoRepositoryBackend::File(repository) => repository.all_in_project(project_id).await,
+        }
+    }
+
+    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity> {
+        match self {
+            TodoRepositoryBackend::Db(repository) => repository.update(id, payload).await,
+            TodoRepositoryBackend::MySql(repository) => repository.update(id, payload).await,
+            TodoRepositoryBackend::Sqlite(repository) => repository.update(id, payload).await,
+            TodoRepositoryBackend::File(repository) => repository.update(id, payload).await,
+        }
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        match self {
+            TodoRepositoryBackend::Db(repository) => repository.delete(id).await,
+            TodoRepositoryBackend::MySql(repository) => repository.delete(id).await,
+            TodoRepositoryBackend::Sqlite(repository) => repository.delete(id).await,
+            TodoRepositoryBackend::File(repository) => repository.delete(id).await,
+        }
+    }
+
+    async fn merge_labels(&self, target: i32, sources: &[i32]) -> anyhow::Result<()> {
+        match self {
+            TodoRepositoryBackend::Db(repository) => repository.merge_labels(target, sources).await,
+            TodoRepositoryBackend::MySql(repository) => {
+                repository.merge_labels(target, sources).await
+            }
+            TodoRepositoryBackend::Sqlite(repository) => {
+                repository.merge_labels(target, sources).await
+            }
+            TodoRepositoryBackend::File(repository) => {
+                repository.merge_labels(target, sources).await
+            }
+        }
+    }
+
+    async fn add_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        match self {
+            TodoRepositoryBackend::Db(repository) => {
+                repository.add_dependency(id, blocker_id).await
+            }
+            TodoRepositoryBackend::MySql(repository) => {
+                repository.add_dependency(id, blocker_id).await
+            }
+            TodoRepositoryBackend::Sqlite(repository) => {
+                repository.add_dependency(id, blocker_id).await
+            }
+            TodoRepositoryBackend::File(repository) => {
+                repository.add_dependency(id, blocker_id).await
+            }
+        }
+    }
+
+    async fn remove_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+        match self {
+            TodoRepositoryBackend::Db(repository) => {
+                repository.remove_dependency(id, blocker_id).await
+            }
+            TodoRepositoryBackend::MySql(repository) => {
+                repository.remove_dependency(id, blocker_id).await
+            }
+            TodoRepositoryBackend::Sqlite(repository) => {
+                repository.remove_dependency(id, blocker_id).await
+            }
+            TodoRepositoryBackend::File(repository) => {
+                repository.remove_dependency(id, blocker_id).await
+            }
+        }
+    }
+}
+
+#[async_trait]
+pub trait TodoRepository: Clone + std::marker::Send + std::marker::Sync + 'static {
+    async fn create(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity>;
+    async fn find(&self, id: i32) -> anyhow::Result<TodoEntity>;
+    async fn all(&self) -> anyhow::Result<Vec<TodoEntity>>;
+    async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<TodoEntity>>;
+    async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity>;
+    async fn delete(&self, id: i32) -> anyhow::Result<()>;
+    /// `sources` のラベルを付けたTodoを `target` に付け替え、`sources` を削除する
+    ///
+    /// 同じTodoに `target` が重複して付かないようにする。DBの実装は1つのトランザクションで行う
+    async fn merge_labels(&self, target: i32, sources: &[i32]) -> anyhow::Result<()>;
+    /// `id` のTodoが `blocker_id` のTodoの完了を待つようにする。すでにある場合は `Duplicate`
+    ///
+    /// 依存関係が循環しないことは呼び出し側で確認する
+    async fn add_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity>;
+    /// 依存関係がない場合は `NotFound(blocker_id)`
+    async fn remove_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct TodoFromRow {
+    id: i32,
+    text: String,
+    completed: bool,
+    project_id: i32,
+    status_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+pub struct TodoWithLabelFromRow {
+    id: i32,
+    text: String,
+    notes: Option<String>,
+    completed: bool,
+    project_id: i32,
+    status_id: Option<i32>,
+    label_id: Option<i32>,
+    label_name: Option<String>,
+    label_color: Option<String>,
+    label_description: Option<String>,
+    label_icon: Option<String>,
+    label_position: Option<i32>,
+    label_parent_id: Option<i32>,
+    label_exclusive: Option<bool>,
+    label_created_at: Option<DateTime<Utc>>,
+    label_updated_at: Option<DateTime<Utc>>,
+    priority: Option<Priority>,
+    due_at: Option<DateTime<Utc>>,
+    comment_count: i64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+impl TodoWithLabelFromRow {
+    // ラベルが紐づいていない行は外部結合でラベルの列がすべてnullになる
+    fn label(&self) -> Option<Label> {
+        Some(Label {
+            id: self.label_id?,
+            name: self.label_name.clone()?,
+            color: self.label_color.clone()?,
+            description: self.label_description.clone(),
+            icon: self.label_icon.clone(),
+            position: self.label_position?,
+            parent_id: self.label_parent_id,
+            exclusive: self.label_exclusive?,
+            created_at: self.label_created_at?,
+            updated_at: self.label_updated_at?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+struct DependencyFromRow {
+    todo_id: i32,
+    blocker_id: i32,
+    blocker_completed: bool,
+}
+
+/// ブロッカーのIDと、未完了のブロッカーがあるかを設定する
+fn apply_dependencies(todos: &mut [TodoEntity], rows: &[DependencyFromRow]) {
+    for todo in todos {
+        let blockers: Vec<_> = rows.iter().filter(|row| row.todo_id == todo.id).collect();
+        todo.blocked_by = blockers.iter().map(|row| row.blocker_id).collect();
+        todo.blocked = blockers.iter().any(|row| !row.blocker_completed);
+    }
+}
+
+/// 指定したTodoと、完了を待っているTodoを間接的なものも含めて返す
+pub fn blockers(todos: &[TodoEntity], id: i32) -> Vec<i32> {
+    reachable_blockers(id, |current| {
+        todos
+            .iter()
+            .find(|todo| todo.id == current)
+            .map(|todo| todo.blocked_by.clone())
+            .unwrap_or_default()
+    })
+}
+
+// `blocked_by` はTodoのIDからブロッカーのIDを返す
+fn reachable_blockers(id: i32, blocked_by: impl Fn(i32) -> Vec<i32>) -> Vec<i32> {
+    let mut ids = vec![id];
+    let mut index = 0;
+    while let Some(current) = ids.get(index).copied() {
+        // 依存関係が循環していても止まるよう、追加済みのTodoは辿らない
+        for blocker_id in blocked_by(current) {
+            if !ids.contains(&blocker_id) {
+                ids.push(blocker_id);
+            }
+        }
+        index += 1;
+    }
+    ids
+}
+
+/// ファイル・メモリの実装で、依存関係を追加する前に循環しないか確認する
+fn check_cycle(
+    id: i32,
+    blocker_id: i32,
+    blocked_by: impl Fn(i32) -> Vec<i32>,
+) -> Result<(), DependencyCycleError> {
+    if reachable_blockers(blocker_id, blocked_by).contains(&id) {
+        return Err(DependencyCycleError {
+            todo_id: id,
+            blocker_id,
+        });
+    }
+    std::result::Result::Ok(())
+}
+
+/// 自身や、このTodoを(間接的に)待っているTodoをブロッカーにすると依存関係が循環する
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("todo [{blocker_id}] already waits for todo [{todo_id}]")]
+pub struct DependencyCycleError {
+    pub todo_id: i32,
+    pub blocker_id: i32,
+}
+
+/// 未完了のTodoを、ブロッカーが先に来るよう並べる
+///
+/// 同時に着手できるものは優先度の高い順、期限の近い順 (期限なしは後)、ID順に並べる。
+/// 循環していて順序が決まらないTodoは最後にID順で並べる
+pub fn next_todos(todos: Vec<TodoEntity>) -> Vec<TodoEntity> {
+    let mut pending: Vec<TodoEntity> = todos.into_iter().filter(|todo| !todo.completed).collect();
+    pending.sort_by(|a, b| {
+        b.priority
+            .cmp(&a.priority)
+            .then(a.due_at.is_none().cmp(&b.due_at.is_none()))
+            .then(a.due_at.cmp(&b.due_at))
+            .then(a.id.cmp(&b.id))
+    });
+    let mut ordered = Vec::with_capacity(pending.len());
+    // 未完了のブロッカーがなくなったTodoを先頭から取り出していく
+    while let Some(index) = pending.iter().position(|todo| {
+        todo.blocked_by
+            .iter()
+            .all(|id| !pending.iter().any(|other| other.id == *id))
+    }) {
+        ordered.push(pending.remove(index));
+    }
+    pending.sort_by_key(|todo| todo.id);
+    ordered.extend(pending);
+    ordered
+}
+
+/// 更新後の完了日時。完了済みのまま更新した場合は最初に完了した日時を保つ
+fn completed_at(
+    (was_completed, completed_at): (bool, Option<DateTime<Utc>>),
+    completed: bool,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    match (was_completed, completed) {
+        (_, false) => None,
+        (false, true) => Some(now),
+        (true, true) => completed_at,
+    }
+}
+
+fn fold_entities(rows: Vec<TodoWithLabelFromRow>) -> Vec<TodoEntity> {
+    // let mut rows = rows.iter();
+    let mut accum: Vec<TodoEntity> = vec![];
+    // 'outer: while let Some(row) = rows.next() {
+    'outer: for row in rows {
+        let todos = accum.iter_mut();
+        // while let Some(todo) = todos.next() {
+        for todo in todos {
+            // idが一致＝Todoに紐づくラベルが複数存在している
+            if todo.id == row.id {
+                todo.labels.extend(row.label());
+                continue 'outer;
+            }
+        }
+
+        // Todoのidに一致がなかった時のみ到達、TodoEntityを作成
+        let labels = row.label().into_iter().collect();
+
+        accum.push(TodoEntity {
+            id: row.id,
+            text: row.text.clone(),
+            notes: row.notes.clone(),
+            completed: row.completed,
+            project_id: row.project_id,
+            status_id: row.status_id,
+            labels,
+            priority: row.priority,
+            due_at: row.due_at,
+            // 依存関係は別に読み出して apply_dependencies で設定する
+            blocked_by: vec![],
+            blocked: false,
+            comment_count: row.comment_count,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            completed_at: row.completed_at,
+        });
+    }
+    for todo in accum.iter_mut() {
+        sort_labels(&mut todo.labels);
+    }
+    accum
+}
+
+#[cfg(test)]
+mod helper_test {
+    use super::*;
+
+    fn todo(id: i32, blocked_by: Vec<i32>, priority: Option<Priority>) -> TodoEntity {
+        TodoEntity {
+            blocked_by,
+            priority,
+            ..TodoEntity::new(id, format!("todo {}", id), vec![])
+        }
+    }
+
+    #[test]
+    fn should_order_next_todos() {
+        let mut done = todo(1, vec![], None);
+        done.completed = true;
+        let todos = vec![
+            done,
+            todo(2, vec![3], Some(Priority::Urgent)),
+            todo(3, vec![1], None),
+            todo(4, vec![], Some(Priority::High)),
+            todo(5, vec![6], None),
+            todo(6, vec![5], None),
+        ];
+        assert_eq!(blockers(&todos, 2), vec![2, 3, 1]);
+        assert_eq!(blockers(&todos, 5), vec![5, 6]);
+
+        // 完了済みは除き、ブロッカーが先、循環しているものは最後
+        let ids: Vec<_> = next_todos(todos).iter().map(|todo| todo.id).collect();
+        assert_eq!(ids, vec![4, 3, 2, 5, 6]);
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use crate::repositories::label::LabelRepositoryForDb;
+    use dotenv::dotenv;
+    use sqlx::PgPool;
+    use std::env;
+
+    #[test]
+    fn fold_entities_test() {
+        let label_1 = Label {
+            position: 1,
+            ..Label::new(1, String::from("label 1"))
+        };
+        // 並び順が先のラベルを先に返す
+        let label_2 = Label {
+            color: String::from("#ff0000"),
+            icon: Some(String::from("🏠")),
+            ..Label::new(2, String::from("label 2"))
+        };
+        let rows = vec![
+            TodoWithLabelFromRow {
+                id: 1,
+                text: String::from("todo 1"),
+                notes: None,
+                completed: false,
+                project_id: DEFAULT_PROJECT_ID,
+                status_id: None,
+                priority: None,
+                due_at: None,
+                label_id: Some(label_1.id),
+                label_name: Some(label_1.name.clone()),
+                label_color: Some(label_1.color.clone()),
+                label_description: label_1.description.clone(),
+                label_icon: label_1.icon.clone(),
+                label_position: Some(label_1.position),
+                label_parent_id: label_1.parent_id,
+                label_exclusive: Some(label_1.exclusive),
+                label_created_at: Some(label_1.created_at),
+                label_updated_at: Some(label_1.updated_at),
+                comment_count: 0,
+                created_at: DateTime::UNIX_EPOCH,
+                updated_at: DateTime::UNIX_EPOCH,
+                completed_at: None,
+            },
+            TodoWithLabelFromRow {
+                id: 1,
+                text: String::from("todo 1"),
+                notes: None,
+                completed: false,
+                project_id: DEFAULT_PROJECT_ID,
+                status_id: None,
+                priority: None,
+                due_at: None,
+                label_id: Some(label_2.id),
+                label_name: Some(label_2.name.clone()),
+                label_color: Some(label_2.color.clone()),
+                label_description: label_2.description.clone(),
+                label_icon: label_2.icon.clone(),
+                label_position: Some(label_2.position),
+                label_parent_id: label_2.parent_id,
+                label_exclusive: Some(label_2.exclusive),
+                label_created_at: Some(label_2.created_at),
+                label_updated_at: Some(label_2.updated_at),
+                comment_count: 0,
+                created_at: DateTime::UNIX_EPOCH,
+                updated_at: DateTime::UNIX_EPOCH,
+                completed_at: None,
+            },
+            TodoWithLabelFromRow {
+                id: 2,
+                text: String::from("todo 2"),
+                notes: None,
+                completed: false,
+                project_id: DEFAULT_PROJECT_ID,
+                status_id: None,
+                priority: None,
+                due_at: None,
+                label_id: Some(label_1.id),
+                label_name: Some(label_1.name.clone()),
+                label_color: Some(label_1.color.clone()),
+                label_description: label_1.description.clone(),
+                label_icon: label_1.icon.clone(),
+                label_position: Some(label_1.position),
+                label_parent_id: label_1.parent_id,
+                label_exclusive: Some(label_1.exclusive),
+                label_created_at: Some(label_1.created_at),
+                label_updated_at: Some(label_1.updated_at),
+                comment_count: 0,
+                created_at: DateTime::UNIX_EPOCH,
+                updated_at: DateTime::UNIX_EPOCH,
+                completed_at: None,
+            },
+        ];
+        let res = fold_entities(rows);
+        assert_eq!(
+            res,
+            vec![
+                TodoEntity {
+                    id: 1,
+                    text: String::from("todo 1"),
+                    notes: None,
+                    completed: false,
+                    project_id: DEFAULT_PROJECT_ID,
+                    status_id: None,
+                    priority: None,
+                    due_at: None,
+                    blocked_by: vec![],
+                    blocked: false,
+                    labels: vec![label_2.clone(), label_1.clone()],
+                    comment_count: 0,
+                    created_at: DateTime::UNIX_EPOCH,
+                    updated_at: DateTime::UNIX_EPOCH,
+                    completed_at: None,
+                },
+                TodoEntity {
+                    id: 2,
+                    text: String::from("todo 2"),
+                    notes: None,
+                    completed: false,
+                    project_id: DEFAULT_PROJECT_ID,
+                    status_id: None,
+                    priority: None,
+                    due_at: None,
+                    blocked_by: vec![],
+                    blocked: false,
+                    labels: vec![label_1.clone()],
+                    comment_count: 0,
+                    created_at: DateTime::UNIX_EPOCH,
+                    updated_at: DateTime::UNIX_EPOCH,
+                    completed_at: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn crud_scenario_db() {
+        dotenv().ok();
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+
+        let id = scenario::crud_scenario(
+            &TodoRepositoryForDb::new(pool.clone()),
+            &LabelRepositoryForDb::new(pool.clone()),
+        )
+        .await;
+
+        let todo_rows = sqlx::query(
+            r#"
+                select * from todos where id=$1;
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&pool)
+        .await
+        .expect("[delete] todo_labels fetch error");
+        assert!(todo_rows.is_empty());
+
+        let rows = sqlx::query(
+            r#"
+                select * from todo_labels where todo_id=$1;
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&pool)
+        .await
+        .expect("[delete] todo_labels fetch error");
+        assert!(rows.is_empty());
+    }
+
+    #[tokio::test]
+    async fn delete_label_scenario_db() {
+        dotenv().ok();
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+
+        scenario::delete_label_scenario(
+            &TodoRepositoryForDb::new(pool.clone()),
+            &LabelRepositoryForDb::new(pool),
+        )
+        .await;
+    }
+}
+
+/// DBの実装ごとに同じ操作を確認するシナリオ
+///
+/// 他のテストのデータが残っているDBでも動くよう、作成したデータだけを確認する。削除したTodoのIDを返す
+#[cfg(test)]
+mod scenario {
+    use super::*;
+    use crate::repositories::label::{CreateLabel, ExclusiveLabelError, LabelRepository};
+
+    pub async fn crud_scenario<T: TodoRepository, L: LabelRepository>(
+        repository: &T,
+        labels: &L,
+    ) -> i32 {
+        // label data prepare
+        let label_1 = labels
+            .create(CreateLabel::new(format!(
+                "test label from repositories/todo.rs {}",
+                rand::random::<u32>()
+            )))
+            .await
+            .expect("Failed to insert label data.");
+        let todo_text = "[crud_scenario] text";
+
+        // create
+        let created = repository
+            .create(CreateTodo::new(todo_text.to_string(), vec![label_1.id]))
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(created.text, todo_text);
+        assert!(!created.completed);
+        assert_eq!(created.labels, vec![label_1.clone()]);
+        assert_eq!(created.updated_at, created.created_at);
+        assert_eq!(created.completed_at, None);
+
+        // find
+        let todo = repository
+            .find(created.id)
+            .await
+            .expect("[find] returned Err");
+        assert_eq!(created, todo);
+
+        // all
+        let todos = repository.all().await.expect("[all] returned Err");
+        assert!(todos.contains(&created));
+
+        // all_in_project
+        let todos = repository
+            .all_in_project(DEFAULT_PROJECT_ID)
+            .await
+            .expect("[all_in_project] returned Err");
+        assert!(todos.contains(&created));
+        let todos = repository.all_in_project(i32::MAX).await.unwrap();
+        assert!(todos.is_empty());
+
+        // update
+        let updated_text = "[crud_scenario] updated text";
+        let due_at = DateTime::from_timestamp(1_735_657_200, 0).unwrap();
+        let todo = repository
+            .update(
+                todo.id,
+                UpdateTodo {
+                    text: Some(updated_text.to_string()),
+                    notes: Some(Some("- [ ] step".to_string())),
+                    completed: Some(true),
+                    labels: Some(vec![]),
+                    project_id: None,
+                    status_id: None,
+                    priority: Some(Some(Priority::High)),
+                    due_at: Some(Some(due_at)),
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert_eq!(created.id, todo.id);
+        assert_eq!(todo.text, updated_text);
+        assert!(todo.completed);
+        assert!(todo.labels.is_empty());
+        assert_eq!(todo.notes.as_deref(), Some("- [ ] step"));
+        assert_eq!(todo.priority, Some(Priority::High));
+        assert_eq!(todo.due_at, Some(due_at));
+        assert!(todo.updated_at >= created.updated_at);
+        // 完了日時は完了にした更新の日時になる
+        assert_eq!(todo.completed_at, Some(todo.updated_at));
+        let completed_at = todo.completed_at;
+
+        // ラベルを複数付け直す (並び順はDBによって異なるためIDでそろえる)
+        let label_2 = labels
+            .create(CreateLabel::new(format!(
+                "test label from repositories/todo.rs {}",
+                rand::random::<u32>()
+            )))
+            .await
+            .expect("Failed to insert label data.");
+        let todo = repository
+            .update(
+                todo.id,
+                UpdateTodo {
+                    labels: Some(vec![label_1.id, label_2.id]),
+                    ..Default::default()
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        let mut todo_labels = todo.labels.clone();
+        todo_labels.sort_by_key(|label| label.id);
+        assert_eq!(todo_labels, vec![label_1.clone(), label_2.clone()]);
+        // 完了済みのまま更新しても完了日時は変わらない
+        assert_eq!(todo.completed_at, completed_at);
+        // 省略した項目は変わらない
+        assert_eq!(todo.notes.as_deref(), Some("- [ ] step"));
+        assert_eq!(todo.priority, Some(Priority::High));
+
+        // merge_labels (付け替え先がすでに付いているTodoには重複して付けない)
+        let label_3 = labels
+            .create(CreateLabel::new(format!(
+                "test label from repositories/todo.rs {}",
+                rand::random::<u32>()
+            )))
+            .await
+            .expect("Failed to insert label data.");
+        let label_4 = labels
+            .create(CreateLabel {
+                parent_id: Some(label_3.id),
+                ..CreateLabel::new(format!(
+                    "test label from repositories/todo.rs {}",
+                    rand::random::<u32>()
+                ))
+            })
+            .await
+            .expect("Failed to insert label data.");
+        let other = repository
+            .create(CreateTodo::new(todo_text.to_string(), vec![label_3.id]))
+            .await
+            .expect("[create] returned Err");
+        repository
+            .merge_labels(label_1.id, &[label_2.id, label_3.id])
+            .await
+            .expect("[merge_labels] returned Err");
+        let todo = repository.find(todo.id).await.expect("[find] returned Err");
+        assert_eq!(todo.labels, vec![label_1.clone()]);
+        let other = repository
+            .find(other.id)
+            .await
+            .expect("[find] returned Err");
+        assert_eq!(other.labels, vec![label_1.clone()]);
+        let all_labels = labels.all().await.expect("[all] returned Err");
+        assert!(!all_labels
+            .iter()
+            .any(|label| label.id == label_2.id || label.id == label_3.id));
+        let child = all_labels.iter().find(|label| label.id == label_4.id);
+        assert_eq!(child.and_then(|label| label.parent_id), Some(label_1.id));
+        let res = repository.merge_labels(label_1.id, &[i32::MAX]).await;
+        assert!(res.is_err());
+
+        // dependencies (完了済みのブロッカーではブロックされない)
+        let other = repository
+            .add_dependency(other.id, todo.id)
+            .await
+            .expect("[add_dependency] returned Err");
+        assert_eq!(other.blocked_by, vec![todo.id]);
+        assert!(!other.blocked);
+        let next = repository
+            .create(CreateTodo::new(todo_text.to_string(), vec![]))
+            .await
+            .expect("[create] returned Err");
+        let next = repository
+            .add_dependency(next.id, other.id)
+            .await
+            .expect("[add_dependency] returned Err");
+        assert_eq!(next.blocked_by, vec![other.id]);
+        assert!(next.blocked);
+        let todos = repository.all().await.expect("[all] returned Err");
+        assert!(todos.contains(&next));
+        assert!(repository.add_dependency(next.id, other.id).await.is_err());
+        assert!(repository.add_dependency(next.id, i32::MAX).await.is_err());
+        let next = repository
+            .remove_dependency(next.id, other.id)
+            .await
+            .expect("[remove_dependency] returned Err");
+        assert!(next.blocked_by.is_empty());
+        assert!(!next.blocked);
+        assert!(repository
+            .remove_dependency(next.id, other.id)
+            .await
+            .is_err());
+        repository
+            .add_dependency(next.id, other.id)
+            .await
+            .expect("[add_dependency] returned Err");
+        // next -> other -> todo と待っているので、逆向きや自身は循環する
+        for (id, blocker_id) in [(todo.id, next.id), (other.id, next.id), (next.id, next.id)] {
+            let res = repository.add_dependency(id, blocker_id).await;
+            let e = res.expect_err("[add_dependency] returned Ok for cycle");
+            assert_eq!(
+                e.downcast_ref::<DependencyCycleError>(),
+                Some(&DependencyCycleError {
+                    todo_id: id,
+                    blocker_id
+                })
+            );
+        }
+        let todo_1 = repository.find(todo.id).await.expect("[find] returned Err");
+        assert!(todo_1.blocked_by.is_empty());
+
+        // delete
+        repository
+            .delete(todo.id)
+            .await
+            .expect("[delete] returned Err");
+        let res = repository.find(created.id).await; // expect not found err
+        assert!(res.is_err());
+        repository
+            .delete(other.id)
+            .await
+            .expect("[delete] returned Err");
+        // 削除したTodoはブロッカーから外れる
+        let next = repository.find(next.id).await.expect("[find] returned Err");
+        assert!(next.blocked_by.is_empty());
+        assert!(!next.blocked);
+        repository
+            .delete(next.id)
+            .await
+            .expect("[delete] returned Err");
+
+        // delete label data prepare
+        labels
+            .delete(label_4.id, DeleteLabel::Detach)
+            .await
+            .expect("[delete] returned Err");
+        labels
+            .delete(label_1.id, DeleteLabel::Detach)
+            .await
+            .expect("[delete] returned Err");
+
+        todo.id
+    }
+
+    /// 使っているTodoがあるラベルを `DeleteLabel` の各モードで削除する
+    pub async fn delete_label_scenario<T: TodoRepository, L: LabelRepository>(
+        repository: &T,
+        labels: &L,
+    ) {
+        let name = format!(
+            "test label from repositories/todo.rs delete {}",
+            rand::random::<u32>()
+        );
+        let create = |name: String, parent_id: Option<i32>, exclusive: bool| {
+            labels.create(CreateLabel {
+                parent_id,
+                exclusive,
+                ..CreateLabel::new(name)
+            })
+        };
+        let group = create(format!("{} group", name), None, true)
+            .await
+            .expect("[create] returned Err");
+        let high = create(format!("{} high", name), Some(group.id), false)
+            .await
+            .expect("[create] returned Err");
+        let low = create(format!("{} low", name), Some(group.id), false)
+            .await
+            .expect("[create] returned Err");
+        let old = create(format!("{} old", name), None, false)
+            .await
+            .expect("[create] returned Err");
+        let todo = repository
+            .create(CreateTodo::new(name.clone(), vec![old.id, high.id]))
+            .await
+            .expect("[create] returned Err");
+
+        // restrict は使っているTodoの件数を返して削除しない
+        let err = labels
+            .delete(old.id, DeleteLabel::Restrict)
+            .await
+            .expect_err("[delete] restrict returned Ok");
+        assert_eq!(
+            err.downcast_ref::<LabelInUseError>().map(|e| e.usage),
+            Some(1)
+        );
+        // 排他的なグループに違反する付け替えは、何も変更せずに失敗する
+        let err = labels
+            .delete(old.id, DeleteLabel::Reassign(low.id))
+            .await
+            .expect_err("[delete] reassign returned Ok");
+        assert!(err.downcast_ref::<ExclusiveLabelError>().is_some());
+        let found = repository.find(todo.id).await.expect("[find] returned Err");
+        assert_eq!(found.labels.len(), 2);
+        assert!(labels
+            .delete(old.id, DeleteLabel::Reassign(i32::MAX))
+            .await
+            .is_err());
+
+        // reassign
+        let todo_ids = labels
+            .delete(old.id, DeleteLabel::Reassign(group.id))
+            .await
+            .expect("[delete] returned Err");
+        assert_eq!(todo_ids, vec![todo.id]);
+        let found = repository.find(todo.id).await.expect("[find] returned Err");
+        let mut label_ids: Vec<i32> = found.labels.iter().map(|label| label.id).collect();
+        label_ids.sort_unstable();
+        assert_eq!(label_ids, vec![group.id, high.id]);
+        assert!(found.updated_at >= todo.updated_at);
+
+        // detach
+        let todo_ids = labels
+            .delete(high.id, DeleteLabel::Detach)
+            .await
+            .expect("[delete] returned Err");
+        assert_eq!(todo_ids, vec![todo.id]);
+        let found = repository.find(todo.id).await.expect("[find] returned Err");
+        assert_eq!(found.labels.len(), 1);
+        let err = labels
+            .delete(high.id, DeleteLabel::Detach)
+            .await
+            .expect_err("[delete] returned Ok");
+        assert!(matches!(
+            err.downcast_ref::<RepositoryError>(),
+            Some(RepositoryError::NotFound(id)) if *id == high.id
+        ));
+
+        // 子は最上位に移るため、最上位のラベルと名前が重なる場合は削除しない
+        let root = create(format!("{} LOW", name), None, false)
+            .await
+            .expect("[create] returned Err");
+        let err = labels
+            .delete(group.id, DeleteLabel::Detach)
+            .await
+            .expect_err("[delete] returned Ok");
+        assert!(matches!(
+            err.downcast_ref::<RepositoryError>(),
+            Some(RepositoryError::Duplicate(id)) if *id == root.id
+        ));
 
-fn fold_entities(rows: Vec<TodoWithLabelFromRow>) -> Vec<TodoEntity> {
-    // let mut rows = rows.iter();
-    let mut accum: Vec<TodoEntity> = vec![];
-    // 'outer: while let Some(row) = rows.next() {
-    'outer: for row in rows {
-        let todos = accum.iter_mut();
-        // while let Some(todo) = todos.next() {
-        for todo in todos {
-            // idが一致＝Todoに紐づくラベルが複数存在している
-            if todo.id == row.id {
-                todo.labels.push(Label {
-                    id: row.label_id.unwrap(),
-                    name: row.label_name.clone().unwrap(),
-                });
-                continue 'outer;
-            }
+        repository
+            .delete(todo.id)
+            .await
+            .expect("[delete] returned Err");
+        for label in [root, low, group] {
+            labels
+                .delete(label.id, DeleteLabel::Restrict)
+                .await
+                .expect("[delete] returned Err");
         }
-
-        // Todoのidに一致がなかった時のみ到達、TodoEntityを作成
-        let labels = if row.label_id.is_some() {
-            vec![Label {
-                id: row.label_id.unwrap(),
-                name: row.label_name.clone().unwrap(),
-            }]
-        } else {
-            vec![]
-        };
-
-        accum.push(TodoEntity {
-            id: row.id,
-            text: row.text.clone(),
-            completed: row.completed,
-            labels,
-        });
     }
-    accum
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct CreateTodo {
-    pub text: String,
-    labels: Vec<i32>,
-}
+#[cfg(test)]
+mod sqlite_test {
+    use super::*;
+    use crate::repositories::{connect_sqlite, label::LabelRepositoryForSqlite};
+
+    #[tokio::test]
+    async fn crud_scenario_sqlite() {
+        let pool = connect_sqlite("sqlite::memory:")
+            .await
+            .expect("fail connect sqlite");
+        let repository = TodoRepositoryForSqlite::new(pool.clone());
+        let id = scenario::crud_scenario(&repository, &LabelRepositoryForSqlite::new(pool.clone()))
+            .await;
+        assert!(repository.delete(id).await.is_err());
+
+        let rows = sqlx::query(
+            r#"
+                select * from todo_labels where todo_id=$1;
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&pool)
+        .await
+        .expect("[delete] todo_labels fetch error");
+        assert!(rows.is_empty());
+    }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct UpdateTodo {
-    pub text: Option<String>,
-    completed: Option<bool>,
-    labels: Option<Vec<i32>>,
+    #[tokio::test]
+    async fn delete_label_scenario_sqlite() {
+        let pool = connect_sqlite("sqlite::memory:")
+            .await
+            .expect("fail connect sqlite");
+        scenario::delete_label_scenario(
+            &TodoRepositoryForSqlite::new(pool.clone()),
+            &LabelRepositoryForSqlite::new(pool),
+        )
+        .await;
+    }
 }
 
 #[cfg(test)]
-#[cfg(feature = "database-test")]
-mod test {
+#[cfg(feature = "mysql-test")]
+mod mysql_test {
     use super::*;
+    use crate::repositories::{connect_mysql, label::LabelRepositoryForMySql};
     use dotenv::dotenv;
-    use sqlx::PgPool;
     use std::env;
 
-    #[test]
-    fn fold_entities_test() {
-        let label_1 = Label {
-            id: 1,
-            name: String::from("label 1"),
-        };
-        let label_2 = Label {
-            id: 2,
-            name: String::from("label 2"),
-        };
-        let rows = vec![
-            TodoWithLabelFromRow {
-                id: 1,
-                text: String::from("todo 1"),
-                completed: false,
-                label_id: Some(label_1.id),
-                label_name: Some(label_1.name.clone()),
-            },
-            TodoWithLabelFromRow {
-                id: 1,
-                text: String::from("todo 1"),
-                completed: false,
-                label_id: Some(label_2.id),
-                label_name: Some(label_2.name.clone()),
-            },
-            TodoWithLabelFromRow {
-                id: 2,
-                text: String::from("todo 2"),
-                completed: false,
-                label_id: Some(label_1.id),
-                label_name: Some(label_1.name.clone()),
-            },
-        ];
-        let res = fold_entities(rows);
-        assert_eq!(
-            res,
-            vec![
-                TodoEntity {
-                    id: 1,
-                    text: String::from("todo 1"),
-                    completed: false,
-                    labels: vec![label_1.clone(), label_2.clone()],
-                },
-                TodoEntity {
-                    id: 2,
-                    text: String::from("todo 2"),
-                    completed: false,
-                    labels: vec![label_1.clone()],
-                },
-            ]
-        );
-    }
-
     #[tokio::test]
-    async fn crud_scenario_db() {
+    async fn crud_scenario_mysql() {
         dotenv().ok();
-        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
-        let pool = PgPool::connect(database_url)
+        let database_url = &env::var("MYSQL_DATABASE_URL").expect("undefined [MYSQL_DATABASE_URL]");
+        let pool = connect_mysql(database_url)
             .await
-            .expect(&format!("fail connect database, url is [{}]", database_url));
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+        let repository = TodoRepositoryForMySql::new(pool.clone());
+        let id =
+            scenario::crud_scenario(&repository, &LabelRepositoryForMySql::new(pool.clone())).await;
+        assert!(repository.delete(id).await.is_err());
 
-        // label data prepare
-        let label_name = String::from("test label from repositories/todo.rs");
-        let optional_label = sqlx::query_as::<_, Label>(
+        let rows = sqlx::query(
             r#"
-                select * from labels where name = $1;
+                select * from todo_labels where todo_id=?;
             "#,
         )
-        .bind(label_name.clone())
-        .fetch_optional(&pool)
+        .bind(id)
+        .fetch_all(&pool)
         .await
-        .expect("Failed to prepare label data.");
-        let label_1 = if let Some(label) = optional_label {
-            label
-        } else {
-            let label = sqlx::query_as::<_, Label>(
-                r#"
-                    insert into labels (name) values ($1) returning *;
-                "#,
-            )
-            .bind(label_name)
-            .fetch_one(&pool)
+        .expect("[delete] todo_labels fetch error");
+        assert!(rows.is_empty());
+    }
+
+    #[tokio::test]
+    async fn delete_label_scenario_mysql() {
+        dotenv().ok();
+        let database_url = &env::var("MYSQL_DATABASE_URL").expect("undefined [MYSQL_DATABASE_URL]");
+        let pool = connect_mysql(database_url)
             .await
-            .expect("Failed to insert label data.");
-            label
-        };
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+        scenario::delete_label_scenario(
+            &TodoRepositoryForMySql::new(pool.clone()),
+            &LabelRepositoryForMySql::new(pool),
+        )
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod file_test {
+    use super::*;
+    use crate::repositories::label::{CreateLabel, LabelRepository};
 
-        let repository = TodoRepositoryForDb::new(pool.clone());
+    #[tokio::test]
+    async fn crud_scenario_file() {
+        let dir = std::env::temp_dir().join(format!("todo-file-todo-{}", rand::random::<u32>()));
+        let labels = LabelRepositoryForFile::open(&dir).expect("fail open label store");
+        let label_1 = labels
+            .create(CreateLabel::new("test label".to_string()))
+            .await
+            .expect("Failed to insert label data.");
+        let repository = TodoRepositoryForFile::open(&dir, &labels).expect("fail open store");
         let todo_text = "[crud_scenario] text";
+        let expected = TodoEntity::new(1, todo_text.to_string(), vec![label_1.clone()]);
 
         // create
         let created = repository
             .create(CreateTodo::new(todo_text.to_string(), vec![label_1.id]))
             .await
             .expect("[create] returned Err");
-        assert_eq!(created.text, todo_text);
-        assert!(!created.completed);
-        assert_eq!(*created.labels.first().unwrap(), label_1);
+        let expected = TodoEntity {
+            created_at: created.created_at,
+            updated_at: created.created_at,
+            ..expected
+        };
+        assert_eq!(created, expected);
+        let res = repository
+            .create(CreateTodo::new(todo_text.to_string(), vec![999]))
+            .await;
+        assert!(res.is_err());
+
+        // 再起動しても残っている
+        drop((labels, repository));
+        let labels = LabelRepositoryForFile::open(&dir).expect("fail reopen label store");
+        let repository = TodoRepositoryForFile::open(&dir, &labels).expect("fail reopen store");
 
         // find
         let todo = repository
             .find(created.id)
             .await
             .expect("[find] returned Err");
-        assert_eq!(created, todo);
+        assert_eq!(todo, expected);
 
         // all
         let todos = repository.all().await.expect("[all] returned Err");
-        let todo = todos.first().unwrap();
-        assert_eq!(created, *todo);
+        assert_eq!(todos, vec![expected.clone()]);
+
+        // all_in_project
+        let todos = repository
+            .all_in_project(DEFAULT_PROJECT_ID)
+            .await
+            .expect("[all_in_project] returned Err");
+        assert_eq!(todos, vec![expected]);
+        let todos = repository.all_in_project(2).await.unwrap();
+        assert!(todos.is_empty());
 
         // update
         let updated_text = "[crud_scenario] updated text";
@@ -377,60 +2503,108 @@ mod test {
                 todo.id,
                 UpdateTodo {
                     text: Some(updated_text.to_string()),
+                    notes: None,
                     completed: Some(true),
                     labels: Some(vec![]),
+                    project_id: None,
+                    status_id: None,
+                    priority: None,
+                    due_at: None,
                 },
             )
             .await
             .expect("[update] returned Err");
         assert_eq!(created.id, todo.id);
         assert_eq!(todo.text, updated_text);
-        assert!(todo.labels.len() == 0);
+        assert!(todo.completed);
+        assert!(todo.completed_at.is_some());
+        assert!(todo.labels.is_empty());
+
+        // 削除されたラベルは外れる
+        let todo = repository
+            .update(
+                todo.id,
+                UpdateTodo {
+                    labels: Some(vec![label_1.id]),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(todo.labels, vec![label_1.clone()]);
+        labels
+            .delete(label_1.id, DeleteLabel::Detach)
+            .await
+            .unwrap();
+        assert!(repository.find(todo.id).await.unwrap().labels.is_empty());
 
         // delete
-        let _ = repository
+        repository
             .delete(todo.id)
             .await
             .expect("[delete] returned Err");
         let res = repository.find(created.id).await; // expect not found err
         assert!(res.is_err());
+        assert!(repository.delete(todo.id).await.is_err());
 
-        let todo_rows = sqlx::query(
-            r#"
-                select * from todos where id=$1;
-            "#,
-        )
-        .bind(todo.id)
-        .fetch_all(&pool)
-        .await
-        .expect("[delete] todo_labels fetch error");
-        assert!(todo_rows.len() == 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 
-        let rows = sqlx::query(
-            r#"
-                select * from todo_labels where todo_id=$1;
-            "#,
-        )
-        .bind(todo.id)
-        .fetch_all(&pool)
-        .await
-        .expect("[delete] todo_labels fetch error");
-        assert!(rows.len() == 0);
+    #[tokio::test]
+    async fn delete_label_scenario_file() {
+        let dir = std::env::temp_dir().join(format!("todo-file-todo-{}", rand::random::<u32>()));
+        let labels = LabelRepositoryForFile::open(&dir).expect("fail open label store");
+        let repository = TodoRepositoryForFile::open(&dir, &labels).expect("fail open store");
+        let labels = labels.with_todos(&repository);
+        scenario::delete_label_scenario(&repository, &labels).await;
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 
-        // delete label data prepare
-        sqlx::query(
-            r#"
-                delete from labels where id=$1;
-            "#,
-        )
-        .bind(label_1.id)
-        .execute(&pool)
-        .await
-        .expect("[delete] returned Err");
-        println!(
-            "[repositories::todo::test::crud_scenario_db] label_1.id is {:?}",
-            label_1.id
-        );
+    #[tokio::test]
+    async fn should_keep_concurrent_changes_file() {
+        let dir = std::env::temp_dir().join(format!("todo-file-todo-{}", rand::random::<u32>()));
+        let labels = LabelRepositoryForFile::open(&dir).expect("fail open label store");
+        let repository = TodoRepositoryForFile::open(&dir, &labels).expect("fail open store");
+        let todo = repository
+            .create(CreateTodo::new("todo".to_string(), vec![]))
+            .await
+            .unwrap();
+        let mut blocker_ids = vec![];
+        for i in 0..10 {
+            let blocker = repository
+                .create(CreateTodo::new(format!("blocker {}", i), vec![]))
+                .await
+                .unwrap();
+            blocker_ids.push(blocker.id);
+        }
+
+        // 依存関係の追加と本文の更新が交互に書き込まれても、どちらも失われない
+        let mut tasks = tokio::task::JoinSet::new();
+        for (i, blocker_id) in blocker_ids.iter().copied().enumerate() {
+            let dependencies = repository.clone();
+            tasks.spawn(async move {
+                dependencies
+                    .add_dependency(todo.id, blocker_id)
+                    .await
+                    .unwrap();
+            });
+            let repository = repository.clone();
+            tasks.spawn(async move {
+                let payload = UpdateTodo {
+                    text: Some(format!("todo {}", i)),
+                    ..Default::default()
+                };
+                repository.update(todo.id, payload).await.unwrap();
+            });
+        }
+        while let Some(res) = tasks.join_next().await {
+            res.unwrap();
+        }
+        let todo = repository.find(todo.id).await.unwrap();
+        assert_eq!(todo.blocked_by, blocker_ids);
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
 
@@ -440,25 +2614,33 @@ pub mod test_utils {
     use axum::async_trait;
     use std::{
         collections::HashMap,
-        sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+        sync::{
+            atomic::{AtomicI32, Ordering},
+            Arc, RwLock, RwLockReadGuard, RwLockWriteGuard,
+        },
     };
 
     use super::*;
+    use crate::repositories::comment::test_utils::CommentRepositoryForMemory;
+    use crate::repositories::label::test_utils::{LabelData, LabelRepositoryForMemory};
+
+    /// メモリの実装で作成・更新日時に使う時計
+    ///
+    /// 既定ではUNIX_EPOCHで止まっているため、日時を含めて結果を比較できる
+    #[derive(Debug, Clone, Default)]
+    pub struct ManualClock(Arc<RwLock<DateTime<Utc>>>);
+
+    impl ManualClock {
+        pub fn new(now: DateTime<Utc>) -> Self {
+            Self(Arc::new(RwLock::new(now)))
+        }
 
-    impl TodoEntity {
-        pub fn new(id: i32, text: String, labels: Vec<Label>) -> Self {
-            Self {
-                id,
-                text,
-                completed: false,
-                labels,
-            }
+        pub fn now(&self) -> DateTime<Utc> {
+            *self.0.read().unwrap()
         }
-    }
 
-    impl CreateTodo {
-        pub fn new(text: String, labels: Vec<i32>) -> Self {
-            Self { text, labels }
+        pub fn advance(&self, duration: chrono::Duration) {
+            *self.0.write().unwrap() += duration;
         }
     }
 
@@ -467,25 +2649,118 @@ pub mod test_utils {
     #[derive(Debug, Clone)]
     pub struct TodoRepositoryForMemory {
         store: Arc<RwLock<TodoDatas>>,
+        // DBのserialと同じく、削除済みのIDを再利用しない (同期のトゥームストーンと衝突するため)
+        sequence: Arc<AtomicI32>,
         labels: Vec<Label>,
+        label_store: Option<Arc<RwLock<LabelData>>>,
+        comments: Option<CommentRepositoryForMemory>,
+        clock: ManualClock,
     }
 
     impl TodoRepositoryForMemory {
         pub fn new(labels: Vec<Label>) -> Self {
             TodoRepositoryForMemory {
                 store: Arc::default(),
+                sequence: Arc::default(),
                 labels,
+                label_store: None,
+                comments: None,
+                clock: ManualClock::default(),
+            }
+        }
+
+        /// 作成・更新日時を外から進められるよう、時計を共有する
+        pub fn with_clock(mut self, clock: ManualClock) -> Self {
+            self.clock = clock;
+            self
+        }
+
+        /// ラベルの統合でDBの実装と同じく統合元のラベルを削除するため、ラベルのストアを共有する
+        pub fn with_label_store(mut self, labels: &LabelRepositoryForMemory) -> Self {
+            self.label_store = Some(labels.store());
+            self
+        }
+
+        /// コメント数の集計とTodo削除時のコメント削除のために、コメントのストアを共有する
+        pub fn with_comments(mut self, comments: CommentRepositoryForMemory) -> Self {
+            self.comments = Some(comments);
+            self
+        }
+
+        fn with_comment_count(&self, mut todo: TodoEntity) -> TodoEntity {
+            if let Some(comments) = &self.comments {
+                todo.comment_count = comments.count_in_todo(todo.id);
             }
+            todo
         }
 
-        fn write_store_ref(&self) -> RwLockWriteGuard<TodoDatas> {
+        // ブロッカーの完了状態は読み出す時に反映する。削除されたブロッカーは外す
+        fn with_dependencies(store: &TodoDatas, mut todo: TodoEntity) -> TodoEntity {
+            todo.blocked_by.retain(|id| store.contains_key(id));
+            todo.blocked = todo
+                .blocked_by
+                .iter()
+                .any(|id| store.get(id).is_some_and(|blocker| !blocker.completed));
+            todo
+        }
+
+        fn write_store_ref(&self) -> RwLockWriteGuard<'_, TodoDatas> {
             self.store.write().unwrap()
         }
 
-        fn read_store_ref(&self) -> RwLockReadGuard<TodoDatas> {
+        fn read_store_ref(&self) -> RwLockReadGuard<'_, TodoDatas> {
             self.store.read().unwrap()
         }
 
+        /// ラベルの削除に合わせて、`from` のラベルを使っているTodoを `mode` に従って更新する
+        ///
+        /// `labels` はラベルのストアにあるすべてのラベル
+        pub fn replace_label(
+            &self,
+            from: i32,
+            mode: DeleteLabel,
+            labels: &[Label],
+        ) -> anyhow::Result<Vec<i32>> {
+            let mut store = self.write_store_ref();
+            let mut todo_ids: Vec<i32> = store
+                .values()
+                .filter(|todo| todo.labels.iter().any(|label| label.id == from))
+                .map(|todo| todo.id)
+                .collect();
+            todo_ids.sort_unstable();
+            let target = match mode {
+                DeleteLabel::Restrict if !todo_ids.is_empty() => {
+                    let usage = todo_ids.len();
+                    return Err(LabelInUseError {
+                        label_id: from,
+                        usage,
+                    }
+                    .into());
+                }
+                DeleteLabel::Reassign(to) => {
+                    let todo_labels: Vec<(i32, i32)> = todo_ids
+                        .iter()
+                        .flat_map(|id| store[id].labels.iter().map(|label| (*id, label.id)))
+                        .collect();
+                    check_reassign(labels, &todo_labels, from, to)?;
+                    labels.iter().find(|label| label.id == to).cloned()
+                }
+                _ => None,
+            };
+
+            for id in &todo_ids {
+                let todo = store.get_mut(id).unwrap();
+                todo.labels.retain(|label| label.id != from);
+                if let Some(target) = &target {
+                    if !todo.labels.iter().any(|label| label.id == target.id) {
+                        todo.labels.push(target.clone());
+                    }
+                }
+                todo.updated_at = self.clock.now();
+            }
+            Ok(todo_ids)
+        }
+
         fn resolve_labels(&self, labels: Vec<i32>) -> Vec<Label> {
             let mut label_list = self.labels.iter().cloned();
             let labels = labels
@@ -499,10 +2774,20 @@ pub mod test_utils {
     #[async_trait]
     impl TodoRepository for TodoRepositoryForMemory {
         async fn create(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity> {
+            check_exclusive(&self.labels, &payload.labels)?;
             let mut store = self.write_store_ref();
-            let id = (store.len() + 1) as i32;
+            let id = self.sequence.fetch_add(1, Ordering::SeqCst) + 1;
             let labels = self.resolve_labels(payload.labels);
-            let todo = TodoEntity::new(id, payload.text.clone(), labels);
+            let mut todo = TodoEntity::new(id, payload.text.clone(), labels);
+            todo.notes = payload.notes;
+            todo.project_id = payload.project_id.unwrap_or(DEFAULT_PROJECT_ID);
+            todo.status_id = payload.status_id;
+            todo.priority = payload.priority;
+            todo.due_at = payload.due_at;
+            todo.completed = payload.completed;
+            todo.created_at = self.clock.now();
+            todo.updated_at = todo.created_at;
+            todo.completed_at = payload.completed.then_some(todo.created_at);
             store.insert(id, todo.clone());
             Ok(todo)
         }
@@ -511,67 +2796,190 @@ pub mod test_utils {
             let store = self.read_store_ref();
             let todo = store
                 .get(&id)
-                .map(|todo| todo.clone())
+                .cloned()
                 .ok_or(RepositoryError::NotFound(id))?;
-            Ok(todo)
+            Ok(self.with_comment_count(Self::with_dependencies(&store, todo)))
         }
 
         async fn all(&self) -> anyhow::Result<Vec<TodoEntity>> {
             let store = self.read_store_ref();
-            Ok(Vec::from_iter(store.values().map(|todo| todo.clone())))
+            Ok(Vec::from_iter(store.values().map(|todo| {
+                self.with_comment_count(Self::with_dependencies(&store, todo.clone()))
+            })))
+        }
+
+        async fn all_in_project(&self, project_id: i32) -> anyhow::Result<Vec<TodoEntity>> {
+            let store = self.read_store_ref();
+            Ok(Vec::from_iter(
+                store
+                    .values()
+                    .filter(|todo| todo.project_id == project_id)
+                    .map(|todo| {
+                        self.with_comment_count(Self::with_dependencies(&store, todo.clone()))
+                    }),
+            ))
         }
 
         async fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity> {
             let mut store = self.write_store_ref();
             let todo = store.get(&id).context(RepositoryError::NotFound(id))?;
             let text = payload.text.unwrap_or(todo.text.clone());
+            let notes = payload.notes.unwrap_or(todo.notes.clone());
             let completed = payload.completed.unwrap_or(todo.completed);
+            let project_id = payload.project_id.unwrap_or(todo.project_id);
+            let status_id = payload.status_id.unwrap_or(todo.status_id);
+            let priority = payload.priority.unwrap_or(todo.priority);
+            let due_at = payload.due_at.unwrap_or(todo.due_at);
             let labels = match payload.labels {
-                Some(label_ids) => self.resolve_labels(label_ids),
+                Some(label_ids) => {
+                    check_exclusive(&self.labels, &label_ids)?;
+                    self.resolve_labels(label_ids)
+                }
                 None => todo.labels.clone(),
             };
+            let now = self.clock.now();
+            let completed_at = completed_at((todo.completed, todo.completed_at), completed, now);
             let todo = TodoEntity {
                 id,
                 text,
+                notes,
                 completed,
+                project_id,
+                status_id,
                 labels,
+                priority,
+                due_at,
+                blocked_by: todo.blocked_by.clone(),
+                blocked: false,
+                comment_count: 0,
+                created_at: todo.created_at,
+                updated_at: now,
+                completed_at,
             };
             store.insert(id, todo.clone());
-            Ok(todo)
+            Ok(self.with_comment_count(Self::with_dependencies(&store, todo)))
         }
 
         async fn delete(&self, id: i32) -> anyhow::Result<()> {
             let mut store = self.write_store_ref();
             store.remove(&id).ok_or(RepositoryError::NotFound(id))?;
+            if let Some(comments) = &self.comments {
+                comments.delete_in_todo(id);
+            }
+            Ok(())
+        }
+
+        // ラベルのストアを共有していない場合は、Todoのラベルだけを付け替える
+        async fn merge_labels(&self, target: i32, sources: &[i32]) -> anyhow::Result<()> {
+            // ラベルの削除と同じく、ラベル・Todoの順にロックする
+            let mut label_store = self
+                .label_store
+                .as_ref()
+                .map(|store| store.write().unwrap());
+            let target = match &label_store {
+                Some(label_store) => label_store.get(&target).cloned(),
+                None => self.labels.iter().find(|label| label.id == target).cloned(),
+            }
+            .ok_or(RepositoryError::NotFound(target))?;
+            if let Some(label_store) = &label_store {
+                if let Some(source) = sources.iter().find(|id| !label_store.contains_key(id)) {
+                    return Err(RepositoryError::NotFound(*source).into());
+                }
+            }
+            let mut store = self.write_store_ref();
+            for todo in store.values_mut() {
+                if !todo.labels.iter().any(|label| sources.contains(&label.id)) {
+                    continue;
+                }
+                todo.updated_at = self.clock.now();
+                todo.labels.retain(|label| !sources.contains(&label.id));
+                if !todo.labels.contains(&target) {
+                    todo.labels.push(target.clone());
+                }
+            }
+            if let Some(label_store) = label_store.as_mut() {
+                for source in sources {
+                    label_store.remove(source);
+                }
+                // 子のラベルは付け替え先の子にする
+                for label in label_store.values_mut() {
+                    if label
+                        .parent_id
+                        .is_some_and(|parent_id| sources.contains(&parent_id))
+                    {
+                        label.parent_id = (label.id != target.id).then_some(target.id);
+                    }
+                }
+            }
             Ok(())
         }
+
+        async fn add_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+            let mut store = self.write_store_ref();
+            for todo_id in [id, blocker_id] {
+                if !store.contains_key(&todo_id) {
+                    return Err(RepositoryError::NotFound(todo_id).into());
+                }
+            }
+            check_cycle(id, blocker_id, |current| {
+                store
+                    .get(&current)
+                    .map(|todo| todo.blocked_by.clone())
+                    .unwrap_or_default()
+            })?;
+            let todo = store.get_mut(&id).unwrap();
+            if todo.blocked_by.contains(&blocker_id) {
+                return Err(RepositoryError::Duplicate(blocker_id).into());
+            }
+            todo.blocked_by.push(blocker_id);
+            todo.blocked_by.sort_unstable();
+            let todo = todo.clone();
+            Ok(self.with_comment_count(Self::with_dependencies(&store, todo)))
+        }
+
+        async fn remove_dependency(&self, id: i32, blocker_id: i32) -> anyhow::Result<TodoEntity> {
+            let mut store = self.write_store_ref();
+            let todo = store.get_mut(&id).ok_or(RepositoryError::NotFound(id))?;
+            if !todo.blocked_by.contains(&blocker_id) {
+                return Err(RepositoryError::NotFound(blocker_id).into());
+            }
+            todo.blocked_by.retain(|id| *id != blocker_id);
+            let todo = todo.clone();
+            Ok(self.with_comment_count(Self::with_dependencies(&store, todo)))
+        }
     }
 
     #[cfg(test)]
     mod test {
         use super::*;
+        use chrono::{Duration, TimeZone};
 
         #[tokio::test]
         async fn todo_crud_scenario() {
             let text = "todo text".to_string();
             let id = 1;
-            let label_data = Label {
-                id: 1,
-                name: String::from("test label"),
-            };
+            let label_data = Label::new(1, String::from("test label"));
             let labels = vec![label_data.clone()];
             let expected = TodoEntity {
                 id,
                 text: text.clone(),
+                notes: None,
                 completed: false,
+                project_id: DEFAULT_PROJECT_ID,
+                status_id: None,
+                priority: None,
+                due_at: None,
+                blocked_by: vec![],
+                blocked: false,
                 labels: labels.clone(),
+                comment_count: 0,
+                created_at: DateTime::UNIX_EPOCH,
+                updated_at: DateTime::UNIX_EPOCH,
+                completed_at: None,
             };
 
             // create
-            let label_data = Label {
-                id: 1,
-                name: String::from("test label"),
-            };
+            let label_data = Label::new(1, String::from("test label"));
             let labels = vec![label_data.clone()];
             let repository = TodoRepositoryForMemory::new(labels.clone());
             let todo = repository
@@ -586,7 +2994,16 @@ pub mod test_utils {
 
             // all
             let todo = repository.all().await.expect("failed get all todo");
+            assert_eq!(vec![expected.clone()], todo);
+
+            // all_in_project
+            let todo = repository
+                .all_in_project(DEFAULT_PROJECT_ID)
+                .await
+                .expect("failed get project todo");
             assert_eq!(vec![expected], todo);
+            let todo = repository.all_in_project(2).await.unwrap();
+            assert!(todo.is_empty());
 
             // update
             let text = "update todo text".to_string();
@@ -595,8 +3012,13 @@ pub mod test_utils {
                     1,
                     UpdateTodo {
                         text: Some(text.clone()),
+                        notes: None,
                         completed: Some(true),
                         labels: Some(vec![]),
+                        project_id: None,
+                        status_id: None,
+                        priority: None,
+                        due_at: None,
                     },
                 )
                 .await
@@ -605,8 +3027,19 @@ pub mod test_utils {
                 TodoEntity {
                     id,
                     text,
+                    notes: None,
                     completed: true,
+                    project_id: DEFAULT_PROJECT_ID,
+                    status_id: None,
+                    priority: None,
+                    due_at: None,
+                    blocked_by: vec![],
+                    blocked: false,
                     labels: vec![],
+                    comment_count: 0,
+                    created_at: DateTime::UNIX_EPOCH,
+                    updated_at: DateTime::UNIX_EPOCH,
+                    completed_at: Some(DateTime::UNIX_EPOCH),
                 },
                 todo
             );
@@ -615,5 +3048,52 @@ pub mod test_utils {
             let res = repository.delete(id).await;
             assert!(res.is_ok())
         }
+
+        #[tokio::test]
+        async fn should_record_timestamps_with_clock() {
+            let clock = ManualClock::new(Utc.with_ymd_and_hms(2024, 11, 1, 9, 0, 0).unwrap());
+            let created_at = clock.now();
+            let repository = TodoRepositoryForMemory::new(vec![]).with_clock(clock.clone());
+            let todo = repository
+                .create(CreateTodo::new("todo text".to_string(), vec![]))
+                .await
+                .unwrap();
+            assert_eq!(todo.created_at, created_at);
+            assert_eq!(todo.updated_at, created_at);
+            assert_eq!(todo.completed_at, None);
+
+            // 完了にした日時を記録する
+            clock.advance(Duration::hours(1));
+            let completed_at = clock.now();
+            let complete = UpdateTodo {
+                completed: Some(true),
+                ..Default::default()
+            };
+            let todo = repository.update(todo.id, complete.clone()).await.unwrap();
+            assert_eq!(todo.created_at, created_at);
+            assert_eq!(todo.updated_at, completed_at);
+            assert_eq!(todo.completed_at, Some(completed_at));
+
+            // 完了済みのまま更新しても完了日時は変わらない
+            clock.advance(Duration::hours(1));
+            let todo = repository.update(todo.id, complete).await.unwrap();
+            assert_eq!(todo.updated_at, clock.now());
+            assert_eq!(todo.completed_at, Some(completed_at));
+
+            // 未完了に戻すと完了日時を消す
+            clock.advance(Duration::hours(1));
+            let todo = repository
+                .update(
+                    todo.id,
+                    UpdateTodo {
+                        completed: Some(false),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(todo.updated_at, clock.now());
+            assert_eq!(todo.completed_at, None);
+        }
     }
 }
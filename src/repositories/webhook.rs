@@ -0,0 +1,923 @@
+use super::RepositoryError;
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+// 連続でこの回数だけ配信に失敗したWebhookは自動的に無効化する
+pub const DISABLE_AFTER_FAILURES: i32 = 10;
+
+#[async_trait]
+pub trait WebhookRepository: Clone + std::marker::Send + std::marker::Sync + 'static {
+    async fn create(&self, payload: CreateWebhook) -> anyhow::Result<Webhook>;
+    async fn find(&self, id: i32) -> anyhow::Result<Webhook>;
+    async fn all(&self) -> anyhow::Result<Vec<Webhook>>;
+    async fn update(&self, id: i32, payload: UpdateWebhook) -> anyhow::Result<Webhook>;
+    async fn delete(&self, id: i32) -> anyhow::Result<()>;
+    /// イベントを購読している有効なWebhookごとに配信キューへ積む
+    async fn enqueue(
+        &self,
+        event: WebhookEvent,
+        payload: String,
+    ) -> anyhow::Result<Vec<WebhookDelivery>>;
+    /// 配信時刻を過ぎた配信を取得し、他のワーカーに拾われないよう次回時刻を先送りする
+    async fn claim_due(&self, limit: i64) -> anyhow::Result<Vec<OutgoingDelivery>>;
+    async fn mark_delivered(&self, delivery_id: i32, response_status: i32) -> anyhow::Result<()>;
+    /// `retry_at` が `None` の場合はリトライせず失敗として確定する
+    async fn mark_failed(
+        &self,
+        delivery_id: i32,
+        response_status: Option<i32>,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<()>;
+    async fn deliveries(&self, webhook_id: i32) -> anyhow::Result<Vec<WebhookDelivery>>;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+pub enum WebhookEvent {
+    #[serde(rename = "todo.created")]
+    TodoCreated,
+    #[serde(rename = "todo.updated")]
+    TodoUpdated,
+    #[serde(rename = "todo.completed")]
+    TodoCompleted,
+    #[serde(rename = "todo.deleted")]
+    TodoDeleted,
+    #[serde(rename = "label.created")]
+    LabelCreated,
+    #[serde(rename = "label.updated")]
+    LabelUpdated,
+    #[serde(rename = "label.deleted")]
+    LabelDeleted,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::TodoCreated => "todo.created",
+            WebhookEvent::TodoUpdated => "todo.updated",
+            WebhookEvent::TodoCompleted => "todo.completed",
+            WebhookEvent::TodoDeleted => "todo.deleted",
+            WebhookEvent::LabelCreated => "label.created",
+            WebhookEvent::LabelUpdated => "label.updated",
+            WebhookEvent::LabelDeleted => "label.deleted",
+        }
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "todo.created" => Ok(WebhookEvent::TodoCreated),
+            "todo.updated" => Ok(WebhookEvent::TodoUpdated),
+            "todo.completed" => Ok(WebhookEvent::TodoCompleted),
+            "todo.deleted" => Ok(WebhookEvent::TodoDeleted),
+            "label.created" => Ok(WebhookEvent::LabelCreated),
+            "label.updated" => Ok(WebhookEvent::LabelUpdated),
+            "label.deleted" => Ok(WebhookEvent::LabelDeleted),
+            _ => Err(RepositoryError::Unexpected(format!("unknown webhook event [{}]", s)).into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+impl FromStr for DeliveryStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "succeeded" => Ok(DeliveryStatus::Succeeded),
+            "failed" => Ok(DeliveryStatus::Failed),
+            _ => {
+                Err(RepositoryError::Unexpected(format!("unknown delivery status [{}]", s)).into())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    // 署名用の秘密鍵は作成時のレスポンスでのみ返す
+    #[serde(skip)]
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    pub enabled: bool,
+    pub failure_count: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct CreateWebhook {
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct UpdateWebhook {
+    pub url: Option<String>,
+    pub events: Option<Vec<WebhookEvent>>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event: WebhookEvent,
+    pub payload: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// 配信ワーカーが送信に必要とする情報
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutgoingDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub url: String,
+    pub secret: String,
+    pub event: WebhookEvent,
+    pub payload: String,
+    pub attempts: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+struct WebhookFromRow {
+    id: i32,
+    url: String,
+    secret: String,
+    events: Vec<String>,
+    enabled: bool,
+    failure_count: i32,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<WebhookFromRow> for Webhook {
+    type Error = anyhow::Error;
+
+    fn try_from(row: WebhookFromRow) -> Result<Self, Self::Error> {
+        let events = row
+            .events
+            .iter()
+            .map(|event| event.parse())
+            .collect::<anyhow::Result<Vec<WebhookEvent>>>()?;
+        Ok(Webhook {
+            id: row.id,
+            url: row.url,
+            secret: row.secret,
+            events,
+            enabled: row.enabled,
+            failure_count: row.failure_count,
+            created_at: row.created_at,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+struct DeliveryFromRow {
+    id: i32,
+    webhook_id: i32,
+    event: String,
+    payload: String,
+    status: String,
+    attempts: i32,
+    next_attempt_at: DateTime<Utc>,
+    response_status: Option<i32>,
+    last_error: Option<String>,
+    created_at: DateTime<Utc>,
+    delivered_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<DeliveryFromRow> for WebhookDelivery {
+    type Error = anyhow::Error;
+
+    fn try_from(row: DeliveryFromRow) -> Result<Self, Self::Error> {
+        Ok(WebhookDelivery {
+            id: row.id,
+            webhook_id: row.webhook_id,
+            event: row.event.parse()?,
+            payload: row.payload,
+            status: row.status.parse()?,
+            attempts: row.attempts,
+            next_attempt_at: row.next_attempt_at,
+            response_status: row.response_status,
+            last_error: row.last_error,
+            created_at: row.created_at,
+            delivered_at: row.delivered_at,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromRow)]
+struct OutgoingDeliveryFromRow {
+    id: i32,
+    webhook_id: i32,
+    url: String,
+    secret: String,
+    event: String,
+    payload: String,
+    attempts: i32,
+}
+
+impl TryFrom<OutgoingDeliveryFromRow> for OutgoingDelivery {
+    type Error = anyhow::Error;
+
+    fn try_from(row: OutgoingDeliveryFromRow) -> Result<Self, Self::Error> {
+        Ok(OutgoingDelivery {
+            id: row.id,
+            webhook_id: row.webhook_id,
+            url: row.url,
+            secret: row.secret,
+            event: row.event.parse()?,
+            payload: row.payload,
+            attempts: row.attempts,
+        })
+    }
+}
+
+fn event_names(events: &[WebhookEvent]) -> Vec<String> {
+    events
+        .iter()
+        .map(|event| event.as_str().to_string())
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookRepositoryForDb {
+    pool: PgPool,
+}
+
+impl WebhookRepositoryForDb {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl WebhookRepository for WebhookRepositoryForDb {
+    async fn create(&self, payload: CreateWebhook) -> anyhow::Result<Webhook> {
+        let secret = payload.secret.unwrap_or_else(generate_secret);
+        let row = sqlx::query_as::<_, WebhookFromRow>(
+            r#"
+                insert into webhooks (url, secret, events) values ($1, $2, $3) returning *;
+            "#,
+        )
+        .bind(payload.url)
+        .bind(secret)
+        .bind(event_names(&payload.events))
+        .fetch_one(&self.pool)
+        .await?;
+
+        row.try_into()
+    }
+
+    async fn find(&self, id: i32) -> anyhow::Result<Webhook> {
+        let row = sqlx::query_as::<_, WebhookFromRow>(
+            r#"
+                select * from webhooks where id=$1;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        row.try_into()
+    }
+
+    async fn all(&self) -> anyhow::Result<Vec<Webhook>> {
+        let rows = sqlx::query_as::<_, WebhookFromRow>(
+            r#"
+                select * from webhooks order by id asc;
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(Webhook::try_from).collect()
+    }
+
+    async fn update(&self, id: i32, payload: UpdateWebhook) -> anyhow::Result<Webhook> {
+        let old_webhook = self.find(id).await?;
+        let enabled = payload.enabled.unwrap_or(old_webhook.enabled);
+        // 再有効化した時は失敗回数をリセットする
+        let failure_count = if enabled && !old_webhook.enabled {
+            0
+        } else {
+            old_webhook.failure_count
+        };
+        let row = sqlx::query_as::<_, WebhookFromRow>(
+            r#"
+                update webhooks set url=$1, events=$2, enabled=$3, failure_count=$4
+                where id=$5 returning *;
+            "#,
+        )
+        .bind(payload.url.unwrap_or(old_webhook.url))
+        .bind(event_names(&payload.events.unwrap_or(old_webhook.events)))
+        .bind(enabled)
+        .bind(failure_count)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        row.try_into()
+    }
+
+    async fn delete(&self, id: i32) -> anyhow::Result<()> {
+        let result = sqlx::query(
+            r#"
+                delete from webhooks where id=$1;
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id).into());
+        }
+
+        Ok(())
+    }
+
+    async fn enqueue(
+        &self,
+        event: WebhookEvent,
+        payload: String,
+    ) -> anyhow::Result<Vec<WebhookDelivery>> {
+        let rows = sqlx::query_as::<_, DeliveryFromRow>(
+            r#"
+                insert into webhook_deliveries (webhook_id, event, payload)
+                select id, $1, $2 from webhooks where enabled and $1 = any(events)
+                returning *;
+            "#,
+        )
+        .bind(event.as_str())
+        .bind(payload)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(WebhookDelivery::try_from).collect()
+    }
+
+    async fn claim_due(&self, limit: i64) -> anyhow::Result<Vec<OutgoingDelivery>> {
+        let rows = sqlx::query_as::<_, OutgoingDeliveryFromRow>(
+            r#"
+                update webhook_deliveries d set next_attempt_at = now() + interval '1 minute'
+                from webhooks w
+                where d.webhook_id = w.id and d.id in (
+                    select due.id from webhook_deliveries due
+                    join webhooks hook on hook.id = due.webhook_id
+                    where due.status = 'pending' and due.next_attempt_at <= now() and hook.enabled
+                    order by due.next_attempt_at asc
+                    limit $1
+                    for update of due skip locked
+                )
+                returning d.id, d.webhook_id, w.url, w.secret, d.event, d.payload, d.attempts;
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(OutgoingDelivery::try_from).collect()
+    }
+
+    async fn mark_delivered(&self, delivery_id: i32, response_status: i32) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let webhook_id = sqlx::query_scalar::<_, i32>(
+            r#"
+                update webhook_deliveries
+                set status='succeeded', attempts=attempts + 1, response_status=$2,
+                    last_error=null, delivered_at=now()
+                where id=$1 returning webhook_id;
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(response_status)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(RepositoryError::NotFound(delivery_id))?;
+
+        sqlx::query(
+            r#"
+                update webhooks set failure_count=0 where id=$1;
+            "#,
+        )
+        .bind(webhook_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        delivery_id: i32,
+        response_status: Option<i32>,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let webhook_id = sqlx::query_scalar::<_, i32>(
+            r#"
+                update webhook_deliveries
+                set attempts=attempts + 1, response_status=$2, last_error=$3,
+                    status=case when $4::timestamptz is null then 'failed' else 'pending' end,
+                    next_attempt_at=coalesce($4, next_attempt_at)
+                where id=$1 returning webhook_id;
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(response_status)
+        .bind(error)
+        .bind(retry_at)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(RepositoryError::NotFound(delivery_id))?;
+
+        sqlx::query(
+            r#"
+                update webhooks
+                set failure_count=failure_count + 1, enabled=enabled and failure_count + 1 < $2
+                where id=$1;
+            "#,
+        )
+        .bind(webhook_id)
+        .bind(DISABLE_AFTER_FAILURES)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn deliveries(&self, webhook_id: i32) -> anyhow::Result<Vec<WebhookDelivery>> {
+        let rows = sqlx::query_as::<_, DeliveryFromRow>(
+            r#"
+                select * from webhook_deliveries where webhook_id=$1 order by id desc;
+            "#,
+        )
+        .bind(webhook_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(WebhookDelivery::try_from).collect()
+    }
+}
+
+fn generate_secret() -> String {
+    let bytes: [u8; 32] = rand::random();
+    hex::encode(bytes)
+}
+
+#[cfg(test)]
+#[cfg(feature = "database-test")]
+mod test {
+    use super::*;
+    use dotenv::dotenv;
+    use sqlx::PgPool;
+    use std::env;
+
+    #[tokio::test]
+    async fn crud_scenario() {
+        dotenv().ok();
+        let database_url = &env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(database_url)
+            .await
+            .unwrap_or_else(|_| panic!("fail connect database, url is [{}]", database_url));
+
+        let repository = WebhookRepositoryForDb::new(pool);
+        let url = "http://localhost:9999/repositories/webhook.rs";
+
+        // create
+        let webhook = repository
+            .create(CreateWebhook {
+                url: url.to_string(),
+                events: vec![WebhookEvent::LabelDeleted],
+                secret: None,
+            })
+            .await
+            .expect("[create] returned Err");
+        assert_eq!(webhook.url, url);
+        assert!(webhook.enabled);
+        assert_eq!(webhook.secret.len(), 64);
+
+        // enqueue
+        let deliveries = repository
+            .enqueue(WebhookEvent::LabelDeleted, r#"{"id":1}"#.to_string())
+            .await
+            .expect("[enqueue] returned Err");
+        let delivery = deliveries
+            .iter()
+            .find(|delivery| delivery.webhook_id == webhook.id)
+            .expect("[enqueue] delivery not created");
+        assert_eq!(delivery.status, DeliveryStatus::Pending);
+
+        // claim / mark_failed
+        let claimed = repository
+            .claim_due(100)
+            .await
+            .expect("[claim_due] returned Err");
+        assert!(claimed.iter().any(|outgoing| outgoing.id == delivery.id));
+        repository
+            .mark_failed(delivery.id, Some(500), "server error".to_string(), None)
+            .await
+            .expect("[mark_failed] returned Err");
+        let history = repository
+            .deliveries(webhook.id)
+            .await
+            .expect("[deliveries] returned Err");
+        assert_eq!(history[0].status, DeliveryStatus::Failed);
+        assert_eq!(history[0].attempts, 1);
+        assert_eq!(repository.find(webhook.id).await.unwrap().failure_count, 1);
+
+        // update
+        let updated = repository
+            .update(
+                webhook.id,
+                UpdateWebhook {
+                    url: None,
+                    events: Some(vec![WebhookEvent::TodoCreated]),
+                    enabled: Some(false),
+                },
+            )
+            .await
+            .expect("[update] returned Err");
+        assert!(!updated.enabled);
+        assert_eq!(updated.events, vec![WebhookEvent::TodoCreated]);
+
+        // delete
+        repository
+            .delete(webhook.id)
+            .await
+            .expect("[delete] returned Err");
+        assert!(repository.find(webhook.id).await.is_err());
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+    use chrono::Duration;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    };
+
+    #[derive(Debug, Default)]
+    pub struct WebhookData {
+        webhooks: HashMap<i32, Webhook>,
+        deliveries: HashMap<i32, WebhookDelivery>,
+        // DBのserialと同じく、削除済みのIDを再利用しない
+        webhook_sequence: i32,
+        delivery_sequence: i32,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct WebhookRepositoryForMemory {
+        store: Arc<RwLock<WebhookData>>,
+    }
+
+    impl Default for WebhookRepositoryForMemory {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl WebhookRepositoryForMemory {
+        pub fn new() -> Self {
+            WebhookRepositoryForMemory {
+                store: Arc::default(),
+            }
+        }
+
+        fn write_store_ref(&self) -> RwLockWriteGuard<'_, WebhookData> {
+            self.store.write().unwrap()
+        }
+
+        fn read_store_ref(&self) -> RwLockReadGuard<'_, WebhookData> {
+            self.store.read().unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl WebhookRepository for WebhookRepositoryForMemory {
+        async fn create(&self, payload: CreateWebhook) -> anyhow::Result<Webhook> {
+            let mut store = self.write_store_ref();
+            store.webhook_sequence += 1;
+            let id = store.webhook_sequence;
+            let webhook = Webhook {
+                id,
+                url: payload.url,
+                secret: payload.secret.unwrap_or_else(generate_secret),
+                events: payload.events,
+                enabled: true,
+                failure_count: 0,
+                created_at: Utc::now(),
+            };
+            store.webhooks.insert(id, webhook.clone());
+            Ok(webhook)
+        }
+
+        async fn find(&self, id: i32) -> anyhow::Result<Webhook> {
+            let store = self.read_store_ref();
+            let webhook = store
+                .webhooks
+                .get(&id)
+                .cloned()
+                .ok_or(RepositoryError::NotFound(id))?;
+            Ok(webhook)
+        }
+
+        async fn all(&self) -> anyhow::Result<Vec<Webhook>> {
+            let store = self.read_store_ref();
+            let mut webhooks = Vec::from_iter(store.webhooks.values().cloned());
+            webhooks.sort_by_key(|webhook| webhook.id);
+            Ok(webhooks)
+        }
+
+        async fn update(&self, id: i32, payload: UpdateWebhook) -> anyhow::Result<Webhook> {
+            let mut store = self.write_store_ref();
+            let webhook = store
+                .webhooks
+                .get_mut(&id)
+                .ok_or(RepositoryError::NotFound(id))?;
+            if let Some(url) = payload.url {
+                webhook.url = url;
+            }
+            if let Some(events) = payload.events {
+                webhook.events = events;
+            }
+            if let Some(enabled) = payload.enabled {
+                if enabled && !webhook.enabled {
+                    webhook.failure_count = 0;
+                }
+                webhook.enabled = enabled;
+            }
+            Ok(webhook.clone())
+        }
+
+        async fn delete(&self, id: i32) -> anyhow::Result<()> {
+            let mut store = self.write_store_ref();
+            store
+                .webhooks
+                .remove(&id)
+                .ok_or(RepositoryError::NotFound(id))?;
+            store
+                .deliveries
+                .retain(|_id, delivery| delivery.webhook_id != id);
+            Ok(())
+        }
+
+        async fn enqueue(
+            &self,
+            event: WebhookEvent,
+            payload: String,
+        ) -> anyhow::Result<Vec<WebhookDelivery>> {
+            let mut store = self.write_store_ref();
+            let mut webhook_ids = store
+                .webhooks
+                .values()
+                .filter(|webhook| webhook.enabled && webhook.events.contains(&event))
+                .map(|webhook| webhook.id)
+                .collect::<Vec<_>>();
+            webhook_ids.sort();
+
+            let now = Utc::now();
+            let mut deliveries = vec![];
+            for webhook_id in webhook_ids {
+                store.delivery_sequence += 1;
+                let id = store.delivery_sequence;
+                let delivery = WebhookDelivery {
+                    id,
+                    webhook_id,
+                    event,
+                    payload: payload.clone(),
+                    status: DeliveryStatus::Pending,
+                    attempts: 0,
+                    next_attempt_at: now,
+                    response_status: None,
+                    last_error: None,
+                    created_at: now,
+                    delivered_at: None,
+                };
+                store.deliveries.insert(id, delivery.clone());
+                deliveries.push(delivery);
+            }
+            Ok(deliveries)
+        }
+
+        async fn claim_due(&self, limit: i64) -> anyhow::Result<Vec<OutgoingDelivery>> {
+            let mut store = self.write_store_ref();
+            let now = Utc::now();
+            let mut due = store
+                .deliveries
+                .values()
+                .filter(|delivery| {
+                    delivery.status == DeliveryStatus::Pending
+                        && delivery.next_attempt_at <= now
+                        && store
+                            .webhooks
+                            .get(&delivery.webhook_id)
+                            .is_some_and(|webhook| webhook.enabled)
+                })
+                .map(|delivery| (delivery.next_attempt_at, delivery.id))
+                .collect::<Vec<_>>();
+            due.sort();
+            due.truncate(limit as usize);
+
+            let mut claimed = vec![];
+            for (_, id) in due {
+                let delivery = store.deliveries.get_mut(&id).unwrap();
+                delivery.next_attempt_at = now + Duration::minutes(1);
+                let delivery = delivery.clone();
+                let webhook = &store.webhooks[&delivery.webhook_id];
+                claimed.push(OutgoingDelivery {
+                    id: delivery.id,
+                    webhook_id: delivery.webhook_id,
+                    url: webhook.url.clone(),
+                    secret: webhook.secret.clone(),
+                    event: delivery.event,
+                    payload: delivery.payload,
+                    attempts: delivery.attempts,
+                });
+            }
+            Ok(claimed)
+        }
+
+        async fn mark_delivered(
+            &self,
+            delivery_id: i32,
+            response_status: i32,
+        ) -> anyhow::Result<()> {
+            let mut store = self.write_store_ref();
+            let delivery = store
+                .deliveries
+                .get_mut(&delivery_id)
+                .ok_or(RepositoryError::NotFound(delivery_id))?;
+            delivery.status = DeliveryStatus::Succeeded;
+            delivery.attempts += 1;
+            delivery.response_status = Some(response_status);
+            delivery.last_error = None;
+            delivery.delivered_at = Some(Utc::now());
+            let webhook_id = delivery.webhook_id;
+            if let Some(webhook) = store.webhooks.get_mut(&webhook_id) {
+                webhook.failure_count = 0;
+            }
+            Ok(())
+        }
+
+        async fn mark_failed(
+            &self,
+            delivery_id: i32,
+            response_status: Option<i32>,
+            error: String,
+            retry_at: Option<DateTime<Utc>>,
+        ) -> anyhow::Result<()> {
+            let mut store = self.write_store_ref();
+            let delivery = store
+                .deliveries
+                .get_mut(&delivery_id)
+                .ok_or(RepositoryError::NotFound(delivery_id))?;
+            delivery.attempts += 1;
+            delivery.response_status = response_status;
+            delivery.last_error = Some(error);
+            match retry_at {
+                Some(retry_at) => delivery.next_attempt_at = retry_at,
+                None => delivery.status = DeliveryStatus::Failed,
+            }
+            let webhook_id = delivery.webhook_id;
+            if let Some(webhook) = store.webhooks.get_mut(&webhook_id) {
+                webhook.failure_count += 1;
+                webhook.enabled = webhook.enabled && webhook.failure_count < DISABLE_AFTER_FAILURES;
+            }
+            Ok(())
+        }
+
+        async fn deliveries(&self, webhook_id: i32) -> anyhow::Result<Vec<WebhookDelivery>> {
+            let store = self.read_store_ref();
+            let mut deliveries = store
+                .deliveries
+                .values()
+                .filter(|delivery| delivery.webhook_id == webhook_id)
+                .cloned()
+                .collect::<Vec<_>>();
+            deliveries.sort_by_key(|delivery| std::cmp::Reverse(delivery.id));
+            Ok(deliveries)
+        }
+    }
+
+    mod test {
+        use super::*;
+
+        #[tokio::test]
+        async fn webhook_crud_scenario() {
+            let repository = WebhookRepositoryForMemory::new();
+
+            // create
+            let webhook = repository
+                .create(CreateWebhook {
+                    url: "http://localhost/hook".to_string(),
+                    events: vec![WebhookEvent::TodoCreated],
+                    secret: Some("secret".to_string()),
+                })
+                .await
+                .expect("failed create webhook");
+            assert_eq!(webhook.id, 1);
+            assert_eq!(webhook.secret, "secret");
+
+            // enqueue only for subscribed events
+            let deliveries = repository
+                .enqueue(WebhookEvent::TodoDeleted, "{}".to_string())
+                .await
+                .unwrap();
+            assert!(deliveries.is_empty());
+            let deliveries = repository
+                .enqueue(WebhookEvent::TodoCreated, "{}".to_string())
+                .await
+                .unwrap();
+            assert_eq!(deliveries.len(), 1);
+
+            // claim hides the delivery until the lease expires
+            let claimed = repository.claim_due(10).await.unwrap();
+            assert_eq!(claimed.len(), 1);
+            assert!(repository.claim_due(10).await.unwrap().is_empty());
+
+            // repeated failures disable the webhook
+            for _ in 0..DISABLE_AFTER_FAILURES {
+                repository
+                    .mark_failed(
+                        claimed[0].id,
+                        Some(500),
+                        "error".to_string(),
+                        Some(Utc::now()),
+                    )
+                    .await
+                    .unwrap();
+            }
+            let webhook = repository.find(webhook.id).await.unwrap();
+            assert!(!webhook.enabled);
+            assert_eq!(webhook.failure_count, DISABLE_AFTER_FAILURES);
+            assert!(repository.claim_due(10).await.unwrap().is_empty());
+
+            // re-enable resets the failure count
+            let webhook = repository
+                .update(
+                    webhook.id,
+                    UpdateWebhook {
+                        url: None,
+                        events: None,
+                        enabled: Some(true),
+                    },
+                )
+                .await
+                .unwrap();
+            assert!(webhook.enabled);
+            assert_eq!(webhook.failure_count, 0);
+
+            // delete
+            let res = repository.delete(webhook.id).await;
+            assert!(res.is_ok());
+            assert!(repository.deliveries(webhook.id).await.unwrap().is_empty());
+
+            // deleted ids are not reused
+            let created = repository
+                .create(CreateWebhook {
+                    url: "http://localhost/hook".to_string(),
+                    events: vec![WebhookEvent::TodoCreated],
+                    secret: None,
+                })
+                .await
+                .unwrap();
+            assert_eq!(created.id, webhook.id + 1);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+pub mod local;
+pub mod s3;
+
+use crate::repositories::attachment::AttachmentRepository;
+use axum::async_trait;
+use local::LocalStorage;
+use s3::S3Storage;
+use shuttle_runtime::SecretStore;
+use std::{sync::Arc, time::Duration};
+use thiserror::Error;
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+const CLEANUP_BATCH_SIZE: i64 = 50;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Blob not found, key is {0}")]
+    NotFound(String),
+}
+
+/// 添付ファイルの実体(Blob)の保存先
+#[async_trait]
+pub trait BlobStorage: Clone + std::marker::Send + std::marker::Sync + 'static {
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> anyhow::Result<()>;
+    /// 存在しない場合は `StorageError::NotFound` を返す
+    async fn get(&self, key: &str) -> anyhow::Result<Vec<u8>>;
+    /// 存在しないキーの削除は成功として扱う
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+}
+
+/// Secretsの設定で選択されるストレージ
+///
+/// `S3_ENDPOINT` があればS3互換ストレージ、なければ `ATTACHMENT_DIR` (既定は `attachments`) に保存する
+#[derive(Debug, Clone)]
+pub enum StorageBackend {
+    Local(LocalStorage),
+    S3(S3Storage),
+}
+
+impl StorageBackend {
+    pub fn from_secrets(secrets: &SecretStore) -> Self {
+        match secrets.get("S3_ENDPOINT") {
+            Some(endpoint) => StorageBackend::S3(S3Storage::new(
+                endpoint,
+                secrets.get("S3_BUCKET").expect("undefined [S3_BUCKET]"),
+                secrets.get("S3_REGION").unwrap_or("us-east-1".to_string()),
+                secrets
+                    .get("S3_ACCESS_KEY")
+                    .expect("undefined [S3_ACCESS_KEY]"),
+                secrets
+                    .get("S3_SECRET_KEY")
+                    .expect("undefined [S3_SECRET_KEY]"),
+            )),
+            None => StorageBackend::Local(LocalStorage::new(
+                secrets
+                    .get("ATTACHMENT_DIR")
+                    .unwrap_or("attachments".to_string()),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl BlobStorage for StorageBackend {
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> anyhow::Result<()> {
+        match self {
+            StorageBackend::Local(storage) => storage.put(key, data, content_type).await,
+            StorageBackend::S3(storage) => storage.put(key, data, content_type).await,
+        }
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+        match self {
+            StorageBackend::Local(storage) => storage.get(key).await,
+            StorageBackend::S3(storage) => storage.get(key).await,
+        }
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match self {
+            StorageBackend::Local(storage) => storage.delete(key).await,
+            StorageBackend::S3(storage) => storage.delete(key).await,
+        }
+    }
+}
+
+/// Todoの削除で紐付けが外れた添付ファイルのBlobを削除する
+#[derive(Debug, Clone)]
+pub struct AttachmentJanitor<A: AttachmentRepository, S: BlobStorage> {
+    repository: Arc<A>,
+    storage: Arc<S>,
+}
+
+impl<A: AttachmentRepository, S: BlobStorage> AttachmentJanitor<A, S> {
+    pub fn new(repository: Arc<A>, storage: Arc<S>) -> Self {
+        Self {
+            repository,
+            storage,
+        }
+    }
+
+    /// 削除待ちの添付ファイルを定期的に掃除する
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.sweep().await {
+                tracing::error!("fail cleanup attachments: {}", e);
+            }
+            tokio::time::sleep(CLEANUP_INTERVAL).await;
+        }
+    }
+
+    /// Blobとメタデータを削除し、処理した件数を返す
+    pub async fn sweep(&self) -> anyhow::Result<usize> {
+        let attachments = self.repository.orphaned(CLEANUP_BATCH_SIZE).await?;
+        let count = attachments.len();
+        for attachment in attachments {
+            // Blobの削除に失敗した場合はメタデータを残して次回に再試行する
+            self.storage.delete(&attachment.storage_key).await?;
+            self.repository.delete(attachment.id).await?;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    };
+
+    type BlobData = HashMap<String, Vec<u8>>;
+
+    #[derive(Debug, Clone)]
+    pub struct MemoryStorage {
+        store: Arc<RwLock<BlobData>>,
+    }
+
+    impl Default for MemoryStorage {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl MemoryStorage {
+        pub fn new() -> Self {
+            MemoryStorage {
+                store: Arc::default(),
+            }
+        }
+
+        fn write_store_ref(&self) -> RwLockWriteGuard<'_, BlobData> {
+            self.store.write().unwrap()
+        }
+
+        fn read_store_ref(&self) -> RwLockReadGuard<'_, BlobData> {
+            self.store.read().unwrap()
+        }
+
+        pub fn blob_count(&self) -> usize {
+            self.read_store_ref().len()
+        }
+    }
+
+    #[async_trait]
+    impl BlobStorage for MemoryStorage {
+        async fn put(&self, key: &str, data: Vec<u8>, _content_type: &str) -> anyhow::Result<()> {
+            self.write_store_ref().insert(key.to_string(), data);
+            Ok(())
+        }
+
+        async fn get(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+            let store = self.read_store_ref();
+            let data = store
+                .get(key)
+                .cloned()
+                .ok_or(StorageError::NotFound(key.to_string()))?;
+            Ok(data)
+        }
+
+        async fn delete(&self, key: &str) -> anyhow::Result<()> {
+            self.write_store_ref().remove(key);
+            Ok(())
+        }
+    }
+
+    mod test {
+        use super::*;
+        use crate::repositories::attachment::{
+            test_utils::AttachmentRepositoryForMemory, CreateAttachment,
+        };
+
+        #[tokio::test]
+        async fn should_sweep_orphaned_attachments() {
+            let repository = Arc::new(AttachmentRepositoryForMemory::new());
+            let storage = Arc::new(MemoryStorage::new());
+            for (todo_id, key) in [(1, "todos/1/a"), (2, "todos/2/b")] {
+                storage
+                    .put(key, b"data".to_vec(), "text/plain")
+                    .await
+                    .unwrap();
+                repository
+                    .create(CreateAttachment {
+                        todo_id,
+                        filename: "data.txt".to_string(),
+                        content_type: "text/plain".to_string(),
+                        size: 4,
+                        storage_key: key.to_string(),
+                    })
+                    .await
+                    .unwrap();
+            }
+
+            let janitor = AttachmentJanitor::new(repository.clone(), storage.clone());
+            assert_eq!(janitor.sweep().await.unwrap(), 0);
+
+            repository.detach_todo(1).await.unwrap();
+            assert_eq!(janitor.sweep().await.unwrap(), 1);
+            assert_eq!(storage.blob_count(), 1);
+            assert!(storage.get("todos/1/a").await.is_err());
+            assert!(repository.find(1).await.is_err());
+            assert_eq!(repository.all_in_todo(2).await.unwrap().len(), 1);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use super::{BlobStorage, StorageError};
+use axum::async_trait;
+use std::{
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+/// ローカルのファイルシステムに保存する
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// キーをルートディレクトリ配下のパスに変換する。ルートの外を指すキーは拒否する
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        let relative = Path::new(key);
+        let is_safe = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if key.is_empty() || !is_safe {
+            anyhow::bail!("invalid storage key [{}]", key);
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl BlobStorage for LocalStorage {
+    async fn put(&self, key: &str, data: Vec<u8>, _content_type: &str) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, data).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Vec<u8>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                Err(StorageError::NotFound(key.to_string()).into())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_storage_scenario() {
+        let root =
+            std::env::temp_dir().join(format!("todo-local-storage-{}", rand::random::<u32>()));
+        let storage = LocalStorage::new(&root);
+
+        storage
+            .put("todos/1/memo", b"hello".to_vec(), "text/plain")
+            .await
+            .expect("failed put blob");
+        assert_eq!(storage.get("todos/1/memo").await.unwrap(), b"hello");
+
+        storage.delete("todos/1/memo").await.unwrap();
+        let err = storage.get("todos/1/memo").await.unwrap_err();
+        assert!(err.downcast_ref::<StorageError>().is_some());
+        // 削除済みのキーをもう一度削除しても失敗しない
+        assert!(storage.delete("todos/1/memo").await.is_ok());
+
+        // ルートの外には書き込めない
+        assert!(storage
+            .put("../escape", vec![], "text/plain")
+            .await
+            .is_err());
+        assert!(storage
+            .put("/etc/passwd", vec![], "text/plain")
+            .await
+            .is_err());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
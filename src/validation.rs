@@ -0,0 +1,626 @@
+use crate::repositories::{
+    label::{
+        exclusive_conflicts, CreateLabel, ExclusiveLabelError, Label, LabelRepository, MergeLabels,
+        UpdateLabel,
+    },
+    project::{CreateProject, UpdateProject},
+    status::{CreateStatus, UpdateStatus},
+    sync::PushTodo,
+    todo::{CreateTodo, QuickAdd, TodoDependency, UpdateTodo},
+};
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::FixedOffset;
+use serde::{de::DeserializeOwned, Serialize};
+use shuttle_runtime::SecretStore;
+use std::{collections::HashSet, sync::Arc};
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+const ERR_STR_EMPTY: &str = "Error!: Can not be Empty";
+const ERR_STR_UNDER: &str = "Error!: Under text length";
+const ERR_STR_OVER: &str = "Error!: Over text length";
+const ERR_STR_CONTROL: &str = "Error!: Control characters are not allowed";
+const ERR_STR_INVALID_LABEL: &str = "Error!: Label id must be positive";
+const ERR_STR_DUPLICATE_LABEL: &str = "Error!: Duplicate label id";
+const ERR_STR_TOO_MANY_LABELS: &str = "Error!: Too many labels";
+const ERR_STR_NO_SOURCES: &str = "Error!: Specify labels to merge";
+const ERR_STR_UNKNOWN_LABEL: &str = "Error!: Label not found";
+const ERR_STR_COLOR: &str = "Error!: Color must be #rrggbb";
+const ERR_STR_UTC_OFFSET: &str = "Error!: UTC offset must be +hh:mm";
+const ERR_STR_INVALID_TODO: &str = "Error!: Todo id must be positive";
+const ERR_STR_INVALID_WIP_LIMIT: &str = "Error!: WIP limit must be positive";
+const ERR_STR_EXCLUSIVE_LABEL: &str =
+    "Error!: Only one label can be selected from an exclusive label group";
+
+/// 文字数の制限。文字数は書記素クラスタ(見た目の1文字)で数える
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextRule {
+    pub min: usize,
+    pub max: usize,
+    /// trueの場合は改行とタブを許可する。改行はLFにそろえる
+    pub multiline: bool,
+}
+
+impl TextRule {
+    pub const fn new(min: usize, max: usize) -> Self {
+        Self {
+            min,
+            max,
+            multiline: false,
+        }
+    }
+
+    pub const fn multiline(min: usize, max: usize) -> Self {
+        Self {
+            min,
+            max,
+            multiline: true,
+        }
+    }
+
+    /// 前後の空白を取り除いてNFCに正規化し、制限を満たさない場合はエラーを追加する
+    pub fn apply(&self, field: &str, value: &mut String, errors: &mut ValidationErrors) {
+        *value = value.trim().nfc().collect();
+        if self.multiline {
+            *value = value.replace("\r\n", "\n");
+        }
+        let allowed = |c: char| self.multiline && (c == '\n' || c == '\t');
+        if value.chars().any(|c| c.is_control() && !allowed(c)) {
+            errors.push(field, "control_character", ERR_STR_CONTROL);
+        }
+        let count = value.graphemes(true).count();
+        if count == 0 {
+            errors.push(field, "empty", ERR_STR_EMPTY);
+        } else if count < self.min {
+            errors.push(field, "too_short", ERR_STR_UNDER);
+        } else if count > self.max {
+            errors.push(field, "too_long", ERR_STR_OVER);
+        }
+    }
+
+    /// 省略できる値に使う。空白だけの値は省略(None)として扱う
+    pub fn apply_optional(
+        &self,
+        field: &str,
+        value: &mut Option<String>,
+        errors: &mut ValidationErrors,
+    ) {
+        if value.as_deref().is_some_and(|v| v.trim().is_empty()) {
+            *value = None;
+        }
+        if let Some(value) = value {
+            self.apply(field, value, errors);
+        }
+    }
+}
+
+/// `#rrggbb` 形式の色か確認し、小文字にそろえる
+pub fn apply_color(field: &str, value: &mut String, errors: &mut ValidationErrors) {
+    *value = value.trim().to_ascii_lowercase();
+    let valid = value.len() == 7
+        && value.starts_with('#')
+        && value[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !valid {
+        errors.push(field, "invalid_color", ERR_STR_COLOR);
+    }
+}
+
+/// 入力値の制限
+///
+/// `create_app` で作ったルーターに `Extension(Arc<ValidationRules>)` を追加すると変更できる。ない場合は既定値を使う
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationRules {
+    pub todo_text: TextRule,
+    /// Todoのメモ。改行を含められる
+    pub todo_notes: TextRule,
+    pub label_name: TextRule,
+    pub label_description: TextRule,
+    /// アイコン名または絵文字
+    pub label_icon: TextRule,
+    pub project_name: TextRule,
+    /// かんばんの列の名前
+    pub status_name: TextRule,
+    /// 1件のTodoに付けられるラベルの数
+    pub max_labels: usize,
+}
+
+impl Default for ValidationRules {
+    fn default() -> Self {
+        Self {
+            todo_text: TextRule::new(1, 100),
+            todo_notes: TextRule::multiline(1, 10000),
+            label_name: TextRule::new(1, 100),
+            label_description: TextRule::new(1, 200),
+            label_icon: TextRule::new(1, 32),
+            project_name: TextRule::new(1, 100),
+            status_name: TextRule::new(1, 100),
+            max_labels: 20,
+        }
+    }
+}
+
+impl ValidationRules {
+    /// `TODO_TEXT_MAX_LENGTH`・`TODO_NOTES_MAX_LENGTH`・`LABEL_NAME_MAX_LENGTH`・`MAX_LABELS_PER_TODO` で既定値を上書きする
+    pub fn from_secrets(secrets: &SecretStore) -> anyhow::Result<Self> {
+        let mut rules = Self::default();
+        if let Some(max) = secrets.get("TODO_TEXT_MAX_LENGTH") {
+            rules.todo_text.max = max.parse()?;
+        }
+        if let Some(max) = secrets.get("TODO_NOTES_MAX_LENGTH") {
+            rules.todo_notes.max = max.parse()?;
+        }
+        if let Some(max) = secrets.get("LABEL_NAME_MAX_LENGTH") {
+            rules.label_name.max = max.parse()?;
+        }
+        if let Some(max) = secrets.get("MAX_LABELS_PER_TODO") {
+            rules.max_labels = max.parse()?;
+        }
+        Ok(rules)
+    }
+
+    /// ラベルIDの並びを検証する。存在の確認は `ValidJson` が行う
+    pub fn apply_labels(&self, field: &str, labels: &[i32], errors: &mut ValidationErrors) {
+        self.apply_label_count(field, labels.len(), errors);
+        apply_label_ids(field, labels, errors);
+    }
+
+    /// 1件のTodoに付けるラベルの数を確認する
+    pub fn apply_label_count(&self, field: &str, count: usize, errors: &mut ValidationErrors) {
+        if count > self.max_labels {
+            errors.push(field, "too_many", ERR_STR_TOO_MANY_LABELS);
+        }
+    }
+}
+
+/// ラベルIDが正の値で重複していないことを確認する
+fn apply_label_ids(field: &str, labels: &[i32], errors: &mut ValidationErrors) {
+    let mut seen = HashSet::new();
+    for (index, id) in labels.iter().enumerate() {
+        let field = format!("{}[{}]", field, index);
+        if *id <= 0 {
+            errors.push(&field, "invalid", ERR_STR_INVALID_LABEL);
+        } else if !seen.insert(id) {
+            errors.push(&field, "duplicate", ERR_STR_DUPLICATE_LABEL);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// 検証エラーの一覧。400で `{"errors": [...]}` を返す
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn push(&mut self, field: &str, code: &str, message: &str) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            code: code.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+// ValidJsonの確認の後に別のリクエストでラベルが変更された場合、リポジトリで検出される
+impl From<&ExclusiveLabelError> for ValidationErrors {
+    fn from(_: &ExclusiveLabelError) -> Self {
+        let mut errors = Self::default();
+        errors.push("labels", "exclusive", ERR_STR_EXCLUSIVE_LABEL);
+        errors
+    }
+}
+
+impl IntoResponse for ValidationErrors {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, Json(self)).into_response()
+    }
+}
+
+/// リクエストボディの正規化と検証
+pub trait Validate {
+    /// 値を正規化しながら検証し、見つかったエラーをすべて `errors` に追加する
+    fn validate(&mut self, rules: &ValidationRules, errors: &mut ValidationErrors);
+
+    /// 存在を確認するラベルID
+    fn label_ids(&self) -> Option<&[i32]> {
+        None
+    }
+}
+
+impl Validate for CreateTodo {
+    fn validate(&mut self, rules: &ValidationRules, errors: &mut ValidationErrors) {
+        rules.todo_text.apply("text", &mut self.text, errors);
+        rules
+            .todo_notes
+            .apply_optional("notes", &mut self.notes, errors);
+        rules.apply_labels("labels", &self.labels, errors);
+    }
+
+    fn label_ids(&self) -> Option<&[i32]> {
+        Some(&self.labels)
+    }
+}
+
+impl Validate for UpdateTodo {
+    fn validate(&mut self, rules: &ValidationRules, errors: &mut ValidationErrors) {
+        if let Some(text) = &mut self.text {
+            rules.todo_text.apply("text", text, errors);
+        }
+        // nullはメモを消す指定のため検証しない
+        if let Some(notes) = &mut self.notes {
+            rules.todo_notes.apply_optional("notes", notes, errors);
+        }
+        if let Some(labels) = &self.labels {
+            rules.apply_labels("labels", labels, errors);
+        }
+    }
+
+    fn label_ids(&self) -> Option<&[i32]> {
+        self.labels.as_deref()
+    }
+}
+
+impl Validate for CreateLabel {
+    fn validate(&mut self, rules: &ValidationRules, errors: &mut ValidationErrors) {
+        rules.label_name.apply("name", &mut self.name, errors);
+        if let Some(color) = &mut self.color {
+            apply_color("color", color, errors);
+        }
+        rules
+            .label_description
+            .apply_optional("description", &mut self.description, errors);
+        rules
+            .label_icon
+            .apply_optional("icon", &mut self.icon, errors);
+    }
+}
+
+impl Validate for UpdateLabel {
+    fn validate(&mut self, rules: &ValidationRules, errors: &mut ValidationErrors) {
+        if let Some(name) = &mut self.name {
+            rules.label_name.apply("name", name, errors);
+        }
+        if let Some(color) = &mut self.color {
+            apply_color("color", color, errors);
+        }
+        // nullは説明・アイコンを外す指定のため検証しない
+        if let Some(description) = &mut self.description {
+            rules
+                .label_description
+                .apply_optional("description", description, errors);
+        }
+        if let Some(icon) = &mut self.icon {
+            rules.label_icon.apply_optional("icon", icon, errors);
+        }
+    }
+}
+
+impl Validate for CreateProject {
+    fn validate(&mut self, rules: &ValidationRules, errors: &mut ValidationErrors) {
+        rules.project_name.apply("name", &mut self.name, errors);
+    }
+}
+
+impl Validate for UpdateProject {
+    fn validate(&mut self, rules: &ValidationRules, errors: &mut ValidationErrors) {
+        if let Some(name) = &mut self.name {
+            rules.project_name.apply("name", name, errors);
+        }
+    }
+}
+
+/// WIP制限は正の値に限る
+fn apply_wip_limit(field: &str, wip_limit: Option<i32>, errors: &mut ValidationErrors) {
+    if wip_limit.is_some_and(|limit| limit <= 0) {
+        errors.push(field, "invalid", ERR_STR_INVALID_WIP_LIMIT);
+    }
+}
+
+impl Validate for CreateStatus {
+    fn validate(&mut self, rules: &ValidationRules, errors: &mut ValidationErrors) {
+        rules.status_name.apply("name", &mut self.name, errors);
+        apply_wip_limit("wip_limit", self.wip_limit, errors);
+    }
+}
+
+impl Validate for UpdateStatus {
+    fn validate(&mut self, rules: &ValidationRules, errors: &mut ValidationErrors) {
+        if let Some(name) = &mut self.name {
+            rules.status_name.apply("name", name, errors);
+        }
+        // nullはWIP制限を解除する指定
+        apply_wip_limit("wip_limit", self.wip_limit.flatten(), errors);
+    }
+}
+
+// 解析した本文やラベルはハンドラで検証する。入力はラベルなどの分だけ本文より長くてもよい
+impl Validate for QuickAdd {
+    fn validate(&mut self, _rules: &ValidationRules, errors: &mut ValidationErrors) {
+        TextRule::new(1, 500).apply("input", &mut self.input, errors);
+        if let Some(offset) = &mut self.utc_offset {
+            *offset = offset.trim().to_string();
+            if offset.parse::<FixedOffset>().is_err() {
+                errors.push("utc_offset", "invalid", ERR_STR_UTC_OFFSET);
+            }
+        }
+    }
+}
+
+// ブロッカーの存在と循環はハンドラで確認する
+impl Validate for TodoDependency {
+    fn validate(&mut self, _rules: &ValidationRules, errors: &mut ValidationErrors) {
+        if self.blocker_id <= 0 {
+            errors.push("blocker_id", "invalid", ERR_STR_INVALID_TODO);
+        }
+    }
+}
+
+// ブロッカーの存在と循環は反映するときに確認する
+impl Validate for PushTodo {
+    fn validate(&mut self, rules: &ValidationRules, errors: &mut ValidationErrors) {
+        // 削除では値を使わない
+        if self.deleted_at.is_some() {
+            return;
+        }
+        // オフラインで作成したTodoは本文が必須
+        if self.id.is_none() {
+            self.text.get_or_insert_with(String::new);
+        }
+        if let Some(text) = &mut self.text {
+            rules.todo_text.apply("text", text, errors);
+        }
+        if let Some(notes) = &mut self.notes {
+            rules.todo_notes.apply_optional("notes", notes, errors);
+        }
+        if let Some(labels) = &self.labels {
+            rules.apply_labels("labels", labels, errors);
+        }
+        if let Some(blocked_by) = &mut self.blocked_by {
+            // サーバーと同じ順に並べて、順序の違いを変更として扱わない
+            blocked_by.sort_unstable();
+            blocked_by.dedup();
+            if blocked_by.iter().any(|id| *id <= 0) {
+                errors.push("blocked_by", "invalid", ERR_STR_INVALID_TODO);
+            }
+        }
+    }
+
+    fn label_ids(&self) -> Option<&[i32]> {
+        self.labels.as_deref()
+    }
+}
+
+// 統合元の存在は統合先との関係と合わせてハンドラで確認する
+impl Validate for MergeLabels {
+    fn validate(&mut self, _rules: &ValidationRules, errors: &mut ValidationErrors) {
+        if self.sources.is_empty() {
+            errors.push("sources", "empty", ERR_STR_NO_SOURCES);
+        }
+        apply_label_ids("sources", &self.sources, errors);
+    }
+}
+
+/// ラベルの存在と排他的なグループの確認に使う。ラベルのリポジトリの型に依存せずにExtensionから取り出せるようにする
+#[async_trait]
+pub trait LabelCatalog: std::marker::Send + std::marker::Sync {
+    async fn labels(&self) -> anyhow::Result<Vec<Label>>;
+}
+
+#[async_trait]
+impl<L: LabelRepository> LabelCatalog for L {
+    async fn labels(&self) -> anyhow::Result<Vec<Label>> {
+        self.all().await
+    }
+}
+
+/// JSONのボディを正規化・検証して取り出す
+///
+/// エラーは最初の1件で止めずにすべて返す。ラベルIDの存在と排他的なグループは `Extension(Arc<dyn LabelCatalog>)` があれば確認する
+#[derive(Debug, Clone)]
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate + std::marker::Send,
+    S: std::marker::Send + std::marker::Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let rules = req
+            .extensions()
+            .get::<Arc<ValidationRules>>()
+            .cloned()
+            .unwrap_or_default();
+        let catalog = req.extensions().get::<Arc<dyn LabelCatalog>>().cloned();
+        let Json(mut payload) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let errors = validate(&mut payload, &rules, catalog.as_deref())
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+        if errors.is_empty() {
+            Ok(ValidJson(payload))
+        } else {
+            Err(errors.into_response())
+        }
+    }
+}
+
+/// `ValidJson` と同じ正規化と検証を行う。同期のpushのように、1つのリクエストで複数の値を受け取る場合に使う
+pub async fn validate<T: Validate>(
+    payload: &mut T,
+    rules: &ValidationRules,
+    catalog: Option<&dyn LabelCatalog>,
+) -> anyhow::Result<ValidationErrors> {
+    let mut errors = ValidationErrors::default();
+    payload.validate(rules, &mut errors);
+    if let (Some(ids), Some(catalog)) = (payload.label_ids(), catalog) {
+        if !ids.is_empty() {
+            let labels = catalog.labels().await?;
+            let known: HashSet<i32> = labels.iter().map(|label| label.id).collect();
+            for (index, id) in ids.iter().enumerate() {
+                if *id > 0 && !known.contains(id) {
+                    errors.push(
+                        &format!("labels[{}]", index),
+                        "not_found",
+                        ERR_STR_UNKNOWN_LABEL,
+                    );
+                }
+            }
+            for (index, _) in exclusive_conflicts(&labels, ids) {
+                errors.push(
+                    &format!("labels[{}]", index),
+                    "exclusive",
+                    ERR_STR_EXCLUSIVE_LABEL,
+                );
+            }
+        }
+    }
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn apply(rule: TextRule, value: &str) -> (String, Vec<String>) {
+        let mut value = value.to_string();
+        let mut errors = ValidationErrors::default();
+        rule.apply("text", &mut value, &mut errors);
+        let codes = errors.errors.into_iter().map(|error| error.code).collect();
+        (value, codes)
+    }
+
+    #[test]
+    fn should_count_graphemes() {
+        let rule = TextRule::new(1, 3);
+        // 日本語や結合文字、絵文字も1文字として数える
+        assert_eq!(apply(rule, "日本語").1, Vec::<String>::new());
+        assert_eq!(apply(rule, "👨‍👩‍👧‍👦🇯🇵e\u{301}").1, Vec::<String>::new());
+        assert_eq!(apply(rule, "日本語で").1, vec!["too_long"]);
+        assert_eq!(apply(TextRule::new(1, 100), &"あ".repeat(100)).1.len(), 0);
+    }
+
+    #[test]
+    fn should_normalize_text() {
+        let rule = TextRule::new(1, 100);
+        // 前後の空白を取り除き、NFCに正規化する
+        assert_eq!(apply(rule, "  カ\u{3099}  ").0, "ガ");
+        assert_eq!(apply(rule, " e\u{301} ").0, "\u{e9}");
+        assert_eq!(apply(rule, "   ").1, vec!["empty"]);
+        assert_eq!(apply(rule, "a\u{7}b").1, vec!["control_character"]);
+        assert_eq!(apply(rule, "line\nbreak").1, vec!["control_character"]);
+
+        // メモは改行とタブを許可し、改行をLFにそろえる
+        let rule = TextRule::multiline(1, 100);
+        assert_eq!(
+            apply(rule, " - [ ] a\r\n\t- [x] b \n").0,
+            "- [ ] a\n\t- [x] b"
+        );
+        assert_eq!(apply(rule, "a\u{7}b").1, vec!["control_character"]);
+    }
+
+    #[test]
+    fn should_collect_all_errors() {
+        let mut payload = CreateTodo::new("\u{1b}".repeat(101), vec![1, 0, 1]);
+        let mut errors = ValidationErrors::default();
+        payload.validate(&ValidationRules::default(), &mut errors);
+        let fields: Vec<(&str, &str)> = errors
+            .errors
+            .iter()
+            .map(|error| (error.field.as_str(), error.code.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("text", "control_character"),
+                ("text", "too_long"),
+                ("labels[1]", "invalid"),
+                ("labels[2]", "duplicate"),
+            ]
+        );
+
+        // 省略したフィールドは検証しない
+        let mut payload = UpdateTodo {
+            completed: Some(true),
+            ..Default::default()
+        };
+        let mut errors = ValidationErrors::default();
+        payload.validate(&ValidationRules::default(), &mut errors);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn should_validate_status() {
+        // 名前はバイト数ではなく文字数で数える
+        let mut payload = CreateStatus {
+            name: format!(" {} ", "完".repeat(100)),
+            position: None,
+            is_done: true,
+            wip_limit: Some(0),
+        };
+        let mut errors = ValidationErrors::default();
+        payload.validate(&ValidationRules::default(), &mut errors);
+        assert_eq!(payload.name, "完".repeat(100));
+        let fields: Vec<&str> = errors.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["wip_limit"]);
+
+        // nullはWIP制限の解除として受け付ける
+        let mut payload = UpdateStatus {
+            name: Some("完".repeat(101)),
+            position: None,
+            is_done: None,
+            wip_limit: Some(None),
+        };
+        let mut errors = ValidationErrors::default();
+        payload.validate(&ValidationRules::default(), &mut errors);
+        let codes: Vec<&str> = errors.errors.iter().map(|e| e.code.as_str()).collect();
+        assert_eq!(codes, vec!["too_long"]);
+    }
+
+    #[test]
+    fn should_validate_label_style() {
+        let mut payload = CreateLabel {
+            color: Some(" #AbCdEf ".to_string()),
+            description: Some("  ".to_string()),
+            icon: Some("🏷️".to_string()),
+            ..CreateLabel::new("label".to_string())
+        };
+        let mut errors = ValidationErrors::default();
+        payload.validate(&ValidationRules::default(), &mut errors);
+        assert!(errors.is_empty());
+        assert_eq!(payload.color.as_deref(), Some("#abcdef"));
+        assert_eq!(payload.description, None);
+
+        let count = |color: &str| {
+            let mut errors = ValidationErrors::default();
+            apply_color("color", &mut color.to_string(), &mut errors);
+            errors.errors.len()
+        };
+        assert_eq!(count("#00ff00"), 0);
+        assert_eq!(count("00ff00"), 1);
+        assert_eq!(count("#0f0"), 1);
+        assert_eq!(count("#gggggg"), 1);
+        assert_eq!(count("#ｆｆｆｆｆｆ"), 1);
+    }
+}
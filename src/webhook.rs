@@ -0,0 +1,257 @@
+use crate::repositories::webhook::{OutgoingDelivery, WebhookEvent, WebhookRepository};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::json;
+use sha2::Sha256;
+use std::{sync::Arc, time::Duration};
+
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+// 1配信あたりの最大試行回数
+pub const MAX_ATTEMPTS: i32 = 6;
+const RETRY_BASE_SECONDS: i64 = 10;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const BATCH_SIZE: i64 = 20;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// リクエストボディのHMAC-SHA256署名を `sha256=<hex>` 形式で返す
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// `attempts` 回目の失敗後に次の試行まで待つ時間 (10s, 20s, 40s, ...)
+pub fn retry_delay(attempts: i32) -> chrono::Duration {
+    let exponent = (attempts - 1).clamp(0, 16) as u32;
+    chrono::Duration::seconds(RETRY_BASE_SECONDS * 2_i64.pow(exponent))
+}
+
+/// イベントを配信キューに積む。レスポンスを待たせないようにバックグラウンドで実行する
+pub fn notify<T: WebhookRepository, P: Serialize>(
+    repository: Arc<T>,
+    event: WebhookEvent,
+    data: &P,
+) {
+    let body = json!({
+        "event": event,
+        "timestamp": Utc::now(),
+        "data": data,
+    })
+    .to_string();
+
+    tokio::spawn(async move {
+        if let Err(e) = repository.enqueue(event, body).await {
+            tracing::error!("fail enqueue webhook [{}]: {}", event.as_str(), e);
+        }
+    });
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcher<T: WebhookRepository> {
+    repository: Arc<T>,
+    client: reqwest::Client,
+}
+
+impl<T: WebhookRepository> WebhookDispatcher<T> {
+    pub fn new(repository: Arc<T>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("fail build webhook http client");
+        Self { repository, client }
+    }
+
+    /// 配信キューをポーリングし続ける
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.deliver_due().await {
+                tracing::error!("fail deliver webhooks: {}", e);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// 配信時刻を過ぎたものを送信し、処理した件数を返す
+    pub async fn deliver_due(&self) -> anyhow::Result<usize> {
+        let deliveries = self.repository.claim_due(BATCH_SIZE).await?;
+        let count = deliveries.len();
+        for delivery in deliveries {
+            // 結果を記録できなかった配信は、回収時の期限が過ぎてから再送される
+            let id = delivery.id;
+            if let Err(e) = self.deliver(delivery).await {
+                tracing::error!("fail record webhook delivery [{}]: {}", id, e);
+            }
+        }
+        Ok(count)
+    }
+
+    async fn deliver(&self, delivery: OutgoingDelivery) -> anyhow::Result<()> {
+        let signature = sign(&delivery.secret, delivery.payload.as_bytes());
+        let result = self
+            .client
+            .post(&delivery.url)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                mime::APPLICATION_JSON.as_ref(),
+            )
+            .header(SIGNATURE_HEADER, signature)
+            .header(EVENT_HEADER, delivery.event.as_str())
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .body(delivery.payload.clone())
+            .send()
+            .await;
+
+        let (response_status, error) = match result {
+            Ok(res) if res.status().is_success() => {
+                tracing::debug!("webhook delivered [{}] to {}", delivery.id, delivery.url);
+                return self
+                    .repository
+                    .mark_delivered(delivery.id, res.status().as_u16() as i32)
+                    .await;
+            }
+            Ok(res) => (
+                Some(res.status().as_u16() as i32),
+                format!("unexpected status {}", res.status()),
+            ),
+            Err(e) => (None, e.to_string()),
+        };
+
+        let attempts = delivery.attempts + 1;
+        let retry_at = (attempts < MAX_ATTEMPTS).then(|| Utc::now() + retry_delay(attempts));
+        tracing::warn!(
+            "webhook delivery [{}] failed (attempt {}): {}",
+            delivery.id,
+            attempts,
+            error
+        );
+        self.repository
+            .mark_failed(delivery.id, response_status, error, retry_at)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::repositories::webhook::{
+        test_utils::WebhookRepositoryForMemory, CreateWebhook, DeliveryStatus,
+    };
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        routing::post,
+        Extension, Router,
+    };
+    use std::sync::Mutex;
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+    async fn spawn_receiver(status: StatusCode) -> (String, Received) {
+        let received: Received = Arc::default();
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    move |Extension(received): Extension<Received>,
+                          headers: HeaderMap,
+                          body: String| async move {
+                        received.lock().unwrap().push((headers, body));
+                        status
+                    },
+                ),
+            )
+            .layer(Extension(received.clone()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/hook", addr), received)
+    }
+
+    #[test]
+    fn sign_test() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn retry_delay_test() {
+        assert_eq!(retry_delay(1), chrono::Duration::seconds(10));
+        assert_eq!(retry_delay(2), chrono::Duration::seconds(20));
+        assert_eq!(retry_delay(5), chrono::Duration::seconds(160));
+    }
+
+    #[tokio::test]
+    async fn should_deliver_signed_payload() {
+        let (url, received) = spawn_receiver(StatusCode::OK).await;
+        let repository = Arc::new(WebhookRepositoryForMemory::new());
+        let webhook = repository
+            .create(CreateWebhook {
+                url,
+                events: vec![WebhookEvent::TodoCreated],
+                secret: Some("secret".to_string()),
+            })
+            .await
+            .unwrap();
+        repository
+            .enqueue(WebhookEvent::TodoCreated, r#"{"id":1}"#.to_string())
+            .await
+            .unwrap();
+
+        let dispatcher = WebhookDispatcher::new(repository.clone());
+        assert_eq!(dispatcher.deliver_due().await.unwrap(), 1);
+
+        let (headers, body) = received
+            .lock()
+            .unwrap()
+            .first()
+            .cloned()
+            .expect("webhook not received");
+        assert_eq!(body, r#"{"id":1}"#);
+        assert_eq!(headers[SIGNATURE_HEADER], sign("secret", body.as_bytes()));
+        assert_eq!(headers[EVENT_HEADER], "todo.created");
+
+        let history = repository.deliveries(webhook.id).await.unwrap();
+        assert_eq!(history[0].status, DeliveryStatus::Succeeded);
+        assert_eq!(history[0].response_status, Some(200));
+    }
+
+    #[tokio::test]
+    async fn should_schedule_retry_on_failure() {
+        let (url, _received) = spawn_receiver(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let repository = Arc::new(WebhookRepositoryForMemory::new());
+        let webhook = repository
+            .create(CreateWebhook {
+                url,
+                events: vec![WebhookEvent::LabelCreated],
+                secret: None,
+            })
+            .await
+            .unwrap();
+        repository
+            .enqueue(WebhookEvent::LabelCreated, "{}".to_string())
+            .await
+            .unwrap();
+
+        let dispatcher = WebhookDispatcher::new(repository.clone());
+        let before = Utc::now();
+        assert_eq!(dispatcher.deliver_due().await.unwrap(), 1);
+
+        let history = repository.deliveries(webhook.id).await.unwrap();
+        assert_eq!(history[0].status, DeliveryStatus::Pending);
+        assert_eq!(history[0].attempts, 1);
+        assert_eq!(history[0].response_status, Some(500));
+        assert!(history[0].next_attempt_at >= before + retry_delay(1));
+        assert_eq!(repository.find(webhook.id).await.unwrap().failure_count, 1);
+        // 次の試行時刻まではキューから取り出されない
+        assert_eq!(dispatcher.deliver_due().await.unwrap(), 0);
+    }
+}
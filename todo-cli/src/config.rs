@@ -0,0 +1,87 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// `cargo shuttle run` の既定のポート
+pub const DEFAULT_SERVER_URL: &str = "http://localhost:8000";
+
+/// `~/.config/todo/config.toml` (OSごとの設定ディレクトリ) に置く設定
+///
+/// ```toml
+/// server_url = "https://example.shuttleapp.rs"
+/// token = "secret"
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct Config {
+    pub server_url: Option<String>,
+    pub token: Option<String>,
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("todo").join("config.toml"))
+    }
+
+    /// ファイルがなければ空の設定として扱う
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("fail read config [{}]", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("invalid config [{}]", path.display()))
+    }
+
+    /// コマンドライン引数・環境変数の値を設定ファイルより優先する
+    pub fn merge(self, server_url: Option<String>, token: Option<String>) -> Self {
+        Config {
+            server_url: server_url.or(self.server_url),
+            token: token.or(self.token),
+        }
+    }
+
+    pub fn server_url(&self) -> &str {
+        self.server_url.as_deref().unwrap_or(DEFAULT_SERVER_URL)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_load_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        assert_eq!(Config::load(&path).unwrap(), Config::default());
+
+        fs::write(
+            &path,
+            "server_url = \"https://example.com\"\ntoken = \"secret\"\n",
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.server_url(), "https://example.com");
+        assert_eq!(config.token.as_deref(), Some("secret"));
+
+        fs::write(&path, "server_url = ").unwrap();
+        assert!(Config::load(&path).is_err());
+    }
+
+    #[test]
+    fn should_prefer_arguments() {
+        let config = Config {
+            server_url: Some("https://example.com".to_string()),
+            token: Some("secret".to_string()),
+        };
+        let merged = config
+            .clone()
+            .merge(Some("http://localhost:3000".to_string()), None);
+        assert_eq!(merged.server_url(), "http://localhost:3000");
+        assert_eq!(merged.token.as_deref(), Some("secret"));
+        assert_eq!(Config::default().server_url(), DEFAULT_SERVER_URL);
+    }
+}
@@ -0,0 +1,365 @@
+mod config;
+mod output;
+
+use anyhow::{bail, Context};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use config::Config;
+use output::Format;
+use std::{io::Write, path::PathBuf};
+use todo_client::{CreateLabel, CreateTodo, Label, TodoClient, TodoEntity, UpdateTodo};
+
+/// Todo APIをターミナルから操作する
+#[derive(Debug, Parser)]
+#[command(name = "todo", version)]
+struct Cli {
+    /// 設定ファイル (既定は設定ディレクトリの todo/config.toml)
+    #[arg(long, global = true, env = "TODO_CONFIG")]
+    config: Option<PathBuf>,
+    /// APIのURL (設定ファイルの server_url より優先する)
+    #[arg(long, global = true, env = "TODO_SERVER_URL")]
+    server: Option<String>,
+    /// 認証トークン (設定ファイルの token より優先する)
+    #[arg(long, global = true, env = "TODO_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// 出力形式
+    #[arg(short, long, global = true, value_enum, default_value_t = Format::Table)]
+    output: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Todoを追加する
+    Add {
+        text: String,
+        /// ラベル名またはID (複数指定できる)
+        #[arg(short, long = "label")]
+        labels: Vec<String>,
+        /// 追加先のプロジェクトID
+        #[arg(long)]
+        project: Option<i32>,
+    },
+    /// Todoの一覧を表示する (既定は未完了のみ)
+    Ls {
+        /// 完了済みのみ表示する
+        #[arg(long, conflicts_with = "all")]
+        done: bool,
+        /// 完了済みも含めて表示する
+        #[arg(long)]
+        all: bool,
+        /// ラベル名またはIDで絞り込む
+        #[arg(short, long)]
+        label: Option<String>,
+        /// プロジェクトIDで絞り込む
+        #[arg(long)]
+        project: Option<i32>,
+    },
+    /// Todoを完了にする
+    Done { id: i32 },
+    /// Todoを編集する
+    Edit {
+        id: i32,
+        #[arg(long)]
+        text: Option<String>,
+        /// ラベルを指定したものに置き換える (複数指定できる)
+        #[arg(short, long = "label")]
+        labels: Vec<String>,
+        /// ラベルをすべて外す
+        #[arg(long, conflicts_with = "labels")]
+        clear_labels: bool,
+        /// 移動先のプロジェクトID
+        #[arg(long)]
+        project: Option<i32>,
+    },
+    /// Todoを削除する
+    Rm { id: i32 },
+    /// ラベルを操作する
+    Labels {
+        #[command(subcommand)]
+        command: LabelCommand,
+    },
+    /// シェルの補完スクリプトを出力する
+    Completions { shell: Shell },
+}
+
+#[derive(Debug, Subcommand)]
+enum LabelCommand {
+    /// ラベルを追加する
+    Add {
+        name: String,
+        /// `#rrggbb` 形式の色
+        #[arg(long)]
+        color: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        /// アイコン名または絵文字
+        #[arg(long)]
+        icon: Option<String>,
+    },
+    /// ラベルの一覧を表示する
+    Ls,
+    /// ラベルを削除する
+    Rm {
+        /// ラベル名またはID
+        label: String,
+    },
+    /// 重複したラベルを1つにまとめる。統合元のラベルは削除する
+    Merge {
+        /// 統合先のラベル名またはID
+        into: String,
+        /// 統合元のラベル名またはID
+        #[arg(required = true)]
+        sources: Vec<String>,
+    },
+}
+
+/// ラベル名またはIDをラベルIDに変換する
+fn resolve_label(labels: &[Label], name_or_id: &str) -> anyhow::Result<i32> {
+    labels
+        .iter()
+        .find(|label| label.name == name_or_id)
+        .or_else(|| {
+            let id = name_or_id.parse::<i32>().ok()?;
+            labels.iter().find(|label| label.id == id)
+        })
+        .map(|label| label.id)
+        .with_context(|| format!("label not found [{}]", name_or_id))
+}
+
+fn resolve_labels(labels: &[Label], names: &[String]) -> anyhow::Result<Vec<i32>> {
+    names
+        .iter()
+        .map(|name| resolve_label(labels, name))
+        .collect()
+}
+
+/// `done` がNoneの場合は完了状態で絞り込まない
+fn filter_todos(
+    todos: Vec<TodoEntity>,
+    done: Option<bool>,
+    label_id: Option<i32>,
+) -> Vec<TodoEntity> {
+    todos
+        .into_iter()
+        .filter(|todo| done.is_none_or(|done| todo.completed == done))
+        .filter(|todo| label_id.is_none_or(|id| todo.labels.iter().any(|label| label.id == id)))
+        .collect()
+}
+
+async fn run(cli: Cli, out: &mut impl Write) -> anyhow::Result<()> {
+    let command = match cli.command {
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "todo", out);
+            return Ok(());
+        }
+        command => command,
+    };
+
+    let config_path = match cli.config.or_else(Config::default_path) {
+        Some(path) => Some(path),
+        None if cli.server.is_some() => None,
+        None => bail!("config directory not found, specify --config or --server"),
+    };
+    let config = match config_path {
+        Some(path) => Config::load(&path)?,
+        None => Config::default(),
+    }
+    .merge(cli.server, cli.token);
+    let mut client = TodoClient::new(config.server_url())
+        .with_context(|| format!("invalid server url [{}]", config.server_url()))?;
+    if let Some(token) = config.token {
+        client = client.with_token(token);
+    }
+    let format = cli.output;
+
+    match command {
+        Command::Add {
+            text,
+            labels,
+            project,
+        } => {
+            let labels = if labels.is_empty() {
+                vec![]
+            } else {
+                resolve_labels(&client.all_label().await?, &labels)?
+            };
+            let mut payload = CreateTodo::new(text, labels);
+            payload.project_id = project;
+            let todo = client.create_todo(&payload).await?;
+            write!(out, "{}", output::todo(format, &todo)?)?;
+        }
+        Command::Ls {
+            done,
+            all,
+            label,
+            project,
+        } => {
+            let todos = match project {
+                Some(project_id) => client.all_project_todo(project_id).await?,
+                None => client.all_todo().await?,
+            };
+            let label_id = match label {
+                Some(label) => Some(resolve_label(&client.all_label().await?, &label)?),
+                None => None,
+            };
+            let done = if all { None } else { Some(done) };
+            let todos = filter_todos(todos, done, label_id);
+            write!(out, "{}", output::todos(format, &todos)?)?;
+        }
+        Command::Done { id } => {
+            // サーバーはtextを必須としているため、現在の値を送り直す
+            let current = client.find_todo(id).await?;
+            let todo = client
+                .update_todo(
+                    id,
+                    &UpdateTodo {
+                        text: Some(current.text),
+                        completed: Some(true),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            write!(out, "{}", output::todo(format, &todo)?)?;
+        }
+        Command::Edit {
+            id,
+            text,
+            labels,
+            clear_labels,
+            project,
+        } => {
+            let current = client.find_todo(id).await?;
+            let labels = match (labels.is_empty(), clear_labels) {
+                (_, true) => Some(vec![]),
+                (true, false) => None,
+                (false, false) => Some(resolve_labels(&client.all_label().await?, &labels)?),
+            };
+            let todo = client
+                .update_todo(
+                    id,
+                    &UpdateTodo {
+                        text: Some(text.unwrap_or(current.text)),
+                        labels,
+                        project_id: project,
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            write!(out, "{}", output::todo(format, &todo)?)?;
+        }
+        Command::Rm { id } => client.delete_todo(id).await?,
+        Command::Labels { command } => match command {
+            LabelCommand::Add {
+                name,
+                color,
+                description,
+                icon,
+            } => {
+                let payload = CreateLabel {
+                    color,
+                    description,
+                    icon,
+                    ..CreateLabel::new(name)
+                };
+                let label = client.create_label(&payload).await?;
+                write!(out, "{}", output::label(format, &label)?)?;
+            }
+            LabelCommand::Ls => {
+                let labels = client.all_label().await?;
+                write!(out, "{}", output::labels(format, &labels)?)?;
+            }
+            LabelCommand::Rm { label } => {
+                let id = resolve_label(&client.all_label().await?, &label)?;
+                client.delete_label(id).await?;
+            }
+            LabelCommand::Merge { into, sources } => {
+                let labels = client.all_label().await?;
+                let id = resolve_label(&labels, &into)?;
+                let sources = resolve_labels(&labels, &sources)?;
+                let label = client.merge_labels(id, sources).await?;
+                write!(out, "{}", output::label(format, &label)?)?;
+            }
+        },
+        // 接続前に処理している
+        Command::Completions { .. } => unreachable!(),
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    run(cli, &mut std::io::stdout()).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn label_fixture() -> Vec<Label> {
+        vec![
+            Label::new(1, "work".to_string()),
+            Label::new(2, "2024".to_string()),
+            Label::new(2024, "home".to_string()),
+        ]
+    }
+
+    #[test]
+    fn verify_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn should_parse_commands() {
+        let cli = Cli::parse_from(["todo", "add", "牛乳を買う", "--label", "home", "-l", "2"]);
+        assert!(matches!(
+            cli.command,
+            Command::Add { ref labels, .. } if labels == &["home", "2"]
+        ));
+        assert_eq!(cli.output, Format::Table);
+
+        let cli = Cli::parse_from(["todo", "ls", "--done", "-o", "json"]);
+        assert!(matches!(cli.command, Command::Ls { done: true, .. }));
+        assert_eq!(cli.output, Format::Json);
+
+        assert!(Cli::try_parse_from(["todo", "ls", "--done", "--all"]).is_err());
+        assert!(Cli::try_parse_from(["todo", "done", "abc"]).is_err());
+    }
+
+    #[test]
+    fn should_resolve_label_by_name_before_id() {
+        let labels = label_fixture();
+        assert_eq!(resolve_label(&labels, "work").unwrap(), 1);
+        // 数字だけの名前は名前として優先する
+        assert_eq!(resolve_label(&labels, "2024").unwrap(), 2);
+        assert_eq!(resolve_label(&labels, "1").unwrap(), 1);
+        assert!(resolve_label(&labels, "unknown").is_err());
+        assert!(resolve_labels(&labels, &["work".to_string(), "x".to_string()]).is_err());
+    }
+
+    #[test]
+    fn should_filter_todos() {
+        let labels = label_fixture();
+        let mut done = TodoEntity::new(1, "done".to_string(), vec![labels[0].clone()]);
+        done.completed = true;
+        let todos = vec![done, TodoEntity::new(2, "open".to_string(), vec![])];
+
+        let ids = |todos: Vec<TodoEntity>| todos.iter().map(|todo| todo.id).collect::<Vec<_>>();
+        assert_eq!(ids(filter_todos(todos.clone(), Some(false), None)), vec![2]);
+        assert_eq!(ids(filter_todos(todos.clone(), Some(true), None)), vec![1]);
+        assert_eq!(ids(filter_todos(todos.clone(), None, None)), vec![1, 2]);
+        assert_eq!(ids(filter_todos(todos, None, Some(1))), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn should_generate_completions() {
+        let cli = Cli::parse_from(["todo", "completions", "bash"]);
+        let mut out = Vec::new();
+        run(cli, &mut out).await.unwrap();
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.contains("labels"));
+    }
+}
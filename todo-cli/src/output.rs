@@ -0,0 +1,155 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use todo_client::{Label, TodoEntity};
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+/// 全角文字を含む列も揃うよう、表示幅で詰めて表にする
+fn table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.width()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    let headers = headers.iter().map(|header| header.to_string()).collect();
+    std::iter::once(headers)
+        .chain(rows)
+        .map(|row| {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.width())))
+                .collect::<Vec<_>>()
+                .join("  ");
+            line.trim_end().to_string() + "\n"
+        })
+        .collect()
+}
+
+fn json<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(value)? + "\n")
+}
+
+pub fn todos(format: Format, todos: &[TodoEntity]) -> anyhow::Result<String> {
+    match format {
+        Format::Json => json(todos),
+        Format::Table => Ok(table(
+            &["ID", "DONE", "TEXT", "LABELS", "PROJECT"],
+            todos
+                .iter()
+                .map(|todo| {
+                    vec![
+                        todo.id.to_string(),
+                        if todo.completed { "x" } else { "" }.to_string(),
+                        todo.text.clone(),
+                        todo.labels
+                            .iter()
+                            .map(|label| label.name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(","),
+                        todo.project_id.to_string(),
+                    ]
+                })
+                .collect(),
+        )),
+    }
+}
+
+pub fn todo(format: Format, todo: &TodoEntity) -> anyhow::Result<String> {
+    match format {
+        Format::Json => json(todo),
+        Format::Table => todos(format, std::slice::from_ref(todo)),
+    }
+}
+
+pub fn labels(format: Format, labels: &[Label]) -> anyhow::Result<String> {
+    match format {
+        Format::Json => json(labels),
+        Format::Table => Ok(table(
+            &["ID", "NAME", "COLOR", "DESCRIPTION"],
+            labels
+                .iter()
+                .map(|label| {
+                    let name = match &label.icon {
+                        Some(icon) => format!("{} {}", icon, label.name),
+                        None => label.name.clone(),
+                    };
+                    vec![
+                        label.id.to_string(),
+                        name,
+                        label.color.clone(),
+                        label.description.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect(),
+        )),
+    }
+}
+
+pub fn label(format: Format, label: &Label) -> anyhow::Result<String> {
+    match format {
+        Format::Json => json(label),
+        Format::Table => labels(format, std::slice::from_ref(label)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn should_align_table_by_display_width() {
+        let mut todo = TodoEntity::new(
+            12,
+            "牛乳を買う".to_string(),
+            vec![
+                Label::new(1, "home".to_string()),
+                Label::new(2, "急ぎ".to_string()),
+            ],
+        );
+        todo.completed = true;
+        let todos = vec![todo, TodoEntity::new(3, "write".to_string(), vec![])];
+
+        assert_eq!(
+            super::todos(Format::Table, &todos).unwrap(),
+            "ID  DONE  TEXT        LABELS     PROJECT\n\
+             12  x     牛乳を買う  home,急ぎ  1\n\
+             3         write                  1\n"
+        );
+    }
+
+    #[test]
+    fn should_show_label_style() {
+        let labels = vec![
+            Label {
+                icon: Some("🏠".to_string()),
+                description: Some("家のこと".to_string()),
+                ..Label::new(1, "home".to_string())
+            },
+            Label {
+                color: "#ff0000".to_string(),
+                ..Label::new(2, "work".to_string())
+            },
+        ];
+        assert_eq!(
+            super::labels(Format::Table, &labels).unwrap(),
+            "ID  NAME     COLOR    DESCRIPTION\n\
+             1   🏠 home  #808080  家のこと\n\
+             2   work     #ff0000\n"
+        );
+    }
+
+    #[test]
+    fn should_output_json() {
+        let label = Label::new(1, "work".to_string());
+        let output = super::label(Format::Json, &label).unwrap();
+        assert_eq!(serde_json::from_str::<Label>(&output).unwrap(), label);
+    }
+}
@@ -0,0 +1,41 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Invalid url: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+    /// 通信の失敗やレスポンスのデコードの失敗
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    /// サーバーが2xx以外を返した
+    ///
+    /// `message` はレスポンスボディ (`Todo not found` や検証エラーのJSONなど)。空の場合はステータスの説明になる
+    #[error("Server returned {status}: {message}")]
+    Api { status: StatusCode, message: String },
+}
+
+impl ClientError {
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ClientError::Api { status, .. } => Some(*status),
+            ClientError::Request(e) => e.status(),
+            ClientError::InvalidUrl(_) => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(StatusCode::NOT_FOUND)
+    }
+
+    /// エラーレスポンスのボディを読み取って `Api` エラーにする
+    pub(crate) async fn from_response(res: reqwest::Response) -> Self {
+        let status = res.status();
+        let body = res.text().await.unwrap_or_default();
+        let message = match body.trim() {
+            "" => status.canonical_reason().unwrap_or_default().to_string(),
+            body => body.to_string(),
+        };
+        ClientError::Api { status, message }
+    }
+}
@@ -0,0 +1,447 @@
+//! Todo APIの型付きクライアント
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), todo_client::ClientError> {
+//! use todo_client::{CreateTodo, TodoClient};
+//!
+//! let client = TodoClient::new("http://localhost:3000")?.with_token("secret");
+//! let todo = client
+//!     .create_todo(&CreateTodo::new("牛乳を買う".to_string(), vec![]))
+//!     .await?;
+//! client.delete_todo(todo.id).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod error;
+
+pub use error::ClientError;
+pub use todo_types::{
+    CreateLabel, CreateProject, CreateTodo, Label, MergeLabels, Priority, Project, QuickAdd,
+    QuickAddLabel, QuickAddPreview, TodoDependency, TodoEntity, UpdateLabel, UpdateProject,
+    UpdateTodo, DEFAULT_LABEL_COLOR, DEFAULT_PROJECT_ID,
+};
+
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use url::Url;
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn sleep(duration: Duration) {
+    gloo_timers::future::sleep(duration).await
+}
+
+/// 再送しても結果が変わらないメソッドのみリトライする
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE
+    )
+}
+
+/// 一時的な障害とみなすレスポンス
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct TodoClient {
+    base_url: Url,
+    client: reqwest::Client,
+    token: Option<String>,
+    max_retries: u32,
+    retry_delay: Duration,
+}
+
+impl TodoClient {
+    /// `base_url` はAPIのルート (`https://example.com` や `https://example.com/api`)
+    pub fn new(base_url: &str) -> Result<Self> {
+        let mut base_url = Url::parse(base_url)?;
+        // パスを持つURLでもjoinで末尾が置き換わらないようにする
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+        Ok(Self {
+            base_url,
+            client: reqwest::Client::new(),
+            token: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_delay: DEFAULT_RETRY_DELAY,
+        })
+    }
+
+    /// `Authorization: Bearer` ヘッダで送るトークン
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// 冪等なリクエストのリトライ回数と初回の待ち時間 (以降は倍々に延ばす)
+    pub fn with_retry(mut self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// タイムアウトやプロキシなどを設定したreqwestのクライアントを使う
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    // --------------
+    // todo
+    // --------------
+    pub async fn create_todo(&self, payload: &CreateTodo) -> Result<TodoEntity> {
+        self.json(Method::POST, "todos", |req| req.json(payload))
+            .await
+    }
+
+    /// `Fix login bug tomorrow 15:00 #backend !high` のような1行からTodoを作る
+    pub async fn quick_add(&self, payload: &QuickAdd) -> Result<TodoEntity> {
+        self.json(Method::POST, "todos/quick", |req| req.json(payload))
+            .await
+    }
+
+    /// `quick_add` で作られる内容を確認する。Todoは作らない
+    pub async fn preview_quick_add(&self, payload: &QuickAdd) -> Result<QuickAddPreview> {
+        let payload = QuickAdd {
+            preview: true,
+            ..payload.clone()
+        };
+        self.json(Method::POST, "todos/quick", |req| req.json(&payload))
+            .await
+    }
+
+    pub async fn find_todo(&self, id: i32) -> Result<TodoEntity> {
+        self.json(Method::GET, &format!("todos/{}", id), |req| req)
+            .await
+    }
+
+    pub async fn all_todo(&self) -> Result<Vec<TodoEntity>> {
+        self.json(Method::GET, "todos", |req| req).await
+    }
+
+    /// サーバーはtextを必須としているため、変更しない場合も現在のtextを指定する
+    pub async fn update_todo(&self, id: i32, payload: &UpdateTodo) -> Result<TodoEntity> {
+        self.json(Method::PATCH, &format!("todos/{}", id), |req| {
+            req.json(payload)
+        })
+        .await
+    }
+
+    pub async fn delete_todo(&self, id: i32) -> Result<()> {
+        self.send(Method::DELETE, &format!("todos/{}", id), |req| req)
+            .await?;
+        Ok(())
+    }
+
+    /// `id` のTodoが `blocker_id` のTodoの完了を待つようにする
+    pub async fn add_dependency(&self, id: i32, blocker_id: i32) -> Result<TodoEntity> {
+        let payload = TodoDependency { blocker_id };
+        self.json(Method::POST, &format!("todos/{}/dependencies", id), |req| {
+            req.json(&payload)
+        })
+        .await
+    }
+
+    pub async fn remove_dependency(&self, id: i32, blocker_id: i32) -> Result<()> {
+        let payload = TodoDependency { blocker_id };
+        self.send(
+            Method::DELETE,
+            &format!("todos/{}/dependencies", id),
+            |req| req.json(&payload),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// 未完了のTodoを、次に着手できる順に返す
+    pub async fn next_todos(&self) -> Result<Vec<TodoEntity>> {
+        self.json(Method::GET, "todos/next", |req| req).await
+    }
+
+    // --------------
+    // label
+    // --------------
+    pub async fn create_label(&self, payload: &CreateLabel) -> Result<Label> {
+        self.json(Method::POST, "labels", |req| req.json(payload))
+            .await
+    }
+
+    pub async fn all_label(&self) -> Result<Vec<Label>> {
+        self.json(Method::GET, "labels", |req| req).await
+    }
+
+    pub async fn update_label(&self, id: i32, payload: &UpdateLabel) -> Result<Label> {
+        self.json(Method::PATCH, &format!("labels/{}", id), |req| {
+            req.json(payload)
+        })
+        .await
+    }
+
+    pub async fn delete_label(&self, id: i32) -> Result<()> {
+        self.send(Method::DELETE, &format!("labels/{}", id), |req| req)
+            .await?;
+        Ok(())
+    }
+
+    /// `sources` のラベルを `id` のラベルにまとめる
+    pub async fn merge_labels(&self, id: i32, sources: Vec<i32>) -> Result<Label> {
+        let payload = MergeLabels { sources };
+        self.json(Method::POST, &format!("labels/{}/merge", id), |req| {
+            req.json(&payload)
+        })
+        .await
+    }
+
+    // --------------
+    // project
+    // --------------
+    pub async fn create_project(&self, payload: &CreateProject) -> Result<Project> {
+        self.json(Method::POST, "projects", |req| req.json(payload))
+            .await
+    }
+
+    pub async fn find_project(&self, id: i32) -> Result<Project> {
+        self.json(Method::GET, &format!("projects/{}", id), |req| req)
+            .await
+    }
+
+    /// `include_archived` がtrueの場合はアーカイブ済みのプロジェクトも含める
+    pub async fn all_project(&self, include_archived: bool) -> Result<Vec<Project>> {
+        self.json(Method::GET, "projects", |req| {
+            req.query(&[("archived", include_archived)])
+        })
+        .await
+    }
+
+    pub async fn update_project(&self, id: i32, payload: &UpdateProject) -> Result<Project> {
+        self.json(Method::PATCH, &format!("projects/{}", id), |req| {
+            req.json(payload)
+        })
+        .await
+    }
+
+    pub async fn delete_project(&self, id: i32) -> Result<()> {
+        self.send(Method::DELETE, &format!("projects/{}", id), |req| req)
+            .await?;
+        Ok(())
+    }
+
+    /// プロジェクトに所属するTodoのみを返す
+    pub async fn all_project_todo(&self, project_id: i32) -> Result<Vec<TodoEntity>> {
+        self.json(
+            Method::GET,
+            &format!("projects/{}/todos", project_id),
+            |req| req,
+        )
+        .await
+    }
+
+    async fn json<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        build: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<T> {
+        let res = self.send(method, path, build).await?;
+        Ok(res.json().await?)
+    }
+
+    /// リクエストを送り、2xx以外は `ClientError::Api` にする
+    ///
+    /// `build` はリトライのたびに呼ばれる
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        build: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response> {
+        let url = self.base_url.join(path)?;
+        let max_retries = if is_idempotent(&method) {
+            self.max_retries
+        } else {
+            0
+        };
+
+        let mut attempt = 0;
+        loop {
+            let mut request = build(self.client.request(method.clone(), url.clone()));
+            if let Some(token) = &self.token {
+                request = request.bearer_auth(token);
+            }
+            let result = request.send().await;
+
+            let retryable = match &result {
+                Ok(res) => is_retryable_status(res.status()),
+                Err(e) => e.is_request() || e.is_timeout(),
+            };
+            if retryable && attempt < max_retries {
+                sleep(self.retry_delay * 2u32.pow(attempt)).await;
+                attempt += 1;
+                continue;
+            }
+
+            let res = result?;
+            if !res.status().is_success() {
+                return Err(ClientError::from_response(res).await);
+            }
+            return Ok(res);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+        Extension, Json, Router,
+    };
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    type Counter = Arc<AtomicU32>;
+
+    /// 呼び出し回数を数える、Todo APIの代わりのサーバーを立てる
+    async fn spawn_server() -> (String, Counter) {
+        let counter: Counter = Arc::default();
+        let app = Router::new()
+            // 2回目までは一時的な障害を返す
+            .route(
+                "/api/todos",
+                get(|Extension(counter): Extension<Counter>| async move {
+                    match counter.fetch_add(1, Ordering::SeqCst) {
+                        0 | 1 => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+                        _ => Json(vec![TodoEntity::new(1, "todo".to_string(), vec![])])
+                            .into_response(),
+                    }
+                })
+                .post(|Extension(counter): Extension<Counter>| async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    StatusCode::SERVICE_UNAVAILABLE
+                }),
+            )
+            .route(
+                "/api/todos/:id",
+                get(|| async { (StatusCode::NOT_FOUND, "Todo not found") })
+                    .delete(|| async { StatusCode::NOT_FOUND }),
+            )
+            .route(
+                "/api/labels",
+                get(|headers: HeaderMap| async move {
+                    match headers.get("authorization") {
+                        Some(value) if value == "Bearer secret" => {
+                            Json(vec![Label::new(1, "work".to_string())]).into_response()
+                        }
+                        _ => StatusCode::UNAUTHORIZED.into_response(),
+                    }
+                }),
+            )
+            .layer(Extension(counter.clone()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/api", addr), counter)
+    }
+
+    #[test]
+    fn should_keep_base_path() {
+        let client = TodoClient::new("http://localhost:3000/api").unwrap();
+        assert_eq!(
+            client.base_url().join("todos/1").unwrap().as_str(),
+            "http://localhost:3000/api/todos/1"
+        );
+        assert!(TodoClient::new("/api").is_err());
+    }
+
+    #[tokio::test]
+    async fn should_retry_idempotent_request() {
+        let (base_url, counter) = spawn_server().await;
+        let client = TodoClient::new(&base_url)
+            .unwrap()
+            .with_retry(3, Duration::from_millis(1));
+
+        let todos = client.all_todo().await.expect("failed retry");
+        assert_eq!(todos[0].id, 1);
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn should_not_retry_non_idempotent_request() {
+        let (base_url, counter) = spawn_server().await;
+        let client = TodoClient::new(&base_url)
+            .unwrap()
+            .with_retry(3, Duration::from_millis(1));
+
+        let err = client
+            .create_todo(&CreateTodo::new("todo".to_string(), vec![]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn should_decode_error_response() {
+        let (base_url, _) = spawn_server().await;
+        let client = TodoClient::new(&base_url).unwrap();
+
+        let err = client.find_todo(1).await.unwrap_err();
+        assert!(err.is_not_found());
+        assert!(matches!(
+            err,
+            ClientError::Api { ref message, .. } if message == "Todo not found"
+        ));
+
+        // ボディがなければステータスの説明を使う
+        let err = client.delete_todo(1).await.unwrap_err();
+        assert_eq!(err.to_string(), "Server returned 404 Not Found: Not Found");
+    }
+
+    #[tokio::test]
+    async fn should_send_token() {
+        let (base_url, _) = spawn_server().await;
+
+        let err = TodoClient::new(&base_url)
+            .unwrap()
+            .all_label()
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), Some(StatusCode::UNAUTHORIZED));
+
+        let labels = TodoClient::new(&base_url)
+            .unwrap()
+            .with_token("secret")
+            .all_label()
+            .await
+            .unwrap();
+        assert_eq!(labels, vec![Label::new(1, "work".to_string())]);
+    }
+}
@@ -17,6 +17,7 @@ import { useState, FC, memo, useCallback, useMemo, Fragment } from "react";
 import { modalInnerStyle } from "../styles/modal";
 import { Label, NewLabelPayload } from "../types/todo";
 import styles from "../style.module.css";
+import { labelText } from "../lib/labelStyle";
 
 type Props = {
   labels: Label[];
@@ -41,6 +42,7 @@ const SideNav: FC<Props> = memo(
     onResetErrText,
   }) => {
     const [editName, setEditName] = useState("");
+    const [editColor, setEditColor] = useState("#808080");
     const [openLabelModal, setOpenLabelModal] = useState(false);
 
     const handleOpenModal = useCallback(() => {
@@ -55,9 +57,9 @@ const SideNav: FC<Props> = memo(
 
     const onSubmit = useCallback(() => {
       if (editName.trim() === "") return;
-      onSubmitNewLabel({ name: editName });
+      onSubmitNewLabel({ name: editName, color: editColor });
       setEditName("");
-    }, [editName, onSubmitNewLabel]);
+    }, [editName, editColor, onSubmitNewLabel]);
 
     const labelList = useMemo(
       () =>
@@ -74,8 +76,10 @@ const SideNav: FC<Props> = memo(
               }}
             >
               <Stack direction="row" alignItems="center" spacing={1}>
-                <LabelIcon fontSize="small" />
-                <span>{label.name}</span>
+                <LabelIcon fontSize="small" sx={{ color: label.color }} />
+                <span title={label.description ?? undefined}>
+                  {labelText(label)}
+                </span>
               </Stack>
             </ListItemButton>
           </ListItem>
@@ -121,6 +125,13 @@ const SideNav: FC<Props> = memo(
                   value={editName}
                   onChange={(e) => setEditName(e.target.value)}
                 />
+                <TextField
+                  label="color"
+                  type="color"
+                  variant="filled"
+                  value={editColor}
+                  onChange={(e) => setEditColor(e.target.value)}
+                />
                 <Box textAlign="right">
                   <Button onClick={onSubmit}>Submit</Button>
                 </Box>
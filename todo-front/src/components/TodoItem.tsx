@@ -22,6 +22,7 @@ import {
 } from "@mui/material";
 import { modalInnerStyle } from "../styles/modal";
 import { toggleLabels } from "../lib/toggleLabels";
+import { labelChipSx, labelText } from "../lib/labelStyle";
 import styles from "../style.module.css";
 
 type Props = {
@@ -92,7 +93,12 @@ const TodoItem: FC<Props> = memo(({ todo, onUpdate, onDelete, labels }) => {
             </Typography>
             <Stack direction="row" spacing={1}>
               {todo.labels?.map((label) => (
-                <Chip key={label.id} label={label.name} />
+                <Chip
+                  key={label.id}
+                  label={labelText(label)}
+                  title={label.description ?? undefined}
+                  sx={labelChipSx(label)}
+                />
               ))}
             </Stack>
           </Stack>
@@ -0,0 +1,17 @@
+import { Label } from "../types/todo";
+
+// 明るい背景には黒、暗い背景には白の文字を使う
+const textColor = (color: string) => {
+  const rgb = parseInt(color.slice(1), 16);
+  const luma =
+    299 * ((rgb >> 16) & 0xff) + 587 * ((rgb >> 8) & 0xff) + 114 * (rgb & 0xff);
+  return luma > 128000 ? "#000" : "#fff";
+};
+
+export const labelText = (label: Label) =>
+  label.icon ? `${label.icon} ${label.name}` : label.name;
+
+export const labelChipSx = (label: Label) => ({
+  bgcolor: label.color,
+  color: textColor(label.color),
+});
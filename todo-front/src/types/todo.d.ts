@@ -1,10 +1,28 @@
 export type Todo = {
   id: number;
   text: string;
+  // Markdown
+  notes: string | null;
   completed: boolean;
   labels: Label[];
+  priority: Priority | null;
+  // ISO 8601
+  due_at: string | null;
+  // 完了を待っているTodoのID
+  blocked_by: number[];
+  // 未完了のブロッカーがある
+  blocked: boolean;
+  created_at: string;
+  updated_at: string;
+  completed_at: string | null;
+  // メモのタスクリストの進み具合 (GETのみ)
+  checklist?: { done: number; total: number } | null;
+  // ?render=html の場合のみ
+  notes_html?: string;
 };
 
+export type Priority = "low" | "medium" | "high" | "urgent";
+
 export type NewTodoPayload = {
   text: string;
   labels: number[];
@@ -13,10 +31,27 @@ export type NewTodoPayload = {
 export type Label = {
   id: number;
   name: string;
+  // #rrggbb
+  color: string;
+  description: string | null;
+  icon: string | null;
+  position: number;
+  parent_id: number | null;
+  // 子のラベルはTodoごとに1つだけ選べる
+  exclusive: boolean;
+  // ISO 8601
+  created_at: string;
+  updated_at: string;
 };
 
 export type NewLabelPayload = {
   name: string;
+  color?: string;
+  description?: string;
+  icon?: string;
+  position?: number;
+  parent_id?: number;
+  exclusive?: boolean;
 };
 
 export type UpdateTodoPayload = {
@@ -0,0 +1,414 @@
+use crate::backend::Backend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use todo::repositories::todo::{CreateTodo, TodoEntity, UpdateTodo};
+use todo_client::Label;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    Search,
+    Add,
+    Edit(i32),
+    Delete(i32),
+}
+
+/// キー操作の結果としてBackendに依頼する処理
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Create(CreateTodo),
+    Update(i32, UpdateTodo),
+    Delete(i32),
+    Refresh,
+    Quit,
+}
+
+#[derive(Debug)]
+pub struct App {
+    pub todos: Vec<TodoEntity>,
+    pub labels: Vec<Label>,
+    pub selected: usize,
+    pub search: String,
+    pub label_filter: Option<i32>,
+    pub mode: Mode,
+    pub input: String,
+    pub message: Option<String>,
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self {
+            todos: vec![],
+            labels: vec![],
+            selected: 0,
+            search: String::new(),
+            label_filter: None,
+            mode: Mode::Normal,
+            input: String::new(),
+            message: None,
+        }
+    }
+
+    pub fn set_data(&mut self, todos: Vec<TodoEntity>, labels: Vec<Label>) {
+        // 削除されたラベルで絞り込んだままにしない
+        if let Some(id) = self.label_filter {
+            if !labels.iter().any(|label| label.id == id) {
+                self.label_filter = None;
+            }
+        }
+        self.todos = todos;
+        self.labels = labels;
+        self.selected = self.selected.min(self.visible().len().saturating_sub(1));
+    }
+
+    /// 検索文字列(タイトルとメモ)とラベルで絞り込んだTodo
+    pub fn visible(&self) -> Vec<&TodoEntity> {
+        self.todos
+            .iter()
+            .filter(|todo| todo.matches(&self.search))
+            .filter(|todo| {
+                self.label_filter
+                    .is_none_or(|id| todo.labels.iter().any(|label| label.id == id))
+            })
+            .collect()
+    }
+
+    pub fn selected_todo(&self) -> Option<&TodoEntity> {
+        self.visible().get(self.selected).copied()
+    }
+
+    pub fn label_filter_name(&self) -> Option<&str> {
+        let id = self.label_filter?;
+        self.labels
+            .iter()
+            .find(|label| label.id == id)
+            .map(|label| label.name.as_str())
+    }
+
+    /// ラベルなし → 1つ目のラベル → … → 最後のラベル → ラベルなし の順に切り替える
+    fn cycle_label_filter(&mut self) {
+        let next = match self.label_filter {
+            None => self.labels.first(),
+            Some(id) => self.labels.iter().skip_while(|label| label.id != id).nth(1),
+        };
+        self.label_filter = next.map(|label| label.id);
+        self.selected = 0;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Action::Quit);
+        }
+        self.message = None;
+
+        match self.mode.clone() {
+            Mode::Normal => self.handle_normal_key(key),
+            Mode::Delete(id) => {
+                self.mode = Mode::Normal;
+                match key.code {
+                    KeyCode::Char('y') => Some(Action::Delete(id)),
+                    _ => None,
+                }
+            }
+            Mode::Search => {
+                match key.code {
+                    KeyCode::Enter => self.mode = Mode::Normal,
+                    KeyCode::Esc => {
+                        self.search.clear();
+                        self.mode = Mode::Normal;
+                    }
+                    KeyCode::Backspace => {
+                        self.search.pop();
+                    }
+                    KeyCode::Char(c) => self.search.push(c),
+                    _ => {}
+                }
+                self.selected = 0;
+                None
+            }
+            Mode::Add | Mode::Edit(_) => self.handle_input_key(key),
+        }
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) -> Option<Action> {
+        let len = self.visible().len();
+        match key.code {
+            KeyCode::Char('q') => return Some(Action::Quit),
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.selected = (self.selected + 1).min(len.saturating_sub(1));
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            KeyCode::Char(' ') | KeyCode::Char('x') => {
+                let todo = self.selected_todo()?;
+                // サーバーはtextを必須としているため、現在の値を送り直す
+                return Some(Action::Update(
+                    todo.id,
+                    UpdateTodo {
+                        text: Some(todo.text.clone()),
+                        completed: Some(!todo.completed),
+                        ..Default::default()
+                    },
+                ));
+            }
+            KeyCode::Char('a') => {
+                self.input.clear();
+                self.mode = Mode::Add;
+            }
+            KeyCode::Char('e') => {
+                let (id, text) = self
+                    .selected_todo()
+                    .map(|todo| (todo.id, todo.text.clone()))?;
+                self.input = text;
+                self.mode = Mode::Edit(id);
+            }
+            KeyCode::Char('d') => {
+                let id = self.selected_todo()?.id;
+                self.mode = Mode::Delete(id);
+            }
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Char('l') => self.cycle_label_filter(),
+            KeyCode::Char('r') => return Some(Action::Refresh),
+            KeyCode::Esc => {
+                self.search.clear();
+                self.label_filter = None;
+                self.selected = 0;
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn handle_input_key(&mut self, key: KeyEvent) -> Option<Action> {
+        match key.code {
+            KeyCode::Esc => self.mode = Mode::Normal,
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Enter => {
+                let text = self.input.trim().to_string();
+                let mode = std::mem::replace(&mut self.mode, Mode::Normal);
+                if text.is_empty() {
+                    return None;
+                }
+                return match mode {
+                    // 絞り込み中のラベルを付けて追加する
+                    Mode::Add => Some(Action::Create(CreateTodo::new(
+                        text,
+                        self.label_filter.into_iter().collect(),
+                    ))),
+                    Mode::Edit(id) => Some(Action::Update(
+                        id,
+                        UpdateTodo {
+                            text: Some(text),
+                            ..Default::default()
+                        },
+                    )),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// 最新のTodoとラベルを読み込み直す
+pub async fn refresh(app: &mut App, backend: &impl Backend) {
+    match (backend.all_todo().await, backend.all_label().await) {
+        (Ok(todos), Ok(labels)) => app.set_data(todos, labels),
+        (Err(e), _) | (_, Err(e)) => app.message = Some(format!("Error: {}", e)),
+    }
+}
+
+/// 変更を反映し、一覧を読み込み直す
+pub async fn apply(app: &mut App, backend: &impl Backend, action: Action) {
+    let result = match action {
+        Action::Create(payload) => backend.create_todo(payload).await.map(|_| ()),
+        Action::Update(id, payload) => backend.update_todo(id, payload).await.map(|_| ()),
+        Action::Delete(id) => backend.delete_todo(id).await,
+        Action::Refresh | Action::Quit => Ok(()),
+    };
+    refresh(app, backend).await;
+    if let Err(e) = result {
+        app.message = Some(format!("Error: {}", e));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Vecに保存するだけのBackend
+    struct MemoryBackend {
+        todos: Mutex<Vec<TodoEntity>>,
+        labels: Vec<Label>,
+    }
+
+    #[async_trait]
+    impl Backend for MemoryBackend {
+        async fn all_todo(&self) -> anyhow::Result<Vec<TodoEntity>> {
+            Ok(self.todos.lock().unwrap().clone())
+        }
+
+        async fn all_label(&self) -> anyhow::Result<Vec<Label>> {
+            Ok(self.labels.clone())
+        }
+
+        async fn create_todo(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity> {
+            let mut todos = self.todos.lock().unwrap();
+            let labels = self
+                .labels
+                .iter()
+                .filter(|label| payload.labels.contains(&label.id))
+                .cloned()
+                .collect();
+            let todo = TodoEntity::new(todos.len() as i32 + 1, payload.text, labels);
+            todos.push(todo.clone());
+            Ok(todo)
+        }
+
+        async fn update_todo(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity> {
+            let mut todos = self.todos.lock().unwrap();
+            let todo = todos
+                .iter_mut()
+                .find(|todo| todo.id == id)
+                .ok_or(anyhow::anyhow!("Todo not found"))?;
+            todo.text = payload.text.unwrap_or(todo.text.clone());
+            todo.completed = payload.completed.unwrap_or(todo.completed);
+            Ok(todo.clone())
+        }
+
+        async fn delete_todo(&self, id: i32) -> anyhow::Result<()> {
+            self.todos.lock().unwrap().retain(|todo| todo.id != id);
+            Ok(())
+        }
+    }
+
+    fn backend_fixture() -> MemoryBackend {
+        let labels = vec![
+            Label::new(1, "work".to_string()),
+            Label::new(2, "home".to_string()),
+        ];
+        MemoryBackend {
+            todos: Mutex::new(vec![
+                TodoEntity::new(1, "write report".to_string(), vec![labels[0].clone()]),
+                TodoEntity {
+                    notes: Some("low-fat, 2 liters".to_string()),
+                    ..TodoEntity::new(2, "buy milk".to_string(), vec![labels[1].clone()])
+                },
+                TodoEntity::new(3, "Review PR".to_string(), vec![labels[0].clone()]),
+            ]),
+            labels,
+        }
+    }
+
+    fn press(app: &mut App, keys: &str) -> Option<Action> {
+        keys.chars()
+            .map(|c| {
+                let code = match c {
+                    '\n' => KeyCode::Enter,
+                    '\x1b' => KeyCode::Esc,
+                    '\x08' => KeyCode::Backspace,
+                    c => KeyCode::Char(c),
+                };
+                app.handle_key(KeyEvent::from(code))
+            })
+            .last()
+            .flatten()
+    }
+
+    fn visible_ids(app: &App) -> Vec<i32> {
+        app.visible().iter().map(|todo| todo.id).collect()
+    }
+
+    #[tokio::test]
+    async fn should_filter_by_search_and_label() {
+        let backend = backend_fixture();
+        let mut app = App::new();
+        refresh(&mut app, &backend).await;
+        assert_eq!(visible_ids(&app), vec![1, 2, 3]);
+
+        press(&mut app, "/re\n");
+        assert_eq!(visible_ids(&app), vec![1, 3]);
+        // メモも検索する
+        press(&mut app, "/\x08\x08liter\n");
+        assert_eq!(visible_ids(&app), vec![2]);
+        press(&mut app, "\x1b");
+        press(&mut app, "l");
+        assert_eq!(app.label_filter_name(), Some("work"));
+        press(&mut app, "/view\n");
+        assert_eq!(visible_ids(&app), vec![3]);
+
+        // Escで絞り込みを解除する
+        press(&mut app, "\x1b");
+        assert_eq!(visible_ids(&app), vec![1, 2, 3]);
+        press(&mut app, "lll");
+        assert_eq!(app.label_filter, None);
+    }
+
+    #[tokio::test]
+    async fn should_toggle_add_edit_and_delete() {
+        let backend = backend_fixture();
+        let mut app = App::new();
+        refresh(&mut app, &backend).await;
+
+        // toggle
+        let action = press(&mut app, "j ").unwrap();
+        assert_eq!(
+            action,
+            Action::Update(
+                2,
+                UpdateTodo {
+                    text: Some("buy milk".to_string()),
+                    completed: Some(true),
+                    ..Default::default()
+                }
+            )
+        );
+        apply(&mut app, &backend, action).await;
+        assert!(app.todos[1].completed);
+
+        // add (絞り込み中のラベルが付く)
+        press(&mut app, "l");
+        let action = press(&mut app, "anew task\n").unwrap();
+        apply(&mut app, &backend, action).await;
+        assert_eq!(app.todos[3].labels[0].name, "work");
+        assert_eq!(app.mode, Mode::Normal);
+
+        // edit (末尾の "report" を書き換える)
+        press(&mut app, "\x1b");
+        press(&mut app, "e\x08\x08\x08\x08\x08\x08summary");
+        assert_eq!(app.input, "write summary");
+        let action = press(&mut app, "\n").unwrap();
+        apply(&mut app, &backend, action).await;
+        assert_eq!(app.todos[0].text, "write summary");
+
+        // delete (y以外は取り消す)
+        assert_eq!(press(&mut app, "dn"), None);
+        let action = press(&mut app, "dy").unwrap();
+        assert_eq!(action, Action::Delete(1));
+        apply(&mut app, &backend, action).await;
+        assert_eq!(visible_ids(&app), vec![2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn should_show_backend_error() {
+        let backend = backend_fixture();
+        let mut app = App::new();
+        refresh(&mut app, &backend).await;
+
+        apply(
+            &mut app,
+            &backend,
+            Action::Update(99, UpdateTodo::default()),
+        )
+        .await;
+        assert_eq!(app.message.as_deref(), Some("Error: Todo not found"));
+        assert_eq!(press(&mut app, "q"), Some(Action::Quit));
+        assert_eq!(app.message, None);
+    }
+}
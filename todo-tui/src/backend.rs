@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use todo::repositories::{
+    label::LabelRepository,
+    todo::{CreateTodo, TodoEntity, TodoRepository, UpdateTodo},
+};
+use todo_client::{Label, TodoClient};
+
+/// TUIから使うTodoの操作
+#[async_trait]
+pub trait Backend: Send + Sync {
+    async fn all_todo(&self) -> anyhow::Result<Vec<TodoEntity>>;
+    async fn all_label(&self) -> anyhow::Result<Vec<Label>>;
+    async fn create_todo(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity>;
+    async fn update_todo(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity>;
+    async fn delete_todo(&self, id: i32) -> anyhow::Result<()>;
+}
+
+/// HTTP APIを経由する
+#[async_trait]
+impl Backend for TodoClient {
+    async fn all_todo(&self) -> anyhow::Result<Vec<TodoEntity>> {
+        Ok(TodoClient::all_todo(self).await?)
+    }
+
+    async fn all_label(&self) -> anyhow::Result<Vec<Label>> {
+        Ok(TodoClient::all_label(self).await?)
+    }
+
+    async fn create_todo(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity> {
+        Ok(TodoClient::create_todo(self, &payload).await?)
+    }
+
+    async fn update_todo(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity> {
+        Ok(TodoClient::update_todo(self, id, &payload).await?)
+    }
+
+    async fn delete_todo(&self, id: i32) -> anyhow::Result<()> {
+        Ok(TodoClient::delete_todo(self, id).await?)
+    }
+}
+
+/// Repositoryへ直接接続する (サーバーを立てずに使うローカルモード)
+///
+/// ハンドラを通らないため、Webhookの通知やステータスとの連動は行われない
+pub struct RepositoryBackend<T: TodoRepository, L: LabelRepository> {
+    todos: T,
+    labels: L,
+}
+
+impl<T: TodoRepository, L: LabelRepository> RepositoryBackend<T, L> {
+    pub fn new(todos: T, labels: L) -> Self {
+        Self { todos, labels }
+    }
+}
+
+#[async_trait]
+impl<T: TodoRepository, L: LabelRepository> Backend for RepositoryBackend<T, L> {
+    async fn all_todo(&self) -> anyhow::Result<Vec<TodoEntity>> {
+        self.todos.all().await
+    }
+
+    async fn all_label(&self) -> anyhow::Result<Vec<Label>> {
+        self.labels.all().await
+    }
+
+    async fn create_todo(&self, payload: CreateTodo) -> anyhow::Result<TodoEntity> {
+        self.todos.create(payload).await
+    }
+
+    async fn update_todo(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<TodoEntity> {
+        self.todos.update(id, payload).await
+    }
+
+    async fn delete_todo(&self, id: i32) -> anyhow::Result<()> {
+        self.todos.delete(id).await
+    }
+}
@@ -0,0 +1,114 @@
+mod app;
+mod backend;
+mod ui;
+
+use app::{Action, App};
+use backend::{Backend, RepositoryBackend};
+use clap::Parser;
+use ratatui::{
+    crossterm::event::{self, Event, KeyEventKind},
+    DefaultTerminal,
+};
+use std::time::{Duration, Instant};
+use todo::repositories::{
+    label::{LabelRepositoryForDb, LabelRepositoryForMySql, LabelRepositoryForSqlite},
+    todo::{TodoRepositoryForDb, TodoRepositoryForMySql, TodoRepositoryForSqlite},
+    Database,
+};
+use todo_client::TodoClient;
+
+// 他の端末やブラウザでの変更を取り込む間隔
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+const TICK: Duration = Duration::from_millis(250);
+
+/// Todoを操作するTUI
+#[derive(Debug, Parser)]
+#[command(name = "todo-tui", version)]
+struct Cli {
+    /// APIのURL
+    #[arg(long, env = "TODO_SERVER_URL", default_value = "http://localhost:8000")]
+    server: String,
+    /// 認証トークン
+    #[arg(long, env = "TODO_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// 指定した場合はAPIを使わずにDBへ直接接続する (--server より優先する)
+    ///
+    /// `sqlite:todo.db` のように指定するとサーバーなしのオフラインで使える
+    #[arg(long, env = "TODO_DATABASE_URL")]
+    database_url: Option<String>,
+}
+
+async fn run(terminal: &mut DefaultTerminal, backend: impl Backend) -> anyhow::Result<()> {
+    let mut app = App::new();
+    app::refresh(&mut app, &backend).await;
+    let mut refreshed_at = Instant::now();
+
+    loop {
+        terminal.draw(|frame| ui::draw(frame, &app))?;
+
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    match app.handle_key(key) {
+                        Some(Action::Quit) => return Ok(()),
+                        Some(action) => {
+                            app::apply(&mut app, &backend, action).await;
+                            refreshed_at = Instant::now();
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+
+        if refreshed_at.elapsed() >= REFRESH_INTERVAL {
+            app::refresh(&mut app, &backend).await;
+            refreshed_at = Instant::now();
+        }
+    }
+}
+
+async fn start(backend: impl Backend) -> anyhow::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, backend).await;
+    ratatui::restore();
+    result
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    match cli.database_url {
+        Some(database_url) => match Database::connect(&database_url).await? {
+            Database::Postgres(pool) => {
+                start(RepositoryBackend::new(
+                    TodoRepositoryForDb::new(pool.clone()),
+                    LabelRepositoryForDb::new(pool),
+                ))
+                .await
+            }
+            Database::MySql(pool) => {
+                start(RepositoryBackend::new(
+                    TodoRepositoryForMySql::new(pool.clone()),
+                    LabelRepositoryForMySql::new(pool),
+                ))
+                .await
+            }
+            Database::Sqlite(pool) => {
+                start(RepositoryBackend::new(
+                    TodoRepositoryForSqlite::new(pool.clone()),
+                    LabelRepositoryForSqlite::new(pool),
+                ))
+                .await
+            }
+        },
+        None => {
+            let mut client = TodoClient::new(&cli.server)?;
+            if let Some(token) = cli.token {
+                client = client.with_token(token);
+            }
+            start(client).await
+        }
+    }
+}
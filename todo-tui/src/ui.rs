@@ -0,0 +1,187 @@
+use crate::app::{App, Mode};
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use todo_client::Label;
+
+// ラベルの色が読めない場合は、ラベルIDごとに色を変える
+const LABEL_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Green,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Blue,
+    Color::Red,
+];
+
+const HELP: &str = "j/k:移動 space:完了 a:追加 e:編集 d:削除 /:検索 l:ラベル r:再読込 q:終了";
+
+/// `#rrggbb` 形式の色を読む
+fn parse_color(color: &str) -> Option<Color> {
+    let hex = color.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    Some(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+fn label_chip(label: &Label) -> Span<'static> {
+    let (bg, fg) = match parse_color(&label.color) {
+        // 明るい背景には黒、暗い背景には白の文字を使う
+        Some(Color::Rgb(r, g, b)) => {
+            let luma = 299 * r as u32 + 587 * g as u32 + 114 * b as u32;
+            let fg = if luma > 128_000 {
+                Color::Black
+            } else {
+                Color::White
+            };
+            (Color::Rgb(r, g, b), fg)
+        }
+        _ => (
+            LABEL_COLORS[label.id.unsigned_abs() as usize % LABEL_COLORS.len()],
+            Color::Black,
+        ),
+    };
+    let text = match &label.icon {
+        Some(icon) => format!(" {} {} ", icon, label.name),
+        None => format!(" {} ", label.name),
+    };
+    Span::styled(text, Style::default().fg(fg).bg(bg))
+}
+
+fn header(app: &App) -> Line<'static> {
+    let mut spans = vec![Span::from(" Todo ").bold()];
+    if let Some(name) = app.label_filter_name() {
+        spans.push(Span::from(format!(" label:{}", name)));
+    }
+    if !app.search.is_empty() {
+        spans.push(Span::from(format!(" search:{}", app.search)));
+    }
+    spans.push(Span::from(format!(
+        " ({}/{})",
+        app.visible().len(),
+        app.todos.len()
+    )));
+    Line::from(spans)
+}
+
+fn footer(app: &App) -> Line<'static> {
+    match &app.mode {
+        Mode::Normal => match &app.message {
+            Some(message) => Line::from(message.clone()).red(),
+            None => Line::from(HELP).dark_gray(),
+        },
+        Mode::Search => Line::from(format!("/{}", app.search)),
+        Mode::Add => Line::from(format!("追加: {}", app.input)),
+        Mode::Edit(id) => Line::from(format!("編集 #{}: {}", id, app.input)),
+        Mode::Delete(id) => Line::from(format!("#{} を削除しますか? (y/n)", id)).yellow(),
+    }
+}
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [header_area, list_area, footer_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    frame.render_widget(Paragraph::new(header(app)), header_area);
+
+    let items: Vec<ListItem> = app
+        .visible()
+        .into_iter()
+        .map(|todo| {
+            let mut spans = vec![
+                Span::from(if todo.completed { "[x] " } else { "[ ] " }),
+                if todo.completed {
+                    Span::styled(
+                        todo.text.clone(),
+                        Style::default()
+                            .fg(Color::DarkGray)
+                            .add_modifier(Modifier::CROSSED_OUT),
+                    )
+                } else {
+                    Span::from(todo.text.clone())
+                },
+            ];
+            for label in &todo.labels {
+                spans.push(Span::from(" "));
+                spans.push(label_chip(label));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::bordered())
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    let mut state = ListState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(list, list_area, &mut state);
+
+    frame.render_widget(Paragraph::new(footer(app)), footer_area);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ratatui::{backend::TestBackend, Terminal};
+    use todo::repositories::todo::TodoEntity;
+
+    fn render(app: &App) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(40, 6)).unwrap();
+        terminal.draw(|frame| draw(frame, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|cells| cells.iter().map(|cell| cell.symbol()).collect::<String>())
+            .map(|line| line.trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn should_render_todos_with_label_chips() {
+        let mut app = App::new();
+        let work = Label::new(1, "work".to_string());
+        let mut done = TodoEntity::new(2, "done task".to_string(), vec![]);
+        done.completed = true;
+        app.set_data(
+            vec![
+                TodoEntity::new(1, "write".to_string(), vec![work.clone()]),
+                done,
+            ],
+            vec![work],
+        );
+
+        let lines = render(&app);
+        assert_eq!(lines[0], " Todo  (2/2)");
+        assert_eq!(lines[2], format!("│{:<38}│", "> [ ] write  work "));
+        assert_eq!(lines[3], format!("│{:<38}│", "  [x] done task"));
+
+        app.mode = Mode::Delete(1);
+        assert!(render(&app)[5].starts_with("#1 "));
+        assert!(render(&app)[5].ends_with("(y/n)"));
+    }
+
+    #[test]
+    fn should_use_label_color() {
+        let mut label = Label::new(1, "work".to_string());
+        label.color = "#ffcc00".to_string();
+        let chip = label_chip(&label);
+        assert_eq!(chip.style.bg, Some(Color::Rgb(0xff, 0xcc, 0x00)));
+        assert_eq!(chip.style.fg, Some(Color::Black));
+
+        label.color = "#102030".to_string();
+        label.icon = Some("★".to_string());
+        let chip = label_chip(&label);
+        assert_eq!(chip.content, " ★ work ");
+        assert_eq!(chip.style.fg, Some(Color::White));
+
+        // 読めない色は既定の色で表示する
+        label.color = "red".to_string();
+        assert_eq!(label_chip(&label).style.bg, Some(LABEL_COLORS[1]));
+    }
+}
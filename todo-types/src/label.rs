@@ -0,0 +1,148 @@
+use crate::deserialize_double_option;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 色を指定せずに作成したラベルの色
+pub const DEFAULT_LABEL_COLOR: &str = "#808080";
+
+fn default_color() -> String {
+    DEFAULT_LABEL_COLOR.to_string()
+}
+
+// 色・説明・アイコン・並び順・日時は後から追加したため、持たないデータ(古いサーバーの応答や保存済みのファイル)は既定値で読む
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Label {
+    pub id: i32,
+    pub name: String,
+    /// `#rrggbb` 形式(小文字)の色
+    #[serde(default = "default_color")]
+    pub color: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// アイコン名または絵文字
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// 並び順。小さいほど先に表示し、同じ場合はID順
+    #[serde(default)]
+    pub position: i32,
+    /// 親のラベル。`area/backend` のような階層を作る
+    #[serde(default)]
+    pub parent_id: Option<i32>,
+    /// trueの場合、1件のTodoには子のラベルを1つまでしか付けられない
+    #[serde(default)]
+    pub exclusive: bool,
+    #[serde(default)]
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Label {
+    pub fn new(id: i32, name: String) -> Self {
+        Label {
+            id,
+            name,
+            color: default_color(),
+            description: None,
+            icon: None,
+            position: 0,
+            parent_id: None,
+            exclusive: false,
+            created_at: DateTime::UNIX_EPOCH,
+            updated_at: DateTime::UNIX_EPOCH,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateLabel {
+    pub name: String,
+    /// 省略した場合は `DEFAULT_LABEL_COLOR`
+    pub color: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    /// 省略した場合は最後に並べる
+    pub position: Option<i32>,
+    pub parent_id: Option<i32>,
+    #[serde(default)]
+    pub exclusive: bool,
+}
+
+impl CreateLabel {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            color: None,
+            description: None,
+            icon: None,
+            position: None,
+            parent_id: None,
+            exclusive: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UpdateLabel {
+    pub name: Option<String>,
+    pub color: Option<String>,
+    // nullを指定すると説明・アイコンを外す
+    // 省略とnullを区別するため、Noneの場合はキーごと送らない
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub description: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub icon: Option<Option<String>>,
+    pub position: Option<i32>,
+    // nullを指定すると最上位のラベルにする
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub parent_id: Option<Option<i32>>,
+    pub exclusive: Option<bool>,
+}
+
+/// 重複したラベルを1つにまとめる
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MergeLabels {
+    /// 統合して削除するラベル
+    pub sources: Vec<i32>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn label_fills_missing_fields() {
+        let label: Label = serde_json::from_str(r#"{ "id": 1, "name": "work" }"#).unwrap();
+        assert_eq!(label, Label::new(1, "work".to_string()));
+        assert_eq!(label.color, DEFAULT_LABEL_COLOR);
+    }
+
+    #[test]
+    fn update_label_distinguishes_null_and_missing() {
+        let missing: UpdateLabel = serde_json::from_str(r##"{ "color": "#ff0000" }"##).unwrap();
+        assert_eq!(missing.description, None);
+        let null: UpdateLabel = serde_json::from_str(r#"{ "icon": null }"#).unwrap();
+        assert_eq!(null.icon, Some(None));
+        assert!(serde_json::to_value(&missing)
+            .unwrap()
+            .get("description")
+            .is_none());
+    }
+}
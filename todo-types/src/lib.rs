@@ -0,0 +1,31 @@
+//! Todo APIのリクエスト・レスポンスの型
+//!
+//! サーバー(`todo`)・フロントエンド(`front-rust`)・クライアント(`todo-client`)から使うため、serde以外には依存しない。
+//! DBの行からの変換(`sqlx` feature)とOpenAPIのスキーマ(`openapi` feature)は必要な側でのみ有効にする。
+
+mod label;
+mod project;
+mod todo;
+
+pub use label::{CreateLabel, Label, MergeLabels, UpdateLabel, DEFAULT_LABEL_COLOR};
+pub use project::{CreateProject, Project, UpdateProject};
+pub use todo::{
+    CreateTodo, Priority, QuickAdd, QuickAddLabel, QuickAddPreview, TodoDependency, TodoEntity,
+    UpdateTodo,
+};
+
+use serde::{Deserialize, Deserializer};
+
+// マイグレーションで作成されるデフォルトプロジェクト
+pub const DEFAULT_PROJECT_ID: i32 = 1;
+
+/// 更新用payloadで、キーの省略(None)と明示的なnull(Some(None))を区別する
+///
+/// `#[serde(default, deserialize_with = "deserialize_double_option")]` と組み合わせて使う
+pub fn deserialize_double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
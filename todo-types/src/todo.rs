@@ -0,0 +1,267 @@
+use crate::{deserialize_double_option, Label, DEFAULT_PROJECT_ID};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Todoの優先度。DBには数値で保存し、大きいほど優先度が高い
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "sqlx", derive(sqlx::Type))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[repr(i32)]
+pub enum Priority {
+    Low = 1,
+    Medium = 2,
+    High = 3,
+    Urgent = 4,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TodoEntity {
+    pub id: i32,
+    pub text: String,
+    /// 詳細を書くメモ (Markdown)
+    #[serde(default)]
+    pub notes: Option<String>,
+    pub completed: bool,
+    pub project_id: i32,
+    pub status_id: Option<i32>,
+    pub labels: Vec<Label>,
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    /// 期限
+    #[serde(default)]
+    pub due_at: Option<DateTime<Utc>>,
+    /// 完了を待っているTodo (ブロッカー) のID
+    #[serde(default)]
+    pub blocked_by: Vec<i32>,
+    /// 未完了のブロッカーがある。ブロックされている間は完了にできない
+    #[serde(default)]
+    pub blocked: bool,
+    pub comment_count: i64,
+    // 古いサーバーのレスポンスには含まれない
+    #[serde(default)]
+    pub created_at: DateTime<Utc>,
+    /// 内容・ラベル・完了状態などを最後に変更した日時
+    #[serde(default)]
+    pub updated_at: DateTime<Utc>,
+    /// 未完了の場合と、完了日時を記録する前に完了したTodoはnull
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl TodoEntity {
+    pub fn new(id: i32, text: String, labels: Vec<Label>) -> Self {
+        Self {
+            id,
+            text,
+            notes: None,
+            completed: false,
+            project_id: DEFAULT_PROJECT_ID,
+            status_id: None,
+            labels,
+            priority: None,
+            due_at: None,
+            blocked_by: vec![],
+            blocked: false,
+            comment_count: 0,
+            created_at: DateTime::UNIX_EPOCH,
+            updated_at: DateTime::UNIX_EPOCH,
+            completed_at: None,
+        }
+    }
+
+    /// タイトルかメモに `query` を含むか。大文字・小文字は区別しない
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.text.to_lowercase().contains(&query)
+            || self
+                .notes
+                .as_ref()
+                .is_some_and(|notes| notes.to_lowercase().contains(&query))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateTodo {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    pub labels: Vec<i32>,
+    pub project_id: Option<i32>,
+    pub status_id: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<Utc>>,
+    // ステータスから導出されるため、リクエストでは受け付けない
+    #[serde(skip)]
+    pub completed: bool,
+}
+
+impl CreateTodo {
+    pub fn new(text: String, labels: Vec<i32>) -> Self {
+        Self {
+            text,
+            notes: None,
+            labels,
+            project_id: None,
+            status_id: None,
+            priority: None,
+            due_at: None,
+            completed: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UpdateTodo {
+    pub text: Option<String>,
+    // nullを指定するとメモを消す
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub notes: Option<Option<String>>,
+    pub completed: Option<bool>,
+    pub labels: Option<Vec<i32>>,
+    pub project_id: Option<i32>,
+    // nullを指定するとステータスの割り当てを外す
+    // 省略とnullを区別するため、Noneの場合はキーごと送らない
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub status_id: Option<Option<i32>>,
+    // nullを指定すると優先度・期限を外す
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub priority: Option<Option<Priority>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+}
+
+/// `POST /todos/{id}/dependencies` と `DELETE /todos/{id}/dependencies` のリクエスト
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TodoDependency {
+    /// 先に完了させるTodoのID
+    pub blocker_id: i32,
+}
+
+/// `POST /todos/quick` のリクエスト
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct QuickAdd {
+    /// `Fix login bug tomorrow 15:00 #backend !high` のような1行
+    pub input: String,
+    /// trueの場合は作成せずに解析の結果だけを返す
+    #[serde(default)]
+    pub preview: bool,
+    /// trueの場合、存在しないラベルを作成する
+    #[serde(default)]
+    pub create_labels: bool,
+    /// 日時を解釈するタイムゾーン (`+09:00` など)。省略した場合はUTC
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utc_offset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<i32>,
+}
+
+impl QuickAdd {
+    pub fn new(input: String) -> Self {
+        Self {
+            input,
+            ..Default::default()
+        }
+    }
+}
+
+/// 解析したラベル。存在しないラベルは `id` がnull
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct QuickAddLabel {
+    pub name: String,
+    pub id: Option<i32>,
+}
+
+/// `POST /todos/quick` の解析の結果 (`preview` の場合に返す)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct QuickAddPreview {
+    pub text: String,
+    pub labels: Vec<QuickAddLabel>,
+    pub priority: Option<Priority>,
+    pub due_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn create_todo_payload() {
+        let payload = CreateTodo::new("todo".to_string(), vec![1, 2]);
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            serde_json::json!({
+                "text": "todo",
+                "labels": [1, 2],
+                "project_id": null,
+                "status_id": null,
+            })
+        );
+    }
+
+    #[test]
+    fn update_todo_distinguishes_null_and_missing() {
+        let missing: UpdateTodo = serde_json::from_str(r#"{ "text": "todo" }"#).unwrap();
+        assert_eq!(missing.status_id, None);
+        let null: UpdateTodo = serde_json::from_str(r#"{ "status_id": null }"#).unwrap();
+        assert_eq!(null.status_id, Some(None));
+        let some: UpdateTodo = serde_json::from_str(r#"{ "status_id": 3 }"#).unwrap();
+        assert_eq!(some.status_id, Some(Some(3)));
+        let null: UpdateTodo = serde_json::from_str(r#"{ "notes": null }"#).unwrap();
+        assert_eq!(null.notes, Some(None));
+    }
+
+    #[test]
+    fn should_match_text_and_notes() {
+        let todo = TodoEntity {
+            notes: Some("- [ ] Buy **Milk**".to_string()),
+            ..TodoEntity::new(1, "Shopping".to_string(), vec![])
+        };
+        assert!(todo.matches("shop"));
+        assert!(todo.matches("MILK"));
+        assert!(!todo.matches("bread"));
+        assert!(!TodoEntity::new(2, "todo".to_string(), vec![]).matches("milk"));
+    }
+
+    #[test]
+    fn update_todo_omits_missing_status() {
+        let payload = UpdateTodo {
+            completed: Some(true),
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&payload).unwrap();
+        assert!(value.get("status_id").is_none());
+
+        let payload = UpdateTodo {
+            status_id: Some(None),
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["status_id"], serde_json::Value::Null);
+    }
+}